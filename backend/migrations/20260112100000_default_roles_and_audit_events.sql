@@ -0,0 +1,20 @@
+-- Roles assigned server-side at signup and required for the admin API
+INSERT INTO roles (id, name)
+VALUES
+    (gen_random_uuid()::TEXT, 'customer'),
+    (gen_random_uuid()::TEXT, 'admin')
+ON CONFLICT (name) DO NOTHING;
+
+-- Append-only record of privileged changes
+CREATE TABLE IF NOT EXISTS audit_events (
+    id TEXT PRIMARY KEY,
+    actor_id TEXT,
+    action TEXT NOT NULL,
+    target_type TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_events_target ON audit_events(target_type, target_id);
@@ -0,0 +1,33 @@
+-- Invoices issued to users, credited to their account once settled.
+-- Amounts are in millisatoshis, like account balances.
+CREATE TABLE IF NOT EXISTS invoices (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id TEXT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    payment_request TEXT NOT NULL,
+    payment_hash TEXT NOT NULL UNIQUE,
+    amount_msat NUMERIC NOT NULL,
+    memo TEXT NOT NULL DEFAULT '',
+    description_hash TEXT,
+    comment TEXT,
+    source TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open',
+    preimage TEXT,
+    expires_at TIMESTAMPTZ NOT NULL,
+    settled_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_invoices_user_id ON invoices(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_invoices_open ON invoices(status) WHERE status = 'open';
+
+-- Ledger entries now record the account they moved and the direction.
+ALTER TABLE transactions
+    ADD COLUMN IF NOT EXISTS account_id TEXT REFERENCES accounts(id) ON DELETE CASCADE,
+    ADD COLUMN IF NOT EXISTS direction TEXT NOT NULL DEFAULT 'outgoing';
+
+-- A payment hash is credited at most once.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_incoming_payment_hash
+    ON transactions(payment_hash)
+    WHERE direction = 'incoming';
@@ -0,0 +1,6 @@
+-- An invoice is paid at most once: a payment hash may only have one
+-- outgoing payment that is in flight or has succeeded.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_outgoing_payment_hash
+    ON transactions(payment_hash)
+    WHERE direction = 'outgoing'
+      AND payment_status IN ('pending', 'succeeded');
@@ -0,0 +1,26 @@
+-- LNURL-withdraw links. Each link holds a reservation taken from the
+-- owner's balance when it is created; whatever is left of it goes back
+-- to the balance once the link expires, is used up or is cancelled.
+CREATE TABLE IF NOT EXISTS withdraw_links (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id TEXT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    k1 TEXT NOT NULL UNIQUE,
+    min_withdrawable_msat NUMERIC NOT NULL,
+    max_withdrawable_msat NUMERIC NOT NULL,
+    max_uses INTEGER NOT NULL,
+    uses INTEGER NOT NULL DEFAULT 0,
+    total_msat NUMERIC NOT NULL,
+    withdrawn_msat NUMERIC NOT NULL DEFAULT 0,
+    reserved_msat NUMERIC NOT NULL,
+    spent_msat NUMERIC NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'active',
+    expires_at TIMESTAMPTZ NOT NULL,
+    released_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_withdraw_links_user_id ON withdraw_links(user_id);
+CREATE INDEX IF NOT EXISTS idx_withdraw_links_active ON withdraw_links(expires_at) WHERE status = 'active';
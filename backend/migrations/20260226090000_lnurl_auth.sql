@@ -0,0 +1,28 @@
+-- Accounts created through LNURL-auth have no email or password
+ALTER TABLE users ALTER COLUMN email DROP NOT NULL;
+ALTER TABLE users ALTER COLUMN password_hash DROP NOT NULL;
+
+-- Wallet linking keys (LUD-04) that can sign in as a user
+CREATE TABLE IF NOT EXISTS lnurl_auth_keys (
+    linking_key TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_lnurl_auth_keys_user_id ON lnurl_auth_keys(user_id);
+
+-- k1 challenges handed out for a wallet to sign
+CREATE TABLE IF NOT EXISTS lnurl_auth_challenges (
+    k1 TEXT PRIMARY KEY,
+    action TEXT NOT NULL,
+    user_id TEXT REFERENCES users(id) ON DELETE CASCADE,
+    linking_key TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    expires_at TIMESTAMPTZ NOT NULL,
+    verified_at TIMESTAMPTZ,
+    consumed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_lnurl_auth_challenges_expires_at ON lnurl_auth_challenges(expires_at);
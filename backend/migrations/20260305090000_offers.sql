@@ -0,0 +1,15 @@
+-- Reusable BOLT12 offers that credit the owning account
+CREATE TABLE IF NOT EXISTS offers (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id TEXT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    offer TEXT NOT NULL,
+    offer_id TEXT NOT NULL UNIQUE,
+    description TEXT NOT NULL,
+    amount_msat NUMERIC,
+    status TEXT NOT NULL DEFAULT 'active',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_offers_user_id ON offers(user_id);
@@ -0,0 +1,13 @@
+-- Invoices without an amount let the payer choose how much to send, so the
+-- amount that actually settled is recorded separately from the one asked for.
+ALTER TABLE invoices
+    ALTER COLUMN amount_msat DROP NOT NULL,
+    ADD COLUMN IF NOT EXISTS amount_paid_msat NUMERIC;
+
+UPDATE invoices
+SET amount_paid_msat = transactions.amount
+FROM transactions
+WHERE transactions.payment_hash = invoices.payment_hash
+  AND transactions.direction = 'incoming'
+  AND invoices.status = 'settled'
+  AND invoices.amount_paid_msat IS NULL;
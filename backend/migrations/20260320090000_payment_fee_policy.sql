@@ -0,0 +1,9 @@
+-- Routing fees are recorded apart from the amount the payee received.
+ALTER TABLE transactions
+    ADD COLUMN IF NOT EXISTS fee_msat NUMERIC NOT NULL DEFAULT 0;
+
+-- Optional ceilings on the routing fee users with a role may pay, in
+-- millisatoshis and as a percentage of the amount. The lower one applies.
+ALTER TABLE roles
+    ADD COLUMN IF NOT EXISTS max_fee_msat NUMERIC,
+    ADD COLUMN IF NOT EXISTS max_fee_percent DOUBLE PRECISION;
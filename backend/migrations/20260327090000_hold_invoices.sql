@@ -0,0 +1,8 @@
+-- Hold invoices are accepted by the node first and settled or canceled later
+ALTER TABLE invoices ADD COLUMN IF NOT EXISTS accepted_at TIMESTAMPTZ;
+ALTER TABLE invoices ADD COLUMN IF NOT EXISTS htlc_expiry_height INTEGER;
+ALTER TABLE invoices ADD COLUMN IF NOT EXISTS settle_by TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_invoices_hold_in_flight
+    ON invoices(status)
+    WHERE source = 'hold' AND status IN ('open', 'accepted');
@@ -0,0 +1,32 @@
+-- Addresses in the node wallet handed out to accounts for on-chain deposits
+CREATE TABLE IF NOT EXISTS deposit_addresses (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id TEXT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    address TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_deposit_addresses_user_id ON deposit_addresses(user_id);
+
+-- Outputs received on deposit addresses. They are credited once deep enough
+-- and the credit is reversed if a reorg takes the transaction back out.
+CREATE TABLE IF NOT EXISTS deposits (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id TEXT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    address TEXT NOT NULL REFERENCES deposit_addresses(address) ON DELETE CASCADE,
+    txid TEXT NOT NULL,
+    vout INTEGER NOT NULL,
+    amount_msat NUMERIC NOT NULL,
+    confirmations INTEGER NOT NULL DEFAULT 0,
+    block_height INTEGER,
+    status TEXT NOT NULL DEFAULT 'pending',
+    credited_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (txid, vout)
+);
+
+CREATE INDEX IF NOT EXISTS idx_deposits_user_id ON deposits(user_id);
+CREATE INDEX IF NOT EXISTS idx_deposits_status ON deposits(status);
@@ -0,0 +1,38 @@
+-- Transactions paying out a batch of on-chain withdrawals
+CREATE TABLE IF NOT EXISTS onchain_batches (
+    id TEXT PRIMARY KEY,
+    fee_tier TEXT NOT NULL,
+    txid TEXT NOT NULL,
+    sat_per_vbyte BIGINT NOT NULL,
+    fee_sat NUMERIC,
+    status TEXT NOT NULL DEFAULT 'broadcast',
+    broadcast_height INTEGER NOT NULL,
+    bumped_height INTEGER,
+    bump_count INTEGER NOT NULL DEFAULT 0,
+    confirmed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_onchain_batches_status ON onchain_batches(status);
+
+-- Withdrawals to an on-chain address, queued until the next batch of their fee tier
+CREATE TABLE IF NOT EXISTS onchain_withdrawals (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id TEXT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    transaction_id TEXT NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    batch_id TEXT REFERENCES onchain_batches(id),
+    address TEXT NOT NULL,
+    amount_msat NUMERIC NOT NULL,
+    fee_tier TEXT NOT NULL,
+    fee_reserve_msat NUMERIC NOT NULL,
+    fee_msat NUMERIC,
+    status TEXT NOT NULL DEFAULT 'queued',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_onchain_withdrawals_user_id ON onchain_withdrawals(user_id);
+CREATE INDEX IF NOT EXISTS idx_onchain_withdrawals_status ON onchain_withdrawals(status);
+CREATE INDEX IF NOT EXISTS idx_onchain_withdrawals_batch_id ON onchain_withdrawals(batch_id);
@@ -0,0 +1,34 @@
+-- Submarine swaps between on-chain coins and Lightning balances
+CREATE TABLE IF NOT EXISTS swaps (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id TEXT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    -- 'submarine' (chain to Lightning) or 'reverse' (Lightning to chain)
+    kind TEXT NOT NULL,
+    provider_swap_id TEXT NOT NULL UNIQUE,
+    provider_status TEXT,
+    status TEXT NOT NULL DEFAULT 'created',
+    invoice TEXT NOT NULL,
+    payment_hash TEXT NOT NULL,
+    amount_msat NUMERIC NOT NULL,
+    onchain_amount_sat BIGINT NOT NULL,
+    lockup_address TEXT NOT NULL,
+    -- Where the coins end up: the claim address of a reverse swap, the
+    -- refund address of a submarine swap
+    destination_address TEXT NOT NULL,
+    redeem_script TEXT NOT NULL,
+    timeout_block_height INTEGER NOT NULL,
+    preimage TEXT,
+    private_key TEXT NOT NULL,
+    transaction_id TEXT REFERENCES transactions(id) ON DELETE CASCADE,
+    fee_reserve_msat NUMERIC NOT NULL DEFAULT 0,
+    routing_fee_msat NUMERIC,
+    fee_msat NUMERIC,
+    lockup_txid TEXT,
+    sweep_txid TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_swaps_user_id ON swaps(user_id);
+CREATE INDEX IF NOT EXISTS idx_swaps_status ON swaps(status);
@@ -0,0 +1,19 @@
+-- Currency each account's balance and statements are shown in
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS display_currency TEXT NOT NULL DEFAULT 'USD';
+
+-- Price of one bitcoin in each fiat currency, as recorded over time
+CREATE TABLE IF NOT EXISTS exchange_rates (
+    id TEXT PRIMARY KEY,
+    currency TEXT NOT NULL,
+    rate NUMERIC NOT NULL,
+    source TEXT NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_exchange_rates_currency_fetched_at ON exchange_rates(currency, fetched_at);
+
+-- Rate in effect when a transaction was made, so statements keep their fiat value
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS fiat_currency TEXT;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS fiat_rate NUMERIC;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_unvalued ON transactions(created_at) WHERE fiat_rate IS NULL;
@@ -0,0 +1,4 @@
+-- Invoices billed in fiat, with the rate their msat amount was locked at
+ALTER TABLE invoices ADD COLUMN IF NOT EXISTS fiat_currency TEXT;
+ALTER TABLE invoices ADD COLUMN IF NOT EXISTS fiat_amount NUMERIC;
+ALTER TABLE invoices ADD COLUMN IF NOT EXISTS fiat_rate NUMERIC;
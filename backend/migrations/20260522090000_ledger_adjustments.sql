@@ -0,0 +1,23 @@
+-- Manual balance corrections made by support staff. Each one is requested
+-- by one admin and only applied once a different admin approves it.
+CREATE TABLE IF NOT EXISTS ledger_adjustments (
+    id TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL REFERENCES accounts(id),
+    -- Signed: positive credits the account, negative debits it
+    amount_msat NUMERIC NOT NULL CHECK (amount_msat <> 0),
+    reason TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'applied', 'rejected')),
+    requested_by TEXT NOT NULL REFERENCES users(id),
+    reviewed_by TEXT REFERENCES users(id),
+    reviewed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (status <> 'applied' OR reviewed_by <> requested_by)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ledger_adjustments_status
+    ON ledger_adjustments (status, created_at DESC);
+
+CREATE INDEX IF NOT EXISTS idx_ledger_adjustments_account_id
+    ON ledger_adjustments (account_id);
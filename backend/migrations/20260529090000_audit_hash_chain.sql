@@ -0,0 +1,143 @@
+-- Turns 'audit_events' into a tamper-evident, append-only log. Each event
+-- records the request it came from and is chained to the one before it by
+-- a SHA-256 hash over its own fields and the previous hash.
+ALTER TABLE audit_events
+    ADD COLUMN IF NOT EXISTS seq BIGINT,
+    ADD COLUMN IF NOT EXISTS request_id TEXT,
+    ADD COLUMN IF NOT EXISTS ip_address TEXT,
+    ADD COLUMN IF NOT EXISTS prev_hash TEXT,
+    ADD COLUMN IF NOT EXISTS hash TEXT;
+
+-- Hash of one event. The fields go through a JSON array so NULLs and empty
+-- strings hash differently, and the timestamp as microseconds so the
+-- session time zone does not matter.
+CREATE OR REPLACE FUNCTION audit_event_hash(
+    prev_hash TEXT,
+    id TEXT,
+    actor_id TEXT,
+    action TEXT,
+    target_type TEXT,
+    target_id TEXT,
+    before JSONB,
+    after JSONB,
+    request_id TEXT,
+    ip_address TEXT,
+    created_at TIMESTAMPTZ
+) RETURNS TEXT
+LANGUAGE SQL
+STABLE
+AS $$
+    SELECT encode(
+        sha256(convert_to(jsonb_build_array(
+            prev_hash,
+            id,
+            actor_id,
+            action,
+            target_type,
+            target_id,
+            before,
+            after,
+            request_id,
+            ip_address,
+            (extract(epoch FROM created_at) * 1000000)::BIGINT
+        )::TEXT, 'UTF8')),
+        'hex'
+    )
+$$;
+
+-- Chain the events recorded before the log was hashed, oldest first
+DO $$
+DECLARE
+    event RECORD;
+    next_seq BIGINT := 0;
+    last_hash TEXT := NULL;
+BEGIN
+    FOR event IN SELECT * FROM audit_events ORDER BY created_at, id LOOP
+        next_seq := next_seq + 1;
+
+        UPDATE audit_events
+        SET seq = next_seq,
+            prev_hash = last_hash,
+            hash = audit_event_hash(
+                last_hash,
+                event.id,
+                event.actor_id,
+                event.action,
+                event.target_type,
+                event.target_id,
+                event.before,
+                event.after,
+                event.request_id,
+                event.ip_address,
+                event.created_at
+            )
+        WHERE id = event.id
+        RETURNING hash INTO last_hash;
+    END LOOP;
+END $$;
+
+ALTER TABLE audit_events
+    ALTER COLUMN seq SET NOT NULL,
+    ALTER COLUMN hash SET NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_audit_events_seq ON audit_events(seq);
+CREATE INDEX IF NOT EXISTS idx_audit_events_actor_id ON audit_events(actor_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_audit_events_action ON audit_events(action, created_at DESC);
+
+-- Links every new event to the last one. Writers take turns until their
+-- transaction ends, so the chain follows commit order.
+CREATE OR REPLACE FUNCTION audit_events_chain() RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    head_seq BIGINT;
+    head_hash TEXT;
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext('audit_events_chain'));
+
+    SELECT seq, hash
+    INTO head_seq, head_hash
+    FROM audit_events
+    ORDER BY seq DESC
+    LIMIT 1;
+
+    NEW.seq := COALESCE(head_seq, 0) + 1;
+    NEW.prev_hash := head_hash;
+    NEW.hash := audit_event_hash(
+        NEW.prev_hash,
+        NEW.id,
+        NEW.actor_id,
+        NEW.action,
+        NEW.target_type,
+        NEW.target_id,
+        NEW.before,
+        NEW.after,
+        NEW.request_id,
+        NEW.ip_address,
+        NEW.created_at
+    );
+
+    RETURN NEW;
+END $$;
+
+CREATE OR REPLACE FUNCTION audit_events_append_only() RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END $$;
+
+DROP TRIGGER IF EXISTS audit_events_chain ON audit_events;
+CREATE TRIGGER audit_events_chain
+    BEFORE INSERT ON audit_events
+    FOR EACH ROW EXECUTE FUNCTION audit_events_chain();
+
+DROP TRIGGER IF EXISTS audit_events_append_only ON audit_events;
+CREATE TRIGGER audit_events_append_only
+    BEFORE UPDATE OR DELETE ON audit_events
+    FOR EACH ROW EXECUTE FUNCTION audit_events_append_only();
+
+DROP TRIGGER IF EXISTS audit_events_no_truncate ON audit_events;
+CREATE TRIGGER audit_events_no_truncate
+    BEFORE TRUNCATE ON audit_events
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_events_append_only();
@@ -0,0 +1,13 @@
+-- Payments routed through the node record what was reserved for them, so a
+-- payment whose outcome was not known when it was made can be finished
+-- later by tracking it on the node.
+ALTER TABLE transactions
+    ADD COLUMN IF NOT EXISTS fee_reserve_msat NUMERIC,
+    ADD COLUMN IF NOT EXISTS api_key_id TEXT REFERENCES api_keys(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS withdraw_link_id TEXT REFERENCES withdraw_links(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_routed_pending
+    ON transactions(created_at)
+    WHERE direction = 'outgoing'
+      AND payment_status = 'pending'
+      AND fee_reserve_msat IS NOT NULL;
@@ -0,0 +1,6 @@
+-- Batches are recorded before they are sent, so they have no txid until the
+-- node reports one, and keep the txids of the transactions they replaced
+ALTER TABLE onchain_batches
+    ALTER COLUMN txid DROP NOT NULL,
+    ALTER COLUMN status SET DEFAULT 'sending',
+    ADD COLUMN IF NOT EXISTS replaced_txids TEXT[] NOT NULL DEFAULT '{}';
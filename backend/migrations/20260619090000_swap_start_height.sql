@@ -0,0 +1,12 @@
+-- Chain height when a swap was created, where the node starts looking for
+-- its lockup. Existing swaps look back two weeks from their timeout, which
+-- is longer than any swap provider gives a swap to run.
+ALTER TABLE swaps
+    ADD COLUMN IF NOT EXISTS start_height INTEGER;
+
+UPDATE swaps
+SET start_height = GREATEST(timeout_block_height - 2016, 0)
+WHERE start_height IS NULL;
+
+ALTER TABLE swaps
+    ALTER COLUMN start_height SET NOT NULL;
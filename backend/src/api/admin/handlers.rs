@@ -0,0 +1,426 @@
+// API Route handler for admin related Endpoints
+use crate::common::common::{ApiResponse, PaginationFilter, PaginationMeta};
+use crate::common::common::{service_error_to_http, validation_error_response};
+use crate::db::models::{
+    Account, AdminCreateUser, AdminReason, AssignRole, AuditChainStatus, AuditEvent,
+    AuditEventFilter, CreateLedgerAdjustment, LedgerAdjustment, LedgerAdjustmentFilter,
+    Transaction, User, UserProfile, UserSearch, UserWithAccount,
+};
+use crate::service::admin_service::AdminService;
+use crate::service::audit_service::AuditService;
+use crate::service::user_service::UserService;
+use crate::utilities::auth::AdminUser;
+use crate::utilities::client::ClientInfo;
+use axum::{
+    extract::{Extension, Json, Path, Query},
+    http::StatusCode,
+    response::Json as ResponseJson,
+};
+use sqlx::PgPool;
+use validator::Validate;
+
+#[axum::debug_handler]
+pub async fn create_user(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Json(payload): Json<AdminCreateUser>,
+) -> Result<ResponseJson<ApiResponse<UserWithAccount>>, (StatusCode, String)> {
+    tracing::info!("Admin {} creating new User", admin.user_id());
+
+    let service = UserService::new(&pool);
+
+    match service
+        .admin_create_user(admin.user_id(), &client, payload)
+        .await
+    {
+        Ok(account) => Ok(ResponseJson(ApiResponse::success(
+            account,
+            "User created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn assign_user_role(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(user_id): Path<String>,
+    Json(payload): Json<AssignRole>,
+) -> Result<ResponseJson<ApiResponse<User>>, (StatusCode, String)> {
+    tracing::info!(
+        "Admin {} assigning role {} to User {}",
+        admin.user_id(),
+        payload.role_id,
+        user_id
+    );
+
+    let service = UserService::new(&pool);
+
+    match service
+        .assign_role(admin.user_id(), &client, &user_id, payload)
+        .await
+    {
+        Ok(user) => Ok(ResponseJson(ApiResponse::success(
+            user,
+            "User role updated successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn search_users(
+    Extension(pool): Extension<PgPool>,
+    _admin: AdminUser,
+    Query(search): Query<UserSearch>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<User>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = AdminService::new(&pool);
+
+    match service.search_users(search.q.as_deref(), &filter).await {
+        Ok((users, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            users,
+            PaginationMeta::from_filter(&filter, total),
+            "Users retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_user(
+    Extension(pool): Extension<PgPool>,
+    _admin: AdminUser,
+    Path(user_id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<UserProfile>>, (StatusCode, String)> {
+    let service = AdminService::new(&pool);
+
+    match service.get_user(&user_id).await {
+        Ok(profile) => Ok(ResponseJson(ApiResponse::success(
+            profile,
+            "User retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_user_accounts(
+    Extension(pool): Extension<PgPool>,
+    _admin: AdminUser,
+    Path(user_id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<Vec<Account>>>, (StatusCode, String)> {
+    let service = AdminService::new(&pool);
+
+    match service.list_accounts(&user_id).await {
+        Ok(accounts) => Ok(ResponseJson(ApiResponse::success(
+            accounts,
+            "Accounts retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_user_transactions(
+    Extension(pool): Extension<PgPool>,
+    _admin: AdminUser,
+    Path(user_id): Path<String>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<Transaction>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = AdminService::new(&pool);
+
+    match service.list_transactions(&user_id, &filter).await {
+        Ok((transactions, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            transactions,
+            PaginationMeta::from_filter(&filter, total),
+            "Transactions retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn freeze_user(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(user_id): Path<String>,
+    Json(payload): Json<AdminReason>,
+) -> Result<ResponseJson<ApiResponse<User>>, (StatusCode, String)> {
+    tracing::info!("Admin {} freezing User {}", admin.user_id(), user_id);
+
+    let service = AdminService::new(&pool);
+
+    match service
+        .freeze_user(admin.user_id(), &client, &user_id, payload)
+        .await
+    {
+        Ok(user) => Ok(ResponseJson(ApiResponse::success(
+            user,
+            "User frozen successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn unfreeze_user(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(user_id): Path<String>,
+    Json(payload): Json<AdminReason>,
+) -> Result<ResponseJson<ApiResponse<User>>, (StatusCode, String)> {
+    tracing::info!("Admin {} unfreezing User {}", admin.user_id(), user_id);
+
+    let service = AdminService::new(&pool);
+
+    match service
+        .unfreeze_user(admin.user_id(), &client, &user_id, payload)
+        .await
+    {
+        Ok(user) => Ok(ResponseJson(ApiResponse::success(
+            user,
+            "User unfrozen successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn freeze_account(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(account_id): Path<String>,
+    Json(payload): Json<AdminReason>,
+) -> Result<ResponseJson<ApiResponse<Account>>, (StatusCode, String)> {
+    tracing::info!("Admin {} freezing account {}", admin.user_id(), account_id);
+
+    let service = AdminService::new(&pool);
+
+    match service
+        .freeze_account(admin.user_id(), &client, &account_id, payload)
+        .await
+    {
+        Ok(account) => Ok(ResponseJson(ApiResponse::success(
+            account,
+            "Account frozen successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn unfreeze_account(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(account_id): Path<String>,
+    Json(payload): Json<AdminReason>,
+) -> Result<ResponseJson<ApiResponse<Account>>, (StatusCode, String)> {
+    tracing::info!(
+        "Admin {} unfreezing account {}",
+        admin.user_id(),
+        account_id
+    );
+
+    let service = AdminService::new(&pool);
+
+    match service
+        .unfreeze_account(admin.user_id(), &client, &account_id, payload)
+        .await
+    {
+        Ok(account) => Ok(ResponseJson(ApiResponse::success(
+            account,
+            "Account unfrozen successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_adjustments(
+    Extension(pool): Extension<PgPool>,
+    _admin: AdminUser,
+    Query(adjustment_filter): Query<LedgerAdjustmentFilter>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<LedgerAdjustment>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = AdminService::new(&pool);
+
+    match service
+        .list_adjustments(adjustment_filter.status.as_deref(), &filter)
+        .await
+    {
+        Ok((adjustments, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            adjustments,
+            PaginationMeta::from_filter(&filter, total),
+            "Ledger adjustments retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn request_adjustment(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Json(payload): Json<CreateLedgerAdjustment>,
+) -> Result<ResponseJson<ApiResponse<LedgerAdjustment>>, (StatusCode, String)> {
+    tracing::info!(
+        "Admin {} requesting an adjustment of {} msat to account {}",
+        admin.user_id(),
+        payload.amount_msat,
+        payload.account_id
+    );
+
+    let service = AdminService::new(&pool);
+
+    match service
+        .request_adjustment(admin.user_id(), &client, payload)
+        .await
+    {
+        Ok(adjustment) => Ok(ResponseJson(ApiResponse::success(
+            adjustment,
+            "Ledger adjustment awaiting approval",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_adjustment(
+    Extension(pool): Extension<PgPool>,
+    _admin: AdminUser,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<LedgerAdjustment>>, (StatusCode, String)> {
+    let service = AdminService::new(&pool);
+
+    match service.get_adjustment(&id).await {
+        Ok(adjustment) => Ok(ResponseJson(ApiResponse::success(
+            adjustment,
+            "Ledger adjustment retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn approve_adjustment(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<LedgerAdjustment>>, (StatusCode, String)> {
+    tracing::info!(
+        "Admin {} approving ledger adjustment {}",
+        admin.user_id(),
+        id
+    );
+
+    let service = AdminService::new(&pool);
+
+    match service
+        .approve_adjustment(admin.user_id(), &client, &id)
+        .await
+    {
+        Ok(adjustment) => Ok(ResponseJson(ApiResponse::success(
+            adjustment,
+            "Ledger adjustment applied successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn reject_adjustment(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<LedgerAdjustment>>, (StatusCode, String)> {
+    tracing::info!(
+        "Admin {} rejecting ledger adjustment {}",
+        admin.user_id(),
+        id
+    );
+
+    let service = AdminService::new(&pool);
+
+    match service
+        .reject_adjustment(admin.user_id(), &client, &id)
+        .await
+    {
+        Ok(adjustment) => Ok(ResponseJson(ApiResponse::success(
+            adjustment,
+            "Ledger adjustment rejected",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_audit_events(
+    Extension(pool): Extension<PgPool>,
+    _admin: AdminUser,
+    Query(audit_filter): Query<AuditEventFilter>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<AuditEvent>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = AuditService::new(&pool);
+
+    match service.list_events(&audit_filter, &filter).await {
+        Ok((events, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            events,
+            PaginationMeta::from_filter(&filter, total),
+            "Audit events retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn verify_audit_chain(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+) -> Result<ResponseJson<ApiResponse<AuditChainStatus>>, (StatusCode, String)> {
+    tracing::info!("Admin {} verifying the audit log", admin.user_id());
+
+    let service = AuditService::new(&pool);
+
+    match service.verify_chain().await {
+        Ok(status) => {
+            if !status.valid {
+                tracing::error!(
+                    "Audit log hash chain broken at event {:?}",
+                    status.first_invalid_event_id
+                );
+            }
+            Ok(ResponseJson(ApiResponse::success(
+                status,
+                "Audit log verified",
+            )))
+        }
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
@@ -0,0 +1,5 @@
+// Module for admin-only API endpoints.
+// Every handler here requires the caller to hold the configured admin role
+
+pub mod handlers;
+pub mod routes;
@@ -0,0 +1,35 @@
+//! Defines the HTTP routes for admin operations.
+
+use super::handlers::{
+    approve_adjustment, assign_user_role, create_user, freeze_account, freeze_user, get_adjustment,
+    get_user, list_adjustments, list_audit_events, list_user_accounts, list_user_transactions,
+    reject_adjustment, request_adjustment, search_users, unfreeze_account, unfreeze_user,
+    verify_audit_chain,
+};
+
+use axum::{
+    Router,
+    routing::{get, patch, post},
+};
+
+pub async fn admin_router() -> Router {
+    Router::new()
+        .route("/users", get(search_users).post(create_user))
+        .route("/users/{id}", get(get_user))
+        .route("/users/{id}/role", patch(assign_user_role))
+        .route("/users/{id}/accounts", get(list_user_accounts))
+        .route("/users/{id}/transactions", get(list_user_transactions))
+        .route("/users/{id}/freeze", post(freeze_user))
+        .route("/users/{id}/unfreeze", post(unfreeze_user))
+        .route("/accounts/{id}/freeze", post(freeze_account))
+        .route("/accounts/{id}/unfreeze", post(unfreeze_account))
+        .route(
+            "/adjustments",
+            get(list_adjustments).post(request_adjustment),
+        )
+        .route("/adjustments/{id}", get(get_adjustment))
+        .route("/adjustments/{id}/approve", post(approve_adjustment))
+        .route("/adjustments/{id}/reject", post(reject_adjustment))
+        .route("/audit", get(list_audit_events))
+        .route("/audit/verify", get(verify_audit_chain))
+}
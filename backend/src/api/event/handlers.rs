@@ -0,0 +1,80 @@
+// API Route handler for event stream related Endpoints
+use crate::common::common::service_error_to_http;
+use crate::db::models::{AccountEvent, ApiKeyScope};
+use crate::service::event_service::EventBus;
+use crate::utilities::auth::StreamUser;
+use axum::{
+    extract::{
+        Extension,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    response::{
+        Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures::{Stream, StreamExt};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn stream_events(
+    StreamUser { auth }: StreamUser,
+    Extension(bus): Extension<Arc<EventBus>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    auth.require_scope(ApiKeyScope::PaymentsRead)
+        .map_err(service_error_to_http)?;
+
+    tracing::info!("User {} opened an event stream", auth.user_id());
+
+    let events = bus.subscribe(auth.user_id().to_string()).map(|event| {
+        Ok(Event::default()
+            .id(&event.id)
+            .event(&event.event_type)
+            .data(to_json(&event)))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[axum::debug_handler]
+pub async fn stream_events_ws(
+    StreamUser { auth }: StreamUser,
+    Extension(bus): Extension<Arc<EventBus>>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, String)> {
+    auth.require_scope(ApiKeyScope::PaymentsRead)
+        .map_err(service_error_to_http)?;
+
+    tracing::info!("User {} opened an event socket", auth.user_id());
+
+    let events = bus.subscribe(auth.user_id().to_string());
+
+    Ok(ws.on_upgrade(move |socket| forward_events(socket, events)))
+}
+
+/// Sends each event to the socket until either side goes away. Anything
+/// the client sends is ignored.
+async fn forward_events(mut socket: WebSocket, events: impl Stream<Item = Arc<AccountEvent>>) {
+    let mut events = std::pin::pin!(events);
+
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else { break };
+                if socket.send(Message::Text(to_json(&event).into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+fn to_json(event: &AccountEvent) -> String {
+    serde_json::to_string(event).unwrap_or_default()
+}
@@ -0,0 +1,4 @@
+// Module for event stream endpoints: Server-Sent Events and WebSocket.
+
+pub mod handlers;
+pub mod routes;
@@ -0,0 +1,11 @@
+//! Defines the HTTP routes for streaming account events as they happen.
+
+use super::handlers::{stream_events, stream_events_ws};
+
+use axum::{Router, routing::get};
+
+pub async fn event_router() -> Router {
+    Router::new()
+        .route("/", get(stream_events))
+        .route("/ws", get(stream_events_ws))
+}
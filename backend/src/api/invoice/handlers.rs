@@ -0,0 +1,185 @@
+// API Route handler for invoice related Endpoints
+use crate::common::common::ApiResponse;
+use crate::common::common::{PaginationFilter, PaginationMeta};
+use crate::common::common::{service_error_to_http, validation_error_response};
+use crate::db::models::{
+    CreateHoldInvoice, CreateInvoice, CreateOffer, Invoice, Offer, SettleHoldInvoice,
+};
+use crate::service::invoice_service::InvoiceService;
+use crate::service::node_service::LightningClient;
+use crate::service::offer_service::OfferService;
+use crate::service::rate_provider::RateStore;
+use crate::utilities::auth::AuthUser;
+use axum::{
+    extract::{Extension, Json, Path, Query},
+    http::StatusCode,
+    response::Json as ResponseJson,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use validator::Validate;
+
+#[axum::debug_handler]
+pub async fn create_invoice(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Extension(rates): Extension<Arc<RateStore>>,
+    Json(payload): Json<CreateInvoice>,
+) -> Result<ResponseJson<ApiResponse<Invoice>>, (StatusCode, String)> {
+    tracing::info!("User {} creating invoice", auth.user_id());
+
+    let service = InvoiceService::new(&pool, lightning.as_ref());
+
+    match service.create_invoice(&auth, payload, &rates).await {
+        Ok(invoice) => Ok(ResponseJson(ApiResponse::success(
+            invoice,
+            "Invoice created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_invoices(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<Invoice>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = InvoiceService::new(&pool, lightning.as_ref());
+
+    match service.list_invoices(&auth, &filter).await {
+        Ok((invoices, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            invoices,
+            PaginationMeta::from_filter(&filter, total),
+            "Invoices retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn create_hold_invoice(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Json(payload): Json<CreateHoldInvoice>,
+) -> Result<ResponseJson<ApiResponse<Invoice>>, (StatusCode, String)> {
+    tracing::info!("User {} creating hold invoice", auth.user_id());
+
+    let service = InvoiceService::new(&pool, lightning.as_ref());
+
+    match service.create_hold_invoice(&auth, payload).await {
+        Ok(invoice) => Ok(ResponseJson(ApiResponse::success(
+            invoice,
+            "Hold invoice created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn settle_hold_invoice(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Path(id): Path<String>,
+    payload: Option<Json<SettleHoldInvoice>>,
+) -> Result<ResponseJson<ApiResponse<Invoice>>, (StatusCode, String)> {
+    tracing::info!("User {} settling hold invoice {}", auth.user_id(), id);
+
+    let service = InvoiceService::new(&pool, lightning.as_ref());
+
+    // The body is only needed when the caller holds the preimage
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+
+    match service.settle_hold_invoice(&auth, &id, payload).await {
+        Ok(invoice) => Ok(ResponseJson(ApiResponse::success(
+            invoice,
+            "Hold invoice settled successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn cancel_hold_invoice(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<Invoice>>, (StatusCode, String)> {
+    tracing::info!("User {} canceling hold invoice {}", auth.user_id(), id);
+
+    let service = InvoiceService::new(&pool, lightning.as_ref());
+
+    match service.cancel_hold_invoice(&auth, &id).await {
+        Ok(invoice) => Ok(ResponseJson(ApiResponse::success(
+            invoice,
+            "Hold invoice canceled successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn create_offer(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Json(payload): Json<CreateOffer>,
+) -> Result<ResponseJson<ApiResponse<Offer>>, (StatusCode, String)> {
+    tracing::info!("User {} creating offer", auth.user_id());
+
+    let service = OfferService::new(&pool, lightning.as_ref());
+
+    match service.create_offer(&auth, payload).await {
+        Ok(offer) => Ok(ResponseJson(ApiResponse::success(
+            offer,
+            "Offer created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_offers(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+) -> Result<ResponseJson<ApiResponse<Vec<Offer>>>, (StatusCode, String)> {
+    let service = OfferService::new(&pool, lightning.as_ref());
+
+    match service.list_offers(&auth).await {
+        Ok(offers) => Ok(ResponseJson(ApiResponse::success(
+            offers,
+            "Offers retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn disable_offer(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<()>>, (StatusCode, String)> {
+    tracing::info!("User {} disabling offer {}", auth.user_id(), id);
+
+    let service = OfferService::new(&pool, lightning.as_ref());
+
+    match service.disable_offer(&auth, &id).await {
+        Ok(()) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "Offer disabled successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
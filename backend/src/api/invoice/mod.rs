@@ -0,0 +1,4 @@
+// Module for receiving endpoints: invoices and offers.
+
+pub mod handlers;
+pub mod routes;
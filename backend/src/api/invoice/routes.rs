@@ -0,0 +1,21 @@
+//! Defines the HTTP routes for receiving payments.
+
+use super::handlers::{
+    cancel_hold_invoice, create_hold_invoice, create_invoice, create_offer, disable_offer,
+    list_invoices, list_offers, settle_hold_invoice,
+};
+
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
+
+pub async fn invoice_router() -> Router {
+    Router::new()
+        .route("/invoices", get(list_invoices).post(create_invoice))
+        .route("/hold_invoices", post(create_hold_invoice))
+        .route("/hold_invoices/{id}/settle", post(settle_hold_invoice))
+        .route("/hold_invoices/{id}/cancel", post(cancel_hold_invoice))
+        .route("/offers", get(list_offers).post(create_offer))
+        .route("/offers/{id}", delete(disable_offer))
+}
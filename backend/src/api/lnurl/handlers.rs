@@ -0,0 +1,130 @@
+// API Route handler for LNURL related Endpoints
+use crate::common::common::service_error_to_http;
+use crate::db::models::{
+    LnurlAuthCallback, LnurlPayCallback, LnurlPayInvoice, LnurlPayRequest, LnurlStatus,
+    LnurlVerify, LnurlWithdrawCallback, LnurlWithdrawRequest,
+};
+use crate::errors::ServiceError;
+use crate::service::lnurl_auth_service::LnurlAuthService;
+use crate::service::lnurl_service::LnurlService;
+use crate::service::node_service::LightningClient;
+use crate::service::withdraw_service::WithdrawService;
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::Json as ResponseJson,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Wallets expect `{"status": "ERROR", "reason": ...}` rather than our API envelope
+fn lnurl_error(error: ServiceError) -> (StatusCode, ResponseJson<LnurlStatus>) {
+    let (status, reason) = service_error_to_http(error);
+    (
+        status,
+        ResponseJson(LnurlStatus {
+            status: "ERROR".to_string(),
+            reason: Some(reason),
+        }),
+    )
+}
+
+#[axum::debug_handler]
+pub async fn pay_request(
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Path(username): Path<String>,
+) -> Result<ResponseJson<LnurlPayRequest>, (StatusCode, ResponseJson<LnurlStatus>)> {
+    tracing::info!("Serving LNURL-pay request for {}", username);
+
+    let service = LnurlService::new(&pool, lightning.as_ref());
+
+    match service.pay_request(&username).await {
+        Ok(pay_request) => Ok(ResponseJson(pay_request)),
+        Err(error) => Err(lnurl_error(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn pay_callback(
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Path(username): Path<String>,
+    Query(callback): Query<LnurlPayCallback>,
+) -> Result<ResponseJson<LnurlPayInvoice>, (StatusCode, ResponseJson<LnurlStatus>)> {
+    tracing::info!("Issuing LNURL-pay invoice for {}", username);
+
+    let service = LnurlService::new(&pool, lightning.as_ref());
+
+    match service.pay_callback(&username, callback).await {
+        Ok(invoice) => Ok(ResponseJson(invoice)),
+        Err(error) => Err(lnurl_error(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn verify_payment(
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Path(payment_hash): Path<String>,
+) -> Result<ResponseJson<LnurlVerify>, (StatusCode, ResponseJson<LnurlStatus>)> {
+    let service = LnurlService::new(&pool, lightning.as_ref());
+
+    match service.verify(&payment_hash).await {
+        Ok(verify) => Ok(ResponseJson(verify)),
+        Err(error) => Err(lnurl_error(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn withdraw_request(
+    Extension(pool): Extension<PgPool>,
+    Path(k1): Path<String>,
+) -> Result<ResponseJson<LnurlWithdrawRequest>, (StatusCode, ResponseJson<LnurlStatus>)> {
+    let service = WithdrawService::new(&pool);
+
+    match service.withdraw_request(&k1).await {
+        Ok(withdraw_request) => Ok(ResponseJson(withdraw_request)),
+        Err(error) => Err(lnurl_error(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn withdraw_callback(
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Query(callback): Query<LnurlWithdrawCallback>,
+) -> Result<ResponseJson<LnurlStatus>, (StatusCode, ResponseJson<LnurlStatus>)> {
+    tracing::info!("Claiming LNURL-withdraw link");
+
+    let service = WithdrawService::new(&pool);
+
+    match service
+        .claim(lightning.as_ref(), &callback.k1, &callback.pr)
+        .await
+    {
+        Ok(()) => Ok(ResponseJson(LnurlStatus {
+            status: "OK".to_string(),
+            reason: None,
+        })),
+        Err(error) => Err(lnurl_error(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn auth_callback(
+    Extension(pool): Extension<PgPool>,
+    Query(callback): Query<LnurlAuthCallback>,
+) -> Result<ResponseJson<LnurlStatus>, (StatusCode, ResponseJson<LnurlStatus>)> {
+    tracing::info!("Verifying LNURL-auth signature");
+
+    let service = LnurlAuthService::new(&pool);
+
+    match service.callback(callback).await {
+        Ok(()) => Ok(ResponseJson(LnurlStatus {
+            status: "OK".to_string(),
+            reason: None,
+        })),
+        Err(error) => Err(lnurl_error(error)),
+    }
+}
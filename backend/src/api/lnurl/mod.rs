@@ -0,0 +1,5 @@
+// Module for the public LNURL endpoints.
+// Wallets call these without authentication, and errors use the LNURL status envelope
+
+pub mod handlers;
+pub mod routes;
@@ -0,0 +1,17 @@
+//! Defines the HTTP routes for Lightning Addresses and LNURL.
+
+use super::handlers::{
+    auth_callback, pay_callback, pay_request, verify_payment, withdraw_callback, withdraw_request,
+};
+
+use axum::{Router, routing::get};
+
+pub async fn lnurl_router() -> Router {
+    Router::new()
+        .route("/.well-known/lnurlp/{username}", get(pay_request))
+        .route("/api/lnurl/pay/{username}/callback", get(pay_callback))
+        .route("/api/lnurl/verify/{payment_hash}", get(verify_payment))
+        .route("/api/lnurl/withdraw/callback", get(withdraw_callback))
+        .route("/api/lnurl/withdraw/{k1}", get(withdraw_request))
+        .route("/api/lnurl/auth", get(auth_callback))
+}
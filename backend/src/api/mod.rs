@@ -1,4 +1,13 @@
 // Central module for organizing the application's main API endpoints.
 
+pub mod admin;
+pub mod event;
+pub mod invoice;
+pub mod lnurl;
+pub mod onchain;
+pub mod payment;
 pub mod role;
+pub mod swap;
 pub mod user;
+pub mod webhook;
+pub mod withdraw;
@@ -0,0 +1,75 @@
+// API Route handler for on-chain related Endpoints
+use crate::common::common::ApiResponse;
+use crate::common::common::{PaginationFilter, PaginationMeta};
+use crate::common::common::{service_error_to_http, validation_error_response};
+use crate::db::models::{Deposit, DepositAddress};
+use crate::service::deposit_service::DepositService;
+use crate::service::node_service::LightningClient;
+use crate::utilities::auth::AuthUser;
+use axum::{
+    extract::{Extension, Query},
+    http::StatusCode,
+    response::Json as ResponseJson,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use validator::Validate;
+
+#[axum::debug_handler]
+pub async fn create_deposit_address(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+) -> Result<ResponseJson<ApiResponse<DepositAddress>>, (StatusCode, String)> {
+    tracing::info!("User {} creating deposit address", auth.user_id());
+
+    let service = DepositService::new(&pool, lightning.as_ref());
+
+    match service.create_deposit_address(&auth).await {
+        Ok(deposit_address) => Ok(ResponseJson(ApiResponse::success(
+            deposit_address,
+            "Deposit address created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_deposit_addresses(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+) -> Result<ResponseJson<ApiResponse<Vec<DepositAddress>>>, (StatusCode, String)> {
+    let service = DepositService::new(&pool, lightning.as_ref());
+
+    match service.list_deposit_addresses(&auth).await {
+        Ok(deposit_addresses) => Ok(ResponseJson(ApiResponse::success(
+            deposit_addresses,
+            "Deposit addresses retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_deposits(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<Deposit>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = DepositService::new(&pool, lightning.as_ref());
+
+    match service.list_deposits(&auth, &filter).await {
+        Ok((deposits, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            deposits,
+            PaginationMeta::from_filter(&filter, total),
+            "Deposits retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
@@ -0,0 +1,4 @@
+// Module for on-chain endpoints: deposit addresses and deposits.
+
+pub mod handlers;
+pub mod routes;
@@ -0,0 +1,14 @@
+//! Defines the HTTP routes for on-chain deposits.
+
+use super::handlers::{create_deposit_address, list_deposit_addresses, list_deposits};
+
+use axum::{Router, routing::get};
+
+pub async fn onchain_router() -> Router {
+    Router::new()
+        .route(
+            "/addresses",
+            get(list_deposit_addresses).post(create_deposit_address),
+        )
+        .route("/deposits", get(list_deposits))
+}
@@ -0,0 +1,169 @@
+// API Route handler for payment related Endpoints
+use crate::common::common::ApiResponse;
+use crate::common::common::{PaginationFilter, PaginationMeta};
+use crate::common::common::{service_error_to_http, validation_error_response};
+use crate::db::models::{
+    CreateWithdrawLink, DecodePayment, DecodedPayment, Keysend, PayInvoice, PaymentReceipt,
+    TransactionWithFiat, WithdrawLinkWithLnurl,
+};
+use crate::service::fiat_service::FiatService;
+use crate::service::node_service::LightningClient;
+use crate::service::payment_service::PaymentService;
+use crate::service::withdraw_service::WithdrawService;
+use crate::utilities::auth::AuthUser;
+use axum::{
+    extract::{Extension, Json, Path, Query},
+    http::StatusCode,
+    response::Json as ResponseJson,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use validator::Validate;
+
+#[axum::debug_handler]
+pub async fn pay(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Json(payload): Json<PayInvoice>,
+) -> Result<ResponseJson<ApiResponse<PaymentReceipt>>, (StatusCode, String)> {
+    tracing::info!("User {} paying {}", auth.user_id(), payload.destination);
+
+    let service = PaymentService::new(&pool, lightning.as_ref());
+
+    match service.pay(&auth, payload).await {
+        Ok(receipt) => {
+            let message = if receipt.status == "pending" {
+                "Payment is in flight"
+            } else {
+                "Payment sent successfully"
+            };
+            Ok(ResponseJson(ApiResponse::success(receipt, message)))
+        }
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn decode(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Json(payload): Json<DecodePayment>,
+) -> Result<ResponseJson<ApiResponse<DecodedPayment>>, (StatusCode, String)> {
+    tracing::info!("User {} decoding a payment destination", auth.user_id());
+
+    let service = PaymentService::new(&pool, lightning.as_ref());
+
+    match service.decode(&auth, payload).await {
+        Ok(decoded) => Ok(ResponseJson(ApiResponse::success(
+            decoded,
+            "Payment decoded successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn keysend(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Json(payload): Json<Keysend>,
+) -> Result<ResponseJson<ApiResponse<PaymentReceipt>>, (StatusCode, String)> {
+    tracing::info!(
+        "User {} keysending to {}",
+        auth.user_id(),
+        payload.destination
+    );
+
+    let service = PaymentService::new(&pool, lightning.as_ref());
+
+    match service.keysend(&auth, payload).await {
+        Ok(receipt) => {
+            let message = if receipt.status == "pending" {
+                "Payment is in flight"
+            } else {
+                "Payment sent successfully"
+            };
+            Ok(ResponseJson(ApiResponse::success(receipt, message)))
+        }
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn create_withdraw_link(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Json(payload): Json<CreateWithdrawLink>,
+) -> Result<ResponseJson<ApiResponse<WithdrawLinkWithLnurl>>, (StatusCode, String)> {
+    tracing::info!("User {} creating withdraw link", auth.user_id());
+
+    let service = WithdrawService::new(&pool);
+
+    match service.create_withdraw_link(&auth, payload).await {
+        Ok(withdraw_link) => Ok(ResponseJson(ApiResponse::success(
+            withdraw_link,
+            "Withdraw link created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_withdraw_links(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+) -> Result<ResponseJson<ApiResponse<Vec<WithdrawLinkWithLnurl>>>, (StatusCode, String)> {
+    let service = WithdrawService::new(&pool);
+
+    match service.list_withdraw_links(&auth).await {
+        Ok(withdraw_links) => Ok(ResponseJson(ApiResponse::success(
+            withdraw_links,
+            "Withdraw links retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn cancel_withdraw_link(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<()>>, (StatusCode, String)> {
+    tracing::info!("User {} cancelling withdraw link {}", auth.user_id(), id);
+
+    let service = WithdrawService::new(&pool);
+
+    match service.cancel_withdraw_link(&auth, &id).await {
+        Ok(()) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "Withdraw link cancelled successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_transactions(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<TransactionWithFiat>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = FiatService::new(&pool);
+
+    match service.list_transactions(&auth, &filter).await {
+        Ok((transactions, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            transactions,
+            PaginationMeta::from_filter(&filter, total),
+            "Transactions retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
@@ -0,0 +1,4 @@
+// Module for outgoing payment endpoints.
+
+pub mod handlers;
+pub mod routes;
@@ -0,0 +1,24 @@
+//! Defines the HTTP routes for payments.
+
+use super::handlers::{
+    cancel_withdraw_link, create_withdraw_link, decode, keysend, list_transactions,
+    list_withdraw_links, pay,
+};
+
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
+
+pub async fn payment_router() -> Router {
+    Router::new()
+        .route("/pay", post(pay))
+        .route("/decode", post(decode))
+        .route("/keysend", post(keysend))
+        .route("/transactions", get(list_transactions))
+        .route(
+            "/withdraw_links",
+            get(list_withdraw_links).post(create_withdraw_link),
+        )
+        .route("/withdraw_links/{id}", delete(cancel_withdraw_link))
+}
@@ -1,25 +1,31 @@
 // API Route handler for user related Endpoints
 use crate::common::common::ApiResponse;
-use crate::common::common::service_error_to_http;
-use crate::db::models::{CreateRole, NewRole};
+use crate::common::common::{PaginationFilter, PaginationMeta};
+use crate::common::common::{service_error_to_http, validation_error_response};
+use crate::db::models::{CreateRole, NewRole, Role, RoleFeeCeiling, UpdateRole};
 use crate::service::role_service::RoleService;
+use crate::utilities::auth::AdminUser;
+use crate::utilities::client::ClientInfo;
 use axum::{
-    extract::{Extension, Json},
+    extract::{Extension, Json, Path, Query},
     http::StatusCode,
     response::Json as ResponseJson,
 };
 use sqlx::PgPool;
+use validator::Validate;
 
 #[axum::debug_handler]
 pub async fn create_role(
     Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
     Json(payload): Json<CreateRole>,
 ) -> Result<ResponseJson<ApiResponse<NewRole>>, (StatusCode, String)> {
-    tracing::info!("Creating new Role");
+    tracing::info!("Admin {} creating new Role", admin.user_id());
 
     let service = RoleService::new(&pool);
 
-    match service.create_role(payload).await {
+    match service.create_role(admin.user_id(), &client, payload).await {
         Ok(role) => {
             tracing::debug!("Role created successfully: {:?}", role);
             Ok(ResponseJson(ApiResponse::success(
@@ -30,3 +36,165 @@ pub async fn create_role(
         Err(error) => Err(service_error_to_http(error)),
     }
 }
+
+#[axum::debug_handler]
+pub async fn list_roles(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<Role>>>, (StatusCode, String)> {
+    tracing::info!("Admin {} listing Roles", admin.user_id());
+
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = RoleService::new(&pool);
+
+    match service.list_roles(&filter).await {
+        Ok((roles, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            roles,
+            PaginationMeta::from_filter(&filter, total),
+            "Roles retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_role(
+    Extension(pool): Extension<PgPool>,
+    _admin: AdminUser,
+    Path(role_id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<Role>>, (StatusCode, String)> {
+    let service = RoleService::new(&pool);
+
+    match service.get_role(&role_id).await {
+        Ok(role) => Ok(ResponseJson(ApiResponse::success(
+            role,
+            "Role retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn update_role(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(role_id): Path<String>,
+    Json(payload): Json<UpdateRole>,
+) -> Result<ResponseJson<ApiResponse<Role>>, (StatusCode, String)> {
+    tracing::info!("Admin {} updating Role {}", admin.user_id(), role_id);
+
+    let service = RoleService::new(&pool);
+
+    match service
+        .update_role(admin.user_id(), &client, &role_id, payload)
+        .await
+    {
+        Ok(role) => Ok(ResponseJson(ApiResponse::success(
+            role,
+            "Role updated successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn set_fee_ceiling(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(role_id): Path<String>,
+    Json(payload): Json<RoleFeeCeiling>,
+) -> Result<ResponseJson<ApiResponse<Role>>, (StatusCode, String)> {
+    tracing::info!(
+        "Admin {} setting fee ceiling of Role {}",
+        admin.user_id(),
+        role_id
+    );
+
+    let service = RoleService::new(&pool);
+
+    match service
+        .set_fee_ceiling(admin.user_id(), &client, &role_id, payload)
+        .await
+    {
+        Ok(role) => Ok(ResponseJson(ApiResponse::success(
+            role,
+            "Role fee ceiling updated successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn deactivate_role(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(role_id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<Role>>, (StatusCode, String)> {
+    tracing::info!("Admin {} deactivating Role {}", admin.user_id(), role_id);
+
+    let service = RoleService::new(&pool);
+
+    match service
+        .deactivate_role(admin.user_id(), &client, &role_id)
+        .await
+    {
+        Ok(role) => Ok(ResponseJson(ApiResponse::success(
+            role,
+            "Role deactivated successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn reactivate_role(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(role_id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<Role>>, (StatusCode, String)> {
+    tracing::info!("Admin {} reactivating Role {}", admin.user_id(), role_id);
+
+    let service = RoleService::new(&pool);
+
+    match service
+        .reactivate_role(admin.user_id(), &client, &role_id)
+        .await
+    {
+        Ok(role) => Ok(ResponseJson(ApiResponse::success(
+            role,
+            "Role reactivated successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn delete_role(
+    Extension(pool): Extension<PgPool>,
+    admin: AdminUser,
+    client: ClientInfo,
+    Path(role_id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<Role>>, (StatusCode, String)> {
+    tracing::info!("Admin {} deleting Role {}", admin.user_id(), role_id);
+
+    let service = RoleService::new(&pool);
+
+    match service
+        .delete_role(admin.user_id(), &client, &role_id)
+        .await
+    {
+        Ok(role) => Ok(ResponseJson(ApiResponse::success(
+            role,
+            "Role deleted successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
@@ -1,9 +1,24 @@
 //! Defines the HTTP routes for role management.
 
-use super::handlers::create_role;
+use super::handlers::{
+    create_role, deactivate_role, delete_role, get_role, list_roles, reactivate_role,
+    set_fee_ceiling, update_role,
+};
 
-use axum::{Router, routing::post};
+use axum::{
+    Router,
+    routing::{get, post, put},
+};
 
 pub async fn role_router() -> Router {
-    Router::new().route("/new_role", post(create_role))
+    Router::new()
+        .route("/", get(list_roles))
+        .route("/new_role", post(create_role))
+        .route(
+            "/{id}",
+            get(get_role).patch(update_role).delete(delete_role),
+        )
+        .route("/{id}/deactivate", post(deactivate_role))
+        .route("/{id}/reactivate", post(reactivate_role))
+        .route("/{id}/fee_ceiling", put(set_fee_ceiling))
 }
@@ -0,0 +1,103 @@
+// API Route handler for swap related Endpoints
+use crate::common::common::ApiResponse;
+use crate::common::common::{PaginationFilter, PaginationMeta};
+use crate::common::common::{service_error_to_http, validation_error_response};
+use crate::db::models::{CreateReverseSwap, CreateSubmarineSwap, Swap};
+use crate::service::node_service::LightningClient;
+use crate::service::swap_provider::SwapProvider;
+use crate::service::swap_service::SwapService;
+use crate::utilities::auth::AuthUser;
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::Json as ResponseJson,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use validator::Validate;
+
+#[axum::debug_handler]
+pub async fn create_submarine_swap(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Extension(provider): Extension<Arc<dyn SwapProvider>>,
+    Json(payload): Json<CreateSubmarineSwap>,
+) -> Result<ResponseJson<ApiResponse<Swap>>, (StatusCode, String)> {
+    tracing::info!("User {} creating submarine swap", auth.user_id());
+
+    let service = SwapService::new(&pool, lightning.as_ref(), provider.as_ref());
+
+    match service.create_submarine_swap(&auth, payload).await {
+        Ok(swap) => Ok(ResponseJson(ApiResponse::success(
+            swap,
+            "Submarine swap created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn create_reverse_swap(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Extension(provider): Extension<Arc<dyn SwapProvider>>,
+    Json(payload): Json<CreateReverseSwap>,
+) -> Result<ResponseJson<ApiResponse<Swap>>, (StatusCode, String)> {
+    tracing::info!("User {} creating reverse swap", auth.user_id());
+
+    let service = SwapService::new(&pool, lightning.as_ref(), provider.as_ref());
+
+    match service.create_reverse_swap(&auth, payload).await {
+        Ok(swap) => Ok(ResponseJson(ApiResponse::success(
+            swap,
+            "Reverse swap created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_swaps(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Extension(provider): Extension<Arc<dyn SwapProvider>>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<Swap>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = SwapService::new(&pool, lightning.as_ref(), provider.as_ref());
+
+    match service.list_swaps(&auth, &filter).await {
+        Ok((swaps, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            swaps,
+            PaginationMeta::from_filter(&filter, total),
+            "Swaps retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_swap(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Extension(provider): Extension<Arc<dyn SwapProvider>>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<Swap>>, (StatusCode, String)> {
+    let service = SwapService::new(&pool, lightning.as_ref(), provider.as_ref());
+
+    match service.get_swap(&auth, &id).await {
+        Ok(swap) => Ok(ResponseJson(ApiResponse::success(
+            swap,
+            "Swap retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
@@ -0,0 +1,4 @@
+// Module for swap endpoints: submarine and reverse swaps.
+
+pub mod handlers;
+pub mod routes;
@@ -0,0 +1,16 @@
+//! Defines the HTTP routes for swaps between on-chain and Lightning funds.
+
+use super::handlers::{create_reverse_swap, create_submarine_swap, get_swap, list_swaps};
+
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+pub async fn swap_router() -> Router {
+    Router::new()
+        .route("/", get(list_swaps))
+        .route("/submarine", post(create_submarine_swap))
+        .route("/reverse", post(create_reverse_swap))
+        .route("/{id}", get(get_swap))
+}
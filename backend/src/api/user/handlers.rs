@@ -1,10 +1,20 @@
 // API Route handler for user related Endpoints
 use crate::common::common::ApiResponse;
 use crate::common::common::service_error_to_http;
-use crate::db::models::{CreateUser, LoginResponse, User, UserLogin, UserWithAccount};
+use crate::db::models::{
+    AccountBalance, ApiKey, ApiKeyScope, ChangePassword, ConfirmEmailChange, CreateApiKey,
+    CreateUser, LnurlAuthLogin, LnurlAuthToken, LoginResponse, NewApiKey, NotificationPreferences,
+    UpdateDisplayCurrency, UpdateNotificationPreferences, UpdateProfile, UserLogin, UserProfile,
+    UserWithAccount,
+};
+use crate::service::api_key_service::ApiKeyService;
+use crate::service::lnurl_auth_service::LnurlAuthService;
+use crate::service::notification_service::NotificationService;
 use crate::service::user_service::UserService;
+use crate::utilities::auth::AuthUser;
+use crate::utilities::client::ClientInfo;
 use axum::{
-    extract::{Extension, Json},
+    extract::{Extension, Json, Path},
     http::StatusCode,
     response::Json as ResponseJson,
 };
@@ -34,13 +44,14 @@ pub async fn create_user(
 #[axum::debug_handler]
 pub async fn user_login(
     Extension(pool): Extension<PgPool>,
+    client: ClientInfo,
     Json(payload): Json<UserLogin>,
 ) -> Result<ResponseJson<ApiResponse<LoginResponse>>, (StatusCode, String)> {
     tracing::info!("User Attempt Login");
 
     let service = UserService::new(&pool);
 
-    match service.login(payload).await {
+    match service.login(payload, &client).await {
         Ok(response) => {
             tracing::info!("Login successful");
             Ok(ResponseJson(ApiResponse::success(
@@ -51,3 +62,311 @@ pub async fn user_login(
         Err(error) => Err(service_error_to_http(error)),
     }
 }
+
+#[axum::debug_handler]
+pub async fn lnurl_auth_challenge(
+    Extension(pool): Extension<PgPool>,
+) -> Result<ResponseJson<ApiResponse<LnurlAuthLogin>>, (StatusCode, String)> {
+    let service = LnurlAuthService::new(&pool);
+
+    match service.login_challenge().await {
+        Ok(challenge) => Ok(ResponseJson(ApiResponse::success(
+            challenge,
+            "LNURL-auth challenge created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn lnurl_auth_token(
+    Extension(pool): Extension<PgPool>,
+    client: ClientInfo,
+    Json(payload): Json<LnurlAuthToken>,
+) -> Result<ResponseJson<ApiResponse<LoginResponse>>, (StatusCode, String)> {
+    let service = LnurlAuthService::new(&pool);
+
+    match service.redeem(payload, &client).await {
+        Ok(response) => {
+            tracing::info!("LNURL-auth login successful");
+            Ok(ResponseJson(ApiResponse::success(
+                response,
+                "Login successful",
+            )))
+        }
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn lnurl_auth_link(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+) -> Result<ResponseJson<ApiResponse<LnurlAuthLogin>>, (StatusCode, String)> {
+    tracing::info!("User {} linking a wallet", auth.user_id());
+
+    let service = LnurlAuthService::new(&pool);
+
+    match service.link_challenge(&auth).await {
+        Ok(challenge) => Ok(ResponseJson(ApiResponse::success(
+            challenge,
+            "LNURL-auth challenge created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_me(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+) -> Result<ResponseJson<ApiResponse<UserProfile>>, (StatusCode, String)> {
+    if let Err(error) = auth.require_scope(ApiKeyScope::AccountRead) {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = UserService::new(&pool);
+
+    match service.get_profile(auth.user_id()).await {
+        Ok(profile) => Ok(ResponseJson(ApiResponse::success(
+            profile,
+            "Profile retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn update_me(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+    Json(payload): Json<UpdateProfile>,
+) -> Result<ResponseJson<ApiResponse<UserProfile>>, (StatusCode, String)> {
+    tracing::info!("Updating profile of User {}", auth.user_id());
+
+    if let Err(error) = auth.require_session() {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = UserService::new(&pool);
+
+    match service.update_profile(auth.user_id(), payload).await {
+        Ok(profile) => Ok(ResponseJson(ApiResponse::success(
+            profile,
+            "Profile updated successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn set_display_currency(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateDisplayCurrency>,
+) -> Result<ResponseJson<ApiResponse<AccountBalance>>, (StatusCode, String)> {
+    tracing::info!(
+        "User {} showing account {} in {}",
+        auth.user_id(),
+        id,
+        payload.currency
+    );
+
+    if let Err(error) = auth.require_session() {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = UserService::new(&pool);
+
+    match service
+        .set_display_currency(auth.user_id(), &id, payload)
+        .await
+    {
+        Ok(account) => Ok(ResponseJson(ApiResponse::success(
+            account,
+            "Display currency updated successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_notification_preferences(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+) -> Result<ResponseJson<ApiResponse<NotificationPreferences>>, (StatusCode, String)> {
+    if let Err(error) = auth.require_scope(ApiKeyScope::AccountRead) {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = NotificationService::new(&pool);
+
+    match service.get_preferences(auth.user_id()).await {
+        Ok(preferences) => Ok(ResponseJson(ApiResponse::success(
+            preferences,
+            "Notification preferences retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn update_notification_preferences(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+    Json(payload): Json<UpdateNotificationPreferences>,
+) -> Result<ResponseJson<ApiResponse<NotificationPreferences>>, (StatusCode, String)> {
+    tracing::info!(
+        "Updating notification preferences of User {}",
+        auth.user_id()
+    );
+
+    if let Err(error) = auth.require_session() {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = NotificationService::new(&pool);
+
+    match service.update_preferences(auth.user_id(), payload).await {
+        Ok(preferences) => Ok(ResponseJson(ApiResponse::success(
+            preferences,
+            "Notification preferences updated successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn confirm_email(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+    Json(payload): Json<ConfirmEmailChange>,
+) -> Result<ResponseJson<ApiResponse<UserProfile>>, (StatusCode, String)> {
+    tracing::info!("Confirming email change of User {}", auth.user_id());
+
+    if let Err(error) = auth.require_session() {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = UserService::new(&pool);
+
+    match service.confirm_email_change(auth.user_id(), payload).await {
+        Ok(profile) => Ok(ResponseJson(ApiResponse::success(
+            profile,
+            "Email updated successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn change_password(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+    Json(payload): Json<ChangePassword>,
+) -> Result<ResponseJson<ApiResponse<()>>, (StatusCode, String)> {
+    tracing::info!("Changing password of User {}", auth.user_id());
+
+    if let Err(error) = auth.require_session() {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = UserService::new(&pool);
+
+    match service.change_password(auth.user_id(), payload).await {
+        Ok(()) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "Password changed successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn delete_me(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+) -> Result<ResponseJson<ApiResponse<()>>, (StatusCode, String)> {
+    tracing::info!("Deleting User {}", auth.user_id());
+
+    if let Err(error) = auth.require_session() {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = UserService::new(&pool);
+
+    match service.delete_user(auth.user_id()).await {
+        Ok(()) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "User deleted successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn create_api_key(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+    Json(payload): Json<CreateApiKey>,
+) -> Result<ResponseJson<ApiResponse<NewApiKey>>, (StatusCode, String)> {
+    tracing::info!("Creating API key for User {}", auth.user_id());
+
+    if let Err(error) = auth.require_session() {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = ApiKeyService::new(&pool);
+
+    match service.create_api_key(auth.user_id(), payload).await {
+        Ok(api_key) => Ok(ResponseJson(ApiResponse::success(
+            api_key,
+            "API key created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_api_keys(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+) -> Result<ResponseJson<ApiResponse<Vec<ApiKey>>>, (StatusCode, String)> {
+    if let Err(error) = auth.require_session() {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = ApiKeyService::new(&pool);
+
+    match service.list_api_keys(auth.user_id()).await {
+        Ok(api_keys) => Ok(ResponseJson(ApiResponse::success(
+            api_keys,
+            "API keys retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn revoke_api_key(
+    Extension(pool): Extension<PgPool>,
+    auth: AuthUser,
+    Path(api_key_id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<()>>, (StatusCode, String)> {
+    tracing::info!("Revoking API key {} of User {}", api_key_id, auth.user_id());
+
+    if let Err(error) = auth.require_session() {
+        return Err(service_error_to_http(error));
+    }
+
+    let service = ApiKeyService::new(&pool);
+
+    match service.revoke_api_key(auth.user_id(), &api_key_id).await {
+        Ok(()) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "API key revoked successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
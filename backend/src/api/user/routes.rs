@@ -1,11 +1,32 @@
 //! Defines the HTTP routes for user profile and management.
 
-use super::handlers::{create_user, user_login};
+use super::handlers::{
+    change_password, confirm_email, create_api_key, create_user, delete_me, get_me,
+    get_notification_preferences, list_api_keys, lnurl_auth_challenge, lnurl_auth_link,
+    lnurl_auth_token, revoke_api_key, set_display_currency, update_me,
+    update_notification_preferences, user_login,
+};
 
-use axum::{Router, routing::post};
+use axum::{
+    Router,
+    routing::{delete, get, post, put},
+};
 
 pub async fn user_router() -> Router {
     Router::new()
         .route("/new_account", post(create_user))
         .route("/login", post(user_login))
+        .route("/lnurl_auth", post(lnurl_auth_challenge))
+        .route("/lnurl_auth/token", post(lnurl_auth_token))
+        .route("/lnurl_auth/link", post(lnurl_auth_link))
+        .route("/me", get(get_me).patch(update_me).delete(delete_me))
+        .route("/me/email/confirm", post(confirm_email))
+        .route(
+            "/me/notifications",
+            get(get_notification_preferences).put(update_notification_preferences),
+        )
+        .route("/accounts/{id}/currency", put(set_display_currency))
+        .route("/password/change", post(change_password))
+        .route("/api_keys", get(list_api_keys).post(create_api_key))
+        .route("/api_keys/{id}", delete(revoke_api_key))
 }
@@ -0,0 +1,113 @@
+// API Route handler for webhook related Endpoints
+use crate::common::common::ApiResponse;
+use crate::common::common::{PaginationFilter, PaginationMeta};
+use crate::common::common::{service_error_to_http, validation_error_response};
+use crate::db::models::{
+    CreateWebhookEndpoint, NewWebhookEndpoint, WebhookDelivery, WebhookEndpoint,
+};
+use crate::service::webhook_service::WebhookService;
+use crate::utilities::auth::AuthUser;
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::Json as ResponseJson,
+};
+use sqlx::PgPool;
+use validator::Validate;
+
+#[axum::debug_handler]
+pub async fn create_webhook_endpoint(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Json(payload): Json<CreateWebhookEndpoint>,
+) -> Result<ResponseJson<ApiResponse<NewWebhookEndpoint>>, (StatusCode, String)> {
+    tracing::info!("User {} registering webhook endpoint", auth.user_id());
+
+    let service = WebhookService::new(&pool);
+
+    match service.create_endpoint(&auth, payload).await {
+        Ok(endpoint) => Ok(ResponseJson(ApiResponse::success(
+            endpoint,
+            "Webhook endpoint created successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_webhook_endpoints(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+) -> Result<ResponseJson<ApiResponse<Vec<WebhookEndpoint>>>, (StatusCode, String)> {
+    let service = WebhookService::new(&pool);
+
+    match service.list_endpoints(&auth).await {
+        Ok(endpoints) => Ok(ResponseJson(ApiResponse::success(
+            endpoints,
+            "Webhook endpoints retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn disable_webhook_endpoint(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<()>>, (StatusCode, String)> {
+    tracing::info!("User {} disabling webhook endpoint {}", auth.user_id(), id);
+
+    let service = WebhookService::new(&pool);
+
+    match service.disable_endpoint(&auth, &id).await {
+        Ok(()) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "Webhook endpoint disabled successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_webhook_deliveries(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Path(id): Path<String>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<WebhookDelivery>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = WebhookService::new(&pool);
+
+    match service.list_deliveries(&auth, &id, &filter).await {
+        Ok((deliveries, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            deliveries,
+            PaginationMeta::from_filter(&filter, total),
+            "Webhook deliveries retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn retry_webhook_delivery(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<WebhookDelivery>>, (StatusCode, String)> {
+    tracing::info!("User {} retrying webhook delivery {}", auth.user_id(), id);
+
+    let service = WebhookService::new(&pool);
+
+    match service.retry_delivery(&auth, &id).await {
+        Ok(delivery) => Ok(ResponseJson(ApiResponse::success(
+            delivery,
+            "Webhook delivery queued for retry",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
@@ -0,0 +1,4 @@
+// Module for webhook endpoints: registration and delivery log.
+
+pub mod handlers;
+pub mod routes;
@@ -0,0 +1,22 @@
+//! Defines the HTTP routes for webhook endpoints and their deliveries.
+
+use super::handlers::{
+    create_webhook_endpoint, disable_webhook_endpoint, list_webhook_deliveries,
+    list_webhook_endpoints, retry_webhook_delivery,
+};
+
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
+
+pub async fn webhook_router() -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(list_webhook_endpoints).post(create_webhook_endpoint),
+        )
+        .route("/{id}", delete(disable_webhook_endpoint))
+        .route("/{id}/deliveries", get(list_webhook_deliveries))
+        .route("/deliveries/{id}/retry", post(retry_webhook_delivery))
+}
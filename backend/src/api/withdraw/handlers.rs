@@ -0,0 +1,84 @@
+// API Route handler for withdrawal related Endpoints
+use crate::common::common::ApiResponse;
+use crate::common::common::{PaginationFilter, PaginationMeta};
+use crate::common::common::{service_error_to_http, validation_error_response};
+use crate::db::models::{CreateOnchainWithdrawal, OnchainWithdrawal};
+use crate::service::node_service::LightningClient;
+use crate::service::onchain_withdrawal_service::OnchainWithdrawalService;
+use crate::utilities::auth::AuthUser;
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::Json as ResponseJson,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use validator::Validate;
+
+#[axum::debug_handler]
+pub async fn create_onchain_withdrawal(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Json(payload): Json<CreateOnchainWithdrawal>,
+) -> Result<ResponseJson<ApiResponse<OnchainWithdrawal>>, (StatusCode, String)> {
+    tracing::info!("User {} creating on-chain withdrawal", auth.user_id());
+
+    let service = OnchainWithdrawalService::new(&pool, lightning.as_ref());
+
+    match service.create_withdrawal(&auth, payload).await {
+        Ok(withdrawal) => Ok(ResponseJson(ApiResponse::success(
+            withdrawal,
+            "On-chain withdrawal queued successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn list_onchain_withdrawals(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Query(filter): Query<PaginationFilter>,
+) -> Result<ResponseJson<ApiResponse<Vec<OnchainWithdrawal>>>, (StatusCode, String)> {
+    if let Err(errors) = filter.validate() {
+        return Err(validation_error_response(errors));
+    }
+
+    let service = OnchainWithdrawalService::new(&pool, lightning.as_ref());
+
+    match service.list_withdrawals(&auth, &filter).await {
+        Ok((withdrawals, total)) => Ok(ResponseJson(ApiResponse::paginated(
+            withdrawals,
+            PaginationMeta::from_filter(&filter, total),
+            "On-chain withdrawals retrieved successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn cancel_onchain_withdrawal(
+    auth: AuthUser,
+    Extension(pool): Extension<PgPool>,
+    Extension(lightning): Extension<Arc<dyn LightningClient>>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<()>>, (StatusCode, String)> {
+    tracing::info!(
+        "User {} cancelling on-chain withdrawal {}",
+        auth.user_id(),
+        id
+    );
+
+    let service = OnchainWithdrawalService::new(&pool, lightning.as_ref());
+
+    match service.cancel_withdrawal(&auth, &id).await {
+        Ok(()) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "On-chain withdrawal cancelled successfully",
+        ))),
+        Err(error) => Err(service_error_to_http(error)),
+    }
+}
@@ -0,0 +1,4 @@
+// Module for withdrawal endpoints: on-chain withdrawals.
+
+pub mod handlers;
+pub mod routes;
@@ -0,0 +1,19 @@
+//! Defines the HTTP routes for on-chain withdrawals.
+
+use super::handlers::{
+    cancel_onchain_withdrawal, create_onchain_withdrawal, list_onchain_withdrawals,
+};
+
+use axum::{
+    Router,
+    routing::{delete, get},
+};
+
+pub async fn withdraw_router() -> Router {
+    Router::new()
+        .route(
+            "/onchain",
+            get(list_onchain_withdrawals).post(create_onchain_withdrawal),
+        )
+        .route("/onchain/{id}", delete(cancel_onchain_withdrawal))
+}
@@ -292,6 +292,10 @@ pub fn service_error_to_http(error: ServiceError) -> (StatusCode, String) {
         ServiceError::InvalidOperation { message } => {
             (StatusCode::BAD_REQUEST, "invalid_operation", message)
         }
+        ServiceError::Unauthorized { message } => {
+            (StatusCode::UNAUTHORIZED, "unauthorized", message)
+        }
+        ServiceError::Forbidden { message } => (StatusCode::FORBIDDEN, "forbidden", message),
         ServiceError::Database { source } => {
             tracing::error!("Database error: {}", source);
             (
@@ -303,6 +307,9 @@ pub fn service_error_to_http(error: ServiceError) -> (StatusCode, String) {
         ServiceError::ExternalService { message } => {
             (StatusCode::BAD_GATEWAY, "external_service_error", message)
         }
+        ServiceError::Unsupported { message } => {
+            (StatusCode::NOT_IMPLEMENTED, "unsupported", message)
+        }
         ServiceError::InternalError { message } => {
             tracing::error!("Internal error: {}", message);
             (
@@ -1,6 +1,7 @@
 // Application Level Wide Configurations
 
 use anyhow::{Context, Result};
+use bitcoin::Network;
 use std::env;
 
 #[derive(Debug, Clone)]
@@ -14,6 +15,90 @@ pub struct Config {
     pub resent_api_key: String,
     pub from_email: String,
     pub database_url: String,
+    pub default_role: String,
+    pub admin_role: String,
+    /// Email of the user given the admin role while nobody holds it yet
+    pub initial_admin_email: Option<String>,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    pub password_min_length: usize,
+    pub password_breach_list: Option<String>,
+    /// Node the bank runs on: 'lnd' or 'cln'
+    pub node_backend: String,
+    pub lnd_grpc_url: Option<String>,
+    pub lnd_tls_cert: Option<String>,
+    pub lnd_macaroon: Option<String>,
+    /// gRPC endpoint of CLN's `cln-grpc` plugin
+    pub cln_grpc_url: Option<String>,
+    /// CA, client certificate and client key the plugin generated for mutual TLS
+    pub cln_ca_cert: Option<String>,
+    pub cln_client_cert: Option<String>,
+    pub cln_client_key: Option<String>,
+    pub invoice_expiry_seconds: u64,
+    pub public_url: String,
+    pub lnurl_domain: String,
+    pub lnurl_min_sendable_msat: u64,
+    pub lnurl_max_sendable_msat: u64,
+    pub lnurl_comment_allowed: usize,
+    pub lnurl_verify_enabled: bool,
+    pub lnurl_allow_http: bool,
+    /// Allow LNURL services on loopback and private networks, for local
+    /// stand-ins
+    pub lnurl_allow_private_networks: bool,
+    /// TLV record type carrying the receiving account ID on inbound keysends
+    pub keysend_account_record_type: u64,
+    /// Default routing fee budget, as a percentage of the amount
+    pub payment_fee_limit_percent: f64,
+    /// Smallest default routing fee budget, so tiny payments can still route
+    pub payment_fee_limit_floor_msat: u64,
+    /// Default time the node may spend looking for a route
+    pub payment_timeout_seconds: u64,
+    /// Blocks a payment to a hold invoice can be held for before it must be settled
+    pub hold_invoice_cltv_expiry: u64,
+    /// Confirmations an on-chain deposit needs before it is credited
+    pub deposit_min_confirmations: u32,
+    /// Chain the node runs on, which withdrawal addresses must belong to
+    pub bitcoin_network: Network,
+    /// How often queued on-chain withdrawals are sent out in a batch
+    pub onchain_batch_interval_seconds: u64,
+    /// Smallest on-chain withdrawal, keeping outputs well above dust
+    pub onchain_min_withdrawal_sat: u64,
+    /// Blocks an unconfirmed batch waits before its fee is bumped
+    pub onchain_bump_after_blocks: u32,
+    /// Swap provider speaking the Boltz API
+    pub boltz_api_url: String,
+    /// Confirmations the node must see on a provider's lockup before the
+    /// bank claims it and reveals the preimage
+    pub swap_lockup_confirmations: u32,
+    /// Where exchange rates come from: 'http' or 'file'
+    pub rate_provider: String,
+    /// Exchange-rate API speaking the CoinGecko simple price format
+    pub rate_api_url: String,
+    /// JSON file of fixed rates, read by the 'file' provider
+    pub rate_file: Option<String>,
+    /// Fiat currencies accounts can be shown in
+    pub fiat_currencies: Vec<String>,
+    /// How long a fetched rate is reused before asking the provider again
+    pub rate_cache_seconds: u64,
+    /// How often rates are recorded and new transactions valued
+    pub rate_refresh_seconds: u64,
+    /// Margin taken off the rate when converting fiat invoices, as a percentage
+    pub fiat_invoice_spread_percent: f64,
+    /// Allow webhook endpoints over plain HTTP, for local receivers
+    pub webhook_allow_http: bool,
+    /// Allow webhook endpoints on loopback and private networks, for local
+    /// receivers
+    pub webhook_allow_private_networks: bool,
+    /// SMTP server emails are sent through; emails stay queued while unset
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    /// How the SMTP connection is secured: 'starttls', 'tls' or 'none'
+    pub smtp_tls: String,
+    /// Outgoing payments of at least this many sats trigger a security email
+    pub large_withdrawal_alert_sat: u64,
 }
 
 impl Config {
@@ -48,6 +133,267 @@ impl Config {
         let resent_api_key = env::var("RESENT_API_KEY").context("RESENT_API_KEY not set")?;
         let from_email = env::var("FROM_EMAIL").context("FROM_EMAIL not set")?;
 
+        let default_role = env::var("DEFAULT_ROLE").unwrap_or_else(|_| "customer".to_string());
+        let admin_role = env::var("ADMIN_ROLE").unwrap_or_else(|_| "admin".to_string());
+        let initial_admin_email = env::var("INITIAL_ADMIN_EMAIL")
+            .ok()
+            .map(|email| email.trim().to_string())
+            .filter(|email| !email.is_empty());
+
+        let argon2_memory_kib = env::var("ARGON2_MEMORY_KIB")
+            .unwrap_or_else(|_| "19456".to_string())
+            .parse::<u32>()
+            .context("ARGON2_MEMORY_KIB must be a valid number")?;
+
+        let argon2_iterations = env::var("ARGON2_ITERATIONS")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse::<u32>()
+            .context("ARGON2_ITERATIONS must be a valid number")?;
+
+        let argon2_parallelism = env::var("ARGON2_PARALLELISM")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse::<u32>()
+            .context("ARGON2_PARALLELISM must be a valid number")?;
+
+        let password_min_length = env::var("PASSWORD_MIN_LENGTH")
+            .unwrap_or_else(|_| "12".to_string())
+            .parse::<usize>()
+            .context("PASSWORD_MIN_LENGTH must be a valid number")?;
+
+        let password_breach_list = env::var("PASSWORD_BREACH_LIST").ok();
+
+        let node_backend = Some(
+            env::var("NODE_BACKEND")
+                .unwrap_or_else(|_| "lnd".to_string())
+                .to_lowercase(),
+        )
+        .filter(|backend| matches!(backend.as_str(), "lnd" | "cln"))
+        .context("NODE_BACKEND must be 'lnd' or 'cln'")?;
+
+        let lnd_grpc_url = env::var("LND_GRPC_URL").ok().map(|url| {
+            if url.starts_with("https://") {
+                url
+            } else {
+                format!("https://{url}")
+            }
+        });
+        let lnd_tls_cert = env::var("LND_TLS_CERT").ok();
+        let lnd_macaroon = env::var("LND_MACAROON").ok();
+        if node_backend == "lnd" {
+            lnd_grpc_url.as_ref().context("LND_GRPC_URL not set")?;
+            lnd_tls_cert.as_ref().context("LND_TLS_CERT not set")?;
+            lnd_macaroon.as_ref().context("LND_MACAROON not set")?;
+        }
+
+        let cln_grpc_url = env::var("CLN_GRPC_URL").ok().map(|url| {
+            if url.starts_with("https://") {
+                url
+            } else {
+                format!("https://{url}")
+            }
+        });
+        let cln_ca_cert = env::var("CLN_CA_CERT").ok();
+        let cln_client_cert = env::var("CLN_CLIENT_CERT").ok();
+        let cln_client_key = env::var("CLN_CLIENT_KEY").ok();
+        if node_backend == "cln" {
+            cln_grpc_url.as_ref().context("CLN_GRPC_URL not set")?;
+            cln_ca_cert.as_ref().context("CLN_CA_CERT not set")?;
+            cln_client_cert
+                .as_ref()
+                .context("CLN_CLIENT_CERT not set")?;
+            cln_client_key.as_ref().context("CLN_CLIENT_KEY not set")?;
+        }
+
+        let invoice_expiry_seconds = env::var("INVOICE_EXPIRY_SECONDS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()
+            .context("INVOICE_EXPIRY_SECONDS must be a valid number")?;
+
+        let public_url = env::var("PUBLIC_URL")
+            .context("PUBLIC_URL not set")?
+            .trim_end_matches('/')
+            .to_string();
+        let lnurl_domain = env::var("LNURL_DOMAIN").context("LNURL_DOMAIN not set")?;
+
+        let lnurl_min_sendable_msat = env::var("LNURL_MIN_SENDABLE_MSAT")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<u64>()
+            .context("LNURL_MIN_SENDABLE_MSAT must be a valid number")?;
+
+        let lnurl_max_sendable_msat = env::var("LNURL_MAX_SENDABLE_MSAT")
+            .unwrap_or_else(|_| "100000000".to_string())
+            .parse::<u64>()
+            .context("LNURL_MAX_SENDABLE_MSAT must be a valid number")?;
+
+        let lnurl_comment_allowed = env::var("LNURL_COMMENT_ALLOWED")
+            .unwrap_or_else(|_| "255".to_string())
+            .parse::<usize>()
+            .context("LNURL_COMMENT_ALLOWED must be a valid number")?;
+
+        let lnurl_verify_enabled = env::var("LNURL_VERIFY_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .context("LNURL_VERIFY_ENABLED must be true or false")?;
+
+        let lnurl_allow_http = env::var("LNURL_ALLOW_HTTP")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .context("LNURL_ALLOW_HTTP must be true or false")?;
+
+        let lnurl_allow_private_networks = env::var("LNURL_ALLOW_PRIVATE_NETWORKS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .context("LNURL_ALLOW_PRIVATE_NETWORKS must be true or false")?;
+
+        // 696969 is the record podcasting apps already use for a wallet ID
+        let keysend_account_record_type = env::var("KEYSEND_ACCOUNT_RECORD_TYPE")
+            .unwrap_or_else(|_| "696969".to_string())
+            .parse::<u64>()
+            .context("KEYSEND_ACCOUNT_RECORD_TYPE must be a valid number")?;
+
+        let payment_fee_limit_percent = env::var("PAYMENT_FEE_LIMIT_PERCENT")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse::<f64>()
+            .ok()
+            .filter(|percent| (0.0..=100.0).contains(percent))
+            .context("PAYMENT_FEE_LIMIT_PERCENT must be a number between 0 and 100")?;
+
+        let payment_fee_limit_floor_msat = env::var("PAYMENT_FEE_LIMIT_FLOOR_MSAT")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse::<u64>()
+            .context("PAYMENT_FEE_LIMIT_FLOOR_MSAT must be a valid number")?;
+
+        let payment_timeout_seconds = env::var("PAYMENT_TIMEOUT_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()
+            .context("PAYMENT_TIMEOUT_SECONDS must be a valid number")?;
+
+        // A day of blocks; LND will not accept fewer than 18
+        let hold_invoice_cltv_expiry = env::var("HOLD_INVOICE_CLTV_EXPIRY")
+            .unwrap_or_else(|_| "144".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|blocks| (18..=2016).contains(blocks))
+            .context("HOLD_INVOICE_CLTV_EXPIRY must be a number of blocks between 18 and 2016")?;
+
+        let deposit_min_confirmations = env::var("DEPOSIT_MIN_CONFIRMATIONS")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<u32>()
+            .ok()
+            .filter(|confirmations| *confirmations >= 1)
+            .context("DEPOSIT_MIN_CONFIRMATIONS must be a number of at least 1")?;
+
+        let bitcoin_network = env::var("BITCOIN_NETWORK")
+            .unwrap_or_else(|_| "bitcoin".to_string())
+            .parse::<Network>()
+            .context("BITCOIN_NETWORK must be bitcoin, testnet, testnet4, signet or regtest")?;
+
+        let onchain_batch_interval_seconds = env::var("ONCHAIN_BATCH_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "600".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|seconds| *seconds >= 1)
+            .context("ONCHAIN_BATCH_INTERVAL_SECONDS must be a number of at least 1")?;
+
+        let onchain_min_withdrawal_sat = env::var("ONCHAIN_MIN_WITHDRAWAL_SAT")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse::<u64>()
+            .context("ONCHAIN_MIN_WITHDRAWAL_SAT must be a valid number")?;
+
+        let onchain_bump_after_blocks = env::var("ONCHAIN_BUMP_AFTER_BLOCKS")
+            .unwrap_or_else(|_| "6".to_string())
+            .parse::<u32>()
+            .ok()
+            .filter(|blocks| *blocks >= 1)
+            .context("ONCHAIN_BUMP_AFTER_BLOCKS must be a number of at least 1")?;
+
+        let boltz_api_url =
+            env::var("BOLTZ_API_URL").unwrap_or_else(|_| "https://api.boltz.exchange".to_string());
+
+        let swap_lockup_confirmations = env::var("SWAP_LOCKUP_CONFIRMATIONS")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse::<u32>()
+            .ok()
+            .filter(|confirmations| *confirmations >= 1)
+            .context("SWAP_LOCKUP_CONFIRMATIONS must be a number of at least 1")?;
+
+        let rate_provider = Some(
+            env::var("RATE_PROVIDER")
+                .unwrap_or_else(|_| "http".to_string())
+                .to_lowercase(),
+        )
+        .filter(|provider| matches!(provider.as_str(), "http" | "file"))
+        .context("RATE_PROVIDER must be 'http' or 'file'")?;
+
+        let rate_api_url = env::var("RATE_API_URL")
+            .unwrap_or_else(|_| "https://api.coingecko.com/api/v3".to_string());
+
+        let rate_file = env::var("RATE_FILE").ok();
+        if rate_provider == "file" {
+            rate_file
+                .as_ref()
+                .context("RATE_FILE must be set when RATE_PROVIDER is 'file'")?;
+        }
+
+        let fiat_currencies = Some(
+            env::var("FIAT_CURRENCIES")
+                .unwrap_or_else(|_| "USD,EUR,NGN".to_string())
+                .split(',')
+                .map(|currency| currency.trim().to_uppercase())
+                .filter(|currency| !currency.is_empty())
+                .collect::<Vec<String>>(),
+        )
+        .filter(|currencies| !currencies.is_empty())
+        .context("FIAT_CURRENCIES must list at least one currency")?;
+
+        let rate_cache_seconds = env::var("RATE_CACHE_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()
+            .context("RATE_CACHE_SECONDS must be a valid number")?;
+
+        let rate_refresh_seconds = env::var("RATE_REFRESH_SECONDS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|seconds| *seconds >= 1)
+            .context("RATE_REFRESH_SECONDS must be a number of at least 1")?;
+
+        let fiat_invoice_spread_percent = env::var("FIAT_INVOICE_SPREAD_PERCENT")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse::<f64>()
+            .ok()
+            .filter(|percent| (0.0..50.0).contains(percent))
+            .context("FIAT_INVOICE_SPREAD_PERCENT must be a percentage below 50")?;
+
+        let webhook_allow_http = env::var("WEBHOOK_ALLOW_HTTP")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .context("WEBHOOK_ALLOW_HTTP must be true or false")?;
+
+        let webhook_allow_private_networks = env::var("WEBHOOK_ALLOW_PRIVATE_NETWORKS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .context("WEBHOOK_ALLOW_PRIVATE_NETWORKS must be true or false")?;
+
+        let smtp_host = env::var("SMTP_HOST").ok().filter(|host| !host.is_empty());
+
+        let smtp_port = env::var("SMTP_PORT")
+            .unwrap_or_else(|_| "587".to_string())
+            .parse::<u16>()
+            .context("SMTP_PORT must be a valid port")?;
+
+        let smtp_username = env::var("SMTP_USERNAME").ok();
+        let smtp_password = env::var("SMTP_PASSWORD").ok();
+
+        let smtp_tls = Some(env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string()))
+            .filter(|tls| matches!(tls.as_str(), "starttls" | "tls" | "none"))
+            .context("SMTP_TLS must be 'starttls', 'tls' or 'none'")?;
+
+        let large_withdrawal_alert_sat = env::var("LARGE_WITHDRAWAL_ALERT_SAT")
+            .unwrap_or_else(|_| "1000000".to_string())
+            .parse::<u64>()
+            .context("LARGE_WITHDRAWAL_ALERT_SAT must be a valid number")?;
+
         Ok(Config {
             max_connections,
             jwt_secret,
@@ -58,6 +404,58 @@ impl Config {
             acquire_timeout_seconds,
             resent_api_key,
             from_email,
+            default_role,
+            admin_role,
+            initial_admin_email,
+            argon2_memory_kib,
+            argon2_iterations,
+            argon2_parallelism,
+            password_min_length,
+            password_breach_list,
+            node_backend,
+            lnd_grpc_url,
+            lnd_tls_cert,
+            lnd_macaroon,
+            cln_grpc_url,
+            cln_ca_cert,
+            cln_client_cert,
+            cln_client_key,
+            invoice_expiry_seconds,
+            public_url,
+            lnurl_domain,
+            lnurl_min_sendable_msat,
+            lnurl_max_sendable_msat,
+            lnurl_comment_allowed,
+            lnurl_verify_enabled,
+            lnurl_allow_http,
+            lnurl_allow_private_networks,
+            keysend_account_record_type,
+            payment_fee_limit_percent,
+            payment_fee_limit_floor_msat,
+            payment_timeout_seconds,
+            hold_invoice_cltv_expiry,
+            deposit_min_confirmations,
+            bitcoin_network,
+            onchain_batch_interval_seconds,
+            onchain_min_withdrawal_sat,
+            onchain_bump_after_blocks,
+            boltz_api_url,
+            swap_lockup_confirmations,
+            rate_provider,
+            rate_api_url,
+            rate_file,
+            fiat_currencies,
+            rate_cache_seconds,
+            rate_refresh_seconds,
+            fiat_invoice_spread_percent,
+            webhook_allow_http,
+            webhook_allow_private_networks,
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_password,
+            smtp_tls,
+            large_withdrawal_alert_sat,
         })
     }
 }
@@ -5,14 +5,25 @@ use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
 use sqlx::FromRow;
 use sqlx::types::BigDecimal;
+use std::collections::BTreeMap;
 use validator::Validate;
 
+use crate::utilities::password::validate_password_strength;
+
+/// All the bitcoin there will ever be, in satoshis. Larger amounts are
+/// refused before any arithmetic is done on them.
+pub const MAX_AMOUNT_SAT: u64 = 2_100_000_000_000_000;
+
+/// `MAX_AMOUNT_SAT` in millisatoshis
+pub const MAX_AMOUNT_MSAT: u64 = MAX_AMOUNT_SAT * 1000;
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: String,
     pub username: String,
-    pub email: String,
-    pub password_hash: String,
+    pub email: Option<String>,
+    #[serde(skip_serializing)]
+    pub password_hash: Option<String>,
     pub role_id: String,
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
@@ -34,12 +45,91 @@ pub struct CreateUser {
         length(max = 255, message = "Email too long")
     )]
     pub email: String,
-    #[validate(length(min = 1, message = "Password is required"))]
+    #[validate(custom(function = "validate_password_strength"))]
     pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct AdminCreateUser {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "User name must be between 1-255 characters"
+    ))]
+    pub username: String,
+    #[validate(
+        email(message = "Must be a valid email"),
+        length(max = 255, message = "Email too long")
+    )]
+    pub email: String,
+    #[validate(custom(function = "validate_password_strength"))]
+    pub password: String,
+    #[validate(length(min = 1, message = "Role ID is required"))]
+    pub role_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct AssignRole {
     #[validate(length(min = 1, message = "Role ID is required"))]
     pub role_id: String,
 }
 
+/// Search over users for the back office
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSearch {
+    /// Matched against the username and email, or the exact user ID
+    pub q: Option<String>,
+}
+
+/// Why an admin froze or unfroze a user or account
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct AdminReason {
+    #[validate(length(
+        min = 1,
+        max = 500,
+        message = "Reason must be between 1-500 characters"
+    ))]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateLedgerAdjustment {
+    #[validate(length(min = 1, message = "Account ID is required"))]
+    pub account_id: String,
+    /// Positive to credit the account, negative to debit it
+    pub amount_msat: i64,
+    #[validate(length(
+        min = 1,
+        max = 500,
+        message = "Reason must be between 1-500 characters"
+    ))]
+    pub reason: String,
+}
+
+/// Filter on the status of ledger adjustments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerAdjustmentFilter {
+    pub status: Option<String>,
+}
+
+/// A manual balance correction, applied once a second admin approves it
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LedgerAdjustment {
+    pub id: String,
+    pub account_id: String,
+    #[serde_as(as = "DisplayFromStr")]
+    pub amount_msat: BigDecimal,
+    pub reason: String,
+    /// 'pending' until reviewed, then 'applied' or 'rejected'
+    pub status: String,
+    pub requested_by: String,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct UserLogin {
     #[validate(
@@ -63,7 +153,7 @@ pub struct LoginResponse {
 pub struct UserInfo {
     pub id: String,
     pub username: String,
-    pub email: String,
+    pub email: Option<String>,
     pub account_id: String,
     pub role: String,
 }
@@ -74,6 +164,92 @@ pub struct UserWithAccount {
     pub account: Account,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct UserProfile {
+    pub id: String,
+    pub username: String,
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_email: Option<String>,
+    pub role: String,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub accounts: Vec<AccountBalance>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateProfile {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "User name must be between 1-255 characters"
+    ))]
+    pub username: Option<String>,
+    #[validate(
+        email(message = "Must be a valid email"),
+        length(max = 255, message = "Email too long")
+    )]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ConfirmEmailChange {
+    #[validate(length(min = 1, message = "Verification token is required"))]
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ChangePassword {
+    #[validate(length(min = 1, message = "Current password is required"))]
+    pub current_password: String,
+    #[validate(custom(function = "validate_password_strength"))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct EmailVerification {
+    pub id: String,
+    pub user_id: String,
+    pub email: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Which emails a user gets; security emails can be turned off too
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct NotificationPreferences {
+    pub payment_received: bool,
+    pub payment_sent: bool,
+    pub large_withdrawal: bool,
+    pub new_login: bool,
+    pub password_changed: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            payment_received: true,
+            payment_sent: true,
+            large_withdrawal: true,
+            new_login: true,
+            password_changed: true,
+        }
+    }
+}
+
+/// Preferences to change; the ones left out stay as they are
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateNotificationPreferences {
+    pub payment_received: Option<bool>,
+    pub payment_sent: Option<bool>,
+    pub large_withdrawal: Option<bool>,
+    pub new_login: Option<bool>,
+    pub password_changed: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewRole {
     pub role: Role,
@@ -87,17 +263,64 @@ pub struct Account {
     #[serde_as(as = "DisplayFromStr")]
     pub balance: BigDecimal,
     pub is_active: bool,
+    /// Fiat currency the balance and statements are shown in
+    pub display_currency: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_deleted: bool,
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+/// Fiat equivalent of an amount of millisatoshis
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiatValue {
+    pub currency: String,
+    #[serde_as(as = "DisplayFromStr")]
+    pub amount: BigDecimal,
+    /// Price of one bitcoin the amount was valued at
+    #[serde_as(as = "DisplayFromStr")]
+    pub rate: BigDecimal,
+}
+
+/// Account with its balance valued at the latest recorded rate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountBalance {
+    #[serde(flatten)]
+    pub account: Account,
+    /// Missing until a rate for the display currency has been recorded
+    pub fiat_balance: Option<FiatValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateDisplayCurrency {
+    #[validate(length(equal = 3, message = "Currency must be a 3-letter code"))]
+    pub currency: String,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ExchangeRate {
+    pub id: String,
+    pub currency: String,
+    /// Price of one bitcoin in the currency
+    #[serde_as(as = "DisplayFromStr")]
+    pub rate: BigDecimal,
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Role {
     pub id: String,
     pub name: String,
     pub is_active: bool,
+    /// Most users with this role may pay in routing fees, in millisatoshis
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub max_fee_msat: Option<BigDecimal>,
+    /// Most users with this role may pay in routing fees, as a percentage
+    pub max_fee_percent: Option<f64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_deleted: bool,
@@ -110,20 +333,816 @@ pub struct CreateRole {
     pub name: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateRole {
+    #[validate(length(min = 1, max = 255, message = "Name must be between 1-255 characters"))]
+    pub name: Option<String>,
+}
+
+/// Routing fee ceilings for a role; leaving both out removes the ceiling
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct RoleFeeCeiling {
+    pub max_fee_msat: Option<u64>,
+    #[validate(range(min = 0.0, max = 100.0, message = "Percent must be between 0-100"))]
+    pub max_fee_percent: Option<f64>,
+}
+
 #[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Transaction {
     pub id: String,
     pub user_id: String,
+    pub account_id: Option<String>,
+    pub direction: String,
     pub invoice: String,
     #[serde_as(as = "DisplayFromStr")]
     pub amount: BigDecimal,
+    /// Routing fee paid on top of the amount
+    #[serde_as(as = "DisplayFromStr")]
+    pub fee_msat: BigDecimal,
     pub payment_hash: String,
     pub payment_status: String,
+    /// Currency the transaction was valued in when it was made
+    pub fiat_currency: Option<String>,
+    /// Price of one bitcoin in 'fiat_currency' when the transaction was made
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub fiat_rate: Option<BigDecimal>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Transaction with its amount and fee valued at the rate it was made at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionWithFiat {
+    #[serde(flatten)]
+    pub transaction: Transaction,
+    pub fiat_amount: Option<FiatValue>,
+    pub fiat_fee: Option<FiatValue>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Invoice {
+    pub id: String,
+    pub user_id: String,
+    pub account_id: String,
+    pub payment_request: String,
+    pub payment_hash: String,
+    /// Amount asked for, 'None' for invoices the payer chooses the amount of
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub amount_msat: Option<BigDecimal>,
+    /// Amount that actually settled
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub amount_paid_msat: Option<BigDecimal>,
+    /// Currency of invoices billed in fiat
+    pub fiat_currency: Option<String>,
+    /// Amount billed, in 'fiat_currency'
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub fiat_amount: Option<BigDecimal>,
+    /// Price of one bitcoin 'amount_msat' was worked out at, locked until expiry
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub fiat_rate: Option<BigDecimal>,
+    pub memo: String,
+    pub description_hash: Option<String>,
+    pub comment: Option<String>,
+    pub source: String,
+    pub status: String,
+    pub preimage: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub settled_at: Option<DateTime<Utc>>,
+    /// When a payment to a hold invoice arrived
+    pub accepted_at: Option<DateTime<Utc>>,
+    /// Block the held payment must be settled before
+    pub htlc_expiry_height: Option<i32>,
+    /// Estimated time of `htlc_expiry_height`, less the margin after which
+    /// the payment is canceled
+    pub settle_by: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// LUD-06 `payRequest`, served for our Lightning Addresses and read from others
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LnurlPayRequest {
+    pub callback: String,
+    pub max_sendable: u64,
+    pub min_sendable: u64,
+    pub metadata: String,
+    #[serde(default)]
+    pub comment_allowed: usize,
+    pub tag: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LnurlPayCallback {
+    /// Amount in millisatoshis
+    pub amount: u64,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LnurlPayInvoice {
+    pub pr: String,
+    #[serde(default)]
+    pub routes: Vec<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub success_action: Option<SuccessAction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<String>,
+}
+
+/// LUD-09 success action returned alongside an LNURL-pay invoice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "tag", rename_all = "lowercase")]
+pub enum SuccessAction {
+    Message {
+        message: String,
+    },
+    Url {
+        description: String,
+        url: String,
+    },
+    /// LUD-10 message encrypted with the payment preimage
+    Aes {
+        description: String,
+        ciphertext: String,
+        iv: String,
+    },
+}
+
+/// Success action as shown to the payer once the payment went through
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "tag", rename_all = "lowercase")]
+pub enum PaymentSuccessAction {
+    Message {
+        message: String,
+    },
+    Url {
+        description: String,
+        url: String,
+    },
+    Aes {
+        description: String,
+        plaintext: String,
+    },
+}
+
+/// LUD-21 verify response
+#[derive(Debug, Serialize)]
+pub struct LnurlVerify {
+    pub status: String,
+    pub settled: bool,
+    pub preimage: Option<String>,
+    pub pr: String,
+}
+
+/// LNURL status envelope, used for errors
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LnurlStatus {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Permissions that can be granted to an API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiKeyScope {
+    #[serde(rename = "account:read")]
+    AccountRead,
+    #[serde(rename = "invoices:read")]
+    InvoicesRead,
+    #[serde(rename = "invoices:write")]
+    InvoicesWrite,
+    #[serde(rename = "payments:read")]
+    PaymentsRead,
+    #[serde(rename = "payments:send")]
+    PaymentsSend,
+    #[serde(rename = "webhooks:manage")]
+    WebhooksManage,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::AccountRead => "account:read",
+            ApiKeyScope::InvoicesRead => "invoices:read",
+            ApiKeyScope::InvoicesWrite => "invoices:write",
+            ApiKeyScope::PaymentsRead => "payments:read",
+            ApiKeyScope::PaymentsSend => "payments:send",
+            ApiKeyScope::WebhooksManage => "webhooks:manage",
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ApiKey {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub secret_hash: String,
+    pub scopes: Vec<String>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub spend_limit_msat: Option<BigDecimal>,
+    #[serde_as(as = "DisplayFromStr")]
+    pub spent_msat: BigDecimal,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ApiKey {
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.iter().any(|granted| granted == scope.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateApiKey {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1-100 characters"))]
+    pub name: String,
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<ApiKeyScope>,
+    #[validate(range(min = 1, message = "Spend limit must be positive"))]
+    pub spend_limit_msat: Option<u64>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NewApiKey {
+    /// Full key, only ever returned once at creation
+    pub key: String,
+    pub api_key: ApiKey,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAuditEvent {
+    pub actor_id: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+/// An entry of the append-only audit log
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuditEvent {
+    pub id: String,
+    /// Position in the hash chain, starting at 1
+    pub seq: i64,
+    /// User who made the change, 'None' for the system
+    pub actor_id: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+    pub ip_address: Option<String>,
+    /// Hash of the event before this one, 'None' for the first event
+    pub prev_hash: Option<String>,
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Filters on the audit log, all optional
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEventFilter {
+    pub actor_id: Option<String>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub request_id: Option<String>,
+    /// Start date (inclusive)
+    pub from: Option<DateTime<Utc>>,
+    /// End date (inclusive)
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Outcome of checking the audit log hash chain
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditChainStatus {
+    pub valid: bool,
+    pub events_checked: u64,
+    /// Hash of the latest event; keeping copies elsewhere also reveals
+    /// events removed from the end of the log
+    pub head_hash: Option<String>,
+    /// First event whose hash or link to the previous event does not match
+    pub first_invalid_event_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct PayInvoice {
+    /// BOLT11 invoice, `lno1...` offer, `lnurl1...` string or `name@domain`
+    /// Lightning Address
+    #[validate(length(min = 1, message = "Destination is required"))]
+    pub destination: String,
+    /// Amount in millisatoshis, required for LNURL, Lightning Addresses,
+    /// invoices without an amount and offers without a price
+    pub amount_msat: Option<u64>,
+    /// LNURL comment, or payer note when paying an offer
+    #[validate(length(max = 2000, message = "Comment must be at most 2000 characters"))]
+    pub comment: Option<String>,
+    #[serde(flatten)]
+    pub routing: RoutingOptions,
+}
+
+/// Caller's limits on how a payment is routed. The fee budget defaults to
+/// the bank's and can never exceed the ceiling of the caller's role.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingOptions {
+    /// Most to spend on routing fees, in millisatoshis
+    pub max_fee_msat: Option<u64>,
+    /// Most to spend on routing fees, as a percentage of the amount
+    pub max_fee_percent: Option<f64>,
+    /// How long the node may keep looking for a route
+    pub timeout_seconds: Option<u64>,
+    /// Channels the payment may leave through
+    #[serde(default)]
+    pub outgoing_chan_ids: Vec<u64>,
+    /// Hex encoded public key of the peer the payment must reach the payee through
+    pub last_hop_pubkey: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct DecodePayment {
+    /// Anything accepted as a `PayInvoice` destination
+    #[validate(length(min = 1, message = "Destination is required"))]
+    pub destination: String,
+    /// Amount to estimate fees for when the destination does not fix one
+    pub amount_msat: Option<u64>,
+    /// Limits the fee budget is worked out from, as when paying
+    #[serde(flatten)]
+    pub routing: RoutingOptions,
+}
+
+/// Preview of a payment destination, shown before the user confirms
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DecodedPayment {
+    /// 'bolt11', 'bolt12_offer', 'lnurl_pay' or 'lightning_address'
+    pub kind: String,
+    pub amount_msat: Option<u64>,
+    /// Range an LNURL-pay service accepts
+    pub min_amount_msat: Option<u64>,
+    pub max_amount_msat: Option<u64>,
+    pub description: Option<String>,
+    pub description_hash: Option<String>,
+    /// Node public key, or the domain of the LNURL service
+    pub payee: Option<String>,
+    pub payment_hash: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Paid by moving funds inside the bank, without routing fees
+    pub internal: bool,
+    /// Longest comment an LNURL-pay service accepts
+    pub comment_allowed: Option<usize>,
+    /// Routing fee the node expects to pay
+    pub fee_estimate_msat: Option<u64>,
+    /// Routing fee reserved on top of the amount while paying
+    pub fee_limit_msat: Option<u64>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct Keysend {
+    /// Hex encoded public key of the receiving node
+    #[validate(length(min = 1, message = "Destination is required"))]
+    pub destination: String,
+    #[validate(range(
+        min = 1,
+        max = MAX_AMOUNT_MSAT,
+        message = "Amount must be between 1 msat and 21 million bitcoin"
+    ))]
+    pub amount_msat: u64,
+    /// Extra TLV records for the recipient by record type, with hex encoded values
+    #[serde(default)]
+    pub custom_records: BTreeMap<u64, String>,
+    #[serde(flatten)]
+    pub routing: RoutingOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentReceipt {
+    pub transaction_id: String,
+    pub payment_hash: String,
+    pub payment_preimage: Option<String>,
+    pub amount_msat: u64,
+    pub fee_msat: u64,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success_action: Option<PaymentSuccessAction>,
+}
+
+/// LNURL-withdraw link funded from a reservation on the owner's balance
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WithdrawLink {
+    pub id: String,
+    pub user_id: String,
+    pub account_id: String,
+    pub title: String,
+    #[serde(skip_serializing)]
+    pub k1: String,
+    #[serde_as(as = "DisplayFromStr")]
+    pub min_withdrawable_msat: BigDecimal,
+    #[serde_as(as = "DisplayFromStr")]
+    pub max_withdrawable_msat: BigDecimal,
+    pub max_uses: i32,
+    pub uses: i32,
+    /// Most that can be withdrawn over all uses
+    #[serde_as(as = "DisplayFromStr")]
+    pub total_msat: BigDecimal,
+    #[serde_as(as = "DisplayFromStr")]
+    pub withdrawn_msat: BigDecimal,
+    /// Taken from the balance up front: the total plus routing fee reserves
+    #[serde_as(as = "DisplayFromStr")]
+    pub reserved_msat: BigDecimal,
+    /// Withdrawn amounts plus routing fees paid
+    #[serde_as(as = "DisplayFromStr")]
+    pub spent_msat: BigDecimal,
+    pub status: String,
+    pub expires_at: DateTime<Utc>,
+    pub released_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WithdrawLinkWithLnurl {
+    #[serde(flatten)]
+    pub withdraw_link: WithdrawLink,
+    /// Bech32 encoded link to render as a QR code
+    pub lnurl: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateWithdrawLink {
+    #[validate(length(min = 1, max = 255, message = "Title must be between 1-255 characters"))]
+    pub title: String,
+    #[validate(range(
+        min = 1000,
+        max = MAX_AMOUNT_MSAT,
+        message = "Minimum must be between 1000 msat and 21 million bitcoin"
+    ))]
+    pub min_withdrawable_msat: u64,
+    #[validate(range(
+        min = 1000,
+        max = MAX_AMOUNT_MSAT,
+        message = "Maximum must be between 1000 msat and 21 million bitcoin"
+    ))]
+    pub max_withdrawable_msat: u64,
+    /// Number of times the link can be used, 1 for a single-use voucher
+    #[validate(range(min = 1, max = 1000, message = "Uses must be between 1-1000"))]
+    pub uses: Option<i32>,
+    /// Cap on the total withdrawn, defaults to the maximum times the uses
+    #[validate(range(
+        min = 1000,
+        max = MAX_AMOUNT_MSAT,
+        message = "Total must be between 1000 msat and 21 million bitcoin"
+    ))]
+    pub total_msat: Option<u64>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// LUD-03 `withdrawRequest`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LnurlWithdrawRequest {
+    pub tag: String,
+    pub callback: String,
+    pub k1: String,
+    pub default_description: String,
+    pub min_withdrawable: u64,
+    pub max_withdrawable: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LnurlWithdrawCallback {
+    pub k1: String,
+    pub pr: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LnurlAuthChallenge {
+    pub k1: String,
+    pub action: String,
+    pub user_id: Option<String>,
+    pub linking_key: Option<String>,
+    pub status: String,
+    pub expires_at: DateTime<Utc>,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub consumed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Challenge shown to the user as a QR code for their wallet to sign
+#[derive(Debug, Serialize)]
+pub struct LnurlAuthLogin {
+    pub k1: String,
+    pub lnurl: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LnurlAuthCallback {
+    pub k1: String,
+    pub sig: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct LnurlAuthToken {
+    #[validate(length(equal = 64, message = "k1 must be 64 hex characters"))]
+    pub k1: String,
+}
+
+/// Reusable BOLT12 offer whose payments credit the owner's account
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Offer {
+    pub id: String,
+    pub user_id: String,
+    pub account_id: String,
+    /// Bech32 encoded `lno1...` offer to share with payers
+    pub offer: String,
+    pub offer_id: String,
+    pub description: String,
+    /// Fixed price, or `None` when the payer chooses the amount
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub amount_msat: Option<BigDecimal>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Address in the node wallet that credits deposits to an account
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DepositAddress {
+    pub id: String,
+    pub user_id: String,
+    pub account_id: String,
+    pub address: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// On-chain output received on a deposit address
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Deposit {
+    pub id: String,
+    pub user_id: String,
+    pub account_id: String,
+    pub address: String,
+    pub txid: String,
+    pub vout: i32,
+    #[serde_as(as = "DisplayFromStr")]
+    pub amount_msat: BigDecimal,
+    pub confirmations: i32,
+    /// Block the transaction confirmed in, 'None' while in the mempool
+    pub block_height: Option<i32>,
+    /// 'pending' until deep enough to credit, then 'credited'. 'dropped'
+    /// when the transaction left the chain and mempool.
+    pub status: String,
+    pub credited_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// How soon an on-chain withdrawal should confirm, which sets its fee rate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeTier {
+    Fast,
+    #[default]
+    Normal,
+    Slow,
+}
+
+impl FeeTier {
+    pub const ALL: [FeeTier; 3] = [FeeTier::Fast, FeeTier::Normal, FeeTier::Slow];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeeTier::Fast => "fast",
+            FeeTier::Normal => "normal",
+            FeeTier::Slow => "slow",
+        }
+    }
+
+    /// Blocks the withdrawal should confirm within
+    pub fn conf_target(&self) -> u32 {
+        match self {
+            FeeTier::Fast => 2,
+            FeeTier::Normal => 6,
+            FeeTier::Slow => 144,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateOnchainWithdrawal {
+    #[validate(length(min = 1, message = "Address is required"))]
+    pub address: String,
+    #[validate(range(
+        min = 1,
+        max = MAX_AMOUNT_SAT,
+        message = "Amount must be between 1 sat and 21 million bitcoin"
+    ))]
+    pub amount_sat: u64,
+    #[serde(default)]
+    pub fee_tier: FeeTier,
+}
+
+/// Withdrawal to an on-chain address, paid out in a batch transaction
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OnchainWithdrawal {
+    pub id: String,
+    pub user_id: String,
+    pub account_id: String,
+    /// Ledger entry the withdrawal is recorded under
+    pub transaction_id: String,
+    pub batch_id: Option<String>,
+    /// Transaction paying the withdrawal, once its batch is broadcast
+    pub txid: Option<String>,
+    pub address: String,
+    #[serde_as(as = "DisplayFromStr")]
+    pub amount_msat: BigDecimal,
+    pub fee_tier: String,
+    /// Fee held from the balance until the batch confirms
+    #[serde_as(as = "DisplayFromStr")]
+    pub fee_reserve_msat: BigDecimal,
+    /// Share of the batch fee charged, once confirmed
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub fee_msat: Option<BigDecimal>,
+    /// 'queued', 'sending', 'broadcast', 'confirmed' or 'cancelled'
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Transaction paying out a batch of on-chain withdrawals
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OnchainBatch {
+    pub id: String,
+    pub fee_tier: String,
+    /// 'None' until the node reports the batch sent
+    pub txid: Option<String>,
+    /// Earlier transactions of the batch, replaced to raise its fee
+    pub replaced_txids: Vec<String>,
+    pub sat_per_vbyte: i64,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub fee_sat: Option<BigDecimal>,
+    /// 'sending', 'broadcast', 'confirmed' or 'failed'
+    pub status: String,
+    pub broadcast_height: i32,
+    pub bumped_height: Option<i32>,
+    pub bump_count: i32,
+    pub confirmed_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateSubmarineSwap {
+    #[validate(range(min = 1, message = "Amount must be at least 1 sat"))]
+    pub amount_sat: u64,
+    /// Where the coins go back to if the swap fails
+    #[validate(length(min = 1, message = "Refund address is required"))]
+    pub refund_address: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateReverseSwap {
+    #[validate(range(min = 1, message = "Amount must be at least 1 sat"))]
+    pub amount_sat: u64,
+    #[validate(length(min = 1, message = "Address is required"))]
+    pub address: String,
+}
+
+/// Swap between on-chain coins and the Lightning balance
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Swap {
+    pub id: String,
+    pub user_id: String,
+    pub account_id: String,
+    /// 'submarine' (chain to Lightning) or 'reverse' (Lightning to chain)
+    pub kind: String,
+    pub provider_swap_id: String,
+    pub provider_status: Option<String>,
+    /// Submarine: 'created', 'funded', 'completed', 'refunded' or 'expired'.
+    /// Reverse: 'created', 'paying', 'claimed', 'completed' or 'failed'.
+    pub status: String,
+    pub invoice: String,
+    pub payment_hash: String,
+    /// Lightning side of the swap
+    #[serde_as(as = "DisplayFromStr")]
+    pub amount_msat: BigDecimal,
+    /// On-chain side of the swap, before the fee to sweep it
+    pub onchain_amount_sat: i64,
+    pub lockup_address: String,
+    /// Claim address of a reverse swap, refund address of a submarine swap
+    pub destination_address: String,
+    pub redeem_script: String,
+    pub timeout_block_height: i32,
+    /// Chain height when the swap was created
+    pub start_height: i32,
+    #[serde(skip_serializing)]
+    pub preimage: Option<String>,
+    #[serde(skip_serializing)]
+    pub private_key: String,
+    pub transaction_id: Option<String>,
+    #[serde_as(as = "DisplayFromStr")]
+    pub fee_reserve_msat: BigDecimal,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub routing_fee_msat: Option<BigDecimal>,
+    /// Everything the swap cost: provider, miner and routing fees
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub fee_msat: Option<BigDecimal>,
+    pub lockup_txid: Option<String>,
+    /// Claim or refund transaction
+    pub sweep_txid: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateOffer {
+    #[validate(length(
+        min = 1,
+        max = 640,
+        message = "Description must be between 1-640 characters"
+    ))]
+    pub description: String,
+    #[validate(range(min = 1, message = "Amount must be at least 1 msat"))]
+    pub amount_msat: Option<u64>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateInvoice {
+    /// Leave this and 'fiat_amount' out to let the payer choose the amount
+    #[validate(range(min = 1, message = "Amount must be at least 1 msat"))]
+    pub amount_msat: Option<u64>,
+    /// Amount to bill in 'currency' instead of 'amount_msat'
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub fiat_amount: Option<BigDecimal>,
+    #[validate(length(equal = 3, message = "Currency must be a 3-letter code"))]
+    pub currency: Option<String>,
+    #[validate(length(max = 639, message = "Memo must be at most 639 characters"))]
+    #[serde(default)]
+    pub memo: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateHoldInvoice {
+    #[validate(range(min = 1, message = "Amount must be at least 1 msat"))]
+    pub amount_msat: u64,
+    #[validate(length(max = 639, message = "Memo must be at most 639 characters"))]
+    #[serde(default)]
+    pub memo: String,
+    /// Hex encoded hash of a preimage only the caller knows. Leave out to
+    /// have the bank keep the preimage.
+    #[validate(length(equal = 64, message = "Payment hash must be 64 hex characters"))]
+    pub payment_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+pub struct SettleHoldInvoice {
+    /// Hex encoded preimage, required when the caller supplied the payment hash
+    #[validate(length(equal = 64, message = "Preimage must be 64 hex characters"))]
+    pub preimage: Option<String>,
+}
+
+/// Fiat amount an invoice is billed in, with the rate it was converted at
+#[derive(Debug, Clone)]
+pub struct FiatQuote {
+    pub currency: String,
+    pub amount: BigDecimal,
+    pub rate: BigDecimal,
+}
+
+/// Invoice to be issued on the node and recorded for a user
+#[derive(Debug, Clone)]
+pub struct NewInvoice {
+    pub amount_msat: Option<u64>,
+    pub fiat: Option<FiatQuote>,
+    pub memo: String,
+    /// Description committed to by its hash instead of the memo, such as LNURL metadata
+    pub hashed_description: Option<String>,
+    pub comment: Option<String>,
+    pub source: String,
+}
+
 // #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 // pub struct CreateTransaction {
 //     #[validate(length(min = 1, message = "User ID is required"))]
@@ -137,3 +1156,89 @@ pub struct Transaction {
 //     #[validate(length(min = 1, message = "Payment Status is required"))]
 //     pub payment_status: String,
 // }
+
+/// Account events webhook endpoints can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "invoice.paid")]
+    InvoicePaid,
+    #[serde(rename = "payment.succeeded")]
+    PaymentSucceeded,
+    #[serde(rename = "payment.failed")]
+    PaymentFailed,
+    #[serde(rename = "deposit.confirmed")]
+    DepositConfirmed,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::InvoicePaid => "invoice.paid",
+            WebhookEvent::PaymentSucceeded => "payment.succeeded",
+            WebhookEvent::PaymentFailed => "payment.failed",
+            WebhookEvent::DepositConfirmed => "deposit.confirmed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateWebhookEndpoint {
+    #[validate(url(message = "Must be a valid URL"))]
+    pub url: String,
+    #[validate(length(min = 1, message = "At least one event is required"))]
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WebhookEndpoint {
+    pub id: String,
+    pub user_id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NewWebhookEndpoint {
+    /// Secret deliveries are signed with, only ever returned once at creation
+    pub secret: String,
+    pub endpoint: WebhookEndpoint,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub endpoint_id: String,
+    pub user_id: String,
+    /// Shared by the deliveries of one event to several endpoints
+    pub event_id: String,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    /// 'pending' until delivered, 'dead' once retries are exhausted
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    /// HTTP status the endpoint last answered with
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An event pushed to the user's open event streams
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountEvent {
+    pub id: String,
+    /// Owner of the account the event is about; streams only see their own
+    #[serde(skip_serializing)]
+    pub user_id: String,
+    /// 'balance.updated', 'invoice.paid', 'payment.updated' or 'deposit.confirmed'
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
@@ -20,6 +20,8 @@ pub enum LightningError {
     StreamingError(String),
     #[error("Channel error: {0}")]
     ChannelError(String),
+    #[error("On-chain wallet error: {0}")]
+    WalletError(String),
     #[error("Not found: {0}")]
     NotFound(String),
     #[error("Parse error: {0}")]
@@ -27,6 +29,45 @@ pub enum LightningError {
     #[error("Network error: {0}")]
     /// Network error.
     NetworkError(String),
+    /// The node backend does not implement the requested feature.
+    #[error("Not supported by the node: {0}")]
+    Unsupported(String),
+}
+
+#[derive(Debug, Error)]
+pub enum SwapError {
+    #[error("Swap provider unreachable: {0}")]
+    ConnectionError(String),
+    /// The provider answered with an error of its own.
+    #[error("Swap provider error: {0}")]
+    ProviderError(String),
+    #[error("Parse error: {0}")]
+    Parse(String),
+}
+
+#[derive(Debug, Error)]
+pub enum RateError {
+    #[error("Rate provider unreachable: {0}")]
+    ConnectionError(String),
+    /// The provider has no rate for the currency.
+    #[error("No exchange rate for {0}")]
+    MissingRate(String),
+    #[error("Parse error: {0}")]
+    Parse(String),
+}
+
+#[derive(Debug, Error)]
+pub enum EmailError {
+    #[error("Email configuration error: {0}")]
+    Config(String),
+    /// The email could not be built, such as for a malformed address.
+    #[error("Invalid email: {0}")]
+    Message(String),
+    /// The SMTP server refused the email for good.
+    #[error("Email rejected: {0}")]
+    Rejected(String),
+    #[error("SMTP error: {0}")]
+    Transport(String),
 }
 
 /// Generic service error that can be used across all entities
@@ -44,6 +85,12 @@ pub enum ServiceError {
     #[error("Invalid operation: {message}")]
     InvalidOperation { message: String },
 
+    #[error("Unauthorized: {message}")]
+    Unauthorized { message: String },
+
+    #[error("Forbidden: {message}")]
+    Forbidden { message: String },
+
     #[error("Database error: {source}")]
     Database {
         #[from]
@@ -51,6 +98,8 @@ pub enum ServiceError {
     },
     #[error("External service error: {message}")]
     ExternalService { message: String },
+    #[error("Not supported: {message}")]
+    Unsupported { message: String },
     #[error("Internal error: {message}")]
     InternalError { message: String },
 }
@@ -85,4 +134,43 @@ impl ServiceError {
             message: message.into(),
         }
     }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::Unauthorized {
+            message: message.into(),
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::Forbidden {
+            message: message.into(),
+        }
+    }
+}
+
+impl From<SwapError> for ServiceError {
+    fn from(error: SwapError) -> Self {
+        Self::ExternalService {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<RateError> for ServiceError {
+    fn from(error: RateError) -> Self {
+        Self::ExternalService {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<LightningError> for ServiceError {
+    fn from(error: LightningError) -> Self {
+        match error {
+            LightningError::Unsupported(message) => Self::Unsupported { message },
+            error => Self::ExternalService {
+                message: error.to_string(),
+            },
+        }
+    }
 }
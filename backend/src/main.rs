@@ -10,12 +10,20 @@ mod service;
 mod utilities;
 
 use crate::common::common::ApiResponse;
-use axum::{Extension, Router, response::Json, routing::get};
+use axum::{Extension, Router, middleware, response::Json, routing::get};
 use chrono::{DateTime, Utc};
 use config::Config;
 use db::Database;
 use serde::Deserialize;
 use serde::Serialize;
+use service::event_service::{EVENT_BUS_CAPACITY, EventBus};
+use service::node_cln_service::{ClnConnection, ClnNode};
+use service::node_service::{LightningClient, LndConnection, LndNode};
+use service::notification_service::EmailSender;
+use service::rate_provider::RateStore;
+use service::swap_provider::{BoltzClient, SwapProvider};
+use std::net::SocketAddr;
+use std::sync::Arc;
 use tracing::info;
 use tracing_subscriber::fmt::init;
 
@@ -25,18 +33,136 @@ async fn main() {
     init();
 
     let config = Config::from_env().unwrap();
+    utilities::password::PasswordPolicy::init(&config).unwrap();
+    let lightning: Arc<dyn LightningClient> = match config.node_backend.as_str() {
+        "cln" => Arc::new(
+            ClnNode::new(ClnConnection::from_config(&config))
+                .await
+                .unwrap(),
+        ),
+        _ => Arc::new(
+            LndNode::new(LndConnection::from_config(&config))
+                .await
+                .unwrap(),
+        ),
+    };
+    info!("Connected to Lightning node {}", lightning.get_node_info());
+    let swap_provider: Arc<dyn SwapProvider> = Arc::new(BoltzClient::from_config(&config).unwrap());
+    let rates = Arc::new(RateStore::from_config(&config).unwrap());
+    let events = Arc::new(EventBus::new(EVENT_BUS_CAPACITY));
+    let email_sender = EmailSender::from_config(&config).unwrap();
+    let fiat_currencies = config.fiat_currencies.clone();
+    let rate_refresh_seconds = config.rate_refresh_seconds;
+    let onchain_batch_interval_seconds = config.onchain_batch_interval_seconds;
+    let encryption_key = config.encryption_key.clone();
+    let webhook_allow_private_networks = config.webhook_allow_private_networks;
     let db = Database::new(config).await.unwrap();
     let pool = db.pool().clone();
+    if let Some(admin) = service::user_service::UserService::new(&pool)
+        .bootstrap_admin()
+        .await
+        .unwrap()
+    {
+        info!("Gave the admin role to initial admin {}", admin.id);
+    }
+    tokio::spawn(service::invoice_service::run_settlement_watcher(
+        pool.clone(),
+        lightning.clone(),
+    ));
+    tokio::spawn(service::payment_service::run_payment_tracker(
+        pool.clone(),
+        lightning.clone(),
+    ));
+    tokio::spawn(service::invoice_service::run_hold_invoice_watcher(
+        pool.clone(),
+        lightning.clone(),
+    ));
+    tokio::spawn(service::deposit_service::run_deposit_watcher(
+        pool.clone(),
+        lightning.clone(),
+    ));
+    tokio::spawn(service::onchain_withdrawal_service::run_onchain_batcher(
+        pool.clone(),
+        lightning.clone(),
+        onchain_batch_interval_seconds,
+    ));
+    tokio::spawn(
+        service::onchain_withdrawal_service::run_onchain_batch_tracker(
+            pool.clone(),
+            lightning.clone(),
+        ),
+    );
+    if lightning.can_watch_addresses() {
+        tokio::spawn(service::swap_service::run_swap_watcher(
+            pool.clone(),
+            lightning.clone(),
+            swap_provider.clone(),
+        ));
+    } else {
+        info!("Swaps are disabled: this node backend cannot watch lockup addresses");
+    }
+    tokio::spawn(service::fiat_service::run_rate_recorder(
+        pool.clone(),
+        rates.clone(),
+        fiat_currencies,
+        rate_refresh_seconds,
+    ));
+    tokio::spawn(service::withdraw_service::run_withdraw_link_sweeper(
+        pool.clone(),
+    ));
+    tokio::spawn(service::event_service::run_event_listener(
+        pool.clone(),
+        events.clone(),
+    ));
+    tokio::spawn(service::webhook_service::run_webhook_dispatcher(
+        pool.clone(),
+        encryption_key,
+        webhook_allow_private_networks,
+    ));
+    match email_sender {
+        Some(sender) => {
+            tokio::spawn(service::notification_service::run_email_sender(
+                pool.clone(),
+                sender,
+            ));
+        }
+        None => tracing::warn!("SMTP_HOST is not set, emails stay queued until it is"),
+    }
     let app = Router::new()
         .route("/", get(handle_root))
         .nest("/api/user", api::user::routes::user_router().await)
         .nest("/api/role", api::role::routes::role_router().await)
-        .layer(Extension(pool));
+        .nest("/api/admin", api::admin::routes::admin_router().await)
+        .nest("/api/invoice", api::invoice::routes::invoice_router().await)
+        .nest("/api/payment", api::payment::routes::payment_router().await)
+        .nest("/api/onchain", api::onchain::routes::onchain_router().await)
+        .nest(
+            "/api/withdraw",
+            api::withdraw::routes::withdraw_router().await,
+        )
+        .nest("/api/swap", api::swap::routes::swap_router().await)
+        .nest(
+            "/api/webhooks",
+            api::webhook::routes::webhook_router().await,
+        )
+        .nest("/api/events", api::event::routes::event_router().await)
+        .merge(api::lnurl::routes::lnurl_router().await)
+        .layer(Extension(pool))
+        .layer(Extension(lightning))
+        .layer(Extension(swap_provider))
+        .layer(Extension(rates))
+        .layer(Extension(events))
+        .layer(middleware::from_fn(utilities::client::set_request_id));
 
     let bind_address = format!("0.0.0.0:{}", 3035);
     let listener = tokio::net::TcpListener::bind(bind_address).await.unwrap();
     info!("Started Moyabank  server on port {}", 3035);
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 
     //  println!("Hello, world!");
 }
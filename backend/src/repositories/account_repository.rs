@@ -32,6 +32,7 @@ impl<'a> AccountRepository<'a> {
             user_id as "user_id!",
             balance as "balance!",
             is_active as "is_active!",
+            display_currency as "display_currency!",
             created_at as "created_at!: DateTime<Utc>",
             updated_at as "updated_at!: DateTime<Utc>",
             is_deleted as "is_deleted!",
@@ -64,6 +65,7 @@ impl<'a> AccountRepository<'a> {
                 user_id as "user_id!",
                 balance as "balance!",
                 is_active as "is_active!",
+                display_currency as "display_currency!",
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>",
                 is_deleted as "is_deleted!",
@@ -80,6 +82,40 @@ impl<'a> AccountRepository<'a> {
         Ok(account)
     }
 
+    /// Retrieves every account owned by a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - user_id to search for
+    ///
+    /// # Returns
+    /// All accounts of the user that are not deleted, oldest first
+    pub async fn get_accounts_by_user_id(&self, user_id: &str) -> Result<Vec<Account>> {
+        let accounts = sqlx::query_as!(
+            Account,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                balance as "balance!",
+                is_active as "is_active!",
+                display_currency as "display_currency!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM accounts
+            WHERE user_id = $1
+              AND is_deleted = false
+            ORDER BY created_at ASC
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(accounts)
+    }
+
     /// Checks if a account already exists in the system.
     ///
     /// # Arguments
@@ -0,0 +1,92 @@
+// DB Repository for API key management Operations
+
+use crate::db::models::ApiKey;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct ApiKeyRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> ApiKeyRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves an API key by its public prefix.
+    ///
+    /// # Arguments
+    /// * 'prefix' - Public part of the key used for lookup
+    ///
+    /// # Returns
+    /// 'Some(ApiKey)' if found, 'None' otherwise. Revoked and expired keys are returned too
+    pub async fn get_api_key_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>> {
+        let api_key = sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                name as "name!",
+                prefix as "prefix!",
+                secret_hash as "secret_hash!",
+                scopes as "scopes!",
+                spend_limit_msat as "spend_limit_msat?",
+                spent_msat as "spent_msat!",
+                expires_at as "expires_at?: DateTime<Utc>",
+                last_used_at as "last_used_at?: DateTime<Utc>",
+                revoked_at as "revoked_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM api_keys
+            WHERE prefix = $1
+            "#,
+            prefix
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(api_key)
+    }
+
+    /// Retrieves the API keys of a user that have not been revoked.
+    ///
+    /// # Arguments
+    /// * 'user_id' - Owner of the keys
+    ///
+    /// # Returns
+    /// The user's keys, newest first
+    pub async fn get_api_keys_by_user_id(&self, user_id: &str) -> Result<Vec<ApiKey>> {
+        let api_keys = sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                name as "name!",
+                prefix as "prefix!",
+                secret_hash as "secret_hash!",
+                scopes as "scopes!",
+                spend_limit_msat as "spend_limit_msat?",
+                spent_msat as "spent_msat!",
+                expires_at as "expires_at?: DateTime<Utc>",
+                last_used_at as "last_used_at?: DateTime<Utc>",
+                revoked_at as "revoked_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM api_keys
+            WHERE user_id = $1
+              AND revoked_at IS NULL
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(api_keys)
+    }
+}
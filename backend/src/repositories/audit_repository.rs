@@ -0,0 +1,110 @@
+// DB Repository for audit log Operations
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::{AuditEvent, AuditEventFilter};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct AuditRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> AuditRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves audit events matching a filter.
+    ///
+    /// # Arguments
+    /// * 'filter' - Fields to match, unset ones match everything
+    /// * 'pagination' - Page to return
+    ///
+    /// # Returns
+    /// Matching events, newest first
+    pub async fn get_events(
+        &self,
+        filter: &AuditEventFilter,
+        pagination: &PaginationFilter,
+    ) -> Result<Vec<AuditEvent>> {
+        let limit = pagination.limit();
+        let offset = pagination.offset();
+
+        let events = sqlx::query_as!(
+            AuditEvent,
+            r#"
+            SELECT
+                id as "id!",
+                seq as "seq!",
+                actor_id as "actor_id?",
+                action as "action!",
+                target_type as "target_type!",
+                target_id as "target_id!",
+                before as "before?",
+                after as "after?",
+                request_id as "request_id?",
+                ip_address as "ip_address?",
+                prev_hash as "prev_hash?",
+                hash as "hash!",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM audit_events
+            WHERE ($1::TEXT IS NULL OR actor_id = $1)
+              AND ($2::TEXT IS NULL OR action = $2)
+              AND ($3::TEXT IS NULL OR target_type = $3)
+              AND ($4::TEXT IS NULL OR target_id = $4)
+              AND ($5::TEXT IS NULL OR request_id = $5)
+              AND ($6::TIMESTAMPTZ IS NULL OR created_at >= $6)
+              AND ($7::TIMESTAMPTZ IS NULL OR created_at <= $7)
+            ORDER BY seq DESC
+            LIMIT $8 OFFSET $9
+            "#,
+            filter.actor_id,
+            filter.action,
+            filter.target_type,
+            filter.target_id,
+            filter.request_id,
+            filter.from,
+            filter.to,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Counts the audit events matching a filter.
+    ///
+    /// # Arguments
+    /// * 'filter' - Fields to match, unset ones match everything
+    pub async fn count_events(&self, filter: &AuditEventFilter) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM audit_events
+            WHERE ($1::TEXT IS NULL OR actor_id = $1)
+              AND ($2::TEXT IS NULL OR action = $2)
+              AND ($3::TEXT IS NULL OR target_type = $3)
+              AND ($4::TEXT IS NULL OR target_id = $4)
+              AND ($5::TEXT IS NULL OR request_id = $5)
+              AND ($6::TIMESTAMPTZ IS NULL OR created_at >= $6)
+              AND ($7::TIMESTAMPTZ IS NULL OR created_at <= $7)
+            "#,
+            filter.actor_id,
+            filter.action,
+            filter.target_type,
+            filter.target_id,
+            filter.request_id,
+            filter.from,
+            filter.to
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
+}
@@ -0,0 +1,185 @@
+// DB Repository for on-chain deposit Operations
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::{Deposit, DepositAddress};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct DepositRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> DepositRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves a deposit address by the address itself.
+    ///
+    /// # Arguments
+    /// * 'address' - Bitcoin address
+    ///
+    /// # Returns
+    /// 'Some(DepositAddress)' if it was handed out to an account, 'None' otherwise
+    pub async fn get_deposit_address(&self, address: &str) -> Result<Option<DepositAddress>> {
+        let deposit_address = sqlx::query_as!(
+            DepositAddress,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                address as "address!",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM deposit_addresses
+            WHERE address = $1
+            "#,
+            address
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(deposit_address)
+    }
+
+    /// Retrieves the deposit addresses handed out to a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - Owner of the addresses
+    ///
+    /// # Returns
+    /// All of the user's deposit addresses, newest first
+    pub async fn get_deposit_addresses_by_user_id(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<DepositAddress>> {
+        let deposit_addresses = sqlx::query_as!(
+            DepositAddress,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                address as "address!",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM deposit_addresses
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(deposit_addresses)
+    }
+
+    /// Retrieves the deposits of a user, newest first.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    /// * 'pagination' - Page to return
+    ///
+    /// # Returns
+    /// The user's deposits on that page, pending ones included
+    pub async fn get_deposits_by_user_id(
+        &self,
+        user_id: &str,
+        pagination: &PaginationFilter,
+    ) -> Result<Vec<Deposit>> {
+        let limit = pagination.limit();
+        let offset = pagination.offset();
+
+        let deposits = sqlx::query_as!(
+            Deposit,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                address as "address!",
+                txid as "txid!",
+                vout as "vout!",
+                amount_msat as "amount_msat!",
+                confirmations as "confirmations!",
+                block_height as "block_height?",
+                status as "status!",
+                credited_at as "credited_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM deposits
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            user_id,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(deposits)
+    }
+
+    /// Counts the deposits of a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    pub async fn count_deposits_by_user_id(&self, user_id: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM deposits
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
+
+    /// Retrieves the deposits a reorg could still affect.
+    ///
+    /// # Arguments
+    /// * 'start_height' - Lowest block height being rescanned
+    ///
+    /// # Returns
+    /// Pending deposits, and credited ones unconfirmed or confirmed at or
+    /// after 'start_height'
+    pub async fn get_deposits_in_flight(&self, start_height: i32) -> Result<Vec<Deposit>> {
+        let deposits = sqlx::query_as!(
+            Deposit,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                address as "address!",
+                txid as "txid!",
+                vout as "vout!",
+                amount_msat as "amount_msat!",
+                confirmations as "confirmations!",
+                block_height as "block_height?",
+                status as "status!",
+                credited_at as "credited_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM deposits
+            WHERE status = 'pending'
+               OR (status = 'credited' AND (block_height IS NULL OR block_height >= $1))
+            ORDER BY created_at ASC
+            "#,
+            start_height
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(deposits)
+    }
+}
@@ -0,0 +1,91 @@
+// DB Repository for pending email change operations
+
+use crate::db::models::EmailVerification;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct EmailVerificationRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> EmailVerificationRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves a usable verification for a user by its token hash.
+    ///
+    /// # Arguments
+    /// * 'user_id' - Owner of the verification
+    /// * 'token_hash' - SHA-256 hex digest of the emailed token
+    ///
+    /// # Returns
+    /// 'Some(EmailVerification)' if found, unconsumed and unexpired, 'None' otherwise
+    pub async fn get_pending_by_token(
+        &self,
+        user_id: &str,
+        token_hash: &str,
+    ) -> Result<Option<EmailVerification>> {
+        let verification = sqlx::query_as!(
+            EmailVerification,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                email as "email!",
+                token_hash as "token_hash!",
+                expires_at as "expires_at!: DateTime<Utc>",
+                consumed_at as "consumed_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM email_verifications
+            WHERE user_id = $1
+              AND token_hash = $2
+              AND consumed_at IS NULL
+              AND expires_at > now()
+            "#,
+            user_id,
+            token_hash
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(verification)
+    }
+
+    /// Retrieves the most recent pending email change of a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - Owner of the verification
+    ///
+    /// # Returns
+    /// 'Some(EmailVerification)' if an unconsumed, unexpired change exists, 'None' otherwise
+    pub async fn get_latest_pending(&self, user_id: &str) -> Result<Option<EmailVerification>> {
+        let verification = sqlx::query_as!(
+            EmailVerification,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                email as "email!",
+                token_hash as "token_hash!",
+                expires_at as "expires_at!: DateTime<Utc>",
+                consumed_at as "consumed_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM email_verifications
+            WHERE user_id = $1
+              AND consumed_at IS NULL
+              AND expires_at > now()
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+            user_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(verification)
+    }
+}
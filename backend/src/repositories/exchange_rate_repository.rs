@@ -0,0 +1,47 @@
+// DB Repository for exchange rate Operations
+
+use crate::db::models::ExchangeRate;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct ExchangeRateRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> ExchangeRateRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the most recently recorded rate of each currency.
+    ///
+    /// # Arguments
+    /// * 'currencies' - Currency codes to look up
+    ///
+    /// # Returns
+    /// One rate per currency that has been recorded at least once
+    pub async fn get_latest_rates(&self, currencies: &[String]) -> Result<Vec<ExchangeRate>> {
+        let rates = sqlx::query_as!(
+            ExchangeRate,
+            r#"
+            SELECT DISTINCT ON (currency)
+                id as "id!",
+                currency as "currency!",
+                rate as "rate!",
+                source as "source!",
+                fetched_at as "fetched_at!: DateTime<Utc>"
+            FROM exchange_rates
+            WHERE currency = ANY($1)
+            ORDER BY currency, fetched_at DESC
+            "#,
+            currencies
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rates)
+    }
+}
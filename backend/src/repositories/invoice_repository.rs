@@ -0,0 +1,275 @@
+// DB Repository for invoice management Operations
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::Invoice;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct InvoiceRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> InvoiceRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves an invoice by its payment hash.
+    ///
+    /// # Arguments
+    /// * 'payment_hash' - Hex encoded payment hash
+    ///
+    /// # Returns
+    /// 'Some(Invoice)' if found, 'None' otherwise
+    pub async fn get_invoice_by_payment_hash(&self, payment_hash: &str) -> Result<Option<Invoice>> {
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                payment_request as "payment_request!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat?",
+                amount_paid_msat as "amount_paid_msat?",
+                fiat_currency as "fiat_currency?",
+                fiat_amount as "fiat_amount?",
+                fiat_rate as "fiat_rate?",
+                memo as "memo!",
+                description_hash as "description_hash?",
+                comment as "comment?",
+                source as "source!",
+                status as "status!",
+                preimage as "preimage?",
+                expires_at as "expires_at!: DateTime<Utc>",
+                settled_at as "settled_at?: DateTime<Utc>",
+                accepted_at as "accepted_at?: DateTime<Utc>",
+                htlc_expiry_height as "htlc_expiry_height?",
+                settle_by as "settle_by?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM invoices
+            WHERE payment_hash = $1
+            "#,
+            payment_hash
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    /// Retrieves an invoice by its ID.
+    ///
+    /// # Arguments
+    /// * 'id' - Invoice ID
+    ///
+    /// # Returns
+    /// 'Some(Invoice)' if found, 'None' otherwise
+    pub async fn get_invoice_by_id(&self, id: &str) -> Result<Option<Invoice>> {
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                payment_request as "payment_request!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat?",
+                amount_paid_msat as "amount_paid_msat?",
+                fiat_currency as "fiat_currency?",
+                fiat_amount as "fiat_amount?",
+                fiat_rate as "fiat_rate?",
+                memo as "memo!",
+                description_hash as "description_hash?",
+                comment as "comment?",
+                source as "source!",
+                status as "status!",
+                preimage as "preimage?",
+                expires_at as "expires_at!: DateTime<Utc>",
+                settled_at as "settled_at?: DateTime<Utc>",
+                accepted_at as "accepted_at?: DateTime<Utc>",
+                htlc_expiry_height as "htlc_expiry_height?",
+                settle_by as "settle_by?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM invoices
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    /// Retrieves the invoices of a user, newest first.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    /// * 'pagination' - Page to return
+    ///
+    /// # Returns
+    /// The user's invoices on that page
+    pub async fn get_invoices_by_user_id(
+        &self,
+        user_id: &str,
+        pagination: &PaginationFilter,
+    ) -> Result<Vec<Invoice>> {
+        let limit = pagination.limit();
+        let offset = pagination.offset();
+
+        let invoices = sqlx::query_as!(
+            Invoice,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                payment_request as "payment_request!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat?",
+                amount_paid_msat as "amount_paid_msat?",
+                fiat_currency as "fiat_currency?",
+                fiat_amount as "fiat_amount?",
+                fiat_rate as "fiat_rate?",
+                memo as "memo!",
+                description_hash as "description_hash?",
+                comment as "comment?",
+                source as "source!",
+                status as "status!",
+                preimage as "preimage?",
+                expires_at as "expires_at!: DateTime<Utc>",
+                settled_at as "settled_at?: DateTime<Utc>",
+                accepted_at as "accepted_at?: DateTime<Utc>",
+                htlc_expiry_height as "htlc_expiry_height?",
+                settle_by as "settle_by?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM invoices
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            user_id,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(invoices)
+    }
+
+    /// Counts the invoices of a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    pub async fn count_invoices_by_user_id(&self, user_id: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM invoices
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
+
+    /// Retrieves every invoice still waiting for payment.
+    ///
+    /// # Returns
+    /// Open invoices, oldest first, including ones that have passed their expiry
+    pub async fn get_open_invoices(&self) -> Result<Vec<Invoice>> {
+        let invoices = sqlx::query_as!(
+            Invoice,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                payment_request as "payment_request!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat?",
+                amount_paid_msat as "amount_paid_msat?",
+                fiat_currency as "fiat_currency?",
+                fiat_amount as "fiat_amount?",
+                fiat_rate as "fiat_rate?",
+                memo as "memo!",
+                description_hash as "description_hash?",
+                comment as "comment?",
+                source as "source!",
+                status as "status!",
+                preimage as "preimage?",
+                expires_at as "expires_at!: DateTime<Utc>",
+                settled_at as "settled_at?: DateTime<Utc>",
+                accepted_at as "accepted_at?: DateTime<Utc>",
+                htlc_expiry_height as "htlc_expiry_height?",
+                settle_by as "settle_by?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM invoices
+            WHERE status = 'open'
+            ORDER BY created_at ASC
+            "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(invoices)
+    }
+
+    /// Retrieves every hold invoice still waiting to be paid, settled or canceled.
+    ///
+    /// # Returns
+    /// Open and accepted hold invoices, oldest first
+    pub async fn get_hold_invoices_in_flight(&self) -> Result<Vec<Invoice>> {
+        let invoices = sqlx::query_as!(
+            Invoice,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                payment_request as "payment_request!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat?",
+                amount_paid_msat as "amount_paid_msat?",
+                fiat_currency as "fiat_currency?",
+                fiat_amount as "fiat_amount?",
+                fiat_rate as "fiat_rate?",
+                memo as "memo!",
+                description_hash as "description_hash?",
+                comment as "comment?",
+                source as "source!",
+                status as "status!",
+                preimage as "preimage?",
+                expires_at as "expires_at!: DateTime<Utc>",
+                settled_at as "settled_at?: DateTime<Utc>",
+                accepted_at as "accepted_at?: DateTime<Utc>",
+                htlc_expiry_height as "htlc_expiry_height?",
+                settle_by as "settle_by?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM invoices
+            WHERE source = 'hold'
+              AND status IN ('open', 'accepted')
+            ORDER BY created_at ASC
+            "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(invoices)
+    }
+}
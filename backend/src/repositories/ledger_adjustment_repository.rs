@@ -0,0 +1,116 @@
+// DB Repository for ledger adjustment Operations
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::LedgerAdjustment;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct LedgerAdjustmentRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> LedgerAdjustmentRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves a ledger adjustment by its id.
+    ///
+    /// # Arguments
+    /// * 'id' - id to search for
+    ///
+    /// # Returns
+    /// 'Some(LedgerAdjustment)' if found, 'None' otherwise
+    pub async fn get_adjustment_by_id(&self, id: &str) -> Result<Option<LedgerAdjustment>> {
+        let adjustment = sqlx::query_as!(
+            LedgerAdjustment,
+            r#"
+            SELECT
+                id as "id!",
+                account_id as "account_id!",
+                amount_msat as "amount_msat!",
+                reason as "reason!",
+                status as "status!",
+                requested_by as "requested_by!",
+                reviewed_by as "reviewed_by?",
+                reviewed_at as "reviewed_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM ledger_adjustments
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(adjustment)
+    }
+
+    /// Retrieves ledger adjustments, optionally only those in one status.
+    ///
+    /// # Arguments
+    /// * 'status' - 'pending', 'applied' or 'rejected', or 'None' for all
+    /// * 'pagination' - Page to return
+    ///
+    /// # Returns
+    /// Matching adjustments, newest first
+    pub async fn get_adjustments(
+        &self,
+        status: Option<&str>,
+        pagination: &PaginationFilter,
+    ) -> Result<Vec<LedgerAdjustment>> {
+        let limit = pagination.limit();
+        let offset = pagination.offset();
+
+        let adjustments = sqlx::query_as!(
+            LedgerAdjustment,
+            r#"
+            SELECT
+                id as "id!",
+                account_id as "account_id!",
+                amount_msat as "amount_msat!",
+                reason as "reason!",
+                status as "status!",
+                requested_by as "requested_by!",
+                reviewed_by as "reviewed_by?",
+                reviewed_at as "reviewed_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM ledger_adjustments
+            WHERE $1::TEXT IS NULL OR status = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            status,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(adjustments)
+    }
+
+    /// Counts ledger adjustments, optionally only those in one status.
+    ///
+    /// # Arguments
+    /// * 'status' - 'pending', 'applied' or 'rejected', or 'None' for all
+    pub async fn count_adjustments(&self, status: Option<&str>) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM ledger_adjustments
+            WHERE $1::TEXT IS NULL OR status = $1
+            "#,
+            status
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
+}
@@ -0,0 +1,85 @@
+// DB Repository for LNURL-auth operations
+
+use crate::db::models::{LnurlAuthChallenge, User};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct LnurlAuthRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> LnurlAuthRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves a challenge by its k1.
+    ///
+    /// # Arguments
+    /// * 'k1' - Hex encoded challenge handed to the wallet
+    ///
+    /// # Returns
+    /// 'Some(LnurlAuthChallenge)' if found, 'None' otherwise
+    pub async fn get_challenge_by_k1(&self, k1: &str) -> Result<Option<LnurlAuthChallenge>> {
+        let challenge = sqlx::query_as!(
+            LnurlAuthChallenge,
+            r#"
+            SELECT
+                k1 as "k1!",
+                action as "action!",
+                user_id as "user_id?",
+                linking_key as "linking_key?",
+                status as "status!",
+                expires_at as "expires_at!: DateTime<Utc>",
+                verified_at as "verified_at?: DateTime<Utc>",
+                consumed_at as "consumed_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM lnurl_auth_challenges
+            WHERE k1 = $1
+            "#,
+            k1
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(challenge)
+    }
+
+    /// Retrieves the user a wallet linking key signs in as.
+    ///
+    /// # Arguments
+    /// * 'linking_key' - Hex encoded compressed public key of the wallet
+    ///
+    /// # Returns
+    /// 'Some(User)' if the key is linked to a user that is not deleted, 'None' otherwise
+    pub async fn get_user_by_linking_key(&self, linking_key: &str) -> Result<Option<User>> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT
+                u.id,
+                u.role_id,
+                u.username,
+                u.password_hash,
+                u.email,
+                u.is_active,
+                u.created_at,
+                u.updated_at,
+                u.is_deleted,
+                u.deleted_at
+            FROM lnurl_auth_keys k
+            JOIN users u ON u.id = k.user_id
+            WHERE k.linking_key = $1
+              AND u.is_deleted = false
+            "#,
+            linking_key
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(user)
+    }
+}
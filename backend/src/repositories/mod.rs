@@ -1,4 +1,18 @@
 pub mod account_repository;
+pub mod api_key_repository;
+pub mod audit_repository;
+pub mod deposit_repository;
+pub mod email_verification_repository;
+pub mod exchange_rate_repository;
+pub mod invoice_repository;
+pub mod ledger_adjustment_repository;
+pub mod lnurl_auth_repository;
+pub mod notification_repository;
+pub mod offer_repository;
+pub mod onchain_withdrawal_repository;
 pub mod role_repository;
+pub mod swap_repository;
 pub mod transaction_repository;
 pub mod user_repository;
+pub mod webhook_repository;
+pub mod withdraw_link_repository;
@@ -0,0 +1,48 @@
+// DB Repository for notification preference Operations
+
+use crate::db::models::NotificationPreferences;
+use anyhow::Result;
+use sqlx::PgPool;
+
+pub struct NotificationRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> NotificationRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the notification preferences a user has saved.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    ///
+    /// # Returns
+    /// 'Some(NotificationPreferences)' if the user changed any, 'None' otherwise
+    pub async fn get_preferences_by_user_id(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<NotificationPreferences>> {
+        let preferences = sqlx::query_as!(
+            NotificationPreferences,
+            r#"
+            SELECT
+                payment_received as "payment_received!",
+                payment_sent as "payment_sent!",
+                large_withdrawal as "large_withdrawal!",
+                new_login as "new_login!",
+                password_changed as "password_changed!"
+            FROM notification_preferences
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(preferences)
+    }
+}
@@ -0,0 +1,85 @@
+// DB Repository for BOLT12 offer Operations
+
+use crate::db::models::Offer;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct OfferRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> OfferRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves an offer by the ID the node derives from it.
+    ///
+    /// # Arguments
+    /// * 'offer_id' - Hex encoded BOLT12 offer ID
+    ///
+    /// # Returns
+    /// 'Some(Offer)' if found, 'None' otherwise. Disabled offers are returned too
+    pub async fn get_offer_by_offer_id(&self, offer_id: &str) -> Result<Option<Offer>> {
+        let offer = sqlx::query_as!(
+            Offer,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                offer as "offer!",
+                offer_id as "offer_id!",
+                description as "description!",
+                amount_msat as "amount_msat?",
+                status as "status!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM offers
+            WHERE offer_id = $1
+            "#,
+            offer_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(offer)
+    }
+
+    /// Retrieves the offers created by a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - Owner of the offers
+    ///
+    /// # Returns
+    /// All of the user's offers, newest first
+    pub async fn get_offers_by_user_id(&self, user_id: &str) -> Result<Vec<Offer>> {
+        let offers = sqlx::query_as!(
+            Offer,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                offer as "offer!",
+                offer_id as "offer_id!",
+                description as "description!",
+                amount_msat as "amount_msat?",
+                status as "status!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM offers
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(offers)
+    }
+}
@@ -0,0 +1,160 @@
+// DB Repository for on-chain withdrawal Operations
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::{OnchainBatch, OnchainWithdrawal};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct OnchainWithdrawalRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> OnchainWithdrawalRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the on-chain withdrawals of a user, newest first.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    /// * 'pagination' - Page to return
+    ///
+    /// # Returns
+    /// The user's withdrawals on that page, with the txid of their batch
+    pub async fn get_withdrawals_by_user_id(
+        &self,
+        user_id: &str,
+        pagination: &PaginationFilter,
+    ) -> Result<Vec<OnchainWithdrawal>> {
+        let limit = pagination.limit();
+        let offset = pagination.offset();
+
+        let withdrawals = sqlx::query_as!(
+            OnchainWithdrawal,
+            r#"
+            SELECT
+                w.id as "id!",
+                w.user_id as "user_id!",
+                w.account_id as "account_id!",
+                w.transaction_id as "transaction_id!",
+                w.batch_id as "batch_id?",
+                b.txid as "txid?",
+                w.address as "address!",
+                w.amount_msat as "amount_msat!",
+                w.fee_tier as "fee_tier!",
+                w.fee_reserve_msat as "fee_reserve_msat!",
+                w.fee_msat as "fee_msat?",
+                w.status as "status!",
+                w.created_at as "created_at!: DateTime<Utc>",
+                w.updated_at as "updated_at!: DateTime<Utc>"
+            FROM onchain_withdrawals w
+            LEFT JOIN onchain_batches b ON b.id = w.batch_id
+            WHERE w.user_id = $1
+            ORDER BY w.created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            user_id,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(withdrawals)
+    }
+
+    /// Counts the on-chain withdrawals of a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    pub async fn count_withdrawals_by_user_id(&self, user_id: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM onchain_withdrawals
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
+
+    /// Retrieves the withdrawals paid out by a batch.
+    ///
+    /// # Arguments
+    /// * 'batch_id' - Batch ID
+    pub async fn get_withdrawals_by_batch_id(
+        &self,
+        batch_id: &str,
+    ) -> Result<Vec<OnchainWithdrawal>> {
+        let withdrawals = sqlx::query_as!(
+            OnchainWithdrawal,
+            r#"
+            SELECT
+                w.id as "id!",
+                w.user_id as "user_id!",
+                w.account_id as "account_id!",
+                w.transaction_id as "transaction_id!",
+                w.batch_id as "batch_id?",
+                b.txid as "txid?",
+                w.address as "address!",
+                w.amount_msat as "amount_msat!",
+                w.fee_tier as "fee_tier!",
+                w.fee_reserve_msat as "fee_reserve_msat!",
+                w.fee_msat as "fee_msat?",
+                w.status as "status!",
+                w.created_at as "created_at!: DateTime<Utc>",
+                w.updated_at as "updated_at!: DateTime<Utc>"
+            FROM onchain_withdrawals w
+            JOIN onchain_batches b ON b.id = w.batch_id
+            WHERE w.batch_id = $1
+            ORDER BY w.created_at ASC
+            "#,
+            batch_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(withdrawals)
+    }
+
+    /// Retrieves the batches broadcast but not yet confirmed.
+    ///
+    /// # Returns
+    /// Unconfirmed batches, oldest first
+    pub async fn get_batches_in_flight(&self) -> Result<Vec<OnchainBatch>> {
+        let batches = sqlx::query_as!(
+            OnchainBatch,
+            r#"
+            SELECT
+                id as "id!",
+                fee_tier as "fee_tier!",
+                txid as "txid?",
+                replaced_txids as "replaced_txids!",
+                sat_per_vbyte as "sat_per_vbyte!",
+                fee_sat as "fee_sat?",
+                status as "status!",
+                broadcast_height as "broadcast_height!",
+                bumped_height as "bumped_height?",
+                bump_count as "bump_count!",
+                confirmed_at as "confirmed_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM onchain_batches
+            WHERE status = 'broadcast'
+            ORDER BY created_at ASC
+            "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(batches)
+    }
+}
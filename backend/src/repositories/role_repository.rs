@@ -1,5 +1,6 @@
 // DB Repository for role management Operations
 
+use crate::common::common::PaginationFilter;
 use crate::db::models::Role;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
@@ -39,37 +40,47 @@ impl<'a> RoleRepository<'a> {
         Ok(count.count > Some(0))
     }
 
-    /// Checks if an role already exists in the system.
+    /// Retrieves a role by their id.
     ///
     /// # Arguments
-    /// * 'role_id' - User id to check
+    /// * 'id' - id to search for
     ///
     /// # Returns
-    /// 'true' if a role with this name exists (and is not deleted)
-    pub async fn role_exists(&self, role_id: &str) -> Result<bool> {
-        let count = sqlx::query!(
+    /// 'Some(Role)' if found and active, 'None' otherwise
+    pub async fn get_role_id(&self, id: &str) -> Result<Option<Role>> {
+        let role = sqlx::query_as!(
+            Role,
             r#"
-            SELECT COUNT(*)::BIGINT AS count
+            SELECT
+                id as "id!",
+                name as "name!",
+                is_active as "is_active!",
+                max_fee_msat as "max_fee_msat?",
+                max_fee_percent as "max_fee_percent?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: DateTime<Utc>"
             FROM roles
             WHERE id = $1
               AND is_deleted = false
             "#,
-            role_id
+            id
         )
-        .fetch_one(self.pool)
+        .fetch_optional(self.pool)
         .await?;
 
-        Ok(count.count > Some(0))
+        Ok(role)
     }
 
-    /// Retrieves a role by their id.
+    /// Retrieves a role by its name.
     ///
     /// # Arguments
-    /// * 'id' - id to search for
+    /// * 'name' - name to search for
     ///
     /// # Returns
-    /// 'Some(Role)' if found and active, 'None' otherwise
-    pub async fn get_role_id(&self, id: &str) -> Result<Option<Role>> {
+    /// 'Some(Role)' if found and not deleted, 'None' otherwise
+    pub async fn get_role_by_name(&self, name: &str) -> Result<Option<Role>> {
         let role = sqlx::query_as!(
             Role,
             r#"
@@ -77,19 +88,133 @@ impl<'a> RoleRepository<'a> {
                 id as "id!",
                 name as "name!",
                 is_active as "is_active!",
+                max_fee_msat as "max_fee_msat?",
+                max_fee_percent as "max_fee_percent?",
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>",
                 is_deleted as "is_deleted!",
                 deleted_at as "deleted_at?: DateTime<Utc>"
             FROM roles
-            WHERE id = $1
+            WHERE name = $1
               AND is_deleted = false
             "#,
-            id
+            name
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(role)
+    }
+
+    /// Retrieves the role currently assigned to a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    ///
+    /// # Returns
+    /// 'Some(Role)' if the user exists, 'None' otherwise
+    pub async fn get_role_by_user_id(&self, user_id: &str) -> Result<Option<Role>> {
+        let role = sqlx::query_as!(
+            Role,
+            r#"
+            SELECT
+                roles.id as "id!",
+                roles.name as "name!",
+                roles.is_active as "is_active!",
+                roles.max_fee_msat as "max_fee_msat?",
+                roles.max_fee_percent as "max_fee_percent?",
+                roles.created_at as "created_at!: DateTime<Utc>",
+                roles.updated_at as "updated_at!: DateTime<Utc>",
+                roles.is_deleted as "is_deleted!",
+                roles.deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM users
+            JOIN roles ON roles.id = users.role_id
+            WHERE users.id = $1
+            "#,
+            user_id
         )
         .fetch_optional(self.pool)
         .await?;
 
         Ok(role)
     }
+
+    /// Retrieves a page of roles, newest first.
+    ///
+    /// # Arguments
+    /// * 'pagination' - Page and page size to fetch
+    ///
+    /// # Returns
+    /// The roles on the requested page that are not deleted
+    pub async fn list_roles(&self, pagination: &PaginationFilter) -> Result<Vec<Role>> {
+        let roles = sqlx::query_as!(
+            Role,
+            r#"
+            SELECT
+                id as "id!",
+                name as "name!",
+                is_active as "is_active!",
+                max_fee_msat as "max_fee_msat?",
+                max_fee_percent as "max_fee_percent?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM roles
+            WHERE is_deleted = false
+            ORDER BY created_at DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    /// Counts the roles that are not deleted.
+    ///
+    /// # Returns
+    /// Total number of roles, used for pagination metadata
+    pub async fn count_roles(&self) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM roles
+            WHERE is_deleted = false
+            "#
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
+
+    /// Checks if another role already uses a name.
+    ///
+    /// # Arguments
+    /// * 'name' - Name to check
+    /// * 'role_id' - Role to exclude from the check
+    ///
+    /// # Returns
+    /// 'true' if a different role with this name exists (and is not deleted)
+    pub async fn role_name_taken(&self, name: &str, role_id: &str) -> Result<bool> {
+        let count = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM roles
+            WHERE name = $1
+              AND id <> $2
+              AND is_deleted = false
+            "#,
+            name,
+            role_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(count.count > Some(0))
+    }
 }
@@ -0,0 +1,191 @@
+// DB Repository for swap Operations
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::Swap;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct SwapRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> SwapRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves a swap by its ID.
+    ///
+    /// # Arguments
+    /// * 'id' - Swap ID
+    ///
+    /// # Returns
+    /// 'Some(Swap)' if found, 'None' otherwise
+    pub async fn get_swap_by_id(&self, id: &str) -> Result<Option<Swap>> {
+        let swap = sqlx::query_as!(
+            Swap,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                kind as "kind!",
+                provider_swap_id as "provider_swap_id!",
+                provider_status as "provider_status?",
+                status as "status!",
+                invoice as "invoice!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat!",
+                onchain_amount_sat as "onchain_amount_sat!",
+                lockup_address as "lockup_address!",
+                destination_address as "destination_address!",
+                redeem_script as "redeem_script!",
+                timeout_block_height as "timeout_block_height!",
+                start_height as "start_height!",
+                preimage as "preimage?",
+                private_key as "private_key!",
+                transaction_id as "transaction_id?",
+                fee_reserve_msat as "fee_reserve_msat!",
+                routing_fee_msat as "routing_fee_msat?",
+                fee_msat as "fee_msat?",
+                lockup_txid as "lockup_txid?",
+                sweep_txid as "sweep_txid?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM swaps
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(swap)
+    }
+
+    /// Retrieves the swaps of a user, newest first.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    /// * 'pagination' - Page to return
+    pub async fn get_swaps_by_user_id(
+        &self,
+        user_id: &str,
+        pagination: &PaginationFilter,
+    ) -> Result<Vec<Swap>> {
+        let limit = pagination.limit();
+        let offset = pagination.offset();
+
+        let swaps = sqlx::query_as!(
+            Swap,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                kind as "kind!",
+                provider_swap_id as "provider_swap_id!",
+                provider_status as "provider_status?",
+                status as "status!",
+                invoice as "invoice!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat!",
+                onchain_amount_sat as "onchain_amount_sat!",
+                lockup_address as "lockup_address!",
+                destination_address as "destination_address!",
+                redeem_script as "redeem_script!",
+                timeout_block_height as "timeout_block_height!",
+                start_height as "start_height!",
+                preimage as "preimage?",
+                private_key as "private_key!",
+                transaction_id as "transaction_id?",
+                fee_reserve_msat as "fee_reserve_msat!",
+                routing_fee_msat as "routing_fee_msat?",
+                fee_msat as "fee_msat?",
+                lockup_txid as "lockup_txid?",
+                sweep_txid as "sweep_txid?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM swaps
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            user_id,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(swaps)
+    }
+
+    /// Counts the swaps of a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    pub async fn count_swaps_by_user_id(&self, user_id: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM swaps
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
+
+    /// Retrieves the swaps that have not reached a final state.
+    ///
+    /// # Returns
+    /// Open swaps, oldest first
+    pub async fn get_swaps_in_flight(&self) -> Result<Vec<Swap>> {
+        let swaps = sqlx::query_as!(
+            Swap,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                kind as "kind!",
+                provider_swap_id as "provider_swap_id!",
+                provider_status as "provider_status?",
+                status as "status!",
+                invoice as "invoice!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat!",
+                onchain_amount_sat as "onchain_amount_sat!",
+                lockup_address as "lockup_address!",
+                destination_address as "destination_address!",
+                redeem_script as "redeem_script!",
+                timeout_block_height as "timeout_block_height!",
+                start_height as "start_height!",
+                preimage as "preimage?",
+                private_key as "private_key!",
+                transaction_id as "transaction_id?",
+                fee_reserve_msat as "fee_reserve_msat!",
+                routing_fee_msat as "routing_fee_msat?",
+                fee_msat as "fee_msat?",
+                lockup_txid as "lockup_txid?",
+                sweep_txid as "sweep_txid?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM swaps
+            WHERE status NOT IN ('completed', 'refunded', 'expired', 'failed')
+            ORDER BY created_at ASC
+            "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(swaps)
+    }
+}
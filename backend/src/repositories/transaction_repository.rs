@@ -31,10 +31,15 @@ impl<'a> TransactionRepository<'a> {
             SELECT
                 id as "id!",
                 user_id as "user_id!",
+                account_id as "account_id?",
+                direction as "direction!",
                 invoice as "invoice!",
                 amount as "amount!",
+                fee_msat as "fee_msat!",
                 payment_hash as "payment_hash!",
                 payment_status as "payment_status!",
+                fiat_currency as "fiat_currency?",
+                fiat_rate as "fiat_rate?",
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>"
             FROM transactions
@@ -48,6 +53,47 @@ impl<'a> TransactionRepository<'a> {
         Ok(transaction)
     }
 
+    /// Retrieves the outgoing payment of an invoice that is in flight or has succeeded.
+    ///
+    /// # Arguments
+    /// * 'payment_hash' - Payment hash of the invoice
+    ///
+    /// # Returns
+    /// 'Some(Transaction)' if the invoice is being or has been paid, 'None' otherwise
+    pub async fn get_outgoing_payment_by_payment_hash(
+        &self,
+        payment_hash: &str,
+    ) -> Result<Option<Transaction>> {
+        let transaction = sqlx::query_as!(
+            Transaction,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id?",
+                direction as "direction!",
+                invoice as "invoice!",
+                amount as "amount!",
+                fee_msat as "fee_msat!",
+                payment_hash as "payment_hash!",
+                payment_status as "payment_status!",
+                fiat_currency as "fiat_currency?",
+                fiat_rate as "fiat_rate?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM transactions
+            WHERE payment_hash = $1
+              AND direction = 'outgoing'
+              AND payment_status IN ('pending', 'succeeded')
+            "#,
+            payment_hash
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(transaction)
+    }
+
     /// Retrieves the transaction for a specific user.
     ///
     /// # Arguments
@@ -69,10 +115,15 @@ impl<'a> TransactionRepository<'a> {
             SELECT
                 id as "id!",
                 user_id as "user_id!",
+                account_id as "account_id?",
+                direction as "direction!",
                 invoice as "invoice!",
                 amount as "amount!",
+                fee_msat as "fee_msat!",
                 payment_hash as "payment_hash!",
                 payment_status as "payment_status!",
+                fiat_currency as "fiat_currency?",
+                fiat_rate as "fiat_rate?",
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>"
             FROM transactions
@@ -89,4 +140,23 @@ impl<'a> TransactionRepository<'a> {
 
         Ok(transactions)
     }
+
+    /// Counts the transactions of a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    pub async fn count_transactions_by_user_id(&self, user_id: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM transactions
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
 }
@@ -3,6 +3,7 @@
 use anyhow::Result;
 use sqlx::PgPool;
 
+use crate::common::common::PaginationFilter;
 use crate::db::models::User;
 
 pub struct UserRepository<'a> {
@@ -143,4 +144,75 @@ impl<'a> UserRepository<'a> {
 
         Ok(user)
     }
+
+    /// Searches users by username or email, or by their exact ID.
+    ///
+    /// Returns matching users that are not deleted, newest first. Without a
+    /// query every user is returned.
+    pub async fn search_users(
+        &self,
+        query: Option<&str>,
+        pagination: &PaginationFilter,
+    ) -> Result<Vec<User>> {
+        let limit = pagination.limit();
+        let offset = pagination.offset();
+
+        let users = sqlx::query_as!(
+            User,
+            r#"
+            SELECT
+                id,
+                role_id,
+                username,
+                password_hash,
+                email,
+                is_active,
+                created_at,
+                updated_at,
+                is_deleted,
+                deleted_at
+            FROM users
+            WHERE is_deleted = false
+              AND (
+                $1::TEXT IS NULL
+                OR id = $1
+                OR username ILIKE '%' || $1 || '%'
+                OR email ILIKE '%' || $1 || '%'
+              )
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            query,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    /// Counts the users matching a search.
+    ///
+    /// Returns the number of users 'search_users' would find across all pages.
+    pub async fn count_search_users(&self, query: Option<&str>) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM users
+            WHERE is_deleted = false
+              AND (
+                $1::TEXT IS NULL
+                OR id = $1
+                OR username ILIKE '%' || $1 || '%'
+                OR email ILIKE '%' || $1 || '%'
+              )
+            "#,
+            query
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
 }
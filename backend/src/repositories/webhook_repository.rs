@@ -0,0 +1,185 @@
+// DB Repository for webhook management Operations
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::{WebhookDelivery, WebhookEndpoint};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct WebhookRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> WebhookRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves a webhook endpoint by its id.
+    ///
+    /// # Arguments
+    /// * 'id' - id to search for
+    ///
+    /// # Returns
+    /// 'Some(WebhookEndpoint)' if found, 'None' otherwise
+    pub async fn get_endpoint_by_id(&self, id: &str) -> Result<Option<WebhookEndpoint>> {
+        let endpoint = sqlx::query_as!(
+            WebhookEndpoint,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                url as "url!",
+                events as "events!",
+                disabled_at as "disabled_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM webhook_endpoints
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(endpoint)
+    }
+
+    /// Retrieves the webhook endpoints of a user that are still enabled.
+    ///
+    /// # Arguments
+    /// * 'user_id' - User ID
+    ///
+    /// # Returns
+    /// Enabled endpoints, newest first
+    pub async fn get_endpoints_by_user_id(&self, user_id: &str) -> Result<Vec<WebhookEndpoint>> {
+        let endpoints = sqlx::query_as!(
+            WebhookEndpoint,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                url as "url!",
+                events as "events!",
+                disabled_at as "disabled_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM webhook_endpoints
+            WHERE user_id = $1
+              AND disabled_at IS NULL
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(endpoints)
+    }
+
+    /// Retrieves a webhook delivery by its id.
+    ///
+    /// # Arguments
+    /// * 'id' - id to search for
+    ///
+    /// # Returns
+    /// 'Some(WebhookDelivery)' if found, 'None' otherwise
+    pub async fn get_delivery_by_id(&self, id: &str) -> Result<Option<WebhookDelivery>> {
+        let delivery = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT
+                id as "id!",
+                endpoint_id as "endpoint_id!",
+                user_id as "user_id!",
+                event_id as "event_id!",
+                event_type as "event_type!",
+                payload as "payload!",
+                status as "status!",
+                attempts as "attempts!",
+                next_attempt_at as "next_attempt_at!: DateTime<Utc>",
+                last_attempt_at as "last_attempt_at?: DateTime<Utc>",
+                last_status_code as "last_status_code?",
+                last_error as "last_error?",
+                delivered_at as "delivered_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM webhook_deliveries
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(delivery)
+    }
+
+    /// Retrieves the deliveries made to an endpoint, newest first.
+    ///
+    /// # Arguments
+    /// * 'endpoint_id' - Webhook endpoint ID
+    /// * 'pagination' - Page to return
+    pub async fn get_deliveries_by_endpoint_id(
+        &self,
+        endpoint_id: &str,
+        pagination: &PaginationFilter,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let limit = pagination.limit();
+        let offset = pagination.offset();
+
+        let deliveries = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT
+                id as "id!",
+                endpoint_id as "endpoint_id!",
+                user_id as "user_id!",
+                event_id as "event_id!",
+                event_type as "event_type!",
+                payload as "payload!",
+                status as "status!",
+                attempts as "attempts!",
+                next_attempt_at as "next_attempt_at!: DateTime<Utc>",
+                last_attempt_at as "last_attempt_at?: DateTime<Utc>",
+                last_status_code as "last_status_code?",
+                last_error as "last_error?",
+                delivered_at as "delivered_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM webhook_deliveries
+            WHERE endpoint_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            endpoint_id,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// Counts the deliveries made to an endpoint.
+    ///
+    /// # Arguments
+    /// * 'endpoint_id' - Webhook endpoint ID
+    pub async fn count_deliveries_by_endpoint_id(&self, endpoint_id: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*)::BIGINT AS count
+            FROM webhook_deliveries
+            WHERE endpoint_id = $1
+            "#,
+            endpoint_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count.unwrap_or(0) as u64)
+    }
+}
@@ -0,0 +1,143 @@
+// DB Repository for LNURL-withdraw link Operations
+
+use crate::db::models::WithdrawLink;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub struct WithdrawLinkRepository<'a> {
+    // Shared Connection Pool
+    pool: &'a PgPool,
+}
+
+impl<'a> WithdrawLinkRepository<'a> {
+    // New connection instance
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves a withdraw link by its LNURL secret.
+    ///
+    /// # Arguments
+    /// * 'k1' - Secret carried in the link
+    ///
+    /// # Returns
+    /// 'Some(WithdrawLink)' if found, 'None' otherwise. Closed links are returned too
+    pub async fn get_withdraw_link_by_k1(&self, k1: &str) -> Result<Option<WithdrawLink>> {
+        let withdraw_link = sqlx::query_as!(
+            WithdrawLink,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                title as "title!",
+                k1 as "k1!",
+                min_withdrawable_msat as "min_withdrawable_msat!",
+                max_withdrawable_msat as "max_withdrawable_msat!",
+                max_uses as "max_uses!",
+                uses as "uses!",
+                total_msat as "total_msat!",
+                withdrawn_msat as "withdrawn_msat!",
+                reserved_msat as "reserved_msat!",
+                spent_msat as "spent_msat!",
+                status as "status!",
+                expires_at as "expires_at!: DateTime<Utc>",
+                released_at as "released_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM withdraw_links
+            WHERE k1 = $1
+            "#,
+            k1
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(withdraw_link)
+    }
+
+    /// Retrieves the withdraw links created by a user.
+    ///
+    /// # Arguments
+    /// * 'user_id' - Owner of the links
+    ///
+    /// # Returns
+    /// All of the user's links, newest first
+    pub async fn get_withdraw_links_by_user_id(&self, user_id: &str) -> Result<Vec<WithdrawLink>> {
+        let withdraw_links = sqlx::query_as!(
+            WithdrawLink,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                title as "title!",
+                k1 as "k1!",
+                min_withdrawable_msat as "min_withdrawable_msat!",
+                max_withdrawable_msat as "max_withdrawable_msat!",
+                max_uses as "max_uses!",
+                uses as "uses!",
+                total_msat as "total_msat!",
+                withdrawn_msat as "withdrawn_msat!",
+                reserved_msat as "reserved_msat!",
+                spent_msat as "spent_msat!",
+                status as "status!",
+                expires_at as "expires_at!: DateTime<Utc>",
+                released_at as "released_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM withdraw_links
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(withdraw_links)
+    }
+
+    /// Retrieves active links whose reservation is due to be released.
+    ///
+    /// # Returns
+    /// Links that have expired or can no longer be used
+    pub async fn get_releasable_withdraw_links(&self) -> Result<Vec<WithdrawLink>> {
+        let withdraw_links = sqlx::query_as!(
+            WithdrawLink,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                title as "title!",
+                k1 as "k1!",
+                min_withdrawable_msat as "min_withdrawable_msat!",
+                max_withdrawable_msat as "max_withdrawable_msat!",
+                max_uses as "max_uses!",
+                uses as "uses!",
+                total_msat as "total_msat!",
+                withdrawn_msat as "withdrawn_msat!",
+                reserved_msat as "reserved_msat!",
+                spent_msat as "spent_msat!",
+                status as "status!",
+                expires_at as "expires_at!: DateTime<Utc>",
+                released_at as "released_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM withdraw_links
+            WHERE status = 'active'
+              AND (
+                  expires_at <= now()
+                  OR uses >= max_uses
+                  OR withdrawn_msat >= total_msat
+              )
+            "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(withdraw_links)
+    }
+}
@@ -0,0 +1,689 @@
+// Admin Service Logic
+//! Back-office operations for support staff.
+//!
+//! Admins can look up any user together with their accounts and
+//! transactions without signing in as them, freeze users and accounts, and
+//! correct balances. A balance correction is only requested by one admin and
+//! applied once a different admin approves it. Every change is written to the
+//! audit log in the same transaction.
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::{
+    Account, AdminReason, CreateLedgerAdjustment, LedgerAdjustment, NewAuditEvent, Transaction,
+    User, UserProfile,
+};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::account_repository::AccountRepository;
+use crate::repositories::ledger_adjustment_repository::LedgerAdjustmentRepository;
+use crate::repositories::transaction_repository::TransactionRepository;
+use crate::repositories::user_repository::UserRepository;
+use crate::service::audit_service::AuditService;
+use crate::service::event_service::EventService;
+use crate::service::user_service::UserService;
+use crate::utilities::client::ClientInfo;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Statuses ledger adjustments can be filtered on
+const LEDGER_ADJUSTMENT_STATUSES: [&str; 3] = ["pending", "applied", "rejected"];
+
+// Service layer for Admin related Operation
+pub struct AdminService<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> AdminService<'a> {
+    /// Creates a new admin service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Searches users by username, email or ID.
+    ///
+    /// # Returns
+    /// The page of matching users and how many match in total
+    pub async fn search_users(
+        &self,
+        query: Option<&str>,
+        filter: &PaginationFilter,
+    ) -> ServiceResult<(Vec<User>, u64)> {
+        let query = query.map(str::trim).filter(|query| !query.is_empty());
+
+        let user_repo = UserRepository::new(self.pool);
+        let users = user_repo
+            .search_users(query, filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = user_repo
+            .count_search_users(query)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        Ok((users, total))
+    }
+
+    /// Retrieves the profile of any user.
+    pub async fn get_user(&self, user_id: &str) -> ServiceResult<UserProfile> {
+        UserService::new(self.pool).get_profile(user_id).await
+    }
+
+    /// Retrieves every account of a user, frozen ones included.
+    pub async fn list_accounts(&self, user_id: &str) -> ServiceResult<Vec<Account>> {
+        self.get_user_by_id(user_id).await?;
+
+        let accounts = AccountRepository::new(self.pool)
+            .get_accounts_by_user_id(user_id)
+            .await?;
+
+        Ok(accounts)
+    }
+
+    /// Retrieves the transactions of a user, newest first.
+    pub async fn list_transactions(
+        &self,
+        user_id: &str,
+        filter: &PaginationFilter,
+    ) -> ServiceResult<(Vec<Transaction>, u64)> {
+        self.get_user_by_id(user_id).await?;
+
+        let transaction_repo = TransactionRepository::new(self.pool);
+        let transactions = transaction_repo
+            .get_transactions_by_user_id(user_id, filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = transaction_repo
+            .count_transactions_by_user_id(user_id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        Ok((transactions, total))
+    }
+
+    /// Stops a user from signing in or using their API keys.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for unknown users and admins freezing themselves
+    pub async fn freeze_user(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        user_id: &str,
+        reason: AdminReason,
+    ) -> ServiceResult<User> {
+        if actor_id == user_id {
+            return Err(ServiceError::invalid_operation(
+                "Admins cannot freeze themselves",
+            ));
+        }
+
+        self.set_user_active(actor_id, client, user_id, false, reason)
+            .await
+    }
+
+    /// Lets a frozen user sign in again.
+    pub async fn unfreeze_user(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        user_id: &str,
+        reason: AdminReason,
+    ) -> ServiceResult<User> {
+        self.set_user_active(actor_id, client, user_id, true, reason)
+            .await
+    }
+
+    async fn set_user_active(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        user_id: &str,
+        is_active: bool,
+        reason: AdminReason,
+    ) -> ServiceResult<User> {
+        if let Err(validation_errors) = reason.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let user = self.get_user_by_id(user_id).await?;
+        if user.is_active == is_active {
+            return Ok(user);
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let updated_user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET is_active = $2,
+                updated_at = now()
+            WHERE id = $1
+              AND is_deleted = false
+            RETURNING
+                id as "id!",
+                role_id as "role_id!",
+                username as "username!",
+                password_hash as "password_hash?",
+                email as "email?",
+                is_active as "is_active!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: DateTime<Utc>"
+            "#,
+            user.id,
+            is_active
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        AuditService::record(
+            &mut tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(actor_id.to_string()),
+                action: if is_active {
+                    "user.unfrozen"
+                } else {
+                    "user.frozen"
+                }
+                .to_string(),
+                target_type: "user".to_string(),
+                target_id: user.id.clone(),
+                before: Some(json!({ "is_active": user.is_active })),
+                after: Some(json!({
+                    "is_active": is_active,
+                    "reason": reason.reason,
+                })),
+            },
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(updated_user)
+    }
+
+    /// Stops any money leaving an account.
+    pub async fn freeze_account(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        account_id: &str,
+        reason: AdminReason,
+    ) -> ServiceResult<Account> {
+        self.set_account_active(actor_id, client, account_id, false, reason)
+            .await
+    }
+
+    /// Lets money leave a frozen account again.
+    pub async fn unfreeze_account(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        account_id: &str,
+        reason: AdminReason,
+    ) -> ServiceResult<Account> {
+        self.set_account_active(actor_id, client, account_id, true, reason)
+            .await
+    }
+
+    async fn set_account_active(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        account_id: &str,
+        is_active: bool,
+        reason: AdminReason,
+    ) -> ServiceResult<Account> {
+        if let Err(validation_errors) = reason.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let account = AccountRepository::new(self.pool)
+            .get_account_by_id(account_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Account", account_id))?;
+
+        if account.is_active == is_active {
+            return Ok(account);
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let updated_account = sqlx::query_as!(
+            Account,
+            r#"
+            UPDATE accounts
+            SET is_active = $2,
+                updated_at = now()
+            WHERE id = $1
+              AND is_deleted = false
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                balance as "balance!",
+                is_active as "is_active!",
+                display_currency as "display_currency!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: DateTime<Utc>"
+            "#,
+            account.id,
+            is_active
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        AuditService::record(
+            &mut tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(actor_id.to_string()),
+                action: if is_active {
+                    "account.unfrozen"
+                } else {
+                    "account.frozen"
+                }
+                .to_string(),
+                target_type: "account".to_string(),
+                target_id: account.id.clone(),
+                before: Some(json!({ "is_active": account.is_active })),
+                after: Some(json!({
+                    "is_active": is_active,
+                    "reason": reason.reason,
+                })),
+            },
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(updated_account)
+    }
+
+    /// Retrieves ledger adjustments, optionally only those in one status.
+    pub async fn list_adjustments(
+        &self,
+        status: Option<&str>,
+        filter: &PaginationFilter,
+    ) -> ServiceResult<(Vec<LedgerAdjustment>, u64)> {
+        if status.is_some_and(|status| !LEDGER_ADJUSTMENT_STATUSES.contains(&status)) {
+            return Err(ServiceError::validation(
+                "status: Must be 'pending', 'applied' or 'rejected'",
+            ));
+        }
+
+        let adjustment_repo = LedgerAdjustmentRepository::new(self.pool);
+        let adjustments = adjustment_repo
+            .get_adjustments(status, filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = adjustment_repo
+            .count_adjustments(status)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        Ok((adjustments, total))
+    }
+
+    /// Retrieves a ledger adjustment by its id.
+    pub async fn get_adjustment(&self, adjustment_id: &str) -> ServiceResult<LedgerAdjustment> {
+        LedgerAdjustmentRepository::new(self.pool)
+            .get_adjustment_by_id(adjustment_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Ledger adjustment", adjustment_id))
+    }
+
+    /// Requests a correction of an account balance. Nothing moves until a
+    /// different admin approves it.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Validation failures and zero amounts
+    /// - Unknown accounts
+    pub async fn request_adjustment(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        create_adjustment: CreateLedgerAdjustment,
+    ) -> ServiceResult<LedgerAdjustment> {
+        if let Err(validation_errors) = create_adjustment.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        if create_adjustment.amount_msat == 0 {
+            return Err(ServiceError::validation(
+                "amount_msat: Must not be zero".to_string(),
+            ));
+        }
+
+        let account = AccountRepository::new(self.pool)
+            .get_account_by_id(&create_adjustment.account_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Account", &create_adjustment.account_id))?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let adjustment = sqlx::query_as!(
+            LedgerAdjustment,
+            r#"
+            INSERT INTO ledger_adjustments (id, account_id, amount_msat, reason, requested_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id as "id!",
+                account_id as "account_id!",
+                amount_msat as "amount_msat!",
+                reason as "reason!",
+                status as "status!",
+                requested_by as "requested_by!",
+                reviewed_by as "reviewed_by?",
+                reviewed_at as "reviewed_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            account.id,
+            BigDecimal::from(create_adjustment.amount_msat),
+            create_adjustment.reason,
+            actor_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        AuditService::record(
+            &mut tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(actor_id.to_string()),
+                action: "ledger_adjustment.requested".to_string(),
+                target_type: "ledger_adjustment".to_string(),
+                target_id: adjustment.id.clone(),
+                before: None,
+                after: Some(json!(adjustment)),
+            },
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(adjustment)
+    }
+
+    /// Applies a pending adjustment to the account balance.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Unknown or already reviewed adjustments
+    /// - Admins approving their own request
+    /// - Debits larger than the account balance
+    pub async fn approve_adjustment(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        adjustment_id: &str,
+    ) -> ServiceResult<LedgerAdjustment> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let pending = self.lock_pending(&mut tx, adjustment_id).await?;
+
+        if pending.requested_by == actor_id {
+            return Err(ServiceError::forbidden(
+                "Adjustments must be approved by a second admin",
+            ));
+        }
+
+        // Frozen accounts can still be corrected, but never overdrawn
+        let balance = sqlx::query_scalar!(
+            r#"
+            UPDATE accounts
+            SET balance = balance + $2,
+                updated_at = now()
+            WHERE id = $1
+              AND is_deleted = false
+              AND balance + $2 >= 0
+            RETURNING balance as "balance!"
+            "#,
+            pending.account_id,
+            pending.amount_msat
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        .ok_or_else(|| {
+            ServiceError::invalid_operation("Adjustment would leave the account balance negative")
+        })?;
+
+        let adjustment = self
+            .review(&mut tx, adjustment_id, actor_id, "applied")
+            .await?;
+
+        AuditService::record(
+            &mut tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(actor_id.to_string()),
+                action: "ledger_adjustment.applied".to_string(),
+                target_type: "account".to_string(),
+                target_id: adjustment.account_id.clone(),
+                before: Some(json!({
+                    "balance": (&balance - &adjustment.amount_msat).to_string(),
+                })),
+                after: Some(json!({
+                    "balance": balance.to_string(),
+                    "adjustment_id": adjustment.id,
+                    "amount_msat": adjustment.amount_msat.to_string(),
+                    "reason": adjustment.reason,
+                    "requested_by": adjustment.requested_by,
+                })),
+            },
+        )
+        .await?;
+
+        EventService::balance_updated(&mut tx, &adjustment.account_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(adjustment)
+    }
+
+    /// Turns down a pending adjustment, leaving the balance as it is.
+    pub async fn reject_adjustment(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        adjustment_id: &str,
+    ) -> ServiceResult<LedgerAdjustment> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        self.lock_pending(&mut tx, adjustment_id).await?;
+
+        let adjustment = self
+            .review(&mut tx, adjustment_id, actor_id, "rejected")
+            .await?;
+
+        AuditService::record(
+            &mut tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(actor_id.to_string()),
+                action: "ledger_adjustment.rejected".to_string(),
+                target_type: "ledger_adjustment".to_string(),
+                target_id: adjustment.id.clone(),
+                before: Some(json!({ "status": "pending" })),
+                after: Some(json!({ "status": adjustment.status })),
+            },
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(adjustment)
+    }
+
+    /// Locks a pending adjustment so it can only be reviewed once.
+    async fn lock_pending(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        adjustment_id: &str,
+    ) -> ServiceResult<LedgerAdjustment> {
+        let adjustment = sqlx::query_as!(
+            LedgerAdjustment,
+            r#"
+            SELECT
+                id as "id!",
+                account_id as "account_id!",
+                amount_msat as "amount_msat!",
+                reason as "reason!",
+                status as "status!",
+                requested_by as "requested_by!",
+                reviewed_by as "reviewed_by?",
+                reviewed_at as "reviewed_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM ledger_adjustments
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            adjustment_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        .ok_or_else(|| ServiceError::not_found("Ledger adjustment", adjustment_id))?;
+
+        if adjustment.status != "pending" {
+            return Err(ServiceError::invalid_operation(
+                "Adjustment has already been reviewed",
+            ));
+        }
+
+        Ok(adjustment)
+    }
+
+    async fn review(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        adjustment_id: &str,
+        actor_id: &str,
+        status: &str,
+    ) -> ServiceResult<LedgerAdjustment> {
+        let adjustment = sqlx::query_as!(
+            LedgerAdjustment,
+            r#"
+            UPDATE ledger_adjustments
+            SET status = $2,
+                reviewed_by = $3,
+                reviewed_at = now(),
+                updated_at = now()
+            WHERE id = $1
+            RETURNING
+                id as "id!",
+                account_id as "account_id!",
+                amount_msat as "amount_msat!",
+                reason as "reason!",
+                status as "status!",
+                requested_by as "requested_by!",
+                reviewed_by as "reviewed_by?",
+                reviewed_at as "reviewed_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            adjustment_id,
+            status,
+            actor_id
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(adjustment)
+    }
+
+    async fn get_user_by_id(&self, user_id: &str) -> ServiceResult<User> {
+        UserRepository::new(self.pool)
+            .get_user_by_id(user_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("User", user_id))
+    }
+}
@@ -0,0 +1,219 @@
+// API Key Service Logic
+//! Handles creation, revocation and verification of personal API keys
+//!
+//! Keys have the form `moya_<prefix>_<secret>`. The prefix is stored in
+//! clear for lookup, the secret only as a SHA-256 digest.
+
+use crate::db::models::{ApiKey, CreateApiKey, NewApiKey, User};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::api_key_repository::ApiKeyRepository;
+use crate::repositories::user_repository::UserRepository;
+use crate::utilities::token::{generate_token, hash_token};
+use chrono::Utc;
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Marks a credential as an API key rather than a JWT
+pub const API_KEY_PREFIX: &str = "moya_";
+
+/// Length of the public lookup part of a key
+const API_KEY_LOOKUP_LEN: usize = 12;
+
+// Service layer for API key related Operation
+pub struct ApiKeyService<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> ApiKeyService<'a> {
+    /// Creates a new API key service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Issues a new API key for a user.
+    ///
+    /// # Returns
+    /// 'NewApiKey' holding the full key, which is not retrievable afterwards
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for validation failures or an expiry in the past
+    pub async fn create_api_key(
+        &self,
+        user_id: &str,
+        create_api_key: CreateApiKey,
+    ) -> ServiceResult<NewApiKey> {
+        if let Err(validation_errors) = create_api_key.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        if create_api_key
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            return Err(ServiceError::validation(
+                "expires_at: Expiry must be in the future",
+            ));
+        }
+
+        let mut scopes: Vec<String> = create_api_key
+            .scopes
+            .iter()
+            .map(|scope| scope.as_str().to_string())
+            .collect();
+        scopes.sort();
+        scopes.dedup();
+
+        let prefix = generate_token()[..API_KEY_LOOKUP_LEN].to_string();
+        let secret = generate_token();
+
+        let api_key = sqlx::query_as!(
+            ApiKey,
+            r#"
+            INSERT INTO api_keys (
+                id,
+                user_id,
+                name,
+                prefix,
+                secret_hash,
+                scopes,
+                spend_limit_msat,
+                expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                name as "name!",
+                prefix as "prefix!",
+                secret_hash as "secret_hash!",
+                scopes as "scopes!",
+                spend_limit_msat as "spend_limit_msat?",
+                spent_msat as "spent_msat!",
+                expires_at as "expires_at?: chrono::DateTime<chrono::Utc>",
+                last_used_at as "last_used_at?: chrono::DateTime<chrono::Utc>",
+                revoked_at as "revoked_at?: chrono::DateTime<chrono::Utc>",
+                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            user_id,
+            create_api_key.name,
+            prefix,
+            hash_token(&secret),
+            &scopes,
+            create_api_key.spend_limit_msat.map(BigDecimal::from),
+            create_api_key.expires_at
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(NewApiKey {
+            key: format!("{API_KEY_PREFIX}{prefix}_{secret}"),
+            api_key,
+        })
+    }
+
+    /// Lists the API keys of a user that have not been revoked.
+    pub async fn list_api_keys(&self, user_id: &str) -> ServiceResult<Vec<ApiKey>> {
+        let api_key_repo = ApiKeyRepository::new(self.pool);
+
+        Ok(api_key_repo.get_api_keys_by_user_id(user_id).await?)
+    }
+
+    /// Revokes an API key so it can no longer authenticate.
+    pub async fn revoke_api_key(&self, user_id: &str, api_key_id: &str) -> ServiceResult<()> {
+        let revoked = sqlx::query!(
+            r#"
+            UPDATE api_keys
+            SET revoked_at = now(),
+                updated_at = now()
+            WHERE id = $1
+              AND user_id = $2
+              AND revoked_at IS NULL
+            "#,
+            api_key_id,
+            user_id
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if revoked.rows_affected() == 0 {
+            return Err(ServiceError::not_found("API key", api_key_id));
+        }
+
+        Ok(())
+    }
+
+    /// Resolves a presented key to the key record and its active owner.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError::Unauthorized' for malformed, unknown, revoked
+    /// or expired keys and for keys whose owner is no longer active
+    pub async fn authenticate(&self, raw_key: &str) -> ServiceResult<(ApiKey, User)> {
+        let invalid = || ServiceError::unauthorized("Invalid API key");
+
+        let (prefix, secret) = raw_key
+            .strip_prefix(API_KEY_PREFIX)
+            .and_then(|rest| rest.split_once('_'))
+            .ok_or_else(invalid)?;
+
+        let api_key_repo = ApiKeyRepository::new(self.pool);
+        let api_key = api_key_repo
+            .get_api_key_by_prefix(prefix)
+            .await?
+            .filter(|api_key| api_key.secret_hash == hash_token(secret))
+            .ok_or_else(invalid)?;
+
+        if api_key.revoked_at.is_some() {
+            return Err(ServiceError::unauthorized("API key has been revoked"));
+        }
+
+        if api_key
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            return Err(ServiceError::unauthorized("API key has expired"));
+        }
+
+        let user_repo = UserRepository::new(self.pool);
+        let user = user_repo
+            .get_user_by_id(&api_key.user_id)
+            .await?
+            .filter(|user| user.is_active)
+            .ok_or_else(invalid)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE api_keys
+            SET last_used_at = now()
+            WHERE id = $1
+            "#,
+            api_key.id
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok((api_key, user))
+    }
+}
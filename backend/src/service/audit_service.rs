@@ -0,0 +1,265 @@
+// Audit Service Logic
+//! Records privileged changes alongside the change itself
+//!
+//! The log is append-only: Postgres refuses to update, delete or truncate
+//! its rows. Each event is chained to the one before it by a SHA-256 hash
+//! computed on insert, so editing or removing an event breaks every hash
+//! after it, which `verify_chain` reports.
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::{AuditChainStatus, AuditEvent, AuditEventFilter, NewAuditEvent};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::audit_repository::AuditRepository;
+use crate::utilities::client::ClientInfo;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+// Service layer for audit trail Operation
+pub struct AuditService<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> AuditService<'a> {
+    /// Creates a new audit service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Appends an event to the audit log, with the request it came from.
+    ///
+    /// Takes the connection of the caller's transaction so the event is
+    /// only persisted if the change it describes is committed.
+    pub async fn record(
+        conn: &mut PgConnection,
+        client: &ClientInfo,
+        event: NewAuditEvent,
+    ) -> ServiceResult<()> {
+        // Sequence number and hashes are filled in by the database
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events (
+                id,
+                actor_id,
+                action,
+                target_type,
+                target_id,
+                before,
+                after,
+                request_id,
+                ip_address
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            Uuid::now_v7().to_string(),
+            event.actor_id,
+            event.action,
+            event.target_type,
+            event.target_id,
+            event.before,
+            event.after,
+            client.request_id,
+            client.ip_address
+        )
+        .execute(conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Retrieves audit events matching a filter, newest first.
+    pub async fn list_events(
+        &self,
+        filter: &AuditEventFilter,
+        pagination: &PaginationFilter,
+    ) -> ServiceResult<(Vec<AuditEvent>, u64)> {
+        let audit_repo = AuditRepository::new(self.pool);
+        let events = audit_repo
+            .get_events(filter, pagination)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = audit_repo
+            .count_events(filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        Ok((events, total))
+    }
+
+    /// Recomputes the hash chain over the whole log.
+    ///
+    /// # Returns
+    /// 'AuditChainStatus' naming the first event that was edited, or whose
+    /// predecessor was removed or reordered
+    pub async fn verify_chain(&self) -> ServiceResult<AuditChainStatus> {
+        let first_invalid = sqlx::query_scalar!(
+            r#"
+            SELECT id as "id!"
+            FROM (
+                SELECT
+                    e.*,
+                    lag(hash) OVER (ORDER BY seq) AS expected_prev_hash,
+                    lag(seq) OVER (ORDER BY seq) AS previous_seq
+                FROM audit_events e
+            ) chain
+            WHERE seq <> COALESCE(previous_seq, 0) + 1
+               OR prev_hash IS DISTINCT FROM expected_prev_hash
+               OR hash IS DISTINCT FROM audit_event_hash(
+                    prev_hash,
+                    id,
+                    actor_id,
+                    action,
+                    target_type,
+                    target_id,
+                    before,
+                    after,
+                    request_id,
+                    ip_address,
+                    created_at
+               )
+            ORDER BY seq
+            LIMIT 1
+            "#
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let head = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*)::BIGINT as "events!",
+                (SELECT hash FROM audit_events ORDER BY seq DESC LIMIT 1) as "head_hash?"
+            FROM audit_events
+            "#
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(AuditChainStatus {
+            valid: first_invalid.is_none(),
+            events_checked: head.events as u64,
+            head_hash: head.head_hash,
+            first_invalid_event_id: first_invalid,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Records `count` events and returns their ids in chain order
+    async fn record_events(pool: &PgPool, count: usize) -> Vec<String> {
+        let client = ClientInfo {
+            ip_address: Some("203.0.113.7".to_string()),
+            user_agent: None,
+            request_id: Some("request".to_string()),
+        };
+        let mut conn = pool.acquire().await.unwrap();
+
+        for index in 0..count {
+            AuditService::record(
+                &mut conn,
+                &client,
+                NewAuditEvent {
+                    actor_id: Some("admin".to_string()),
+                    action: "account.frozen".to_string(),
+                    target_type: "account".to_string(),
+                    target_id: format!("account-{index}"),
+                    before: Some(json!({ "is_active": true })),
+                    after: Some(json!({ "is_active": false })),
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        sqlx::query_scalar("SELECT id FROM audit_events ORDER BY seq")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    /// Runs `statement` with the append-only trigger out of the way, as
+    /// someone with direct access to the database could
+    async fn tamper(pool: &PgPool, statement: &str, id: &str) {
+        let mut tx = pool.begin().await.unwrap();
+        sqlx::query("ALTER TABLE audit_events DISABLE TRIGGER audit_events_append_only")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query(statement)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query("ALTER TABLE audit_events ENABLE TRIGGER audit_events_append_only")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test]
+    async fn untouched_chain_verifies(pool: PgPool) {
+        let ids = record_events(&pool, 3).await;
+
+        let status = AuditService::new(&pool).verify_chain().await.unwrap();
+
+        assert!(status.valid);
+        assert_eq!(status.events_checked, 3);
+        assert_eq!(status.first_invalid_event_id, None);
+        let head_hash: String = sqlx::query_scalar("SELECT hash FROM audit_events WHERE id = $1")
+            .bind(&ids[2])
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status.head_hash, Some(head_hash));
+    }
+
+    #[sqlx::test]
+    async fn edited_event_is_reported(pool: PgPool) {
+        let ids = record_events(&pool, 3).await;
+
+        tamper(
+            &pool,
+            r#"UPDATE audit_events SET after = '{"is_active": true}' WHERE id = $1"#,
+            &ids[1],
+        )
+        .await;
+
+        let status = AuditService::new(&pool).verify_chain().await.unwrap();
+        assert!(!status.valid);
+        assert_eq!(status.first_invalid_event_id, Some(ids[1].clone()));
+    }
+
+    #[sqlx::test]
+    async fn removed_event_is_reported_at_its_successor(pool: PgPool) {
+        let ids = record_events(&pool, 3).await;
+
+        tamper(&pool, "DELETE FROM audit_events WHERE id = $1", &ids[1]).await;
+
+        let status = AuditService::new(&pool).verify_chain().await.unwrap();
+        assert!(!status.valid);
+        assert_eq!(status.events_checked, 2);
+        assert_eq!(status.first_invalid_event_id, Some(ids[2].clone()));
+    }
+
+    #[sqlx::test]
+    async fn log_refuses_edits(pool: PgPool) {
+        let ids = record_events(&pool, 1).await;
+
+        let updated = sqlx::query("UPDATE audit_events SET action = 'edited' WHERE id = $1")
+            .bind(&ids[0])
+            .execute(&pool)
+            .await;
+
+        assert!(updated.is_err());
+    }
+}
@@ -0,0 +1,449 @@
+// Deposit Service Logic
+//! Hands out on-chain deposit addresses from the node wallet and credits
+//! what arrives on them.
+//!
+//! Deposits are shown as pending until they reach the configured number of
+//! confirmations. The recent chain is rescanned on every pass, so a reorg
+//! that takes a credited transaction back under that depth reverses the
+//! credit until it confirms again.
+
+use crate::Config;
+use crate::common::common::PaginationFilter;
+use crate::db::models::{ApiKeyScope, Deposit, DepositAddress};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::account_repository::AccountRepository;
+use crate::repositories::deposit_repository::DepositRepository;
+use crate::service::event_service::EventService;
+use crate::service::node_service::LightningClient;
+use crate::utilities::OnchainReceipt;
+use crate::utilities::auth::AuthUser;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use std::collections::HashSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// How often the node wallet is checked for deposits
+const DEPOSIT_POLL_SECONDS: u64 = 30;
+
+/// How far back each pass rescans, about a week of blocks. Deeper reorgs
+/// are not expected.
+const DEPOSIT_RESCAN_BLOCKS: u32 = 1008;
+
+// Service layer for Deposit related Operation
+pub struct DepositService<'a> {
+    pool: &'a PgPool,
+    lightning: &'a dyn LightningClient,
+}
+
+impl<'a> DepositService<'a> {
+    /// Creates a new deposit service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    /// * 'lightning' - Node whose wallet holds the deposits
+    pub fn new(pool: &'a PgPool, lightning: &'a dyn LightningClient) -> Self {
+        Self { pool, lightning }
+    }
+
+    /// Generates a fresh deposit address for the caller's account.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'invoices:write' scope
+    /// - Accounts that cannot receive
+    pub async fn create_deposit_address(&self, auth: &AuthUser) -> ServiceResult<DepositAddress> {
+        auth.require_scope(ApiKeyScope::InvoicesWrite)?;
+
+        let account = AccountRepository::new(self.pool)
+            .get_accoount_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|account| account.is_active)
+            .ok_or_else(|| ServiceError::invalid_operation("Account is not able to receive"))?;
+
+        let address = self.lightning.new_address().await?;
+
+        let deposit_address = sqlx::query_as!(
+            DepositAddress,
+            r#"
+            INSERT INTO deposit_addresses (id, user_id, account_id, address)
+            VALUES ($1, $2, $3, $4)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                address as "address!",
+                created_at as "created_at!: DateTime<Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            account.user_id,
+            account.id,
+            address
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(deposit_address)
+    }
+
+    /// Lists the deposit addresses handed out to the caller.
+    pub async fn list_deposit_addresses(
+        &self,
+        auth: &AuthUser,
+    ) -> ServiceResult<Vec<DepositAddress>> {
+        auth.require_scope(ApiKeyScope::InvoicesRead)?;
+
+        DepositRepository::new(self.pool)
+            .get_deposit_addresses_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })
+    }
+
+    /// Lists the caller's deposits, newest first, with their total count.
+    pub async fn list_deposits(
+        &self,
+        auth: &AuthUser,
+        filter: &PaginationFilter,
+    ) -> ServiceResult<(Vec<Deposit>, u64)> {
+        auth.require_scope(ApiKeyScope::InvoicesRead)?;
+
+        let deposit_repo = DepositRepository::new(self.pool);
+
+        let deposits = deposit_repo
+            .get_deposits_by_user_id(auth.user_id(), filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = deposit_repo
+            .count_deposits_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        Ok((deposits, total))
+    }
+
+    /// Brings deposits in line with the node wallet.
+    ///
+    /// Records new outputs on deposit addresses, credits those with enough
+    /// confirmations and reverses credits a reorg has undone.
+    pub async fn sync_deposits(&self) -> ServiceResult<()> {
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })?;
+
+        let start_height = self
+            .lightning
+            .get_block_height()
+            .await?
+            .saturating_sub(DEPOSIT_RESCAN_BLOCKS);
+        let receipts = self.lightning.list_onchain_receipts(start_height).await?;
+
+        let deposit_repo = DepositRepository::new(self.pool);
+        let mut seen = HashSet::new();
+
+        for receipt in receipts {
+            // Change and channel outputs land in the wallet too
+            let Some(deposit_address) = deposit_repo
+                .get_deposit_address(&receipt.address)
+                .await
+                .map_err(|e| ServiceError::Database { source: e })?
+            else {
+                continue;
+            };
+
+            let deposit = self.record_receipt(&deposit_address, &receipt).await?;
+            seen.insert(deposit.id.clone());
+
+            let deep_enough = receipt.confirmations >= config.deposit_min_confirmations;
+            match deposit.status.as_str() {
+                "pending" if deep_enough => self.credit_deposit(&deposit).await?,
+                "credited" if !deep_enough => self.reverse_deposit(&deposit, "pending").await?,
+                _ => {}
+            }
+        }
+
+        // Anything the wallet no longer reports was replaced or reorged away
+        let in_flight = deposit_repo
+            .get_deposits_in_flight(start_height as i32)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        for deposit in in_flight
+            .iter()
+            .filter(|deposit| !seen.contains(&deposit.id))
+        {
+            if deposit.status == "credited" {
+                self.reverse_deposit(deposit, "dropped").await?;
+            } else {
+                self.drop_deposit(deposit).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records an output seen on a deposit address, or refreshes its depth.
+    async fn record_receipt(
+        &self,
+        deposit_address: &DepositAddress,
+        receipt: &OnchainReceipt,
+    ) -> ServiceResult<Deposit> {
+        let deposit = sqlx::query_as!(
+            Deposit,
+            r#"
+            INSERT INTO deposits (
+                id,
+                user_id,
+                account_id,
+                address,
+                txid,
+                vout,
+                amount_msat,
+                confirmations,
+                block_height
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (txid, vout) DO UPDATE
+            SET confirmations = EXCLUDED.confirmations,
+                block_height = EXCLUDED.block_height,
+                status = CASE
+                    WHEN deposits.status = 'dropped' THEN 'pending'
+                    ELSE deposits.status
+                END,
+                updated_at = now()
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                address as "address!",
+                txid as "txid!",
+                vout as "vout!",
+                amount_msat as "amount_msat!",
+                confirmations as "confirmations!",
+                block_height as "block_height?",
+                status as "status!",
+                credited_at as "credited_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            deposit_address.user_id,
+            deposit_address.account_id,
+            deposit_address.address,
+            receipt.txid,
+            receipt.vout as i32,
+            BigDecimal::from(receipt.amount_sat * 1000),
+            receipt.confirmations as i32,
+            receipt.block_height.map(|height| height as i32)
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(deposit)
+    }
+
+    /// Credits a pending deposit to its account.
+    async fn credit_deposit(&self, deposit: &Deposit) -> ServiceResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let credited = sqlx::query!(
+            r#"
+            UPDATE deposits
+            SET status = 'credited',
+                credited_at = now(),
+                updated_at = now()
+            WHERE id = $1
+              AND status = 'pending'
+            "#,
+            deposit.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if credited.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET balance = balance + $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            deposit.account_id,
+            deposit.amount_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        // On-chain deposits have no payment hash, the outpoint identifies
+        // them. A deposit credited again after a reorg reuses its entry.
+        sqlx::query!(
+            r#"
+            INSERT INTO transactions (
+                id,
+                user_id,
+                account_id,
+                direction,
+                invoice,
+                amount,
+                payment_hash,
+                payment_status
+            )
+            VALUES ($1, $2, $3, 'incoming', $4, $5, $6, 'settled')
+            ON CONFLICT (payment_hash) WHERE direction = 'incoming' DO UPDATE
+            SET payment_status = 'settled',
+                updated_at = now()
+            "#,
+            Uuid::now_v7().to_string(),
+            deposit.user_id,
+            deposit.account_id,
+            deposit.address,
+            deposit.amount_msat,
+            outpoint(deposit)
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        EventService::deposit_confirmed(&mut tx, &deposit.id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!(
+            "Deposit {} credited to account {}",
+            outpoint(deposit),
+            deposit.account_id
+        );
+
+        Ok(())
+    }
+
+    /// Takes back the credit for a deposit a reorg has undone, leaving it
+    /// `status` ('pending' to credit again once it reconfirms).
+    async fn reverse_deposit(&self, deposit: &Deposit, status: &str) -> ServiceResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let reversed = sqlx::query!(
+            r#"
+            UPDATE deposits
+            SET status = $2,
+                credited_at = NULL,
+                updated_at = now()
+            WHERE id = $1
+              AND status = 'credited'
+            "#,
+            deposit.id,
+            status
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if reversed.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        // The funds may already be spent, so the balance can go negative
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET balance = balance - $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            deposit.account_id,
+            deposit.amount_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE transactions
+            SET payment_status = 'reversed',
+                updated_at = now()
+            WHERE payment_hash = $1
+              AND direction = 'incoming'
+              AND payment_status = 'settled'
+            "#,
+            outpoint(deposit)
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        EventService::balance_updated(&mut tx, &deposit.account_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::warn!(
+            "Deposit {} reversed for account {} after a reorg",
+            outpoint(deposit),
+            deposit.account_id
+        );
+
+        Ok(())
+    }
+
+    /// Records a pending deposit whose transaction never made it into a block.
+    async fn drop_deposit(&self, deposit: &Deposit) -> ServiceResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE deposits
+            SET status = 'dropped',
+                updated_at = now()
+            WHERE id = $1
+              AND status = 'pending'
+            "#,
+            deposit.id
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!("Deposit {} dropped from the mempool", outpoint(deposit));
+
+        Ok(())
+    }
+}
+
+/// `txid:vout` reference to the output of a deposit
+fn outpoint(deposit: &Deposit) -> String {
+    format!("{}:{}", deposit.txid, deposit.vout)
+}
+
+/// Keeps on-chain deposits flowing into account balances.
+pub async fn run_deposit_watcher(pool: PgPool, lightning: Arc<dyn LightningClient>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(DEPOSIT_POLL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = DepositService::new(&pool, lightning.as_ref())
+            .sync_deposits()
+            .await
+        {
+            tracing::warn!("Syncing deposits failed: {}", error);
+        }
+    }
+}
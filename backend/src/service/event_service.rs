@@ -0,0 +1,424 @@
+// Event Service Logic
+//! Account events, pushed to the user's open event streams and webhooks.
+//!
+//! The ledger and the settlement watchers report each change from inside the
+//! transaction that makes it. Stream events go out with `pg_notify`, which
+//! Postgres only delivers once that transaction commits, and the listener
+//! fans them out to every connected stream through the in-process
+//! `EventBus`. Running several instances of the bank works the same way,
+//! since each one listens on the channel. Webhook deliveries for the same
+//! change are queued in the outbox by `WebhookService::enqueue`, and the
+//! emails about it by `NotificationService::enqueue`.
+
+use crate::Config;
+use crate::db::models::{AccountEvent, Deposit, Invoice, Transaction, WebhookEvent};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::service::notification_service::{Notification, NotificationService, format_sat};
+use crate::service::webhook_service::WebhookService;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use serde_json::json;
+use sqlx::postgres::PgListener;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+/// Postgres channel account events are sent on
+const EVENT_CHANNEL: &str = "account_events";
+
+/// How long the listener waits before reconnecting to the database
+const EVENT_LISTENER_RETRY_SECONDS: u64 = 5;
+
+/// Events the bus keeps for streams that are slow to read them
+pub const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// Events sent when a stream fell too far behind and missed some
+const EVENT_STREAM_LAGGED: &str = "stream.lagged";
+
+/// In-process fan-out of account events to the open event streams
+pub struct EventBus {
+    sender: broadcast::Sender<Arc<AccountEvent>>,
+}
+
+impl EventBus {
+    /// Creates a bus holding up to `capacity` events for slow streams
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Events of one user, from now on.
+    ///
+    /// A stream that falls more than the bus capacity behind gets a
+    /// 'stream.lagged' event in place of the ones it missed, telling the
+    /// client to reload what it shows.
+    pub fn subscribe(&self, user_id: String) -> impl Stream<Item = Arc<AccountEvent>> + use<> {
+        let mut receiver = self.sender.subscribe();
+
+        async_stream::stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.user_id == user_id => yield event,
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(missed)) => {
+                        yield Arc::new(AccountEvent {
+                            id: Uuid::now_v7().to_string(),
+                            user_id: user_id.clone(),
+                            event_type: EVENT_STREAM_LAGGED.to_string(),
+                            data: json!({ "missed": missed }),
+                            created_at: Utc::now(),
+                        });
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+}
+
+// Service layer for Event related Operation
+pub struct EventService;
+
+impl EventService {
+    /// Sends an event to the user's streams once the caller's transaction
+    /// commits.
+    async fn publish(
+        conn: &mut PgConnection,
+        user_id: &str,
+        event_type: &str,
+        data: serde_json::Value,
+    ) -> ServiceResult<()> {
+        // The user id is left out of what streams see but the listener
+        // needs it to route the event
+        let payload = json!({
+            "id": Uuid::now_v7().to_string(),
+            "user_id": user_id,
+            "type": event_type,
+            "data": data,
+            "created_at": Utc::now(),
+        });
+
+        sqlx::query!(
+            "SELECT pg_notify($1, $2)",
+            EVENT_CHANNEL,
+            payload.to_string()
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Reports the balance of an account as it stands in the caller's
+    /// transaction.
+    pub async fn balance_updated(conn: &mut PgConnection, account_id: &str) -> ServiceResult<()> {
+        let account = sqlx::query!(
+            r#"
+            SELECT user_id, balance
+            FROM accounts
+            WHERE id = $1
+            "#,
+            account_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Self::publish(
+            conn,
+            &account.user_id,
+            "balance.updated",
+            json!({
+                "account_id": account_id,
+                "balance_msat": account.balance.to_string(),
+            }),
+        )
+        .await
+    }
+
+    /// Reports an outgoing payment that was just made, and emails the user
+    /// when it is large enough to look like a withdrawal they should know
+    /// about.
+    pub async fn payment_made(conn: &mut PgConnection, transaction_id: &str) -> ServiceResult<()> {
+        Self::report_payment(conn, transaction_id, true).await
+    }
+
+    /// Reports an outgoing payment that went through or failed, together
+    /// with the balance it left. Payments that went through or failed are
+    /// also queued for webhooks.
+    pub async fn payment_updated(
+        conn: &mut PgConnection,
+        transaction_id: &str,
+    ) -> ServiceResult<()> {
+        Self::report_payment(conn, transaction_id, false).await
+    }
+
+    async fn report_payment(
+        conn: &mut PgConnection,
+        transaction_id: &str,
+        made: bool,
+    ) -> ServiceResult<()> {
+        let transaction = sqlx::query_as!(
+            Transaction,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id?",
+                direction as "direction!",
+                invoice as "invoice!",
+                amount as "amount!",
+                fee_msat as "fee_msat!",
+                payment_hash as "payment_hash!",
+                payment_status as "payment_status!",
+                fiat_currency as "fiat_currency?",
+                fiat_rate as "fiat_rate?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM transactions
+            WHERE id = $1
+            "#,
+            transaction_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Self::publish(
+            conn,
+            &transaction.user_id,
+            "payment.updated",
+            json!({
+                "id": transaction.id,
+                "account_id": transaction.account_id,
+                "payment_hash": transaction.payment_hash,
+                "amount_msat": transaction.amount.to_string(),
+                "fee_msat": transaction.fee_msat.to_string(),
+                "status": transaction.payment_status,
+            }),
+        )
+        .await?;
+
+        if let Some(account_id) = &transaction.account_id {
+            Self::balance_updated(conn, account_id).await?;
+        }
+
+        if made {
+            let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+                message: format!("Config error: {}", e),
+            })?;
+            let threshold_msat = BigDecimal::from(config.large_withdrawal_alert_sat) * 1000;
+
+            if transaction.amount >= threshold_msat {
+                NotificationService::enqueue(
+                    conn,
+                    &transaction.user_id,
+                    Notification::LargeWithdrawal,
+                    vec![
+                        ("amount", format_sat(&transaction.amount)),
+                        ("reference", transaction.payment_hash.clone()),
+                    ],
+                )
+                .await?;
+            }
+        }
+
+        let event = match transaction.payment_status.as_str() {
+            "succeeded" => {
+                NotificationService::enqueue(
+                    conn,
+                    &transaction.user_id,
+                    Notification::PaymentSent,
+                    vec![
+                        ("amount", format_sat(&transaction.amount)),
+                        ("fee", format_sat(&transaction.fee_msat)),
+                        ("reference", transaction.payment_hash.clone()),
+                    ],
+                )
+                .await?;
+
+                WebhookEvent::PaymentSucceeded
+            }
+            "failed" => WebhookEvent::PaymentFailed,
+            _ => return Ok(()),
+        };
+
+        let user_id = transaction.user_id.clone();
+        WebhookService::enqueue(conn, &user_id, event, json!(transaction)).await
+    }
+
+    /// Reports an invoice settled in the caller's transaction and the
+    /// balance it was credited to, and queues 'invoice.paid' for webhooks.
+    pub async fn invoice_paid(conn: &mut PgConnection, payment_hash: &str) -> ServiceResult<()> {
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                payment_request as "payment_request!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat?",
+                amount_paid_msat as "amount_paid_msat?",
+                fiat_currency as "fiat_currency?",
+                fiat_amount as "fiat_amount?",
+                fiat_rate as "fiat_rate?",
+                memo as "memo!",
+                description_hash as "description_hash?",
+                comment as "comment?",
+                source as "source!",
+                status as "status!",
+                preimage as "preimage?",
+                expires_at as "expires_at!: DateTime<Utc>",
+                settled_at as "settled_at?: DateTime<Utc>",
+                accepted_at as "accepted_at?: DateTime<Utc>",
+                htlc_expiry_height as "htlc_expiry_height?",
+                settle_by as "settle_by?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM invoices
+            WHERE payment_hash = $1
+            "#,
+            payment_hash
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Self::publish(
+            conn,
+            &invoice.user_id,
+            "invoice.paid",
+            json!({
+                "id": invoice.id,
+                "account_id": invoice.account_id,
+                "payment_hash": invoice.payment_hash,
+                "amount_paid_msat": invoice.amount_paid_msat.as_ref().map(ToString::to_string),
+                "settled_at": invoice.settled_at,
+            }),
+        )
+        .await?;
+
+        Self::balance_updated(conn, &invoice.account_id).await?;
+
+        if let Some(amount_paid_msat) = &invoice.amount_paid_msat {
+            NotificationService::enqueue(
+                conn,
+                &invoice.user_id,
+                Notification::PaymentReceived,
+                vec![
+                    ("amount", format_sat(amount_paid_msat)),
+                    ("method", "Lightning".to_string()),
+                    ("reference", invoice.payment_hash.clone()),
+                ],
+            )
+            .await?;
+        }
+
+        let user_id = invoice.user_id.clone();
+        WebhookService::enqueue(conn, &user_id, WebhookEvent::InvoicePaid, json!(invoice)).await
+    }
+
+    /// Reports a deposit credited in the caller's transaction and the
+    /// balance it was credited to, and queues 'deposit.confirmed' for
+    /// webhooks.
+    pub async fn deposit_confirmed(conn: &mut PgConnection, deposit_id: &str) -> ServiceResult<()> {
+        let deposit = sqlx::query_as!(
+            Deposit,
+            r#"
+            SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                address as "address!",
+                txid as "txid!",
+                vout as "vout!",
+                amount_msat as "amount_msat!",
+                confirmations as "confirmations!",
+                block_height as "block_height?",
+                status as "status!",
+                credited_at as "credited_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM deposits
+            WHERE id = $1
+            "#,
+            deposit_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Self::publish(
+            conn,
+            &deposit.user_id,
+            "deposit.confirmed",
+            json!({
+                "id": deposit.id,
+                "account_id": deposit.account_id,
+                "txid": deposit.txid,
+                "vout": deposit.vout,
+                "amount_msat": deposit.amount_msat.to_string(),
+            }),
+        )
+        .await?;
+
+        Self::balance_updated(conn, &deposit.account_id).await?;
+
+        NotificationService::enqueue(
+            conn,
+            &deposit.user_id,
+            Notification::PaymentReceived,
+            vec![
+                ("amount", format_sat(&deposit.amount_msat)),
+                ("method", "On-chain deposit".to_string()),
+                ("reference", format!("{}:{}", deposit.txid, deposit.vout)),
+            ],
+        )
+        .await?;
+
+        let user_id = deposit.user_id.clone();
+        WebhookService::enqueue(
+            conn,
+            &user_id,
+            WebhookEvent::DepositConfirmed,
+            json!(deposit),
+        )
+        .await
+    }
+}
+
+/// Listens for committed account events and hands them to the bus,
+/// reconnecting whenever the database connection drops.
+pub async fn run_event_listener(pool: PgPool, bus: Arc<EventBus>) {
+    loop {
+        if let Err(error) = listen(&pool, &bus).await {
+            tracing::warn!("Account event listener stopped: {}", error);
+        }
+
+        tokio::time::sleep(Duration::from_secs(EVENT_LISTENER_RETRY_SECONDS)).await;
+    }
+}
+
+async fn listen(pool: &PgPool, bus: &EventBus) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(EVENT_CHANNEL).await?;
+
+    loop {
+        let notification = listener.recv().await?;
+
+        match serde_json::from_str::<AccountEvent>(notification.payload()) {
+            // Sending only fails when no stream is open
+            Ok(event) => {
+                let _ = bus.sender.send(Arc::new(event));
+            }
+            Err(error) => tracing::warn!("Dropping malformed account event: {}", error),
+        }
+    }
+}
@@ -0,0 +1,640 @@
+// Fiat Service Logic
+//! Fiat valuation of balances and transactions.
+//!
+//! The rate recorder asks the rate store for every supported currency and
+//! keeps what it gets in 'exchange_rates'. Balances are shown at the latest
+//! recorded rate of the account's display currency. Each transaction is
+//! stamped with the rate recorded just before it was made, so statements
+//! keep showing what a payment was worth at the time.
+
+use crate::common::common::PaginationFilter;
+use crate::db::models::{
+    Account, AccountBalance, ApiKeyScope, FiatQuote, FiatValue, TransactionWithFiat,
+};
+use crate::errors::{RateError, ServiceError, ServiceResult};
+use crate::repositories::exchange_rate_repository::ExchangeRateRepository;
+use crate::repositories::transaction_repository::TransactionRepository;
+use crate::service::rate_provider::RateStore;
+use crate::utilities::auth::AuthUser;
+use bigdecimal::{RoundingMode, Signed, ToPrimitive};
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Millisatoshis in one bitcoin
+const MSAT_PER_BTC: i64 = 100_000_000_000;
+
+/// Decimal places fiat amounts are rounded to
+const FIAT_SCALE: i64 = 2;
+
+/// Oldest a recorded rate may be, relative to a transaction, to value it
+const RATE_MATCH_WINDOW_SECONDS: i64 = 3600;
+
+/// Furthest a recorded rate may be, either side of a transaction, to value
+/// it when no rate was recorded shortly before it
+const RATE_NEAREST_WINDOW_SECONDS: i64 = 86_400;
+
+/// Decimal places a rate is kept to once the spread is taken off
+const RATE_SCALE: i64 = 8;
+
+/// Values an amount of millisatoshis at `rate`, the price of one bitcoin.
+pub fn fiat_value(amount_msat: &BigDecimal, currency: &str, rate: &BigDecimal) -> FiatValue {
+    let amount = (amount_msat * rate / BigDecimal::from(MSAT_PER_BTC))
+        .with_scale_round(FIAT_SCALE, RoundingMode::HalfEven);
+
+    FiatValue {
+        currency: currency.to_string(),
+        amount,
+        rate: rate.clone(),
+    }
+}
+
+/// Converts `amount` of `currency` into millisatoshis at the current rate
+/// less `spread_percent`, so the payer covers the margin.
+///
+/// # Returns
+/// The amount in msat, rounded up, and the quote it was worked out from
+pub async fn quote_fiat(
+    rates: &RateStore,
+    currency: &str,
+    amount: &BigDecimal,
+    spread_percent: f64,
+) -> ServiceResult<(u64, FiatQuote)> {
+    let currency = currency.to_uppercase();
+    let market_rate = rates
+        .get_rates(std::slice::from_ref(&currency))
+        .await?
+        .remove(&currency)
+        .filter(|rate| rate.is_positive())
+        .ok_or_else(|| RateError::MissingRate(currency.clone()))?;
+
+    let spread = BigDecimal::from_str(&spread_percent.to_string()).map_err(|e| {
+        ServiceError::InternalError {
+            message: e.to_string(),
+        }
+    })?;
+    let rate = (market_rate * (BigDecimal::from(100) - spread) / BigDecimal::from(100))
+        .with_scale_round(RATE_SCALE, RoundingMode::HalfEven);
+
+    let amount_msat = (amount * BigDecimal::from(MSAT_PER_BTC) / &rate)
+        .with_scale_round(0, RoundingMode::Ceiling)
+        .to_u64()
+        .filter(|amount_msat| *amount_msat >= 1)
+        .ok_or_else(|| ServiceError::validation("fiat_amount: Amount is out of range"))?;
+
+    Ok((
+        amount_msat,
+        FiatQuote {
+            currency,
+            amount: amount.clone(),
+            rate,
+        },
+    ))
+}
+
+// Service layer for Fiat related Operation
+pub struct FiatService<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> FiatService<'a> {
+    /// Creates a new fiat service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Values each account's balance in its display currency.
+    ///
+    /// Accounts whose currency has no recorded rate yet come back without a
+    /// fiat balance.
+    pub async fn value_accounts(
+        &self,
+        accounts: Vec<Account>,
+    ) -> ServiceResult<Vec<AccountBalance>> {
+        let mut currencies: Vec<String> = accounts
+            .iter()
+            .map(|account| account.display_currency.clone())
+            .collect();
+        currencies.sort();
+        currencies.dedup();
+
+        let rates: HashMap<String, BigDecimal> = ExchangeRateRepository::new(self.pool)
+            .get_latest_rates(&currencies)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .into_iter()
+            .map(|rate| (rate.currency, rate.rate))
+            .collect();
+
+        Ok(accounts
+            .into_iter()
+            .map(|account| {
+                let fiat_balance = rates
+                    .get(&account.display_currency)
+                    .map(|rate| fiat_value(&account.balance, &account.display_currency, rate));
+                AccountBalance {
+                    account,
+                    fiat_balance,
+                }
+            })
+            .collect())
+    }
+
+    /// Lists the caller's transactions, newest first, valued at the rate
+    /// each was made at.
+    pub async fn list_transactions(
+        &self,
+        auth: &AuthUser,
+        filter: &PaginationFilter,
+    ) -> ServiceResult<(Vec<TransactionWithFiat>, u64)> {
+        auth.require_scope(ApiKeyScope::PaymentsRead)?;
+
+        let transaction_repo = TransactionRepository::new(self.pool);
+
+        let transactions = transaction_repo
+            .get_transactions_by_user_id(auth.user_id(), filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = transaction_repo
+            .count_transactions_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        let transactions = transactions
+            .into_iter()
+            .map(|transaction| {
+                let (fiat_amount, fiat_fee) =
+                    match (&transaction.fiat_currency, &transaction.fiat_rate) {
+                        (Some(currency), Some(rate)) => (
+                            Some(fiat_value(&transaction.amount, currency, rate)),
+                            Some(fiat_value(&transaction.fee_msat, currency, rate)),
+                        ),
+                        _ => (None, None),
+                    };
+                TransactionWithFiat {
+                    transaction,
+                    fiat_amount,
+                    fiat_fee,
+                }
+            })
+            .collect();
+
+        Ok((transactions, total))
+    }
+
+    /// Fetches the rate of each currency through the rate store and adds it
+    /// to the rate history.
+    ///
+    /// # Returns
+    /// Number of rates recorded
+    pub async fn record_rates(
+        &self,
+        rates: &RateStore,
+        currencies: &[String],
+    ) -> ServiceResult<usize> {
+        let fetched = rates.get_rates(currencies).await?;
+
+        for currency in currencies {
+            let Some(rate) = fetched.get(currency) else {
+                tracing::warn!("{}", RateError::MissingRate(currency.clone()));
+                continue;
+            };
+
+            sqlx::query!(
+                r#"
+                INSERT INTO exchange_rates (id, currency, rate, source)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                Uuid::now_v7().to_string(),
+                currency,
+                rate,
+                rates.source()
+            )
+            .execute(self.pool)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+        }
+
+        Ok(fetched.len())
+    }
+
+    /// Stamps transactions that have no fiat rate yet with the account's
+    /// display currency and the last rate recorded before they were made.
+    ///
+    /// Transactions with no rate recorded shortly before them, such as
+    /// those made before rates were recorded or while the provider was
+    /// down, take the recorded rate nearest to when they were made, as long
+    /// as it is within a day of them. Transactions with no rate that close
+    /// are left unvalued rather than priced at a rate from another time.
+    ///
+    /// # Returns
+    /// Number of transactions valued
+    pub async fn value_transactions(&self) -> ServiceResult<u64> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE transactions t
+            SET fiat_currency = valued.currency,
+                fiat_rate = valued.rate
+            FROM (
+                SELECT
+                    tx.id,
+                    a.display_currency AS currency,
+                    COALESCE(
+                        (
+                            SELECT r.rate
+                            FROM exchange_rates r
+                            WHERE r.currency = a.display_currency
+                              AND r.fetched_at <= tx.created_at
+                              AND r.fetched_at > tx.created_at - make_interval(secs => $1)
+                            ORDER BY r.fetched_at DESC
+                            LIMIT 1
+                        ),
+                        (
+                            SELECT nearest.rate
+                            FROM (
+                                (
+                                    SELECT r.rate, r.fetched_at
+                                    FROM exchange_rates r
+                                    WHERE r.currency = a.display_currency
+                                      AND r.fetched_at <= tx.created_at
+                                    ORDER BY r.fetched_at DESC
+                                    LIMIT 1
+                                )
+                                UNION ALL
+                                (
+                                    SELECT r.rate, r.fetched_at
+                                    FROM exchange_rates r
+                                    WHERE r.currency = a.display_currency
+                                      AND r.fetched_at > tx.created_at
+                                    ORDER BY r.fetched_at ASC
+                                    LIMIT 1
+                                )
+                            ) nearest
+                            WHERE abs(extract(epoch FROM nearest.fetched_at - tx.created_at)) <= $2
+                            ORDER BY abs(extract(epoch FROM nearest.fetched_at - tx.created_at))
+                            LIMIT 1
+                        )
+                    ) AS rate
+                FROM transactions tx
+                JOIN accounts a ON a.id = tx.account_id
+                WHERE tx.fiat_rate IS NULL
+            ) valued
+            WHERE t.id = valued.id
+              AND valued.rate IS NOT NULL
+            "#,
+            RATE_MATCH_WINDOW_SECONDS as f64,
+            RATE_NEAREST_WINDOW_SECONDS as f64
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Records exchange rates and values new transactions every
+/// `interval_seconds`.
+pub async fn run_rate_recorder(
+    pool: PgPool,
+    rates: Arc<RateStore>,
+    currencies: Vec<String>,
+    interval_seconds: u64,
+) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_seconds));
+
+    loop {
+        interval.tick().await;
+
+        let service = FiatService::new(&pool);
+
+        if let Err(error) = service.record_rates(&rates, &currencies).await {
+            tracing::warn!("Recording exchange rates failed: {}", error);
+        }
+
+        match service.value_transactions().await {
+            Ok(0) => {}
+            Ok(count) => tracing::debug!("Valued {} transactions in fiat", count),
+            Err(error) => tracing::warn!("Valuing transactions failed: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::rate_provider::{RateProvider, StaticRateProvider};
+    use async_trait::async_trait;
+    use chrono::{Duration as ChronoDuration, Utc};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Static rates from a file, remembering which currencies were asked for
+    struct CountingProvider {
+        rates: StaticRateProvider,
+        requests: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl CountingProvider {
+        fn from_rates(json: &str) -> Arc<Self> {
+            let path = std::env::temp_dir().join(format!("rates-{}.json", Uuid::now_v7()));
+            std::fs::write(&path, json).unwrap();
+            let rates = StaticRateProvider::from_file(path.to_str().unwrap()).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            Arc::new(Self {
+                rates,
+                requests: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn requests(&self) -> Vec<Vec<String>> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl RateProvider for CountingProvider {
+        fn name(&self) -> &'static str {
+            self.rates.name()
+        }
+
+        async fn get_rates(
+            &self,
+            currencies: &[String],
+        ) -> Result<HashMap<String, BigDecimal>, RateError> {
+            self.requests.lock().unwrap().push(currencies.to_vec());
+            self.rates.get_rates(currencies).await
+        }
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn currencies(codes: &[&str]) -> Vec<String> {
+        codes.iter().map(|code| code.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn rate_file_accepts_numbers_and_strings() {
+        let provider = CountingProvider::from_rates(r#"{"usd": 65000, "EUR": "60000.5"}"#);
+
+        let rates = provider
+            .get_rates(&currencies(&["USD", "eur", "GBP"]))
+            .await
+            .unwrap();
+
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates["USD"], decimal("65000"));
+        assert_eq!(rates["EUR"], decimal("60000.5"));
+    }
+
+    #[tokio::test]
+    async fn rate_store_reuses_rates_within_the_ttl() {
+        let provider = CountingProvider::from_rates(r#"{"USD": 65000, "EUR": 60000}"#);
+        let store = RateStore::new(provider.clone(), Duration::from_secs(60));
+
+        let first = store.get_rates(&currencies(&["USD"])).await.unwrap();
+        let second = store.get_rates(&currencies(&["usd", "EUR"])).await.unwrap();
+
+        assert_eq!(first["USD"], decimal("65000"));
+        assert_eq!(second["USD"], decimal("65000"));
+        assert_eq!(second["EUR"], decimal("60000"));
+        // Only the currency not cached yet goes to the provider
+        assert_eq!(
+            provider.requests(),
+            vec![currencies(&["USD"]), currencies(&["EUR"])]
+        );
+        assert_eq!(store.source(), "file");
+    }
+
+    #[tokio::test]
+    async fn rate_store_refetches_expired_rates() {
+        let provider = CountingProvider::from_rates(r#"{"USD": 65000}"#);
+        let store = RateStore::new(provider.clone(), Duration::ZERO);
+
+        store.get_rates(&currencies(&["USD"])).await.unwrap();
+        store.get_rates(&currencies(&["USD"])).await.unwrap();
+
+        assert_eq!(provider.requests().len(), 2);
+    }
+
+    #[test]
+    fn fiat_value_rounds_half_even_to_cents() {
+        let rate = decimal("65000");
+
+        let value = fiat_value(&BigDecimal::from(150_000_000), "USD", &rate);
+        assert_eq!(value.amount, decimal("97.50"));
+        assert_eq!(value.currency, "USD");
+        assert_eq!(value.rate, rate);
+
+        let rate = decimal("100000");
+        assert_eq!(
+            fiat_value(&BigDecimal::from(125_000), "USD", &rate).amount,
+            decimal("0.12")
+        );
+        assert_eq!(
+            fiat_value(&BigDecimal::from(135_000), "USD", &rate).amount,
+            decimal("0.14")
+        );
+    }
+
+    #[tokio::test]
+    async fn quote_takes_the_spread_off_the_rate_and_rounds_up() {
+        let provider = CountingProvider::from_rates(r#"{"USD": 65000}"#);
+        let store = RateStore::new(provider, Duration::from_secs(60));
+
+        let (amount_msat, quote) = quote_fiat(&store, "usd", &decimal("10"), 1.5)
+            .await
+            .unwrap();
+
+        assert_eq!(quote.currency, "USD");
+        assert_eq!(quote.amount, decimal("10"));
+        assert_eq!(quote.rate, decimal("64025.00000000"));
+        // 10 * 100_000_000_000 / 64025 = 15618898.87..., rounded up
+        assert_eq!(amount_msat, 15_618_899);
+
+        // Valued at the locked rate, the invoice is worth what was billed
+        let value = fiat_value(&BigDecimal::from(amount_msat), &quote.currency, &quote.rate);
+        assert_eq!(value.amount, decimal("10.00"));
+        // At the market rate the payer covers the spread
+        let market = fiat_value(&BigDecimal::from(amount_msat), "USD", &decimal("65000"));
+        assert_eq!(market.amount, decimal("10.15"));
+    }
+
+    #[tokio::test]
+    async fn quote_needs_a_positive_rate() {
+        let provider = CountingProvider::from_rates(r#"{"USD": 0}"#);
+        let store = RateStore::new(provider, Duration::from_secs(60));
+
+        for currency in ["USD", "EUR"] {
+            let result = quote_fiat(&store, currency, &decimal("10"), 0.0).await;
+            assert!(matches!(
+                result,
+                Err(ServiceError::ExternalService { message })
+                    if message == format!("No exchange rate for {currency}")
+            ));
+        }
+    }
+
+    async fn account(pool: &PgPool, currency: &str) -> (String, String) {
+        let user_id = Uuid::now_v7().to_string();
+        let account_id = Uuid::now_v7().to_string();
+
+        sqlx::query(
+            "INSERT INTO users (id, username, role_id)
+             SELECT $1, $1, id FROM roles WHERE name = 'customer'",
+        )
+        .bind(&user_id)
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO accounts (id, user_id, balance, display_currency) VALUES ($1, $2, 0, $3)",
+        )
+        .bind(&account_id)
+        .bind(&user_id)
+        .bind(currency)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        (user_id, account_id)
+    }
+
+    async fn record_rate(pool: &PgPool, currency: &str, rate: &str, minutes_ago: i64) {
+        sqlx::query(
+            "INSERT INTO exchange_rates (id, currency, rate, source, fetched_at)
+             VALUES ($1, $2, $3, 'file', $4)",
+        )
+        .bind(Uuid::now_v7().to_string())
+        .bind(currency)
+        .bind(decimal(rate))
+        .bind(Utc::now() - ChronoDuration::minutes(minutes_ago))
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    /// Records a transaction made `minutes_ago`, optionally at a locked rate
+    async fn transaction(
+        pool: &PgPool,
+        (user_id, account_id): &(String, String),
+        minutes_ago: i64,
+        locked_rate: Option<&str>,
+    ) -> String {
+        let id = Uuid::now_v7().to_string();
+
+        sqlx::query(
+            "INSERT INTO transactions (
+                id, user_id, account_id, direction, invoice, amount, payment_hash,
+                payment_status, created_at, fiat_currency, fiat_rate
+            )
+            VALUES ($1, $2, $3, 'incoming', 'lnbcrt1', 1000, $1, 'settled', $4, $5, $6)",
+        )
+        .bind(&id)
+        .bind(user_id)
+        .bind(account_id)
+        .bind(Utc::now() - ChronoDuration::minutes(minutes_ago))
+        .bind(locked_rate.map(|_| "USD"))
+        .bind(locked_rate.map(decimal))
+        .execute(pool)
+        .await
+        .unwrap();
+
+        id
+    }
+
+    async fn valuation(pool: &PgPool, id: &str) -> (Option<String>, Option<BigDecimal>) {
+        sqlx::query_as("SELECT fiat_currency, fiat_rate FROM transactions WHERE id = $1")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn transactions_take_the_rate_recorded_before_them(pool: PgPool) {
+        let owner = account(&pool, "USD").await;
+        record_rate(&pool, "USD", "60000", 50).await;
+        record_rate(&pool, "USD", "61000", 20).await;
+        record_rate(&pool, "USD", "62000", 5).await;
+        let id = transaction(&pool, &owner, 10, None).await;
+
+        assert_eq!(
+            FiatService::new(&pool).value_transactions().await.unwrap(),
+            1
+        );
+
+        assert_eq!(
+            valuation(&pool, &id).await,
+            (Some("USD".to_string()), Some(decimal("61000")))
+        );
+    }
+
+    #[sqlx::test]
+    async fn transactions_without_a_recent_rate_take_the_nearest_one(pool: PgPool) {
+        let owner = account(&pool, "EUR").await;
+        // Made before any rate was recorded
+        let early = transaction(&pool, &owner, 600, None).await;
+        record_rate(&pool, "EUR", "50000", 500).await;
+        // Made while the provider was down, nearer the rate after the outage
+        let outage = transaction(&pool, &owner, 300, None).await;
+        record_rate(&pool, "EUR", "55000", 200).await;
+
+        assert_eq!(
+            FiatService::new(&pool).value_transactions().await.unwrap(),
+            2
+        );
+
+        assert_eq!(valuation(&pool, &early).await.1, Some(decimal("50000")));
+        assert_eq!(valuation(&pool, &outage).await.1, Some(decimal("55000")));
+    }
+
+    #[sqlx::test]
+    async fn transactions_far_from_any_rate_are_left_unvalued(pool: PgPool) {
+        let owner = account(&pool, "EUR").await;
+        // Three days before the first rate, and half a day before it
+        let stale = transaction(&pool, &owner, 3 * 24 * 60 + 60, None).await;
+        let close = transaction(&pool, &owner, 12 * 60 + 60, None).await;
+        record_rate(&pool, "EUR", "50000", 60).await;
+
+        assert_eq!(
+            FiatService::new(&pool).value_transactions().await.unwrap(),
+            1
+        );
+
+        assert_eq!(valuation(&pool, &stale).await, (None, None));
+        assert_eq!(valuation(&pool, &close).await.1, Some(decimal("50000")));
+    }
+
+    #[sqlx::test]
+    async fn locked_and_unpriced_transactions_are_left_alone(pool: PgPool) {
+        let usd = account(&pool, "USD").await;
+        let gbp = account(&pool, "GBP").await;
+        record_rate(&pool, "USD", "65000", 30).await;
+        let locked = transaction(&pool, &usd, 10, Some("64025")).await;
+        let unpriced = transaction(&pool, &gbp, 10, None).await;
+
+        let service = FiatService::new(&pool);
+        assert_eq!(service.value_transactions().await.unwrap(), 0);
+
+        assert_eq!(
+            valuation(&pool, &locked).await,
+            (Some("USD".to_string()), Some(decimal("64025")))
+        );
+        assert_eq!(valuation(&pool, &unpriced).await, (None, None));
+
+        // Valued once its currency has a rate
+        record_rate(&pool, "GBP", "52000", 0).await;
+        assert_eq!(service.value_transactions().await.unwrap(), 1);
+        assert_eq!(valuation(&pool, &unpriced).await.1, Some(decimal("52000")));
+    }
+}
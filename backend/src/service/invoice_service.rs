@@ -0,0 +1,1121 @@
+// Invoice Service Logic
+//! Issues invoices on the node for users and credits their accounts once
+//! the node reports them settled.
+//!
+//! Hold invoices are paid in two steps: the node accepts the payment and
+//! holds it until the creator settles or cancels it. Payments still held
+//! close to the HTLC's expiry are canceled, so the payer's funds are never
+//! stuck and the node never risks a force close.
+
+use crate::Config;
+use crate::common::common::PaginationFilter;
+use crate::db::models::{
+    Account, ApiKeyScope, CreateHoldInvoice, CreateInvoice, Invoice, NewInvoice, SettleHoldInvoice,
+};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::account_repository::AccountRepository;
+use crate::repositories::invoice_repository::InvoiceRepository;
+use crate::repositories::offer_repository::OfferRepository;
+use crate::service::event_service::EventService;
+use crate::service::fiat_service::quote_fiat;
+use crate::service::node_service::LightningClient;
+use crate::service::rate_provider::RateStore;
+use crate::utilities::auth::AuthUser;
+use crate::utilities::{CustomInvoice, HoldInvoiceRequest, InvoiceRequest, InvoiceStatus};
+use bigdecimal::Signed;
+use chrono::{DateTime, Duration, Utc};
+use futures::StreamExt;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Invoice a user created for themselves
+pub const INVOICE_SOURCE_USER: &str = "user";
+
+/// Invoice issued through a Lightning Address / LNURL-pay request
+pub const INVOICE_SOURCE_LNURL_PAY: &str = "lnurl_pay";
+
+/// Invoice the node issued to a payer of a BOLT12 offer
+pub const INVOICE_SOURCE_BOLT12_OFFER: &str = "bolt12_offer";
+
+/// Spontaneous payment received without an invoice
+pub const INVOICE_SOURCE_KEYSEND: &str = "keysend";
+
+/// Hold invoice the creator settles or cancels once paid
+pub const INVOICE_SOURCE_HOLD: &str = "hold";
+
+/// Invoice a swap provider pays in exchange for on-chain coins
+pub const INVOICE_SOURCE_SWAP: &str = "swap";
+
+/// Pause before resubscribing after the invoice stream drops
+const WATCHER_RETRY_SECONDS: u64 = 5;
+
+/// How often hold invoices are checked with the node. The invoice stream
+/// does not report payments being accepted.
+const HOLD_INVOICE_POLL_SECONDS: u64 = 15;
+
+/// Blocks before the HTLC expires at which a held payment is canceled
+const HOLD_INVOICE_CANCEL_MARGIN_BLOCKS: u32 = 12;
+
+/// Average time between blocks, for estimating deadlines
+const BLOCK_INTERVAL_SECONDS: i64 = 600;
+
+// Service layer for Invoice related Operation
+pub struct InvoiceService<'a> {
+    pool: &'a PgPool,
+    lightning: &'a dyn LightningClient,
+}
+
+impl<'a> InvoiceService<'a> {
+    /// Creates a new invoice service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    /// * 'lightning' - Node the invoices are issued on
+    pub fn new(pool: &'a PgPool, lightning: &'a dyn LightningClient) -> Self {
+        Self { pool, lightning }
+    }
+
+    /// Creates an invoice for the caller, with a fixed amount or one the
+    /// payer chooses.
+    ///
+    /// An amount given in fiat is converted at the current rate less the
+    /// configured spread. That rate stays locked until the invoice expires.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'invoices:write' scope
+    /// - Validation failures, including currencies rates are not kept for
+    /// - Accounts that cannot receive
+    pub async fn create_invoice(
+        &self,
+        auth: &AuthUser,
+        create_invoice: CreateInvoice,
+        rates: &RateStore,
+    ) -> ServiceResult<Invoice> {
+        auth.require_scope(ApiKeyScope::InvoicesWrite)?;
+
+        if let Err(validation_errors) = create_invoice.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let (amount_msat, fiat) = match (&create_invoice.fiat_amount, &create_invoice.currency) {
+            (None, None) => (create_invoice.amount_msat, None),
+            (Some(fiat_amount), Some(currency)) => {
+                if create_invoice.amount_msat.is_some() {
+                    return Err(ServiceError::validation(
+                        "amount_msat: Give either amount_msat or fiat_amount, not both",
+                    ));
+                }
+                if !fiat_amount.is_positive() {
+                    return Err(ServiceError::validation(
+                        "fiat_amount: Amount must be more than 0",
+                    ));
+                }
+
+                let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+                    message: e.to_string(),
+                })?;
+                let currency = currency.to_uppercase();
+                if !config.fiat_currencies.contains(&currency) {
+                    return Err(ServiceError::validation(format!(
+                        "currency: Must be one of {}",
+                        config.fiat_currencies.join(", ")
+                    )));
+                }
+
+                let (amount_msat, quote) = quote_fiat(
+                    rates,
+                    &currency,
+                    fiat_amount,
+                    config.fiat_invoice_spread_percent,
+                )
+                .await?;
+                (Some(amount_msat), Some(quote))
+            }
+            _ => {
+                return Err(ServiceError::validation(
+                    "currency: fiat_amount and currency must be given together",
+                ));
+            }
+        };
+
+        self.issue_invoice(
+            auth.user_id(),
+            NewInvoice {
+                amount_msat,
+                fiat,
+                memo: create_invoice.memo,
+                hashed_description: None,
+                comment: None,
+                source: INVOICE_SOURCE_USER.to_string(),
+            },
+        )
+        .await
+    }
+
+    /// Lists the caller's invoices, newest first, with their total count.
+    pub async fn list_invoices(
+        &self,
+        auth: &AuthUser,
+        filter: &PaginationFilter,
+    ) -> ServiceResult<(Vec<Invoice>, u64)> {
+        auth.require_scope(ApiKeyScope::InvoicesRead)?;
+
+        let invoice_repo = InvoiceRepository::new(self.pool);
+
+        let invoices = invoice_repo
+            .get_invoices_by_user_id(auth.user_id(), filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = invoice_repo
+            .count_invoices_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        Ok((invoices, total))
+    }
+
+    /// Creates a hold invoice for the caller.
+    ///
+    /// The bank keeps the preimage unless the caller supplies the payment
+    /// hash, in which case they must present the preimage to settle.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'invoices:write' scope
+    /// - Validation failures
+    /// - Payment hashes already used by another invoice
+    /// - Accounts that cannot receive
+    pub async fn create_hold_invoice(
+        &self,
+        auth: &AuthUser,
+        create_hold_invoice: CreateHoldInvoice,
+    ) -> ServiceResult<Invoice> {
+        auth.require_scope(ApiKeyScope::InvoicesWrite)?;
+
+        if let Err(validation_errors) = create_hold_invoice.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })?;
+
+        let (payment_hash, preimage) = match &create_hold_invoice.payment_hash {
+            Some(payment_hash) => (
+                decode_hash(payment_hash).ok_or_else(|| {
+                    ServiceError::validation("payment_hash: Invalid payment hash")
+                })?,
+                None,
+            ),
+            None => {
+                let mut preimage = [0u8; 32];
+                rand::thread_rng().fill_bytes(&mut preimage);
+                (Sha256::digest(preimage).into(), Some(preimage))
+            }
+        };
+
+        let invoice_repo = InvoiceRepository::new(self.pool);
+        if invoice_repo
+            .get_invoice_by_payment_hash(&hex::encode(payment_hash))
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .is_some()
+        {
+            return Err(ServiceError::validation(
+                "payment_hash: Payment hash is already in use",
+            ));
+        }
+
+        let account = self.receiving_account(auth.user_id()).await?;
+
+        let new_invoice = NewInvoice {
+            amount_msat: Some(create_hold_invoice.amount_msat),
+            fiat: None,
+            memo: create_hold_invoice.memo,
+            hashed_description: None,
+            comment: None,
+            source: INVOICE_SOURCE_HOLD.to_string(),
+        };
+
+        let node_invoice = self
+            .lightning
+            .create_hold_invoice(HoldInvoiceRequest {
+                invoice: InvoiceRequest {
+                    amount_msat: new_invoice.amount_msat,
+                    memo: new_invoice.memo.clone(),
+                    hashed_description: None,
+                    expiry: config.invoice_expiry_seconds,
+                },
+                payment_hash,
+                cltv_expiry: config.hold_invoice_cltv_expiry,
+            })
+            .await?;
+
+        self.record_issued_invoice(
+            &account,
+            &node_invoice,
+            new_invoice,
+            preimage.map(hex::encode),
+            &config,
+        )
+        .await
+    }
+
+    /// Settles a hold invoice the node has accepted a payment for, crediting
+    /// the caller's account.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'invoices:write' scope
+    /// - Hold invoices that are not the caller's
+    /// - Hold invoices without a payment held
+    /// - Missing preimages, or ones that do not match the payment hash
+    pub async fn settle_hold_invoice(
+        &self,
+        auth: &AuthUser,
+        id: &str,
+        settle_hold_invoice: SettleHoldInvoice,
+    ) -> ServiceResult<Invoice> {
+        auth.require_scope(ApiKeyScope::InvoicesWrite)?;
+
+        if let Err(validation_errors) = settle_hold_invoice.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let invoice = self.owned_hold_invoice(auth, id).await?;
+
+        // The node is the authority on whether a payment is being held
+        let node_invoice = self
+            .lightning
+            .get_invoice_details(&invoice.payment_hash)
+            .await?;
+        if !matches!(node_invoice.state, InvoiceStatus::Accepted) || invoice.status == "canceled" {
+            return Err(ServiceError::invalid_operation(
+                "Hold invoice has no payment waiting to be settled",
+            ));
+        }
+
+        let preimage = settle_hold_invoice
+            .preimage
+            .or(invoice.preimage)
+            .ok_or_else(|| {
+                ServiceError::validation(
+                    "preimage: Preimage is required when you supplied the payment hash",
+                )
+            })?;
+        let preimage = decode_hash(&preimage)
+            .ok_or_else(|| ServiceError::validation("preimage: Invalid preimage"))?;
+        if hex::encode(Sha256::digest(preimage)) != invoice.payment_hash {
+            return Err(ServiceError::validation(
+                "preimage: Preimage does not match the payment hash",
+            ));
+        }
+
+        self.lightning.settle_hold_invoice(preimage).await?;
+
+        let node_invoice = self
+            .lightning
+            .get_invoice_details(&invoice.payment_hash)
+            .await?;
+        self.apply_node_update(&node_invoice).await?;
+
+        self.owned_hold_invoice(auth, id).await
+    }
+
+    /// Cancels a hold invoice, failing back any payment held for it.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'invoices:write' scope
+    /// - Hold invoices that are not the caller's
+    /// - Hold invoices already settled, canceled or expired
+    pub async fn cancel_hold_invoice(&self, auth: &AuthUser, id: &str) -> ServiceResult<Invoice> {
+        auth.require_scope(ApiKeyScope::InvoicesWrite)?;
+
+        let invoice = self.owned_hold_invoice(auth, id).await?;
+
+        if invoice.status != "open" && invoice.status != "accepted" {
+            return Err(ServiceError::invalid_operation(format!(
+                "Hold invoice is already {}",
+                invoice.status
+            )));
+        }
+
+        self.lightning
+            .cancel_hold_invoice(&invoice.payment_hash)
+            .await?;
+        self.close_invoice(&invoice.payment_hash, "canceled")
+            .await?;
+
+        self.owned_hold_invoice(auth, id).await
+    }
+
+    /// Looks up one of the caller's hold invoices.
+    async fn owned_hold_invoice(&self, auth: &AuthUser, id: &str) -> ServiceResult<Invoice> {
+        InvoiceRepository::new(self.pool)
+            .get_invoice_by_id(id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|invoice| {
+                invoice.user_id == auth.user_id() && invoice.source == INVOICE_SOURCE_HOLD
+            })
+            .ok_or_else(|| ServiceError::not_found("Hold invoice", id))
+    }
+
+    /// Issues an invoice on the node that credits the user's account.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' if the user has no active account or the node
+    /// refuses the invoice
+    pub async fn issue_invoice(
+        &self,
+        user_id: &str,
+        new_invoice: NewInvoice,
+    ) -> ServiceResult<Invoice> {
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })?;
+
+        let account = self.receiving_account(user_id).await?;
+
+        let node_invoice = self
+            .lightning
+            .create_invoice(InvoiceRequest {
+                amount_msat: new_invoice.amount_msat,
+                memo: new_invoice.memo.clone(),
+                hashed_description: new_invoice.hashed_description.clone(),
+                expiry: config.invoice_expiry_seconds,
+            })
+            .await?;
+
+        self.record_issued_invoice(&account, &node_invoice, new_invoice, None, &config)
+            .await
+    }
+
+    /// Account of a user that payments can be credited to.
+    async fn receiving_account(&self, user_id: &str) -> ServiceResult<Account> {
+        AccountRepository::new(self.pool)
+            .get_accoount_by_user_id(user_id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|account| account.is_active)
+            .ok_or_else(|| ServiceError::invalid_operation("Account is not able to receive"))
+    }
+
+    /// Records an invoice just issued on the node for an account.
+    ///
+    /// `preimage` is only kept for hold invoices the bank settles itself.
+    async fn record_issued_invoice(
+        &self,
+        account: &Account,
+        node_invoice: &CustomInvoice,
+        new_invoice: NewInvoice,
+        preimage: Option<String>,
+        config: &Config,
+    ) -> ServiceResult<Invoice> {
+        let expires_at = node_invoice
+            .creation_date
+            .and_then(|created| DateTime::from_timestamp(created, 0))
+            .unwrap_or_else(Utc::now)
+            + Duration::seconds(node_invoice.expiry.unwrap_or(config.invoice_expiry_seconds) as i64);
+
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"
+            INSERT INTO invoices (
+                id,
+                user_id,
+                account_id,
+                payment_request,
+                payment_hash,
+                amount_msat,
+                memo,
+                description_hash,
+                comment,
+                source,
+                preimage,
+                expires_at,
+                fiat_currency,
+                fiat_amount,
+                fiat_rate
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                payment_request as "payment_request!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat?",
+                amount_paid_msat as "amount_paid_msat?",
+                fiat_currency as "fiat_currency?",
+                fiat_amount as "fiat_amount?",
+                fiat_rate as "fiat_rate?",
+                memo as "memo!",
+                description_hash as "description_hash?",
+                comment as "comment?",
+                source as "source!",
+                status as "status!",
+                preimage as "preimage?",
+                expires_at as "expires_at!: DateTime<Utc>",
+                settled_at as "settled_at?: DateTime<Utc>",
+                accepted_at as "accepted_at?: DateTime<Utc>",
+                htlc_expiry_height as "htlc_expiry_height?",
+                settle_by as "settle_by?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            account.user_id,
+            account.id,
+            node_invoice.payment_request,
+            node_invoice.payment_hash,
+            new_invoice.amount_msat.map(BigDecimal::from),
+            new_invoice.memo,
+            new_invoice
+                .hashed_description
+                .map(|description| hex::encode(Sha256::digest(description))),
+            new_invoice.comment,
+            new_invoice.source,
+            preimage,
+            expires_at,
+            new_invoice.fiat.as_ref().map(|fiat| fiat.currency.clone()),
+            new_invoice.fiat.as_ref().map(|fiat| fiat.amount.clone()),
+            new_invoice.fiat.as_ref().map(|fiat| fiat.rate.clone())
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(invoice)
+    }
+
+    /// Marks an invoice settled and credits the owning account.
+    ///
+    /// Safe to call more than once for the same invoice; only the first call
+    /// credits the account.
+    ///
+    /// # Returns
+    /// 'true' if the account was credited, 'false' if the invoice is unknown
+    /// or was already settled
+    pub async fn settle_invoice(&self, node_invoice: &CustomInvoice) -> ServiceResult<bool> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let invoice = sqlx::query!(
+            r#"
+            SELECT
+                id, user_id, account_id, payment_request, amount_msat, fiat_currency, fiat_rate
+            FROM invoices
+            WHERE payment_hash = $1
+              AND status IN ('open', 'accepted', 'expired')
+            FOR UPDATE
+            "#,
+            node_invoice.payment_hash
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let Some(invoice) = invoice else {
+            return Ok(false);
+        };
+
+        // Credit what actually settled; invoices without an amount have
+        // nothing else to go by
+        let amount_msat = (node_invoice.amount_paid_msat > 0)
+            .then(|| BigDecimal::from(node_invoice.amount_paid_msat))
+            .or(invoice.amount_msat)
+            .ok_or_else(|| ServiceError::InternalError {
+                message: format!(
+                    "Node reported invoice {} settled without an amount",
+                    node_invoice.payment_hash
+                ),
+            })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE invoices
+            SET status = 'settled',
+                preimage = $2,
+                amount_paid_msat = $3,
+                settled_at = now(),
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            invoice.id,
+            Some(node_invoice.payment_preimage.clone()).filter(|preimage| !preimage.is_empty()),
+            amount_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET balance = balance + $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            invoice.account_id,
+            amount_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO transactions (
+                id,
+                user_id,
+                account_id,
+                direction,
+                invoice,
+                amount,
+                payment_hash,
+                payment_status,
+                fiat_currency,
+                fiat_rate
+            )
+            VALUES ($1, $2, $3, 'incoming', $4, $5, $6, 'settled', $7, $8)
+            "#,
+            Uuid::now_v7().to_string(),
+            invoice.user_id,
+            invoice.account_id,
+            invoice.payment_request,
+            amount_msat,
+            node_invoice.payment_hash,
+            invoice.fiat_currency,
+            invoice.fiat_rate
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        EventService::invoice_paid(&mut tx, &node_invoice.payment_hash).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!("Invoice {} settled", node_invoice.payment_hash);
+
+        Ok(true)
+    }
+
+    /// Credits a payment to a BOLT12 offer to the offer's owner.
+    ///
+    /// The node issues these invoices on its own when answering invoice
+    /// requests, so they are recorded here as they settle. Safe to call more
+    /// than once for the same payment.
+    ///
+    /// # Returns
+    /// 'true' if the account was credited, 'false' if the offer is unknown
+    /// or the payment was already credited
+    pub async fn settle_offer_payment(
+        &self,
+        offer_id: &str,
+        node_invoice: &CustomInvoice,
+    ) -> ServiceResult<bool> {
+        let Some(offer) = OfferRepository::new(self.pool)
+            .get_offer_by_offer_id(offer_id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+        else {
+            tracing::warn!(
+                "Invoice {} settled for unknown offer {}",
+                node_invoice.payment_hash,
+                offer_id
+            );
+            return Ok(false);
+        };
+
+        self.record_node_payment(
+            &offer.user_id,
+            &offer.account_id,
+            &offer.description,
+            INVOICE_SOURCE_BOLT12_OFFER,
+            &offer.offer,
+            node_invoice,
+        )
+        .await
+    }
+
+    /// Credits an inbound keysend to the account named in its TLV records.
+    ///
+    /// Keysends without a known account stay on the node and are only logged.
+    ///
+    /// # Returns
+    /// 'true' if the account was credited, 'false' if no account could be
+    /// found or the payment was already credited
+    pub async fn settle_keysend(&self, node_invoice: &CustomInvoice) -> ServiceResult<bool> {
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })?;
+
+        let account_id = node_invoice
+            .htlcs
+            .iter()
+            .flatten()
+            .find_map(|htlc| htlc.custom_records.get(&config.keysend_account_record_type))
+            .and_then(|record| String::from_utf8(record.clone()).ok());
+
+        let account = match account_id {
+            Some(account_id) => AccountRepository::new(self.pool)
+                .get_account_by_id(account_id.trim())
+                .await
+                .map_err(|e| ServiceError::Database { source: e })?
+                .filter(|account| account.is_active),
+            None => None,
+        };
+
+        let Some(account) = account else {
+            tracing::warn!(
+                "Keysend {} does not name an active account",
+                node_invoice.payment_hash
+            );
+            return Ok(false);
+        };
+
+        self.record_node_payment(
+            &account.user_id,
+            &account.id,
+            "Keysend payment",
+            INVOICE_SOURCE_KEYSEND,
+            INVOICE_SOURCE_KEYSEND,
+            node_invoice,
+        )
+        .await
+    }
+
+    /// Records a payment the node accepted without an invoice issued here
+    /// and credits it to an account.
+    ///
+    /// `ledger_invoice` is what the account's transaction shows as paid.
+    async fn record_node_payment(
+        &self,
+        user_id: &str,
+        account_id: &str,
+        memo: &str,
+        source: &str,
+        ledger_invoice: &str,
+        node_invoice: &CustomInvoice,
+    ) -> ServiceResult<bool> {
+        let amount_msat = BigDecimal::from(if node_invoice.amount_paid_msat > 0 {
+            node_invoice.amount_paid_msat
+        } else {
+            node_invoice.value_msat
+        });
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO invoices (
+                id,
+                user_id,
+                account_id,
+                payment_request,
+                payment_hash,
+                amount_msat,
+                amount_paid_msat,
+                memo,
+                source,
+                status,
+                preimage,
+                expires_at,
+                settled_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $6, $7, $8, 'settled', $9, now(), now())
+            ON CONFLICT (payment_hash) DO NOTHING
+            RETURNING id
+            "#,
+            Uuid::now_v7().to_string(),
+            user_id,
+            account_id,
+            node_invoice.payment_request,
+            node_invoice.payment_hash,
+            amount_msat,
+            memo,
+            source,
+            Some(node_invoice.payment_preimage.clone()).filter(|preimage| !preimage.is_empty())
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if inserted.is_none() {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET balance = balance + $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            account_id,
+            amount_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO transactions (
+                id,
+                user_id,
+                account_id,
+                direction,
+                invoice,
+                amount,
+                payment_hash,
+                payment_status
+            )
+            VALUES ($1, $2, $3, 'incoming', $4, $5, $6, 'settled')
+            "#,
+            Uuid::now_v7().to_string(),
+            user_id,
+            account_id,
+            ledger_invoice,
+            amount_msat,
+            node_invoice.payment_hash
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        EventService::invoice_paid(&mut tx, &node_invoice.payment_hash).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!(
+            "Credited {} payment {} to account {}",
+            source,
+            node_invoice.payment_hash,
+            account_id
+        );
+
+        Ok(true)
+    }
+
+    /// Records an open or accepted invoice as canceled or expired.
+    async fn close_invoice(&self, payment_hash: &str, status: &str) -> ServiceResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE invoices
+            SET status = $2,
+                updated_at = now()
+            WHERE payment_hash = $1
+              AND status IN ('open', 'accepted')
+            "#,
+            payment_hash,
+            status
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Records that the node is holding a payment to a hold invoice, along
+    /// with the block it must be settled by.
+    async fn accept_hold_invoice(&self, node_invoice: &CustomInvoice) -> ServiceResult<()> {
+        let Some(htlc_expiry_height) = htlc_expiry_height(node_invoice) else {
+            return Ok(());
+        };
+
+        let block_height = self.lightning.get_block_height().await?;
+        let blocks_left = htlc_expiry_height
+            .saturating_sub(block_height)
+            .saturating_sub(HOLD_INVOICE_CANCEL_MARGIN_BLOCKS);
+        let settle_by =
+            Utc::now() + Duration::seconds(i64::from(blocks_left) * BLOCK_INTERVAL_SECONDS);
+
+        let accepted = sqlx::query!(
+            r#"
+            UPDATE invoices
+            SET status = 'accepted',
+                accepted_at = now(),
+                htlc_expiry_height = $2,
+                settle_by = $3,
+                updated_at = now()
+            WHERE payment_hash = $1
+              AND status = 'open'
+            "#,
+            node_invoice.payment_hash,
+            htlc_expiry_height as i32,
+            settle_by
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if accepted.rows_affected() > 0 {
+            tracing::info!(
+                "Hold invoice {} accepted, to be settled by block {}",
+                node_invoice.payment_hash,
+                htlc_expiry_height
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Applies an invoice update pushed by the node.
+    pub async fn apply_node_update(&self, node_invoice: &CustomInvoice) -> ServiceResult<()> {
+        match node_invoice.state {
+            InvoiceStatus::Settled => {
+                let settled = self.settle_invoice(node_invoice).await?;
+
+                if !settled {
+                    if let Some(offer_id) = &node_invoice.offer_id {
+                        self.settle_offer_payment(offer_id, node_invoice).await?;
+                    } else if node_invoice.is_keysend == Some(true) {
+                        self.settle_keysend(node_invoice).await?;
+                    }
+                }
+            }
+            InvoiceStatus::Accepted => {
+                self.accept_hold_invoice(node_invoice).await?;
+            }
+            InvoiceStatus::Failed => {
+                self.close_invoice(&node_invoice.payment_hash, "canceled")
+                    .await?;
+            }
+            InvoiceStatus::Open | InvoiceStatus::Expired => {}
+        }
+
+        Ok(())
+    }
+
+    /// Brings every open invoice in line with the node.
+    ///
+    /// Catches settlements that happened while the watcher was not
+    /// subscribed and expires invoices that can no longer be paid.
+    pub async fn reconcile_open_invoices(&self) -> ServiceResult<()> {
+        let invoices = InvoiceRepository::new(self.pool)
+            .get_open_invoices()
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        for invoice in invoices {
+            let node_invoice = match self
+                .lightning
+                .get_invoice_details(&invoice.payment_hash)
+                .await
+            {
+                Ok(node_invoice) => node_invoice,
+                Err(error) => {
+                    tracing::warn!(
+                        "Could not look up invoice {}: {}",
+                        invoice.payment_hash,
+                        error
+                    );
+                    continue;
+                }
+            };
+
+            match node_invoice.state {
+                InvoiceStatus::Open | InvoiceStatus::Expired
+                    if invoice.expires_at <= Utc::now() =>
+                {
+                    self.close_invoice(&invoice.payment_hash, "expired").await?;
+                }
+                _ => self.apply_node_update(&node_invoice).await?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Brings every hold invoice in flight in line with the node, canceling
+    /// held payments that are about to expire.
+    pub async fn sweep_hold_invoices(&self) -> ServiceResult<()> {
+        let invoices = InvoiceRepository::new(self.pool)
+            .get_hold_invoices_in_flight()
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        if invoices.is_empty() {
+            return Ok(());
+        }
+
+        let block_height = self.lightning.get_block_height().await?;
+
+        for invoice in invoices {
+            let node_invoice = match self
+                .lightning
+                .get_invoice_details(&invoice.payment_hash)
+                .await
+            {
+                Ok(node_invoice) => node_invoice,
+                Err(error) => {
+                    tracing::warn!(
+                        "Could not look up hold invoice {}: {}",
+                        invoice.payment_hash,
+                        error
+                    );
+                    continue;
+                }
+            };
+
+            match node_invoice.state {
+                InvoiceStatus::Accepted => {
+                    self.apply_node_update(&node_invoice).await?;
+
+                    if htlc_expiry_height(&node_invoice).is_some_and(|expiry_height| {
+                        expiry_height <= block_height + HOLD_INVOICE_CANCEL_MARGIN_BLOCKS
+                    }) {
+                        tracing::warn!(
+                            "Canceling hold invoice {} before its payment expires",
+                            invoice.payment_hash
+                        );
+                        self.lightning
+                            .cancel_hold_invoice(&invoice.payment_hash)
+                            .await?;
+                        self.close_invoice(&invoice.payment_hash, "canceled")
+                            .await?;
+                    }
+                }
+                InvoiceStatus::Open | InvoiceStatus::Expired
+                    if invoice.expires_at <= Utc::now() =>
+                {
+                    self.close_invoice(&invoice.payment_hash, "expired").await?;
+                }
+                _ => self.apply_node_update(&node_invoice).await?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Earliest block any HTLC paying the invoice expires at
+fn htlc_expiry_height(node_invoice: &CustomInvoice) -> Option<u32> {
+    node_invoice
+        .htlcs
+        .iter()
+        .flatten()
+        .filter_map(|htlc| htlc.expiry_height)
+        .min()
+}
+
+/// Decodes a hex encoded 32 byte hash or preimage
+fn decode_hash(hash_hex: &str) -> Option<[u8; 32]> {
+    hex::decode(hash_hex.trim()).ok()?.try_into().ok()
+}
+
+/// Keeps hold invoices in line with the node, since the invoice stream does
+/// not report payments being held.
+pub async fn run_hold_invoice_watcher(pool: PgPool, lightning: Arc<dyn LightningClient>) {
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(HOLD_INVOICE_POLL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = InvoiceService::new(&pool, lightning.as_ref())
+            .sweep_hold_invoices()
+            .await
+        {
+            tracing::warn!("Sweeping hold invoices failed: {}", error);
+        }
+    }
+}
+
+/// Keeps invoice settlements flowing into account balances.
+///
+/// Runs for the lifetime of the server, resubscribing whenever the node
+/// connection drops.
+pub async fn run_settlement_watcher(pool: PgPool, lightning: Arc<dyn LightningClient>) {
+    loop {
+        if let Err(error) = watch_invoices(&pool, lightning.as_ref()).await {
+            tracing::warn!("Invoice watcher stopped: {}", error);
+        }
+
+        tokio::time::sleep(std::time::Duration::from_secs(WATCHER_RETRY_SECONDS)).await;
+    }
+}
+
+async fn watch_invoices(pool: &PgPool, lightning: &dyn LightningClient) -> ServiceResult<()> {
+    let service = InvoiceService::new(pool, lightning);
+
+    // Subscribe before reconciling so nothing settles unseen in between
+    let mut updates = lightning.subscribe_invoices().await?;
+    service.reconcile_open_invoices().await?;
+
+    while let Some(update) = updates.next().await {
+        let node_invoice = update?;
+        if let Err(error) = service.apply_node_update(&node_invoice).await {
+            tracing::error!(
+                "Failed to apply update for invoice {}: {}",
+                node_invoice.payment_hash,
+                error
+            );
+        }
+    }
+
+    Err(ServiceError::ExternalService {
+        message: "Invoice stream closed".to_string(),
+    })
+}
@@ -0,0 +1,190 @@
+// LND Chain Notifier
+//! Client for LND's ChainNotifier service, which tonic_lnd does not
+//! generate.
+//!
+//! The node watches the chain for a script on the bank's behalf, so coins
+//! sent to an address outside the wallet, such as the lockup of a swap, are
+//! checked against the node's own view of the chain.
+
+use crate::errors::LightningError;
+use crate::service::node_service::LndConnection;
+use hyper::client::HttpConnector;
+use hyper_rustls::HttpsConnector;
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, ServerName};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tonic_lnd::tonic;
+use tonic_lnd::tonic::codegen::http::Uri;
+use tonic_lnd::tonic::codegen::http::uri::PathAndQuery;
+
+/// How long the node gets to answer from what it has already seen. A
+/// transaction that confirms later is found by a later call.
+const CONFIRMATION_WAIT_SECONDS: u64 = 10;
+
+const REGISTER_CONFIRMATIONS_PATH: &str = "/chainrpc.ChainNotifier/RegisterConfirmationsNtfn";
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ConfRequest {
+    /// All zeros to watch `script` instead of a transaction
+    #[prost(bytes = "vec", tag = "1")]
+    txid: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    script: Vec<u8>,
+    #[prost(uint32, tag = "3")]
+    num_confs: u32,
+    #[prost(uint32, tag = "4")]
+    height_hint: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ConfEvent {
+    #[prost(oneof = "conf_event::Event", tags = "1, 2")]
+    event: Option<conf_event::Event>,
+}
+
+mod conf_event {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        Conf(super::ConfDetails),
+        #[prost(message, tag = "2")]
+        Reorg(super::Reorg),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ConfDetails {
+    #[prost(bytes = "vec", tag = "1")]
+    raw_tx: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Reorg {}
+
+type Transport = hyper::Client<HttpsConnector<HttpConnector>, tonic::body::BoxBody>;
+
+pub struct ChainNotifier {
+    transport: Transport,
+    origin: Uri,
+    macaroon: String,
+}
+
+impl ChainNotifier {
+    /// Sets up a client for the node behind `connection`. Nothing is sent
+    /// until the first call.
+    pub async fn new(connection: &LndConnection) -> Result<Self, LightningError> {
+        let connection_error = |err: String| LightningError::ConnectionError(err);
+
+        let certificate = tokio::fs::read(&connection.certificate)
+            .await
+            .map_err(|err| connection_error(err.to_string()))?;
+        let certificates = rustls_pemfile::certs(&mut certificate.as_slice())
+            .map_err(|err| connection_error(err.to_string()))?;
+        let macaroon = tokio::fs::read(&connection.macaroon)
+            .await
+            .map_err(|err| connection_error(err.to_string()))?;
+
+        let tls = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertificates(certificates)))
+            .with_no_client_auth();
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_tls_config(tls)
+            .https_only()
+            .enable_http2()
+            .build();
+
+        Ok(Self {
+            transport: hyper::Client::builder().http2_only(true).build(connector),
+            origin: connection.address.parse().map_err(
+                |err: tonic::codegen::http::uri::InvalidUri| connection_error(err.to_string()),
+            )?,
+            macaroon: hex::encode(macaroon),
+        })
+    }
+
+    /// Raw transaction paying `script_pubkey` with at least `confirmations`
+    /// confirmations, looking from `start_height` on.
+    pub async fn confirmed_transaction(
+        &self,
+        script_pubkey: &[u8],
+        confirmations: u32,
+        start_height: u32,
+    ) -> Result<Option<Vec<u8>>, LightningError> {
+        let mut grpc =
+            tonic::client::Grpc::with_origin(self.transport.clone(), self.origin.clone());
+        grpc.ready()
+            .await
+            .map_err(|err| LightningError::NetworkError(err.to_string()))?;
+
+        let mut request = tonic::Request::new(ConfRequest {
+            txid: vec![0; 32],
+            script: script_pubkey.to_vec(),
+            num_confs: confirmations.max(1),
+            height_hint: start_height,
+        });
+        request.metadata_mut().insert(
+            "macaroon",
+            self.macaroon
+                .parse()
+                .map_err(|_| LightningError::ConnectionError("Invalid macaroon".to_string()))?,
+        );
+
+        let mut events = grpc
+            .server_streaming(
+                request,
+                PathAndQuery::from_static(REGISTER_CONFIRMATIONS_PATH),
+                tonic::codec::ProstCodec::<ConfRequest, ConfEvent>::default(),
+            )
+            .await
+            .map_err(|err| LightningError::NetworkError(err.to_string()))?
+            .into_inner();
+
+        // Dropping the stream cancels the registration on the node
+        let event = match tokio::time::timeout(
+            Duration::from_secs(CONFIRMATION_WAIT_SECONDS),
+            events.message(),
+        )
+        .await
+        {
+            Ok(event) => event.map_err(|err| LightningError::NetworkError(err.to_string()))?,
+            Err(_) => return Ok(None),
+        };
+
+        match event.and_then(|event| event.event) {
+            Some(conf_event::Event::Conf(details)) => Ok(Some(details.raw_tx)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Trusts exactly the certificates LND wrote to its TLS certificate file,
+/// which are self-signed
+struct PinnedCertificates(Vec<Vec<u8>>);
+
+impl ServerCertVerifier for PinnedCertificates {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let presented = intermediates.iter().chain(std::iter::once(end_entity));
+
+        if intermediates.len() + 1 != self.0.len()
+            || !presented
+                .zip(&self.0)
+                .all(|(presented, pinned)| presented.0 == *pinned)
+        {
+            return Err(rustls::Error::General(
+                "Node certificate does not match the configured one".to_string(),
+            ));
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+}
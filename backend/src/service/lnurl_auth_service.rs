@@ -0,0 +1,325 @@
+// LNURL-auth Service Logic
+//! Sign in with a Lightning wallet (LUD-04).
+//!
+//! The frontend asks for a k1 challenge and shows it as an LNURL. The
+//! wallet signs k1 with a key derived for this domain and calls back, and
+//! the frontend then trades the verified k1 for the same JWTs a password
+//! login returns. Unknown keys get a new account without email or password.
+
+use crate::Config;
+use crate::db::models::{LnurlAuthCallback, LnurlAuthLogin, LnurlAuthToken, LoginResponse};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::lnurl_auth_repository::LnurlAuthRepository;
+use crate::repositories::user_repository::UserRepository;
+use crate::service::user_service::UserService;
+use crate::utilities::auth::AuthUser;
+use crate::utilities::client::ClientInfo;
+use crate::utilities::lnurl::{encode_lnurl, verify_auth_signature};
+use crate::utilities::token::generate_token;
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use validator::Validate;
+
+/// How long a wallet has to sign a challenge and the frontend to redeem it
+const CHALLENGE_TTL_MINUTES: i64 = 5;
+
+/// Challenge that signs a wallet in, creating an account for unknown keys
+const ACTION_LOGIN: &str = "login";
+
+/// Challenge that adds a wallet key to a signed in user
+const ACTION_LINK: &str = "link";
+
+// Service layer for LNURL-auth related Operation
+pub struct LnurlAuthService<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> LnurlAuthService<'a> {
+    /// Creates a new LNURL-auth service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Issues a challenge for a wallet to sign in with.
+    pub async fn login_challenge(&self) -> ServiceResult<LnurlAuthLogin> {
+        self.create_challenge(ACTION_LOGIN, None).await
+    }
+
+    /// Issues a challenge that links the signing wallet to the caller.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError::Forbidden' for API keys
+    pub async fn link_challenge(&self, auth: &AuthUser) -> ServiceResult<LnurlAuthLogin> {
+        auth.require_session()?;
+
+        self.create_challenge(ACTION_LINK, Some(auth.user_id()))
+            .await
+    }
+
+    async fn create_challenge(
+        &self,
+        action: &str,
+        user_id: Option<&str>,
+    ) -> ServiceResult<LnurlAuthLogin> {
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })?;
+
+        let k1 = generate_token();
+        let expires_at = Utc::now() + Duration::minutes(CHALLENGE_TTL_MINUTES);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO lnurl_auth_challenges (k1, action, user_id, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            k1,
+            action,
+            user_id,
+            expires_at
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let lnurl = encode_lnurl(&format!(
+            "{}/api/lnurl/auth?tag=login&k1={}&action={}",
+            config.public_url, k1, action
+        ))?;
+
+        Ok(LnurlAuthLogin {
+            k1,
+            lnurl,
+            expires_at,
+        })
+    }
+
+    /// Handles the wallet's signed callback.
+    ///
+    /// A login challenge is bound to the user behind the linking key, who is
+    /// created if the key is new. A link challenge attaches the key to the
+    /// user who requested it.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Unknown, used or expired challenges
+    /// - Signatures that do not verify
+    /// - Keys already linked to another user
+    /// - Inactive users
+    pub async fn callback(&self, callback: LnurlAuthCallback) -> ServiceResult<()> {
+        let linking_key = verify_auth_signature(&callback.k1, &callback.sig, &callback.key)?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let challenge = sqlx::query!(
+            r#"
+            SELECT action, user_id
+            FROM lnurl_auth_challenges
+            WHERE k1 = $1
+              AND status = 'pending'
+              AND expires_at > now()
+            FOR UPDATE
+            "#,
+            callback.k1
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        .ok_or_else(|| ServiceError::validation("k1: Invalid or expired challenge"))?;
+
+        let linked_user = LnurlAuthRepository::new(self.pool)
+            .get_user_by_linking_key(&linking_key)
+            .await?;
+
+        let user_id = match (challenge.action.as_str(), linked_user) {
+            (ACTION_LOGIN, Some(user)) => {
+                if !user.is_active {
+                    return Err(ServiceError::validation("Account is inactive".to_string()));
+                }
+                user.id
+            }
+            (ACTION_LOGIN, None) => {
+                let user_with_account = UserService::new(self.pool)
+                    .create_keyed_user(&mut tx, &keyed_username(&linking_key))
+                    .await?;
+
+                self.insert_linking_key(&mut tx, &linking_key, &user_with_account.user.id)
+                    .await?;
+
+                tracing::info!(
+                    "Created User {} for a new LNURL-auth key",
+                    user_with_account.user.id
+                );
+                user_with_account.user.id
+            }
+            (ACTION_LINK, linked_user) => {
+                let user_id = challenge
+                    .user_id
+                    .ok_or_else(|| ServiceError::InternalError {
+                        message: "Link challenge without a user".to_string(),
+                    })?;
+
+                match linked_user {
+                    Some(user) if user.id != user_id => {
+                        return Err(ServiceError::already_exists(
+                            "Linking key is linked to another user",
+                            &linking_key,
+                        ));
+                    }
+                    Some(_) => {}
+                    None => {
+                        self.insert_linking_key(&mut tx, &linking_key, &user_id)
+                            .await?;
+                    }
+                }
+                user_id
+            }
+            (action, _) => {
+                return Err(ServiceError::InternalError {
+                    message: format!("Unknown LNURL-auth action '{action}'"),
+                });
+            }
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE lnurl_auth_keys
+            SET last_used_at = now()
+            WHERE linking_key = $1
+            "#,
+            linking_key
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE lnurl_auth_challenges
+            SET status = 'verified',
+                user_id = $2,
+                linking_key = $3,
+                verified_at = now()
+            WHERE k1 = $1
+            "#,
+            callback.k1,
+            user_id,
+            linking_key
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    async fn insert_linking_key(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        linking_key: &str,
+        user_id: &str,
+    ) -> ServiceResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO lnurl_auth_keys (linking_key, user_id)
+            VALUES ($1, $2)
+            "#,
+            linking_key,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Trades a signed login challenge for JWT tokens. Each challenge can
+    /// be redeemed once.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' while the wallet has not signed yet, and for
+    /// unknown, redeemed or expired challenges
+    pub async fn redeem(
+        &self,
+        token: LnurlAuthToken,
+        client: &ClientInfo,
+    ) -> ServiceResult<LoginResponse> {
+        if let Err(validation_errors) = token.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let redeemed = sqlx::query!(
+            r#"
+            UPDATE lnurl_auth_challenges
+            SET status = 'consumed',
+                consumed_at = now()
+            WHERE k1 = $1
+              AND action = $2
+              AND status = 'verified'
+              AND expires_at > now()
+            RETURNING user_id as "user_id!"
+            "#,
+            token.k1,
+            ACTION_LOGIN
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let Some(redeemed) = redeemed else {
+            let pending = LnurlAuthRepository::new(self.pool)
+                .get_challenge_by_k1(&token.k1)
+                .await?
+                .is_some_and(|challenge| {
+                    challenge.status == "pending" && challenge.expires_at > Utc::now()
+                });
+
+            return Err(ServiceError::validation(if pending {
+                "k1: Challenge has not been signed yet"
+            } else {
+                "k1: Invalid or expired challenge"
+            }));
+        };
+
+        let user = UserRepository::new(self.pool)
+            .get_user_by_id(&redeemed.user_id)
+            .await?
+            .filter(|user| user.is_active)
+            .ok_or_else(|| ServiceError::validation("Invalid credentials".to_string()))?;
+
+        UserService::new(self.pool).issue_login(user, client).await
+    }
+}
+
+/// Username for an account created from a linking key; derived from the key
+/// so it stays stable but does not reveal it
+fn keyed_username(linking_key: &str) -> String {
+    let digest = Sha256::digest(linking_key.as_bytes());
+    format!("ln{}", hex::encode(&digest[..8]))
+}
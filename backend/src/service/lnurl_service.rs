@@ -0,0 +1,174 @@
+// LNURL Service Logic
+//! Serves LNURL-pay (LUD-06) for Lightning Addresses (LUD-16), with
+//! payment verification (LUD-21).
+
+use crate::Config;
+use crate::db::models::{
+    LnurlPayCallback, LnurlPayInvoice, LnurlPayRequest, LnurlVerify, NewInvoice, User,
+};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::invoice_repository::InvoiceRepository;
+use crate::repositories::user_repository::UserRepository;
+use crate::service::invoice_service::{INVOICE_SOURCE_LNURL_PAY, InvoiceService};
+use crate::service::node_service::LightningClient;
+use sqlx::PgPool;
+
+// Service layer for LNURL related Operation
+pub struct LnurlService<'a> {
+    pool: &'a PgPool,
+    lightning: &'a dyn LightningClient,
+}
+
+impl<'a> LnurlService<'a> {
+    /// Creates a new LNURL service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    /// * 'lightning' - Node the invoices are issued on
+    pub fn new(pool: &'a PgPool, lightning: &'a dyn LightningClient) -> Self {
+        Self { pool, lightning }
+    }
+
+    fn load_config() -> ServiceResult<Config> {
+        Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })
+    }
+
+    /// Looks up the user behind a Lightning Address.
+    async fn get_receiving_user(&self, username: &str) -> ServiceResult<User> {
+        UserRepository::new(self.pool)
+            .get_user_by_username(username)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|user| user.is_active)
+            .ok_or_else(|| ServiceError::not_found("Lightning Address", username))
+    }
+
+    /// Metadata the payer's wallet shows and whose hash the invoice commits to.
+    fn metadata(username: &str, config: &Config) -> String {
+        let identifier = format!("{}@{}", username, config.lnurl_domain);
+        serde_json::json!([
+            ["text/plain", format!("Payment to {identifier}")],
+            ["text/identifier", identifier],
+        ])
+        .to_string()
+    }
+
+    /// Builds the `payRequest` for a user's Lightning Address.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError::NotFound' if no active user has that username
+    pub async fn pay_request(&self, username: &str) -> ServiceResult<LnurlPayRequest> {
+        let config = Self::load_config()?;
+        let user = self.get_receiving_user(username).await?;
+
+        Ok(LnurlPayRequest {
+            callback: format!(
+                "{}/api/lnurl/pay/{}/callback",
+                config.public_url, user.username
+            ),
+            max_sendable: config.lnurl_max_sendable_msat,
+            min_sendable: config.lnurl_min_sendable_msat,
+            metadata: Self::metadata(&user.username, &config),
+            comment_allowed: config.lnurl_comment_allowed,
+            tag: "payRequest".to_string(),
+        })
+    }
+
+    /// Issues an invoice for a `payRequest` callback.
+    ///
+    /// The invoice commits to the hash of the metadata (`h` tag) and is
+    /// credited to the user's account once paid.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Amounts outside the sendable range
+    /// - Comments longer than allowed
+    /// - Unknown users or accounts that cannot receive
+    pub async fn pay_callback(
+        &self,
+        username: &str,
+        callback: LnurlPayCallback,
+    ) -> ServiceResult<LnurlPayInvoice> {
+        let config = Self::load_config()?;
+        let user = self.get_receiving_user(username).await?;
+
+        if callback.amount < config.lnurl_min_sendable_msat
+            || callback.amount > config.lnurl_max_sendable_msat
+        {
+            return Err(ServiceError::validation(format!(
+                "amount: Amount must be between {} and {} msat",
+                config.lnurl_min_sendable_msat, config.lnurl_max_sendable_msat
+            )));
+        }
+
+        let comment = callback.comment.filter(|comment| !comment.is_empty());
+        if comment
+            .as_ref()
+            .is_some_and(|comment| comment.chars().count() > config.lnurl_comment_allowed)
+        {
+            return Err(ServiceError::validation(format!(
+                "comment: Comment must be at most {} characters",
+                config.lnurl_comment_allowed
+            )));
+        }
+
+        let metadata = Self::metadata(&user.username, &config);
+
+        let invoice = InvoiceService::new(self.pool, self.lightning)
+            .issue_invoice(
+                &user.id,
+                NewInvoice {
+                    amount_msat: Some(callback.amount),
+                    fiat: None,
+                    memo: format!("Payment to {}@{}", user.username, config.lnurl_domain),
+                    hashed_description: Some(metadata),
+                    comment,
+                    source: INVOICE_SOURCE_LNURL_PAY.to_string(),
+                },
+            )
+            .await?;
+
+        let verify = config.lnurl_verify_enabled.then(|| {
+            format!(
+                "{}/api/lnurl/verify/{}",
+                config.public_url, invoice.payment_hash
+            )
+        });
+
+        Ok(LnurlPayInvoice {
+            pr: invoice.payment_request,
+            routes: Vec::new(),
+            success_action: None,
+            verify,
+        })
+    }
+
+    /// Reports whether an invoice issued through LNURL-pay has been paid.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError::NotFound' if verification is disabled or the
+    /// invoice was not issued through LNURL-pay
+    pub async fn verify(&self, payment_hash: &str) -> ServiceResult<LnurlVerify> {
+        let config = Self::load_config()?;
+
+        let invoice = InvoiceRepository::new(self.pool)
+            .get_invoice_by_payment_hash(payment_hash)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|invoice| {
+                config.lnurl_verify_enabled && invoice.source == INVOICE_SOURCE_LNURL_PAY
+            })
+            .ok_or_else(|| ServiceError::not_found("Invoice", payment_hash))?;
+
+        let settled = invoice.status == "settled";
+
+        Ok(LnurlVerify {
+            status: "OK".to_string(),
+            settled,
+            preimage: invoice.preimage.filter(|_| settled),
+            pr: invoice.payment_request,
+        })
+    }
+}
@@ -1,5 +1,25 @@
 //  Module for business logic services.
 
+pub mod admin_service;
+pub mod api_key_service;
+pub mod audit_service;
+pub mod deposit_service;
+pub mod event_service;
+pub mod fiat_service;
+pub mod invoice_service;
+pub mod lnd_chain_notifier;
+pub mod lnurl_auth_service;
+pub mod lnurl_service;
+pub mod node_cln_service;
+pub mod node_service;
+pub mod notification_service;
+pub mod offer_service;
+pub mod onchain_withdrawal_service;
+pub mod payment_service;
+pub mod rate_provider;
 pub mod role_service;
+pub mod swap_provider;
+pub mod swap_service;
 pub mod user_service;
-// pub mod node_service;
+pub mod webhook_service;
+pub mod withdraw_service;
@@ -0,0 +1,990 @@
+// CLN Node Service
+//! Core Lightning implementation of `LightningClient`, talking to the
+//! `cln-grpc` plugin over mutual TLS. Unlike LND it supports BOLT12 offers.
+
+use crate::config::Config;
+use crate::errors::LightningError;
+use crate::service::node_service::{InvoiceStream, LightningClient};
+use crate::utilities::{
+    CustomInvoice, HoldInvoiceRequest, InvoiceRequest, InvoiceStatus, KeysendRequest, NodeInfo,
+    OfferInvoice, OnchainReceipt, PaymentOutcome, RoutingPolicy, WalletTransaction,
+};
+use async_trait::async_trait;
+use bitcoin::secp256k1::PublicKey;
+use cln_grpc::pb::{
+    self, amount_or_any, feerate, listfunds_outputs::ListfundsOutputsStatus,
+    listinvoices_invoices::ListinvoicesInvoicesStatus, listpays_pays::ListpaysPaysStatus,
+    node_client::NodeClient,
+};
+use lightning_invoice::Bolt11Invoice;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
+
+/// Name CLN puts on the certificate of its gRPC plugin
+const CLN_TLS_DOMAIN: &str = "cln";
+
+/// Risk factor `getroute` weighs locked up funds by, the default of `pay`
+const ROUTE_RISK_FACTOR: u64 = 10;
+
+#[derive(Debug, Clone)]
+pub struct ClnConnection {
+    pub address: String,
+    pub ca_certificate: String,
+    pub client_certificate: String,
+    pub client_key: String,
+}
+
+impl ClnConnection {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            address: config.cln_grpc_url.clone().unwrap_or_default(),
+            ca_certificate: config.cln_ca_cert.clone().unwrap_or_default(),
+            client_certificate: config.cln_client_cert.clone().unwrap_or_default(),
+            client_key: config.cln_client_key.clone().unwrap_or_default(),
+        }
+    }
+}
+
+pub struct ClnNode {
+    client: NodeClient<Channel>,
+    pub info: NodeInfo,
+}
+
+impl ClnNode {
+    pub async fn new(connection: ClnConnection) -> Result<Self, LightningError> {
+        let read_pem = |path: &str| {
+            std::fs::read(path)
+                .map_err(|err| LightningError::ConnectionError(format!("{path}: {err}")))
+        };
+
+        let tls = ClientTlsConfig::new()
+            .domain_name(CLN_TLS_DOMAIN)
+            .ca_certificate(Certificate::from_pem(read_pem(&connection.ca_certificate)?))
+            .identity(Identity::from_pem(
+                read_pem(&connection.client_certificate)?,
+                read_pem(&connection.client_key)?,
+            ));
+
+        let channel = Channel::from_shared(connection.address)
+            .map_err(|err| LightningError::ConnectionError(err.to_string()))?
+            .tls_config(tls)
+            .map_err(|err| LightningError::ConnectionError(err.to_string()))?
+            .connect()
+            .await
+            .map_err(|err| LightningError::ConnectionError(err.to_string()))?;
+
+        let mut client = NodeClient::new(channel);
+
+        let node_info = client
+            .getinfo(pb::GetinfoRequest {})
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?
+            .into_inner();
+
+        let pubkey = PublicKey::from_slice(&node_info.id)
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?;
+
+        Ok(Self {
+            client,
+            info: NodeInfo {
+                pubkey,
+                alias: node_info.alias.unwrap_or_default(),
+            },
+        })
+    }
+
+    fn node(&self) -> NodeClient<Channel> {
+        self.client.clone()
+    }
+
+    async fn lookup_invoice(
+        &self,
+        payment_hash: Vec<u8>,
+    ) -> Result<Option<pb::ListinvoicesInvoices>, LightningError> {
+        let invoices = self
+            .node()
+            .list_invoices(pb::ListinvoicesRequest {
+                payment_hash: Some(payment_hash),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?
+            .into_inner()
+            .invoices;
+
+        Ok(invoices.into_iter().next())
+    }
+
+    /// Pays with `pay` and, when the call errors, asks the node what became
+    /// of the payment so only definite failures surface as `PaymentError`.
+    async fn pay(
+        &self,
+        request: pb::PayRequest,
+        payment_hash: &[u8],
+    ) -> Result<PaymentOutcome, LightningError> {
+        match self.node().pay(request).await {
+            Ok(response) => {
+                let response = response.into_inner();
+                Ok(PaymentOutcome {
+                    payment_preimage: hex::encode(response.payment_preimage),
+                    fee_msat: msat(&response.amount_sent_msat)
+                        .saturating_sub(msat(&response.amount_msat)),
+                })
+            }
+            Err(err) if connection_lost(&err) => Err(LightningError::NetworkError(err.to_string())),
+            Err(err) => match self.track_payment(&hex::encode(payment_hash)).await {
+                // Never started, or every attempt failed
+                Err(LightningError::NotFound(_)) | Err(LightningError::PaymentError(_)) => {
+                    Err(LightningError::PaymentError(err.message().to_string()))
+                }
+                outcome => outcome,
+            },
+        }
+    }
+
+    /// Decodes a BOLT12 invoice with the node, which `lightning` cannot parse
+    /// from its bech32 form.
+    async fn decode_offer_invoice(
+        &self,
+        invoice: &str,
+    ) -> Result<pb::DecodeResponse, LightningError> {
+        let decoded = self
+            .node()
+            .decode(pb::DecodeRequest {
+                string: invoice.to_string(),
+            })
+            .await
+            .map_err(|err| LightningError::Parse(err.message().to_string()))?
+            .into_inner();
+
+        if !decoded.valid {
+            return Err(LightningError::Parse("Invalid BOLT12 invoice".to_string()));
+        }
+
+        Ok(decoded)
+    }
+
+    async fn block_height(&self) -> Result<u32, LightningError> {
+        let node_info = self
+            .node()
+            .getinfo(pb::GetinfoRequest {})
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?
+            .into_inner();
+
+        Ok(node_info.blockheight)
+    }
+}
+
+fn msat(amount: &Option<pb::Amount>) -> u64 {
+    amount
+        .as_ref()
+        .map(|amount| amount.msat)
+        .unwrap_or_default()
+}
+
+/// The call was cut off, possibly with the payment in flight
+fn connection_lost(err: &tonic::Status) -> bool {
+    matches!(
+        err.code(),
+        tonic::Code::Unavailable | tonic::Code::DeadlineExceeded | tonic::Code::Cancelled
+    )
+}
+
+/// Confirmations of a transaction mined at `block_height`, 0 while unconfirmed
+fn confirmations(block_height: Option<u32>, tip: u32) -> u32 {
+    block_height
+        .filter(|height| *height > 0 && *height <= tip)
+        .map(|height| tip - height + 1)
+        .unwrap_or_default()
+}
+
+/// Picks the estimate for the fewest blocks at or above `conf_target`, in
+/// sat/vbyte, from estimates CLN gives in sat/kvbyte.
+fn fee_rate_for_target(estimates: &[pb::FeeratesPerkbEstimates], conf_target: u32) -> Option<u64> {
+    estimates
+        .iter()
+        .filter(|estimate| estimate.blockcount >= conf_target)
+        .min_by_key(|estimate| estimate.blockcount)
+        .or_else(|| estimates.iter().max_by_key(|estimate| estimate.blockcount))
+        .map(|estimate| u64::from(estimate.feerate).div_ceil(1000).max(1))
+}
+
+fn invoice_from_cln(invoice: pb::ListinvoicesInvoices) -> CustomInvoice {
+    let state = match invoice.status() {
+        ListinvoicesInvoicesStatus::Unpaid => InvoiceStatus::Open,
+        ListinvoicesInvoicesStatus::Paid => InvoiceStatus::Settled,
+        ListinvoicesInvoicesStatus::Expired => InvoiceStatus::Expired,
+    };
+
+    let value_msat = msat(&invoice.amount_msat);
+    let is_keysend = invoice.bolt11.is_none() && invoice.bolt12.is_none();
+
+    CustomInvoice {
+        memo: invoice.description.unwrap_or_default(),
+        payment_hash: hex::encode(invoice.payment_hash),
+        payment_preimage: invoice
+            .payment_preimage
+            .map(hex::encode)
+            .unwrap_or_default(),
+        value: value_msat / 1000,
+        value_msat,
+        amount_paid_msat: msat(&invoice.amount_received_msat),
+        creation_date: None,
+        settle_date: invoice.paid_at.map(|paid_at| paid_at as i64),
+        payment_request: invoice.bolt11.or(invoice.bolt12).unwrap_or_default(),
+        expiry: None,
+        state,
+        is_keysend: Some(is_keysend),
+        is_amp: Some(false),
+        payment_addr: None,
+        // CLN does not report the HTLCs or TLV records of an invoice
+        htlcs: None,
+        offer_id: invoice.local_offer_id.map(hex::encode),
+    }
+}
+
+fn unsupported(feature: &str) -> LightningError {
+    LightningError::Unsupported(format!("{feature} are not supported by CLN"))
+}
+
+#[async_trait]
+impl LightningClient for ClnNode {
+    fn get_node_info(&self) -> &NodeInfo {
+        &self.info
+    }
+
+    async fn create_invoice(
+        &self,
+        request: InvoiceRequest,
+    ) -> Result<CustomInvoice, LightningError> {
+        // Choosing the preimage lets the invoice be labelled by its hash, so
+        // it can be found again to cancel
+        let mut preimage = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut preimage);
+        let payment_hash = Sha256::digest(preimage).to_vec();
+
+        let (description, deschashonly) = match request.hashed_description {
+            Some(description) => (description, Some(true)),
+            None => (request.memo, None),
+        };
+
+        let amount = match request.amount_msat {
+            Some(msat) => amount_or_any::Value::Amount(pb::Amount { msat }),
+            None => amount_or_any::Value::Any(true),
+        };
+
+        self.node()
+            .invoice(pb::InvoiceRequest {
+                amount_msat: Some(pb::AmountOrAny {
+                    value: Some(amount),
+                }),
+                label: hex::encode(&payment_hash),
+                description,
+                deschashonly,
+                preimage: Some(preimage.to_vec()),
+                expiry: Some(request.expiry),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
+
+        self.get_invoice_details(&hex::encode(payment_hash)).await
+    }
+
+    async fn get_invoice_details(
+        &self,
+        payment_hash: &str,
+    ) -> Result<CustomInvoice, LightningError> {
+        let payment_hash =
+            hex::decode(payment_hash).map_err(|err| LightningError::Parse(err.to_string()))?;
+
+        self.lookup_invoice(payment_hash)
+            .await?
+            .map(invoice_from_cln)
+            .ok_or_else(|| LightningError::NotFound("Invoice not found".to_string()))
+    }
+
+    async fn subscribe_invoices(&self) -> Result<InvoiceStream, LightningError> {
+        // Like LND, only stream invoices paid from now on
+        let lastpay_index = self
+            .node()
+            .list_invoices(pb::ListinvoicesRequest::default())
+            .await
+            .map_err(|err| LightningError::StreamingError(err.to_string()))?
+            .into_inner()
+            .invoices
+            .iter()
+            .filter_map(|invoice| invoice.pay_index)
+            .max();
+
+        let mut node = self.node();
+
+        let stream = async_stream::try_stream! {
+            let mut lastpay_index = lastpay_index;
+            loop {
+                let paid = node
+                    .wait_any_invoice(pb::WaitanyinvoiceRequest {
+                        lastpay_index,
+                        timeout: None,
+                    })
+                    .await
+                    .map_err(|err| LightningError::StreamingError(err.to_string()))?
+                    .into_inner();
+                lastpay_index = paid.pay_index.or(lastpay_index);
+
+                // Only the listing says which offer the invoice was for
+                let invoice = node
+                    .list_invoices(pb::ListinvoicesRequest {
+                        payment_hash: Some(paid.payment_hash),
+                        ..Default::default()
+                    })
+                    .await
+                    .map_err(|err| LightningError::StreamingError(err.to_string()))?
+                    .into_inner()
+                    .invoices
+                    .into_iter()
+                    .next();
+
+                if let Some(invoice) = invoice {
+                    yield invoice_from_cln(invoice);
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn create_hold_invoice(
+        &self,
+        _request: HoldInvoiceRequest,
+    ) -> Result<CustomInvoice, LightningError> {
+        Err(unsupported("Hold invoices"))
+    }
+
+    async fn settle_hold_invoice(&self, _preimage: [u8; 32]) -> Result<(), LightningError> {
+        Err(unsupported("Hold invoices"))
+    }
+
+    async fn cancel_hold_invoice(&self, payment_hash: &str) -> Result<(), LightningError> {
+        // Deleting an unpaid invoice is how CLN stops it being paid
+        self.node()
+            .del_invoice(pb::DelinvoiceRequest {
+                label: payment_hash.to_string(),
+                status: pb::delinvoice_request::DelinvoiceStatus::Unpaid as i32,
+                desconly: None,
+            })
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_block_height(&self) -> Result<u32, LightningError> {
+        self.block_height().await
+    }
+
+    async fn new_address(&self) -> Result<String, LightningError> {
+        let response = self
+            .node()
+            .new_addr(pb::NewaddrRequest {
+                addresstype: Some(pb::newaddr_request::NewaddrAddresstype::Bech32 as i32),
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner();
+
+        response
+            .bech32
+            .ok_or_else(|| LightningError::WalletError("Node returned no address".to_string()))
+    }
+
+    async fn list_onchain_receipts(
+        &self,
+        start_height: u32,
+    ) -> Result<Vec<OnchainReceipt>, LightningError> {
+        let tip = self.block_height().await?;
+
+        let outputs = self
+            .node()
+            .list_funds(pb::ListfundsRequest { spent: Some(true) })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner()
+            .outputs;
+
+        let receipts = outputs
+            .into_iter()
+            .filter(|output| output.status() != ListfundsOutputsStatus::Immature)
+            .filter_map(|output| {
+                let block_height = output.blockheight.filter(|height| *height > 0);
+                if block_height.is_some_and(|height| height < start_height) {
+                    return None;
+                }
+
+                Some(OnchainReceipt {
+                    txid: hex::encode(&output.txid),
+                    vout: output.output,
+                    address: output.address?,
+                    amount_sat: msat(&output.amount_msat) / 1000,
+                    confirmations: confirmations(block_height, tip),
+                    block_height,
+                })
+            })
+            .collect();
+
+        Ok(receipts)
+    }
+
+    async fn estimate_fee_rate(&self, conf_target: u32) -> Result<u64, LightningError> {
+        let response = self
+            .node()
+            .feerates(pb::FeeratesRequest {
+                style: pb::feerates_request::FeeratesStyle::Perkb as i32,
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner();
+
+        response
+            .perkb
+            .and_then(|perkb| fee_rate_for_target(&perkb.estimates, conf_target))
+            .ok_or_else(|| LightningError::WalletError("Node has no fee estimates".to_string()))
+    }
+
+    /// CLN keeps no transaction labels, so `label` is not recorded and a lost
+    /// outcome cannot be looked up.
+    async fn send_batch(
+        &self,
+        outputs: &BTreeMap<String, u64>,
+        sat_per_vbyte: u64,
+        _label: &str,
+    ) -> Result<String, LightningError> {
+        let sat_per_kvbyte = u32::try_from(sat_per_vbyte * 1000)
+            .map_err(|_| LightningError::WalletError("Fee rate too high".to_string()))?;
+
+        let response = self
+            .node()
+            .multi_withdraw(pb::MultiwithdrawRequest {
+                outputs: outputs
+                    .iter()
+                    .map(|(address, amount_sat)| pb::OutputDesc {
+                        address: address.clone(),
+                        amount: Some(pb::Amount {
+                            msat: amount_sat * 1000,
+                        }),
+                    })
+                    .collect(),
+                feerate: Some(pb::Feerate {
+                    style: Some(feerate::Style::Perkb(sat_per_kvbyte)),
+                }),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                if connection_lost(&err) {
+                    LightningError::NetworkError(err.to_string())
+                } else {
+                    LightningError::WalletError(err.message().to_string())
+                }
+            })?
+            .into_inner();
+
+        Ok(hex::encode(response.txid))
+    }
+
+    async fn get_wallet_transaction(
+        &self,
+        txid: &str,
+        start_height: u32,
+    ) -> Result<Option<WalletTransaction>, LightningError> {
+        let tip = self.block_height().await?;
+
+        let transactions = self
+            .node()
+            .list_transactions(pb::ListtransactionsRequest {})
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner()
+            .transactions;
+
+        let Some(transaction) = transactions
+            .iter()
+            .find(|transaction| hex::encode(&transaction.hash) == txid)
+        else {
+            return Ok(None);
+        };
+
+        let block_height = Some(transaction.blockheight).filter(|height| *height > 0);
+        if block_height.is_some_and(|height| height < start_height) {
+            return Ok(None);
+        }
+
+        // CLN reports no fee; the inputs spend wallet outputs it does list
+        let wallet_outputs: HashMap<(&[u8], u32), u64> = transactions
+            .iter()
+            .flat_map(|transaction| {
+                transaction.outputs.iter().map(|output| {
+                    (
+                        (transaction.hash.as_slice(), output.index),
+                        msat(&output.amount_msat),
+                    )
+                })
+            })
+            .collect();
+        let inputs_msat: Option<u64> = transaction
+            .inputs
+            .iter()
+            .map(|input| wallet_outputs.get(&(input.txid.as_slice(), input.index)))
+            .sum::<Option<u64>>();
+        let outputs_msat: u64 = transaction
+            .outputs
+            .iter()
+            .map(|output| msat(&output.amount_msat))
+            .sum();
+        let fee_sat = inputs_msat
+            .map(|inputs_msat| inputs_msat.saturating_sub(outputs_msat) / 1000)
+            .unwrap_or_default();
+
+        Ok(Some(WalletTransaction {
+            confirmations: confirmations(block_height, tip),
+            fee_sat,
+        }))
+    }
+
+    async fn find_labeled_transaction(
+        &self,
+        _label: &str,
+        _start_height: u32,
+    ) -> Result<Option<String>, LightningError> {
+        Err(unsupported("Transaction labels"))
+    }
+
+    async fn bump_fee(
+        &self,
+        _txid: &str,
+        _sat_per_vbyte: u64,
+        _label: &str,
+    ) -> Result<String, LightningError> {
+        Err(unsupported("Fee bumps"))
+    }
+
+    async fn publish_transaction(&self, _tx_hex: &str, _label: &str) -> Result<(), LightningError> {
+        // CLN only broadcasts transactions its own wallet built
+        Err(unsupported("Broadcasts of outside transactions"))
+    }
+
+    fn can_watch_addresses(&self) -> bool {
+        false
+    }
+
+    async fn find_confirmed_transaction(
+        &self,
+        _script_pubkey: &[u8],
+        _confirmations: u32,
+        _start_height: u32,
+    ) -> Result<Option<String>, LightningError> {
+        // CLN only watches the chain for its own wallet and channels
+        Err(unsupported("Watching outside addresses"))
+    }
+
+    async fn pay_invoice(
+        &self,
+        payment_request: &str,
+        amount_msat: Option<u64>,
+        policy: &RoutingPolicy,
+    ) -> Result<PaymentOutcome, LightningError> {
+        let bolt11 = Bolt11Invoice::from_str(payment_request)
+            .map_err(|err| LightningError::Parse(err.to_string()))?;
+
+        let request = pb::PayRequest {
+            bolt11: payment_request.to_string(),
+            amount_msat: amount_msat.map(|msat| pb::Amount { msat }),
+            ..pay_request(policy)?
+        };
+
+        self.pay(request, bolt11.payment_hash().as_ref()).await
+    }
+
+    async fn track_payment(&self, payment_hash: &str) -> Result<PaymentOutcome, LightningError> {
+        let payment_hash =
+            hex::decode(payment_hash).map_err(|err| LightningError::Parse(err.to_string()))?;
+
+        let pays = self
+            .node()
+            .list_pays(pb::ListpaysRequest {
+                payment_hash: Some(payment_hash),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::NetworkError(err.to_string()))?
+            .into_inner()
+            .pays;
+
+        if pays.is_empty() {
+            return Err(LightningError::NotFound("Payment not found".to_string()));
+        }
+
+        if let Some(pay) = pays
+            .iter()
+            .find(|pay| pay.status() == ListpaysPaysStatus::Complete)
+        {
+            return Ok(PaymentOutcome {
+                payment_preimage: pay.preimage.clone().map(hex::encode).unwrap_or_default(),
+                fee_msat: msat(&pay.amount_sent_msat).saturating_sub(msat(&pay.amount_msat)),
+            });
+        }
+
+        if pays
+            .iter()
+            .any(|pay| pay.status() == ListpaysPaysStatus::Pending)
+        {
+            return Err(LightningError::NetworkError(
+                "Payment is still in flight".to_string(),
+            ));
+        }
+
+        Err(LightningError::PaymentError(
+            "Every attempt to pay failed".to_string(),
+        ))
+    }
+
+    async fn estimate_route_fee(
+        &self,
+        payment_request: &str,
+        amount_msat: u64,
+    ) -> Result<u64, LightningError> {
+        let bolt11 = Bolt11Invoice::from_str(payment_request)
+            .map_err(|err| LightningError::Parse(err.to_string()))?;
+        let amount_msat = bolt11.amount_milli_satoshis().unwrap_or(amount_msat);
+
+        let route = self
+            .node()
+            .get_route(pb::GetrouteRequest {
+                id: bolt11.recover_payee_pub_key().serialize().to_vec(),
+                amount_msat: Some(pb::Amount { msat: amount_msat }),
+                riskfactor: ROUTE_RISK_FACTOR,
+                cltv: Some(bolt11.min_final_cltv_expiry_delta() as u32),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::NotFound(err.message().to_string()))?
+            .into_inner()
+            .route;
+
+        // The first hop carries the amount plus every fee along the route
+        route
+            .first()
+            .map(|hop| msat(&hop.amount_msat).saturating_sub(amount_msat))
+            .ok_or_else(|| LightningError::NotFound("No route to the payee".to_string()))
+    }
+
+    async fn keysend(
+        &self,
+        _request: KeysendRequest,
+        _policy: &RoutingPolicy,
+    ) -> Result<PaymentOutcome, LightningError> {
+        // CLN picks the preimage itself, so the payment could not be tracked
+        // by the hash the bank records
+        Err(unsupported("Keysends with a chosen preimage"))
+    }
+
+    async fn create_offer(
+        &self,
+        amount_msat: Option<u64>,
+        description: &str,
+    ) -> Result<String, LightningError> {
+        let response = self
+            .node()
+            .offer(pb::OfferRequest {
+                amount: amount_msat
+                    .map(|msat| format!("{msat}msat"))
+                    .unwrap_or_else(|| "any".to_string()),
+                description: description.to_string(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.message().to_string()))?
+            .into_inner();
+
+        Ok(response.bolt12)
+    }
+
+    async fn disable_offer(&self, offer_id: &str) -> Result<(), LightningError> {
+        let offer_id =
+            hex::decode(offer_id).map_err(|err| LightningError::Parse(err.to_string()))?;
+
+        self.node()
+            .disable_offer(pb::DisableofferRequest { offer_id })
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.message().to_string()))?;
+
+        Ok(())
+    }
+
+    async fn fetch_offer_invoice(
+        &self,
+        offer: &str,
+        amount_msat: u64,
+        payer_note: Option<String>,
+    ) -> Result<OfferInvoice, LightningError> {
+        let parsed_offer = lightning::offers::offer::Offer::from_str(offer)
+            .map_err(|err| LightningError::Parse(format!("{err:?}")))?;
+
+        let response = self
+            .node()
+            .fetch_invoice(pb::FetchinvoiceRequest {
+                offer: offer.to_string(),
+                // CLN refuses an amount for offers that set their own
+                amount_msat: parsed_offer
+                    .amount()
+                    .is_none()
+                    .then_some(pb::Amount { msat: amount_msat }),
+                payer_note,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.message().to_string()))?
+            .into_inner();
+
+        let decoded = self.decode_offer_invoice(&response.invoice).await?;
+
+        Ok(OfferInvoice {
+            payment_hash: decoded
+                .invoice_payment_hash
+                .map(hex::encode)
+                .ok_or_else(|| LightningError::Parse("Invoice has no payment hash".to_string()))?,
+            amount_msat: msat(&decoded.invoice_amount_msat),
+            invoice: response.invoice,
+        })
+    }
+
+    async fn pay_offer_invoice(
+        &self,
+        invoice: &str,
+        policy: &RoutingPolicy,
+    ) -> Result<PaymentOutcome, LightningError> {
+        let payment_hash = self
+            .decode_offer_invoice(invoice)
+            .await?
+            .invoice_payment_hash
+            .ok_or_else(|| LightningError::Parse("Invoice has no payment hash".to_string()))?;
+
+        // `pay` takes BOLT12 invoices in place of BOLT11 ones
+        let request = pb::PayRequest {
+            bolt11: invoice.to_string(),
+            ..pay_request(policy)?
+        };
+
+        self.pay(request, &payment_hash).await
+    }
+}
+
+/// Pay request carrying the limits of `policy`, for the caller to fill in
+/// what to pay.
+fn pay_request(policy: &RoutingPolicy) -> Result<pb::PayRequest, LightningError> {
+    // `pay` can avoid channels but not be held to them
+    if !policy.outgoing_chan_ids.is_empty() || policy.last_hop_pubkey.is_some() {
+        return Err(unsupported("Outgoing channel and last hop restrictions"));
+    }
+
+    Ok(pb::PayRequest {
+        maxfee: Some(pb::Amount {
+            msat: policy.fee_limit_msat,
+        }),
+        retry_for: u32::try_from(policy.timeout_seconds).ok(),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{CreateReverseSwap, CreateSubmarineSwap};
+    use crate::errors::{ServiceError, SwapError};
+    use crate::service::swap_provider::SwapProvider;
+    use crate::service::swap_service::SwapService;
+    use crate::utilities::testing::customer;
+    use crate::utilities::{ReverseSwapTerms, SubmarineSwapTerms, SwapUpdate};
+    use sqlx::PgPool;
+
+    fn estimate(blockcount: u32, feerate: u32) -> pb::FeeratesPerkbEstimates {
+        pb::FeeratesPerkbEstimates {
+            blockcount,
+            feerate,
+            smoothed_feerate: feerate,
+        }
+    }
+
+    #[test]
+    fn fee_rate_uses_the_nearest_target_at_or_above() {
+        let estimates = [
+            estimate(2, 20_000),
+            estimate(6, 10_500),
+            estimate(12, 5_000),
+        ];
+
+        assert_eq!(fee_rate_for_target(&estimates, 1), Some(20));
+        assert_eq!(fee_rate_for_target(&estimates, 3), Some(11));
+        assert_eq!(fee_rate_for_target(&estimates, 12), Some(5));
+        // Beyond the longest estimate, the slowest rate is the best guess
+        assert_eq!(fee_rate_for_target(&estimates, 144), Some(5));
+        assert_eq!(fee_rate_for_target(&[], 6), None);
+    }
+
+    #[test]
+    fn fee_rate_never_drops_below_one_sat_per_vbyte() {
+        assert_eq!(fee_rate_for_target(&[estimate(6, 253)], 6), Some(1));
+    }
+
+    #[test]
+    fn confirmations_count_the_block_itself() {
+        assert_eq!(confirmations(Some(100), 100), 1);
+        assert_eq!(confirmations(Some(100), 102), 3);
+        assert_eq!(confirmations(None, 102), 0);
+        // Not yet seen by the tip the node reported
+        assert_eq!(confirmations(Some(103), 102), 0);
+    }
+
+    #[test]
+    fn paid_offer_invoices_carry_their_offer() {
+        let invoice = pb::ListinvoicesInvoices {
+            label: "label".to_string(),
+            description: Some("Coffee".to_string()),
+            payment_hash: vec![0xab; 32],
+            status: ListinvoicesInvoicesStatus::Paid as i32,
+            amount_msat: Some(pb::Amount { msat: 21_000 }),
+            amount_received_msat: Some(pb::Amount { msat: 21_000 }),
+            bolt12: Some("lni1".to_string()),
+            local_offer_id: Some(vec![0xcd; 32]),
+            paid_at: Some(1_700_000_000),
+            payment_preimage: Some(vec![0x01; 32]),
+            ..Default::default()
+        };
+
+        let invoice = invoice_from_cln(invoice);
+
+        assert!(matches!(invoice.state, InvoiceStatus::Settled));
+        assert_eq!(invoice.offer_id, Some(hex::encode([0xcd; 32])));
+        assert_eq!(invoice.payment_hash, hex::encode([0xab; 32]));
+        assert_eq!(invoice.amount_paid_msat, 21_000);
+        assert_eq!(invoice.value, 21);
+        assert_eq!(invoice.payment_request, "lni1");
+        assert_eq!(invoice.is_keysend, Some(false));
+    }
+
+    #[test]
+    fn invoices_without_a_payment_request_are_keysends() {
+        let invoice = invoice_from_cln(pb::ListinvoicesInvoices {
+            status: ListinvoicesInvoicesStatus::Paid as i32,
+            ..Default::default()
+        });
+
+        assert_eq!(invoice.is_keysend, Some(true));
+        assert_eq!(invoice.offer_id, None);
+    }
+
+    #[test]
+    fn restricted_routes_are_refused() {
+        let policy = RoutingPolicy {
+            fee_limit_msat: 1_000,
+            timeout_seconds: 60,
+            outgoing_chan_ids: vec![42],
+            last_hop_pubkey: None,
+        };
+
+        assert!(matches!(
+            pay_request(&policy),
+            Err(LightningError::Unsupported(_))
+        ));
+
+        let request = pay_request(&RoutingPolicy {
+            outgoing_chan_ids: vec![],
+            ..policy
+        })
+        .unwrap();
+        assert_eq!(request.maxfee, Some(pb::Amount { msat: 1_000 }));
+        assert_eq!(request.retry_for, Some(60));
+    }
+
+    /// A node that is never reached: nothing in these tests may call it
+    fn unconnected_node() -> ClnNode {
+        ClnNode {
+            client: NodeClient::new(Channel::from_static("http://127.0.0.1:1").connect_lazy()),
+            info: NodeInfo {
+                pubkey: PublicKey::from_str(
+                    "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                )
+                .unwrap(),
+                alias: "cln".to_string(),
+            },
+        }
+    }
+
+    /// Swap provider that fails the test if it is ever asked for a swap
+    struct UnreachableProvider;
+
+    #[async_trait]
+    impl SwapProvider for UnreachableProvider {
+        async fn create_submarine_swap(
+            &self,
+            _invoice: &str,
+            _refund_key: &PublicKey,
+        ) -> Result<SubmarineSwapTerms, SwapError> {
+            panic!("The provider was asked for a submarine swap");
+        }
+
+        async fn create_reverse_swap(
+            &self,
+            _amount_sat: u64,
+            _payment_hash: &[u8; 32],
+            _claim_key: &PublicKey,
+        ) -> Result<ReverseSwapTerms, SwapError> {
+            panic!("The provider was asked for a reverse swap");
+        }
+
+        async fn get_swap_status(&self, _id: &str) -> Result<SwapUpdate, SwapError> {
+            panic!("The provider was asked for a swap status");
+        }
+    }
+
+    #[sqlx::test]
+    async fn swaps_are_refused_because_lockups_cannot_be_watched(pool: PgPool) {
+        let node = unconnected_node();
+        assert!(!node.can_watch_addresses());
+
+        let service = SwapService::new(&pool, &node, &UnreachableProvider);
+        let auth = customer(&pool, 10_000_000_000).await;
+
+        let submarine = service
+            .create_submarine_swap(
+                &auth,
+                CreateSubmarineSwap {
+                    amount_sat: 100_000,
+                    refund_address: "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080".to_string(),
+                },
+            )
+            .await;
+        assert!(matches!(submarine, Err(ServiceError::Unsupported { .. })));
+
+        let reverse = service
+            .create_reverse_swap(
+                &auth,
+                CreateReverseSwap {
+                    amount_sat: 100_000,
+                    address: "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080".to_string(),
+                },
+            )
+            .await;
+        assert!(matches!(reverse, Err(ServiceError::Unsupported { .. })));
+
+        let swaps: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM swaps")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(swaps, 0);
+    }
+}
@@ -1,498 +1,1173 @@
-// use tonic_lnd::{
-//   Client,
-//   lnrpc::{
-//     Invoice, GetInfoRequest, ListInvoiceRequest, ListPaymentsRequest,
-//     invoice::InvoiceState,
-//     payment::PaymentStatus, 
-//     SendRequest,
-//     NodeInfo,
-//     Payment,
-//   }
-// };
-// use bitcoin::PublicKey;
-// use tempfile::NamedTempFile;
-// use crate::{
-//   error::LightningError,
-//   utils::{
-//     self, NodeInfo, NodeId, CustomInvoice, PaymentType, InvoiceStatus, InvoiceHtlc, 
-//     ChanInfoRequest, ChannelState,
-//   };
-// }
-// use tokio::{
-//     fs::File,
-//     io::{AsyncReadExt, Error},
-//     sync::Mutex,
-//     time::sleep,
-// };
-// use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
-// use serde::{Deserialize, Serialize};
-// use lightning_invoice::{Bolt11InvoiceDescription, Bolt11Invoice};
-// use lightning::ln::PaymentHash;
-// use serde::{Deserialize, Serialize};
-
-// #[derive(Debug, Deserialize)]
-
-
-// #[derive(Serialize, Deserialize, Debug, Clone)]
-// pub struct LndConnection {
-//   #[serde(with = "utils::serde_node_id")]
-//   pub id: NodeId,
-//   #[serde(with = "utils::serde_address")]
-//   pub address: String,
-//   #[serde(deserialize_with = "utils::deserialize_path")]
-//   pub macaroon: String,
-//   #[serde(deserialize_with = "utils::deserialize_path")]
-//   pub certificate: String
-// }
-
-
-// #[derive(Debug, Deserialize)]
-// #[serde(untagged)]
-// pub enum ConnectionRequest {
-//   Lnd(LndConnection)
-// }
-
-
-// pub struct LndNode {
-//   pub client: Mutex<Client>,
-//   pub info: NodeInfo
-// }
-
-// fn parse_node_features(features: HashMap<u32>) -> NodeFeatures {
-//     let mut flags = vec![0; 256];
-//     for f in features.into_iter() {
-//       let byte_offset = (f / 8) as usize;
-//       let mask = 1 << (f % 8);
-//       if flags.len() <= byte_offset {
-//             flags.resize(byte_offset + 1, 0u8);
-//         }
-//         flags[byte_offset] |= mask
-//     }
-//     NodeFeatures::from_le_bytes(flags)
-// }
-
-// impl LndNode {
-
-//   pub async fn new(connection: LndConnection) -> Result<Self, LightningError> {
-//       let mut client = 
-//           Self::connect_lnd_with_hex(connection.address, connection.certificate, connection.macaroon)
-//           .await.map_err(|e| LightningError::connection_error(err.to_string()))?;
-//       let node_info = client.lightning()
-//           .get_info(GetInfoRequest {})
-//           .await
-//           .map_err(|err| LightningError::GetInfoError(err.to_string()))?
-//           .into_inner();
-
-//       let mut alias = node_info.alias;
-//       let pubkey = PublicKey::from_str(&node_info.identity_pubkey)
-//         .map_err(|err| LightningError::GetInfoError(err.to_string()))?
-//       connection.id.validate(&pubkey, &mut alias)?;
-
-//       Ok(Self {
-//         client: Mutex::new(client),
-//         info: NodeInfo {
-//           pubkey,
-//           alias,
-//           features:  parse_node_features(node_info.features.keys().cloned().collect());
-//         }
-//       })
-//   }
-
-//   fn hex_to_temp_file(hex_string: String) -> Result<(NamedTempFile, PathBuf), std::io::Error> {
-//      let bytes = hex::decode(hex_string).map_err(|e|, {
-//         std::io::Error::new(
-//           std::io::ErrorKind::InvalidData,
-//           format!("Invalid hex string: {}", e)
-//         )
-//      })?;
-
-//      let mut temp_file = NamedTempFile::new()?;
-//      temp_file.write_all(&bytes)?;
-//      temp_file.flush()?;
-
-//      let path = temp_file.path().to_path_buf();
-
-//      Ok((temp_file, path))
-//   }
-
-
-//   async fn connect_lnd_with_hex(
-//     address: String,
-//     cert_hex: String,
-//     macaroon_hex: String
-//   ) -> Result<tonic_lnd::Client, Box<dyn std::error::Error>> {
-//       let (cert_file, cert_path) = self.hex_to_temp_file(&cert_hex)?;
-//       let (macaroon_file, macaroon_path) = Self::hex_to_temp_file(&macaroon_hex)?;
-
-//       let client = tonic_lnd::connect(address, cert_path, macaroon_path).await?;
-
-//       drop(cert_file);
-//       drop(macaroon_file);
-
-//       Ok(client)
-//   }
-
-//   async fn get_lnd_client_sub(&self) -> tonic_lnd::LightningClient {
-//     let mut client = self.client.lock().await;
-//     client.lightning.clone()
-//   }
-
-
-// }
-
-// /// A unified interface for Lightning Network node operations across implementations.
-// #[async_trait]
-// pub trait LightningClient: Send {
-
-//   // get node network
-//   async fn get_network(&self) -> Result<Network, LightningError>;
-
-//   async fn get_node_info(&self) -> &NodeInfo;
-
-//    /// Lists all channels, returning only their capacities in millisatoshis.
-//   async fn list_channels(&self) -> Result<Vec<ChannelSummary>, LightningError>;
-
-//   /// Lists all invoices.
-//   async fn list_invoices(&self) -> Result<Vec<CustomInvoice>, LightningError>;
-//   /// Create invoice
-//   async fn create_invoices(&self, amount: u64, description: &str) -> Result<CustomInvoice, LightningError>;  
-//   // Get Invoice Details
-//   async fn get_invoice_details(&self, payment_hash: &PaymentHash) -> Result<CustomInvoice, LightningError>;
-//     /// Gets the onchain wallet balance in satoshis.
-//     async fn get_wallet_balance(&self) -> Result<u64, LightningError>;
-  
-//   async fn pay_invoice(&self, payment_request: &str) -> Result<Payment, LightningError>;
-
-//     // async fn get_channel_info(
-//     //     &self,
-//     //     channel_id: &ShortChannelID,
-//     // ) -> Result<ChannelDetails, LightningError>;
-// }
-
-// #[async_trait]
-// impl LightningClient for LndNode {
-//    async fn get_node_info(&self) -> &NodeInfo {
-//       self.info
-//    }
-
-//    async fn get_network(&self) -> Result<Network, LightningError> {
-//          let mut client = self.client.lock().await;
-//          let info = client.lightning()
-//               .get_info()
-//               .await
-//               .map_err(|err| LightningError::GetInfoError(err.to_string()))?
-//               .into_inner();
-//           if info.chain.is_empty() {
-//             return err(LightningError::ValidationError(format!(
-//               "{}  is not connected to any chain",
-//               self.get_info()
-//             )));
-//           } else if info.chain.len() > 1 {
-//              return err(LightningError::ValidationError(format!(
-//               "{} is connected to more than one chain: {:?}",
-//               self.get_info(),
-//               info.chains.iter().map(|c| c.chain.to_string())
-//             )));
-//           }
-//           Ok(Network::from_str(match info.chain[0].network.as_str(){
-//             "mainnet" => "bitcoin",
-//             x => x
-//           }).map_err(|err| LightningError::ValidationError(err.to_string()))?)
-//    }
-
-//   async fn list_channels(&self) -> Result<Vec<ChannelSummary>, LightningError> {
-//     let mut lightning_lnd = self.get_lnd_client_sub();
-
-//     let channel_list = lightning_lnd
-//         .list_channels(ListChannelsRequest::default())
-//         .await
-//         .map_err(|err| LightningError::ChannelError(err.to_string()))?
-//         .into_inner();
-
-//     let mut last_updates: HashMap<u64, u64> = HashMap::new();
-
-//     for channel in &channel_list.channels {
-//         // fetch only public channels
-//         if !channel.private {
-//             match lightning_lnd
-//                 .get_chan_info(ChanInfoRequest {
-//                     chan_id: channel.chan_id,
-//                 })
-//                 .await
-//             {
-//                 Ok(response) => {
-//                     let chan_info = response.into_inner();
-//                     let mut last_max_update = 0u64;
-
-//                     if let Some(node1_policy) = &chan_info.node1_policy {
-//                         last_max_update =
-//                             last_max_update.max(node1_policy.last_update as u64);
-//                     }
-
-//                     if let Some(node2_policy) = &chan_info.node2_policy {
-//                         last_max_update =
-//                             last_max_update.max(node2_policy.last_update as u64);
-//                     }
-
-//                     if last_max_update > 0 {
-//                         last_updates.insert(channel.chan_id, last_max_update);
-//                     }
-//                 }
-//                 Err(e) => {
-//                     // Channel may not be announced yet
-//                     tracing::debug!(
-//                         "Failed to get channel info for {}: {}",
-//                         channel.chan_id,
-//                         e
-//                     );
-//                 }
-//             }
-//         }
-//     }
-
-//     let channels: Vec<ChannelSummary> = channel_list
-//         .channels
-//         .into_iter()
-//         .map(|channel| {
-//             let channel_state = if channel.active {
-//                 ChannelState::Active
-//             } else {
-//                 ChannelState::Disabled
-//             };
-
-//             let last_update = last_updates.get(&channel.chan_id).copied();
-
-//             ChannelSummary {
-//                 chan_id: ShortChannelID(channel.chan_id),
-//                 alias: None,
-//                 channel_state,
-//                 private: channel.private,
-//                 remote_balance: channel.remote_balance.try_into().unwrap_or(0),
-//                 local_balance: channel.local_balance.try_into().unwrap_or(0),
-//                 capacity: channel.capacity.try_into().unwrap_or(0),
-//                 last_update,
-//                 uptime: Some(channel.uptime as u64),
-//             }
-//         })
-//         .collect();
-
-//     Ok(channels)
-// }
-
-//  async fn list_invoices(&self) -> Result<Vec<CustomInvoice>, LightningError> {
-//        let mut lightning_lnd = self.get_lnd_client_sub();
-//        let request = ListInvoiceRequest {
-//           pending_only: false,
-//           ..Default::default()
-//        }   
-
-//        let response = lightning_lnd.list_invoices(request)
-//                 .await
-//                 .map_err(|err| LightningError::InvoiceError(err.to_string()))
-//                 .into_inner();
-        
-//         let invoices = response.invoice.into_iter()
-//             .map(|invoice| {
-//               let state =
-//                     match InvoiceState::try_from(invoice.state).unwrap_or(InvoiceState::Open) {
-//                         InvoiceState::Open => InvoiceStatus::Open,
-//                         InvoiceState::Settled => InvoiceStatus::Settled,
-//                         InvoiceState::Canceled => InvoiceStatus::Failed,
-//                         InvoiceState::Accepted => InvoiceStatus::Open,
-//                     };
-//               let htlcs = Some(
-//                     invoice
-//                         .htlcs
-//                         .into_iter()
-//                         .map(|htlc| InvoiceHtlc {
-//                             chan_id: Some(htlc.chan_id),
-//                             htlc_index: Some(htlc.htlc_index),
-//                             amt_msat: Some(htlc.amt_msat),
-//                             accept_time: Some(htlc.accept_time),
-//                             resolve_time: Some(htlc.resolve_time),
-//                             expiry_height: htlc.expiry_height.try_into().ok(),
-//                             mpp_total_amt_msat: Some(htlc.mpp_total_amt_msat),
-//                         })
-//                         .collect(),
-//                 );
-
-//               let features = Some(
-//                     invoice
-//                         .features
-//                         .into_iter()
-//                         .map(|(feature_bit, feature_entry)| {
-//                             (
-//                                 feature_bit,
-//                                 Feature {
-//                                     name: Some(feature_entry.name),
-//                                     is_known: Some(feature_entry.is_known),
-//                                     is_required: Some(feature_entry.is_required),
-//                                 },
-//                             )
-//                         })
-//                         .collect(),
-//                 );
-//              CustomInvoice {
-//                     memo: invoice.memo,
-//                     payment_hash: hex::encode(invoice.r_hash),
-//                     payment_preimage: Some(hex::encode(invoice.r_preimage))
-//                         .filter(|preimage_hex| !preimage_hex.is_empty())
-//                         .unwrap_or_default(),
-//                     value: invoice.value as u64,
-//                     value_msat: invoice.value_msat as u64,
-//                     creation_date: Some(invoice.creation_date),
-//                     settle_date: Some(invoice.settle_date),
-//                     payment_request: invoice.payment_request,
-//                     expiry: Some(invoice.expiry as u64),
-//                     state,
-//                     is_keysend: Some(invoice.is_keysend),
-//                     is_amp: Some(invoice.is_amp),
-//                     payment_addr: Some(hex::encode(invoice.payment_addr))
-//                         .filter(|addr_hex| !addr_hex.is_empty()),
-//                     htlcs,
-//                     features,
-//                 }
-//             }).collect();
-
-//           Ok(invoices)
-// }
-
-// async fn create_invoice(
-//     &self,
-//     amount: u64,
-//     description: &str,
-// ) -> Result<CustomInvoice, LightningError> {
-//     // 1. Get a Lightning (LND) gRPC client
-//     let mut lightning_lnd = self.get_lnd_client_sub();
-
-//     // 2. Convert amount to millisatoshis (LND expects msats)
-//     let value_msat = (amount * 1_000) as i64;
-
-//     // 3. Build the invoice request
-//     let invoice_request = Invoice {
-//         value_msat,
-//         memo: description.to_string(),
-//         ..Default::default()
-//     };
-
-//     // 4. Create the invoice on LND
-//     let response = lightning_lnd
-//         .add_invoice(tonic::Request::new(invoice_request))
-//         .await
-//         .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
-
-//     let invoice = response.into_inner();
-
-//     // 5. Parse and validate the BOLT11 invoice string
-//     let _bolt11 = Bolt11Invoice::from_str(&invoice.payment_request)
-//         .map_err(|e| LightningError::InvoiceError(e.to_string()))?;
-
-//     // 6. Map LND invoice → domain invoice
-//     Ok(CustomInvoice {
-//         memo: invoice.memo,
-//         payment_hash: hex::encode(invoice.r_hash),
-//         payment_preimage: hex::encode(invoice.r_preimage)
-//             .is_empty()
-//             .then(|| None)
-//             .unwrap_or_else(|| Some(hex::encode(invoice.r_preimage))),
-//         value: invoice.value as u64,
-//         value_msat: invoice.value_msat as u64,
-//         creation_date: Some(invoice.creation_date),
-//         settle_date: Some(invoice.settle_date),
-//         payment_request: invoice.payment_request,
-//         expiry: Some(invoice.expiry as u64),
-//         state: invoice.state(),
-//         is_keysend: Some(invoice.is_keysend),
-//         is_amp: Some(invoice.is_amp),
-//         payment_addr: (!invoice.payment_addr.is_empty())
-//             .then(|| hex::encode(invoice.payment_addr)),
-//         htlcs: None,
-//         features: None,
-//     })
-// }
-
-// async fn get_invoice_details(&self, payment_hash: &PaymentHash) -> Result<CustomInvoice, LightningError> {
-//     let client = self.get_lnd_client_sub();
-
-//     let request = tonic_lnd::lnrpc::PaymentHash {
-//         r_hash: payment_hash.0.to_vec(),
-//         ..Default::default()
-//     };
-
-//     let response = client.add_invoice.lookup_invoice(request)
-//             .await.map_err(|e| LightningError::InvoiceError(err.to_string()))
-//             .into_inner();
-//     let state = match InvoiceState::try_from(response.state).unwrap_or(InvoiceState::Open) {
-//             InvoiceState::Open => InvoiceStatus::Open,
-//             InvoiceState::Settled => InvoiceStatus::Settled,
-//             InvoiceState::Canceled => InvoiceStatus::Failed,
-//             InvoiceState::Accepted => InvoiceStatus::Open,
-//         };
-    
-//     Ok(CustomInvoice {
-//             memo: response.memo,
-//             payment_hash: hex::encode(response.r_hash),
-//             payment_preimage: Some(hex::encode(response.r_preimage))
-//                 .filter(|preimage_hex| !preimage_hex.is_empty())
-//                 .unwrap_or_default(),
-//             value: response.value as u64,
-//             value_msat: response.value_msat as u64,
-//             creation_date: Some(response.creation_date),
-//             settle_date: Some(response.settle_date),
-//             payment_request: response.payment_request,
-//             expiry: Some(response.expiry as u64),
-//             state,
-//             is_keysend: Some(response.is_keysend),
-//             is_amp: Some(response.is_amp),
-//             payment_addr: Some(hex::encode(response.payment_addr))
-//                 .filter(|addr_hex| !addr_hex.is_empty()),
-//             htlcs: None,
-//             features: None,
-//         })   
-// }
-
-// async fn pay_invoice(&self, payment_request: &str) -> Result<Payment, LightningError> {
-//       let client = self.get_lnd_client_sub();
-
-//       let bolt11 = Bolt11Invoice::from_str(payment_request)
-//           .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
-
-//       let payment_request = SendRequest {
-//         payment_request: payment_request,
-//         timeout_second: 30,
-//         fee_limit_sat: 1000,
-//         ..Default::default()
-//       };
-
-//       // pay the invoice
-//       let response = client.send_payment_sync(Request::new(payment_request)).await
-//                   .map_err(|err| LightningError::PaymentError(err.to_string()))?
-//                   .into_inner();
-      
-//       // check if payment was success
-//       if response.payment_error.is_some() && !response.payment_error.is_empty() {
-//         return Err(LightningError::PaymentError(
-//             response.payment_error,
-//         ));
-//     }
-//     Ok(response)
-//   }
-
-//   async fn get_wallet_balance(&self) -> Result<u64, LightningError> {
-//         let mut client = self.get_lightning_stub().await;
-
-//         let request = tonic_lnd::lnrpc::WalletBalanceRequest {};
-
-//         let response = client
-//             .wallet_balance(request)
-//             .await
-//             .map_err(|e| {
-//                 LightningError::GetInfoError(format!("Failed to get wallet balance: {e}"))
-//             })?
-//             .into_inner();
-
-//         // Return confirmed balance in satoshis
-//         Ok(response.confirmed_balance as u64)
-//     }
-// }
-
+// Lightning Node Service
+//! Unified interface over the Lightning node backing the bank, with the
+//! LND implementation talking to `lnrpc` over gRPC. The CLN implementation
+//! lives in `node_cln_service`.
+
+use crate::config::Config;
+use crate::errors::LightningError;
+use crate::service::lnd_chain_notifier::ChainNotifier;
+use crate::utilities::{
+    CustomInvoice, HoldInvoiceRequest, InvoiceHtlc, InvoiceRequest, InvoiceStatus, KeysendRequest,
+    NodeInfo, OfferInvoice, OnchainReceipt, PaymentOutcome, RoutingPolicy, WalletTransaction,
+};
+use async_trait::async_trait;
+use bitcoin::secp256k1::PublicKey;
+use futures::{Stream, StreamExt};
+use lightning_invoice::Bolt11Invoice;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::pin::Pin;
+use std::str::FromStr;
+use tokio::sync::Mutex;
+use tonic_lnd::{
+    Client,
+    invoicesrpc::{AddHoldInvoiceRequest, CancelInvoiceMsg, SettleInvoiceMsg},
+    lnrpc::{
+        AddressType, FeatureBit, GetInfoRequest, GetTransactionsRequest, HopHint, Invoice,
+        InvoiceSubscription, NewAddressRequest, PaymentFailureReason, PaymentHash,
+        QueryRoutesRequest, RouteHint, SendManyRequest, invoice::InvoiceState,
+        payment::PaymentStatus,
+    },
+    routerrpc::{SendPaymentRequest, TrackPaymentRequest},
+    walletrpc::{EstimateFeeRequest, FinalizePsbtRequest, Transaction as WalletTx},
+};
+
+/// Stream of invoice updates pushed by the node
+pub type InvoiceStream = Pin<Box<dyn Stream<Item = Result<CustomInvoice, LightningError>> + Send>>;
+
+#[derive(Debug, Clone)]
+pub struct LndConnection {
+    pub address: String,
+    pub macaroon: String,
+    pub certificate: String,
+}
+
+impl LndConnection {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            address: config.lnd_grpc_url.clone().unwrap_or_default(),
+            macaroon: config.lnd_macaroon.clone().unwrap_or_default(),
+            certificate: config.lnd_tls_cert.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// A unified interface for Lightning Network node operations across implementations.
+#[async_trait]
+pub trait LightningClient: Send + Sync {
+    fn get_node_info(&self) -> &NodeInfo;
+
+    /// Create invoice
+    async fn create_invoice(
+        &self,
+        request: InvoiceRequest,
+    ) -> Result<CustomInvoice, LightningError>;
+
+    // Get Invoice Details
+    async fn get_invoice_details(
+        &self,
+        payment_hash: &str,
+    ) -> Result<CustomInvoice, LightningError>;
+
+    /// Streams every invoice state change seen by the node.
+    async fn subscribe_invoices(&self) -> Result<InvoiceStream, LightningError>;
+
+    /// Creates an invoice whose payments the node accepts but holds until
+    /// `settle_hold_invoice` or `cancel_hold_invoice` is called.
+    async fn create_hold_invoice(
+        &self,
+        request: HoldInvoiceRequest,
+    ) -> Result<CustomInvoice, LightningError>;
+
+    /// Claims the payment held for the hash of `preimage`.
+    async fn settle_hold_invoice(&self, preimage: [u8; 32]) -> Result<(), LightningError>;
+
+    /// Fails any payment held for an invoice and stops it being paid. Works
+    /// on plain invoices as well, as long as they are not settled.
+    async fn cancel_hold_invoice(&self, payment_hash: &str) -> Result<(), LightningError>;
+
+    /// Height of the best block the node knows of.
+    async fn get_block_height(&self) -> Result<u32, LightningError>;
+
+    /// Generates a fresh address in the node's on-chain wallet.
+    async fn new_address(&self) -> Result<String, LightningError>;
+
+    /// Lists outputs paying the node's on-chain wallet that confirmed at or
+    /// after `start_height`, along with unconfirmed ones.
+    async fn list_onchain_receipts(
+        &self,
+        start_height: u32,
+    ) -> Result<Vec<OnchainReceipt>, LightningError>;
+
+    /// Fee rate in sat/vbyte expected to confirm within `conf_target` blocks.
+    async fn estimate_fee_rate(&self, conf_target: u32) -> Result<u64, LightningError>;
+
+    /// Pays several addresses from the on-chain wallet in one transaction
+    /// labelled `label`.
+    ///
+    /// Returns the txid of the broadcast transaction. A 'WalletError' means
+    /// the node refused to send, while a 'NetworkError' leaves the outcome
+    /// unknown and the label has to be looked up.
+    async fn send_batch(
+        &self,
+        outputs: &BTreeMap<String, u64>,
+        sat_per_vbyte: u64,
+        label: &str,
+    ) -> Result<String, LightningError>;
+
+    /// Looks up a transaction of the on-chain wallet that is unconfirmed or
+    /// confirmed at or after `start_height`.
+    async fn get_wallet_transaction(
+        &self,
+        txid: &str,
+        start_height: u32,
+    ) -> Result<Option<WalletTransaction>, LightningError>;
+
+    /// Finds the txid of the wallet transaction labelled `label`, if it is
+    /// unconfirmed or confirmed at or after `start_height`.
+    async fn find_labeled_transaction(
+        &self,
+        label: &str,
+        start_height: u32,
+    ) -> Result<Option<String>, LightningError>;
+
+    /// Replaces an unconfirmed wallet transaction with one paying
+    /// `sat_per_vbyte` (RBF), taking the extra fee from its change.
+    ///
+    /// Returns the txid of the replacement, which is labelled `label`.
+    async fn bump_fee(
+        &self,
+        txid: &str,
+        sat_per_vbyte: u64,
+        label: &str,
+    ) -> Result<String, LightningError>;
+
+    /// Broadcasts a signed transaction, such as the claim or refund of a swap.
+    async fn publish_transaction(&self, tx_hex: &str, label: &str) -> Result<(), LightningError>;
+
+    /// Whether `find_confirmed_transaction` works on this node. Swaps
+    /// depend on it to see their lockups confirm.
+    fn can_watch_addresses(&self) -> bool;
+
+    /// Finds a transaction paying `script_pubkey`, such as a swap lockup
+    /// outside the wallet, once the node has seen it reach `confirmations`
+    /// confirmations. Blocks before `start_height` are not searched.
+    ///
+    /// Returns the raw transaction in hex.
+    async fn find_confirmed_transaction(
+        &self,
+        script_pubkey: &[u8],
+        confirmations: u32,
+        start_height: u32,
+    ) -> Result<Option<String>, LightningError>;
+
+    /// Pays a BOLT11 invoice within the limits of `policy`. `amount_msat` is
+    /// only given for invoices without an amount.
+    ///
+    /// `LightningError::PaymentError` means the payment definitely failed;
+    /// any other error leaves its outcome unknown.
+    async fn pay_invoice(
+        &self,
+        payment_request: &str,
+        amount_msat: Option<u64>,
+        policy: &RoutingPolicy,
+    ) -> Result<PaymentOutcome, LightningError>;
+
+    /// Waits for the outcome of a payment the node was asked to make, with
+    /// the same error contract as `pay_invoice`.
+    ///
+    /// `LightningError::NotFound` means the node never started the payment.
+    async fn track_payment(&self, payment_hash: &str) -> Result<PaymentOutcome, LightningError>;
+
+    /// Estimates the routing fee for paying a BOLT11 invoice, taking the
+    /// amount from `amount_msat` for invoices without one.
+    ///
+    /// `LightningError::NotFound` means no route to the payee is known.
+    async fn estimate_route_fee(
+        &self,
+        payment_request: &str,
+        amount_msat: u64,
+    ) -> Result<u64, LightningError>;
+
+    /// Sends a spontaneous payment straight to a node, with the same error
+    /// contract as `pay_invoice`.
+    async fn keysend(
+        &self,
+        request: KeysendRequest,
+        policy: &RoutingPolicy,
+    ) -> Result<PaymentOutcome, LightningError>;
+
+    /// Creates a BOLT12 offer the node answers invoice requests for, with a
+    /// fixed price or, without `amount_msat`, one the payer chooses.
+    ///
+    /// Returns the bech32 encoded `lno1...` offer.
+    async fn create_offer(
+        &self,
+        amount_msat: Option<u64>,
+        description: &str,
+    ) -> Result<String, LightningError>;
+
+    /// Stops the node from answering invoice requests for an offer.
+    async fn disable_offer(&self, offer_id: &str) -> Result<(), LightningError>;
+
+    /// Requests an invoice for `amount_msat` from the node behind an offer.
+    async fn fetch_offer_invoice(
+        &self,
+        offer: &str,
+        amount_msat: u64,
+        payer_note: Option<String>,
+    ) -> Result<OfferInvoice, LightningError>;
+
+    /// Pays a BOLT12 invoice fetched with `fetch_offer_invoice`, with the
+    /// same error contract as `pay_invoice`.
+    async fn pay_offer_invoice(
+        &self,
+        invoice: &str,
+        policy: &RoutingPolicy,
+    ) -> Result<PaymentOutcome, LightningError>;
+}
+
+/// TLV record type carrying the preimage of a keysend payment
+pub const KEYSEND_PREIMAGE_RECORD_TYPE: u64 = 5482373484;
+
+/// LND has no BOLT12 support
+const LND_BOLT12_UNSUPPORTED: &str =
+    "BOLT12 offers are not supported by LND, run the bank on CLN for offers";
+
+pub struct LndNode {
+    pub client: Mutex<Client>,
+    pub info: NodeInfo,
+    chain_notifier: ChainNotifier,
+}
+
+impl LndNode {
+    pub async fn new(connection: LndConnection) -> Result<Self, LightningError> {
+        let chain_notifier = ChainNotifier::new(&connection).await?;
+
+        let mut client = tonic_lnd::connect(
+            connection.address,
+            connection.certificate,
+            connection.macaroon,
+        )
+        .await
+        .map_err(|err| LightningError::ConnectionError(err.to_string()))?;
+
+        let node_info = client
+            .lightning()
+            .get_info(GetInfoRequest {})
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?
+            .into_inner();
+
+        let pubkey = PublicKey::from_str(&node_info.identity_pubkey)
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?;
+
+        Ok(Self {
+            client: Mutex::new(client),
+            info: NodeInfo {
+                pubkey,
+                alias: node_info.alias,
+            },
+            chain_notifier,
+        })
+    }
+
+    async fn get_lnd_client_sub(&self) -> tonic_lnd::LightningClient {
+        let mut client = self.client.lock().await;
+        client.lightning().clone()
+    }
+
+    async fn get_router_client(&self) -> tonic_lnd::RouterClient {
+        let mut client = self.client.lock().await;
+        client.router().clone()
+    }
+
+    async fn get_invoices_client(&self) -> tonic_lnd::InvoicesClient {
+        let mut client = self.client.lock().await;
+        client.invoices().clone()
+    }
+
+    async fn get_wallet_client(&self) -> tonic_lnd::WalletKitClient {
+        let mut client = self.client.lock().await;
+        client.wallet().clone()
+    }
+}
+
+fn invoice_from_lnd(invoice: Invoice) -> CustomInvoice {
+    let state = match InvoiceState::try_from(invoice.state).unwrap_or(InvoiceState::Open) {
+        InvoiceState::Open => InvoiceStatus::Open,
+        InvoiceState::Settled => InvoiceStatus::Settled,
+        InvoiceState::Canceled => InvoiceStatus::Failed,
+        InvoiceState::Accepted => InvoiceStatus::Accepted,
+    };
+
+    let htlcs = Some(
+        invoice
+            .htlcs
+            .into_iter()
+            .map(|htlc| InvoiceHtlc {
+                chan_id: Some(htlc.chan_id),
+                htlc_index: Some(htlc.htlc_index),
+                amt_msat: Some(htlc.amt_msat),
+                accept_time: Some(htlc.accept_time),
+                resolve_time: Some(htlc.resolve_time),
+                expiry_height: htlc.expiry_height.try_into().ok(),
+                mpp_total_amt_msat: Some(htlc.mpp_total_amt_msat),
+                custom_records: htlc.custom_records.into_iter().collect(),
+            })
+            .collect(),
+    );
+
+    CustomInvoice {
+        memo: invoice.memo,
+        payment_hash: hex::encode(invoice.r_hash),
+        payment_preimage: hex::encode(invoice.r_preimage),
+        value: invoice.value as u64,
+        value_msat: invoice.value_msat as u64,
+        amount_paid_msat: invoice.amt_paid_msat as u64,
+        creation_date: Some(invoice.creation_date),
+        settle_date: Some(invoice.settle_date),
+        payment_request: invoice.payment_request,
+        expiry: Some(invoice.expiry as u64),
+        state,
+        is_keysend: Some(invoice.is_keysend),
+        is_amp: Some(invoice.is_amp),
+        payment_addr: Some(hex::encode(invoice.payment_addr))
+            .filter(|addr_hex| !addr_hex.is_empty()),
+        htlcs,
+        offer_id: None,
+    }
+}
+
+#[async_trait]
+impl LightningClient for LndNode {
+    fn get_node_info(&self) -> &NodeInfo {
+        &self.info
+    }
+
+    async fn create_invoice(
+        &self,
+        request: InvoiceRequest,
+    ) -> Result<CustomInvoice, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let invoice_request = Invoice {
+            value_msat: request.amount_msat.unwrap_or_default() as i64,
+            memo: request.memo,
+            description_hash: request
+                .hashed_description
+                .map(|description| Sha256::digest(description).to_vec())
+                .unwrap_or_default(),
+            expiry: request.expiry as i64,
+            ..Default::default()
+        };
+
+        let response = lightning_lnd
+            .add_invoice(invoice_request)
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?
+            .into_inner();
+
+        // Read the invoice back so the caller sees what the node stored
+        self.get_invoice_details(&hex::encode(response.r_hash))
+            .await
+    }
+
+    async fn get_invoice_details(
+        &self,
+        payment_hash: &str,
+    ) -> Result<CustomInvoice, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let r_hash =
+            hex::decode(payment_hash).map_err(|err| LightningError::Parse(err.to_string()))?;
+
+        let invoice = lightning_lnd
+            .lookup_invoice(PaymentHash {
+                r_hash,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?
+            .into_inner();
+
+        Ok(invoice_from_lnd(invoice))
+    }
+
+    async fn subscribe_invoices(&self) -> Result<InvoiceStream, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let stream = lightning_lnd
+            .subscribe_invoices(InvoiceSubscription::default())
+            .await
+            .map_err(|err| LightningError::StreamingError(err.to_string()))?
+            .into_inner()
+            .map(|update| {
+                update
+                    .map(invoice_from_lnd)
+                    .map_err(|err| LightningError::StreamingError(err.to_string()))
+            });
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn create_hold_invoice(
+        &self,
+        request: HoldInvoiceRequest,
+    ) -> Result<CustomInvoice, LightningError> {
+        let mut invoices_lnd = self.get_invoices_client().await;
+
+        let hold_request = AddHoldInvoiceRequest {
+            hash: request.payment_hash.to_vec(),
+            value_msat: request.invoice.amount_msat.unwrap_or_default() as i64,
+            memo: request.invoice.memo,
+            description_hash: request
+                .invoice
+                .hashed_description
+                .map(|description| Sha256::digest(description).to_vec())
+                .unwrap_or_default(),
+            expiry: request.invoice.expiry as i64,
+            cltv_expiry: request.cltv_expiry,
+            ..Default::default()
+        };
+
+        invoices_lnd
+            .add_hold_invoice(hold_request)
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
+
+        self.get_invoice_details(&hex::encode(request.payment_hash))
+            .await
+    }
+
+    async fn settle_hold_invoice(&self, preimage: [u8; 32]) -> Result<(), LightningError> {
+        let mut invoices_lnd = self.get_invoices_client().await;
+
+        invoices_lnd
+            .settle_invoice(SettleInvoiceMsg {
+                preimage: preimage.to_vec(),
+            })
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn cancel_hold_invoice(&self, payment_hash: &str) -> Result<(), LightningError> {
+        let mut invoices_lnd = self.get_invoices_client().await;
+
+        let payment_hash =
+            hex::decode(payment_hash).map_err(|err| LightningError::Parse(err.to_string()))?;
+
+        invoices_lnd
+            .cancel_invoice(CancelInvoiceMsg { payment_hash })
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_block_height(&self) -> Result<u32, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let node_info = lightning_lnd
+            .get_info(GetInfoRequest {})
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?
+            .into_inner();
+
+        Ok(node_info.block_height)
+    }
+
+    async fn new_address(&self) -> Result<String, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let response = lightning_lnd
+            .new_address(NewAddressRequest {
+                r#type: AddressType::WitnessPubkeyHash as i32,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner();
+
+        Ok(response.address)
+    }
+
+    async fn list_onchain_receipts(
+        &self,
+        start_height: u32,
+    ) -> Result<Vec<OnchainReceipt>, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let transactions = lightning_lnd
+            .get_transactions(GetTransactionsRequest {
+                start_height: start_height as i32,
+                // Up to the tip, then everything still in the mempool
+                end_height: -1,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner()
+            .transactions;
+
+        let receipts = transactions
+            .into_iter()
+            .flat_map(|transaction| {
+                let block_height = u32::try_from(transaction.block_height)
+                    .ok()
+                    .filter(|height| *height > 0);
+                let confirmations = transaction.num_confirmations.max(0) as u32;
+                let txid = transaction.tx_hash;
+
+                transaction
+                    .output_details
+                    .into_iter()
+                    .filter(|output| output.is_our_address && output.amount > 0)
+                    .map(move |output| OnchainReceipt {
+                        txid: txid.clone(),
+                        vout: output.output_index as u32,
+                        address: output.address,
+                        amount_sat: output.amount as u64,
+                        confirmations,
+                        block_height,
+                    })
+            })
+            .collect();
+
+        Ok(receipts)
+    }
+
+    async fn estimate_fee_rate(&self, conf_target: u32) -> Result<u64, LightningError> {
+        let mut wallet_lnd = self.get_wallet_client().await;
+
+        let response = wallet_lnd
+            .estimate_fee(EstimateFeeRequest {
+                conf_target: conf_target as i32,
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner();
+
+        // A kiloweight is 250 vbytes
+        Ok((response.sat_per_kw.max(0) as u64).div_ceil(250).max(1))
+    }
+
+    async fn send_batch(
+        &self,
+        outputs: &BTreeMap<String, u64>,
+        sat_per_vbyte: u64,
+        label: &str,
+    ) -> Result<String, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let response = lightning_lnd
+            .send_many(SendManyRequest {
+                addr_to_amount: outputs
+                    .iter()
+                    .map(|(address, amount_sat)| (address.clone(), *amount_sat as i64))
+                    .collect(),
+                sat_per_vbyte,
+                label: label.to_string(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| match tonic::Code::from(i32::from(err.code())) {
+                // The call may have been cut off with the transaction sent
+                tonic::Code::Unavailable
+                | tonic::Code::DeadlineExceeded
+                | tonic::Code::Cancelled => LightningError::NetworkError(err.to_string()),
+                _ => LightningError::WalletError(err.message().to_string()),
+            })?
+            .into_inner();
+
+        Ok(response.txid)
+    }
+
+    async fn get_wallet_transaction(
+        &self,
+        txid: &str,
+        start_height: u32,
+    ) -> Result<Option<WalletTransaction>, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let transactions = lightning_lnd
+            .get_transactions(GetTransactionsRequest {
+                start_height: start_height as i32,
+                end_height: -1,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner()
+            .transactions;
+
+        let transaction = transactions
+            .into_iter()
+            .find(|transaction| transaction.tx_hash == txid)
+            .map(|transaction| WalletTransaction {
+                confirmations: transaction.num_confirmations.max(0) as u32,
+                fee_sat: transaction.total_fees.max(0) as u64,
+            });
+
+        Ok(transaction)
+    }
+
+    async fn find_labeled_transaction(
+        &self,
+        label: &str,
+        start_height: u32,
+    ) -> Result<Option<String>, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let transactions = lightning_lnd
+            .get_transactions(GetTransactionsRequest {
+                start_height: start_height as i32,
+                end_height: -1,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner()
+            .transactions;
+
+        Ok(transactions
+            .into_iter()
+            .find(|transaction| transaction.label == label)
+            .map(|transaction| transaction.tx_hash))
+    }
+
+    async fn bump_fee(
+        &self,
+        txid: &str,
+        sat_per_vbyte: u64,
+        label: &str,
+    ) -> Result<String, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        // The parents of the transaction are needed for the amounts it spends
+        let transactions = lightning_lnd
+            .get_transactions(GetTransactionsRequest {
+                start_height: 0,
+                end_height: -1,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner()
+            .transactions;
+
+        let raw_transaction = |txid: &str| {
+            let transaction = transactions
+                .iter()
+                .find(|transaction| transaction.tx_hash == txid)
+                .ok_or_else(|| {
+                    LightningError::NotFound(format!("Transaction {txid} is not in the wallet"))
+                })?;
+            let raw = hex::decode(&transaction.raw_tx_hex)
+                .map_err(|err| LightningError::Parse(err.to_string()))?;
+            let tx: bitcoin::Transaction = bitcoin::consensus::deserialize(&raw)
+                .map_err(|err| LightningError::Parse(err.to_string()))?;
+            Ok::<_, LightningError>((transaction, tx))
+        };
+
+        let (original, original_tx) = raw_transaction(txid)?;
+        if original.num_confirmations > 0 {
+            return Err(LightningError::WalletError(format!(
+                "Transaction {txid} is already confirmed"
+            )));
+        }
+
+        let change_vout = original
+            .output_details
+            .iter()
+            .find(|output| output.is_our_address)
+            .map(|output| output.output_index as u32)
+            .ok_or_else(|| {
+                LightningError::WalletError(format!(
+                    "Transaction {txid} has no change to bump from"
+                ))
+            })?;
+
+        let prevouts = original_tx
+            .input
+            .iter()
+            .map(|input| {
+                let (_, parent) = raw_transaction(&input.previous_output.txid.to_string())?;
+                parent
+                    .output
+                    .get(input.previous_output.vout as usize)
+                    .cloned()
+                    .ok_or_else(|| LightningError::NotFound(input.previous_output.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let psbt = replacement_psbt(&original_tx, change_vout, &prevouts, sat_per_vbyte)?;
+
+        let mut wallet_lnd = self.get_wallet_client().await;
+
+        let signed = wallet_lnd
+            .finalize_psbt(FinalizePsbtRequest {
+                funded_psbt: psbt.serialize(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner();
+
+        let replacement: bitcoin::Transaction =
+            bitcoin::consensus::deserialize(&signed.raw_final_tx)
+                .map_err(|err| LightningError::Parse(err.to_string()))?;
+
+        self.publish_transaction(&hex::encode(&signed.raw_final_tx), label)
+            .await?;
+
+        Ok(replacement.compute_txid().to_string())
+    }
+
+    async fn publish_transaction(&self, tx_hex: &str, label: &str) -> Result<(), LightningError> {
+        let mut wallet_lnd = self.get_wallet_client().await;
+
+        let response = wallet_lnd
+            .publish_transaction(WalletTx {
+                tx_hex: hex::decode(tx_hex)
+                    .map_err(|err| LightningError::Parse(err.to_string()))?,
+                label: label.to_string(),
+            })
+            .await
+            .map_err(|err| LightningError::WalletError(err.to_string()))?
+            .into_inner();
+
+        if !response.publish_error.is_empty() {
+            return Err(LightningError::WalletError(response.publish_error));
+        }
+
+        Ok(())
+    }
+
+    fn can_watch_addresses(&self) -> bool {
+        true
+    }
+
+    async fn find_confirmed_transaction(
+        &self,
+        script_pubkey: &[u8],
+        confirmations: u32,
+        start_height: u32,
+    ) -> Result<Option<String>, LightningError> {
+        let raw_tx = self
+            .chain_notifier
+            .confirmed_transaction(script_pubkey, confirmations, start_height)
+            .await?;
+
+        Ok(raw_tx.map(hex::encode))
+    }
+
+    async fn pay_invoice(
+        &self,
+        payment_request: &str,
+        amount_msat: Option<u64>,
+        policy: &RoutingPolicy,
+    ) -> Result<PaymentOutcome, LightningError> {
+        let mut router_lnd = self.get_router_client().await;
+
+        let request = SendPaymentRequest {
+            payment_request: payment_request.to_string(),
+            amt_msat: amount_msat.unwrap_or_default() as i64,
+            ..routed_request(policy)
+        };
+
+        send_payment(&mut router_lnd, request).await
+    }
+
+    async fn track_payment(&self, payment_hash: &str) -> Result<PaymentOutcome, LightningError> {
+        let mut router_lnd = self.get_router_client().await;
+
+        let payment_hash =
+            hex::decode(payment_hash).map_err(|err| LightningError::Parse(err.to_string()))?;
+
+        let updates = router_lnd
+            .track_payment_v2(TrackPaymentRequest {
+                payment_hash,
+                no_inflight_updates: true,
+            })
+            .await
+            .map_err(tracking_error)?
+            .into_inner();
+
+        payment_result(updates).await
+    }
+
+    async fn estimate_route_fee(
+        &self,
+        payment_request: &str,
+        amount_msat: u64,
+    ) -> Result<u64, LightningError> {
+        let mut lightning_lnd = self.get_lnd_client_sub().await;
+
+        let bolt11 = Bolt11Invoice::from_str(payment_request)
+            .map_err(|err| LightningError::Parse(err.to_string()))?;
+
+        // Payees behind private channels can only be reached through the hints
+        let route_hints = bolt11
+            .route_hints()
+            .into_iter()
+            .map(|hint| RouteHint {
+                hop_hints: hint
+                    .0
+                    .into_iter()
+                    .map(|hop| HopHint {
+                        node_id: hop.src_node_id.to_string(),
+                        chan_id: hop.short_channel_id,
+                        fee_base_msat: hop.fees.base_msat,
+                        fee_proportional_millionths: hop.fees.proportional_millionths,
+                        cltv_expiry_delta: hop.cltv_expiry_delta as u32,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let request = QueryRoutesRequest {
+            pub_key: bolt11.recover_payee_pub_key().to_string(),
+            amt_msat: bolt11.amount_milli_satoshis().unwrap_or(amount_msat) as i64,
+            final_cltv_delta: bolt11.min_final_cltv_expiry_delta() as i32,
+            use_mission_control: true,
+            route_hints,
+            ..Default::default()
+        };
+
+        let response = lightning_lnd
+            .query_routes(request)
+            .await
+            .map_err(|err| LightningError::NotFound(err.message().to_string()))?
+            .into_inner();
+
+        response
+            .routes
+            .first()
+            .map(|route| route.total_fees_msat as u64)
+            .ok_or_else(|| LightningError::NotFound("No route to the payee".to_string()))
+    }
+
+    async fn keysend(
+        &self,
+        request: KeysendRequest,
+        policy: &RoutingPolicy,
+    ) -> Result<PaymentOutcome, LightningError> {
+        let mut router_lnd = self.get_router_client().await;
+
+        let payment_hash = Sha256::digest(request.payment_preimage).to_vec();
+
+        let mut dest_custom_records: HashMap<u64, Vec<u8>> =
+            request.custom_records.into_iter().collect();
+        dest_custom_records.insert(
+            KEYSEND_PREIMAGE_RECORD_TYPE,
+            request.payment_preimage.to_vec(),
+        );
+
+        let request = SendPaymentRequest {
+            dest: request.destination.serialize().to_vec(),
+            amt_msat: request.amount_msat as i64,
+            payment_hash,
+            dest_custom_records,
+            dest_features: vec![FeatureBit::TlvOnionReq as i32],
+            ..routed_request(policy)
+        };
+
+        send_payment(&mut router_lnd, request).await
+    }
+
+    async fn create_offer(
+        &self,
+        _amount_msat: Option<u64>,
+        _description: &str,
+    ) -> Result<String, LightningError> {
+        Err(LightningError::Unsupported(
+            LND_BOLT12_UNSUPPORTED.to_string(),
+        ))
+    }
+
+    async fn disable_offer(&self, _offer_id: &str) -> Result<(), LightningError> {
+        Err(LightningError::Unsupported(
+            LND_BOLT12_UNSUPPORTED.to_string(),
+        ))
+    }
+
+    async fn fetch_offer_invoice(
+        &self,
+        _offer: &str,
+        _amount_msat: u64,
+        _payer_note: Option<String>,
+    ) -> Result<OfferInvoice, LightningError> {
+        Err(LightningError::Unsupported(
+            LND_BOLT12_UNSUPPORTED.to_string(),
+        ))
+    }
+
+    async fn pay_offer_invoice(
+        &self,
+        _invoice: &str,
+        _policy: &RoutingPolicy,
+    ) -> Result<PaymentOutcome, LightningError> {
+        Err(LightningError::Unsupported(
+            LND_BOLT12_UNSUPPORTED.to_string(),
+        ))
+    }
+}
+
+/// Payment request carrying the limits of `policy`, for the caller to fill
+/// in what to pay.
+fn routed_request(policy: &RoutingPolicy) -> SendPaymentRequest {
+    SendPaymentRequest {
+        fee_limit_msat: policy.fee_limit_msat as i64,
+        timeout_seconds: policy.timeout_seconds as i32,
+        outgoing_chan_ids: policy.outgoing_chan_ids.clone(),
+        last_hop_pubkey: policy
+            .last_hop_pubkey
+            .map(|pubkey| pubkey.serialize().to_vec())
+            .unwrap_or_default(),
+        // Only the final state of the payment matters
+        no_inflight_updates: true,
+        ..Default::default()
+    }
+}
+
+/// Outputs below this are non-standard, so a bump may not shrink the change
+/// any further
+const DUST_LIMIT_SAT: u64 = 546;
+
+/// Builds the unsigned replacement of `original` paying `sat_per_vbyte`,
+/// with the extra fee taken out of its output `change_vout`.
+///
+/// `prevouts` are the outputs spent by each input of `original`, in order.
+/// The replacement also pays at least one sat/vbyte more than the original,
+/// as relay policy demands. Its inputs signal RBF, but the original from
+/// SendMany does not, so replacing it relies on full-RBF mempools.
+fn replacement_psbt(
+    original: &bitcoin::Transaction,
+    change_vout: u32,
+    prevouts: &[bitcoin::TxOut],
+    sat_per_vbyte: u64,
+) -> Result<bitcoin::Psbt, LightningError> {
+    if prevouts.len() != original.input.len() {
+        return Err(LightningError::WalletError(
+            "Every input needs the output it spends".to_string(),
+        ));
+    }
+
+    let input_sat: u64 = prevouts.iter().map(|output| output.value.to_sat()).sum();
+    let output_sat: u64 = original
+        .output
+        .iter()
+        .map(|output| output.value.to_sat())
+        .sum();
+    let old_fee = input_sat.checked_sub(output_sat).ok_or_else(|| {
+        LightningError::WalletError("Transaction spends more than its inputs".to_string())
+    })?;
+
+    // Signatures come out the same size, so the replacement keeps the vsize
+    let vsize = original.vsize() as u64;
+    let new_fee = (vsize * sat_per_vbyte).max(old_fee + vsize);
+
+    let mut replacement = original.clone();
+    let change = replacement
+        .output
+        .get_mut(change_vout as usize)
+        .ok_or_else(|| LightningError::WalletError(format!("No output {change_vout}")))?;
+    let change_sat = change
+        .value
+        .to_sat()
+        .checked_sub(new_fee - old_fee)
+        .filter(|change_sat| *change_sat >= DUST_LIMIT_SAT)
+        .ok_or_else(|| {
+            LightningError::WalletError(format!(
+                "Change of {} sat cannot pay a fee of {new_fee} sat",
+                change.value.to_sat()
+            ))
+        })?;
+    change.value = bitcoin::Amount::from_sat(change_sat);
+
+    for input in &mut replacement.input {
+        input.script_sig = bitcoin::ScriptBuf::new();
+        input.witness = bitcoin::Witness::default();
+        input.sequence = bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME;
+    }
+
+    let mut psbt = bitcoin::Psbt::from_unsigned_tx(replacement)
+        .map_err(|err| LightningError::WalletError(err.to_string()))?;
+    for (input, prevout) in psbt.inputs.iter_mut().zip(prevouts) {
+        input.witness_utxo = Some(prevout.clone());
+    }
+
+    Ok(psbt)
+}
+
+/// Sends a payment and sorts failures into definite and unknown outcomes.
+async fn send_payment(
+    router_lnd: &mut tonic_lnd::RouterClient,
+    request: SendPaymentRequest,
+) -> Result<PaymentOutcome, LightningError> {
+    let updates = router_lnd
+        .send_payment_v2(request)
+        .await
+        .map_err(|err| match tonic::Code::from(i32::from(err.code())) {
+            // The call may have been cut off with the payment in flight
+            tonic::Code::Unavailable | tonic::Code::DeadlineExceeded | tonic::Code::Cancelled => {
+                LightningError::NetworkError(err.to_string())
+            }
+            _ => LightningError::PaymentError(err.message().to_string()),
+        })?
+        .into_inner();
+
+    payment_result(updates).await
+}
+
+/// Errors tracking a payment, telling payments the node does not know
+/// apart from a lost connection.
+fn tracking_error(err: tonic_lnd::tonic::Status) -> LightningError {
+    match err.code() {
+        tonic_lnd::tonic::Code::NotFound => LightningError::NotFound(err.message().to_string()),
+        _ => LightningError::NetworkError(err.to_string()),
+    }
+}
+
+/// Waits on the updates of a payment until it succeeds or fails.
+async fn payment_result(
+    mut updates: tonic_lnd::tonic::Streaming<tonic_lnd::lnrpc::Payment>,
+) -> Result<PaymentOutcome, LightningError> {
+    // Skip anything reported before the payment succeeds or fails
+    while let Some(payment) = updates.message().await.map_err(tracking_error)? {
+        match PaymentStatus::try_from(payment.status) {
+            Ok(PaymentStatus::Succeeded) => {
+                return Ok(PaymentOutcome {
+                    payment_preimage: payment.payment_preimage,
+                    fee_msat: payment.fee_msat as u64,
+                });
+            }
+            Ok(PaymentStatus::Failed) => {
+                let reason = match PaymentFailureReason::try_from(payment.failure_reason) {
+                    Ok(PaymentFailureReason::FailureReasonTimeout) => "timed out finding a route",
+                    Ok(PaymentFailureReason::FailureReasonNoRoute) => {
+                        "no route found within the fee limit"
+                    }
+                    Ok(PaymentFailureReason::FailureReasonIncorrectPaymentDetails) => {
+                        "rejected by the payee"
+                    }
+                    Ok(PaymentFailureReason::FailureReasonInsufficientBalance) => {
+                        "insufficient outbound liquidity"
+                    }
+                    _ => "unrecoverable error",
+                };
+                return Err(LightningError::PaymentError(reason.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    Err(LightningError::NetworkError(
+        "Payment updates ended before the payment completed".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{Amount, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+
+    fn p2wpkh(byte: u8) -> ScriptBuf {
+        let mut script = vec![0x00, 0x14];
+        script.extend([byte; 20]);
+        ScriptBuf::from_bytes(script)
+    }
+
+    /// Signed batch spending one 100 000 sat output, paying 50 000 sat out
+    /// and 40 000 sat back as change, for a fee of 10 000 sat
+    fn batch() -> (Transaction, Vec<TxOut>) {
+        let transaction = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: bitcoin::OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::from_slice(&[vec![1; 72], vec![2; 33]]),
+            }],
+            output: vec![
+                TxOut {
+                    value: Amount::from_sat(50_000),
+                    script_pubkey: p2wpkh(1),
+                },
+                TxOut {
+                    value: Amount::from_sat(40_000),
+                    script_pubkey: p2wpkh(2),
+                },
+            ],
+        };
+        let prevouts = vec![TxOut {
+            value: Amount::from_sat(100_000),
+            script_pubkey: p2wpkh(3),
+        }];
+
+        (transaction, prevouts)
+    }
+
+    #[test]
+    fn replacement_takes_the_new_fee_from_the_change() {
+        let (original, prevouts) = batch();
+        let vsize = original.vsize() as u64;
+
+        let psbt = replacement_psbt(&original, 1, &prevouts, 100).unwrap();
+        let replacement = &psbt.unsigned_tx;
+
+        assert_eq!(replacement.output[0].value, Amount::from_sat(50_000));
+        assert_eq!(
+            replacement.output[1].value,
+            Amount::from_sat(40_000 - (vsize * 100 - 10_000))
+        );
+        assert_eq!(
+            replacement.input[0].sequence,
+            Sequence::ENABLE_RBF_NO_LOCKTIME
+        );
+        assert!(replacement.input[0].witness.is_empty());
+        assert_eq!(psbt.inputs[0].witness_utxo, Some(prevouts[0].clone()));
+    }
+
+    #[test]
+    fn replacement_pays_at_least_a_sat_per_vbyte_more() {
+        let (original, prevouts) = batch();
+        let vsize = original.vsize() as u64;
+
+        // Below the rate the original already pays
+        let psbt = replacement_psbt(&original, 1, &prevouts, 1).unwrap();
+
+        assert_eq!(
+            psbt.unsigned_tx.output[1].value,
+            Amount::from_sat(40_000 - vsize)
+        );
+    }
+
+    #[test]
+    fn replacement_keeps_the_change_above_dust() {
+        let (original, prevouts) = batch();
+
+        assert!(matches!(
+            replacement_psbt(&original, 1, &prevouts, 1_000),
+            Err(LightningError::WalletError(_))
+        ));
+        assert!(matches!(
+            replacement_psbt(&original, 2, &prevouts, 100),
+            Err(LightningError::WalletError(_))
+        ));
+    }
+
+    #[test]
+    fn replacement_needs_every_prevout() {
+        let (original, _) = batch();
+
+        assert!(matches!(
+            replacement_psbt(&original, 1, &[], 100),
+            Err(LightningError::WalletError(_))
+        ));
+    }
+}
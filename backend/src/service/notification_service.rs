@@ -0,0 +1,535 @@
+// Notification Service Logic
+//! Transactional emails about payments and account security.
+//!
+//! Emails are rendered from the templates in `templates/email` and written
+//! to the 'email_outbox' in the same transaction as the change they report.
+//! The sender works through the outbox in the background, so a slow or
+//! unreachable SMTP server never holds up a payment. Failed sends are
+//! retried with exponential backoff and dropped after `EMAIL_MAX_ATTEMPTS`.
+
+use crate::Config;
+use crate::db::models::{NotificationPreferences, UpdateNotificationPreferences};
+use crate::errors::{EmailError, ServiceError, ServiceResult};
+use crate::repositories::notification_repository::NotificationRepository;
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Utc};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use sqlx::{PgConnection, PgPool};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How often the outbox is checked for emails to send
+const EMAIL_POLL_SECONDS: u64 = 5;
+
+/// Most emails sent per run
+const EMAIL_BATCH_SIZE: i64 = 20;
+
+/// How long the SMTP server may take to answer
+const EMAIL_SMTP_TIMEOUT_SECONDS: u64 = 15;
+
+/// How long a claimed email is kept from other senders
+const EMAIL_CLAIM_SECONDS: f64 = 120.0;
+
+/// Attempts after which an email is given up on
+const EMAIL_MAX_ATTEMPTS: i32 = 8;
+
+/// Wait before the first retry, doubled on every further attempt
+const EMAIL_RETRY_BASE_SECONDS: i64 = 60;
+
+/// Longest wait between two attempts
+const EMAIL_RETRY_MAX_SECONDS: i64 = 60 * 60;
+
+/// Longest error kept on an email
+const EMAIL_ERROR_MAX_LEN: usize = 500;
+
+/// Longest user agent quoted in an email
+const EMAIL_USER_AGENT_MAX_LEN: usize = 200;
+
+/// Emails the bank sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notification {
+    PaymentReceived,
+    PaymentSent,
+    LargeWithdrawal,
+    NewLogin,
+    PasswordChanged,
+    /// Token confirming a new email address, sent whatever the preferences
+    EmailVerification,
+}
+
+impl Notification {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Notification::PaymentReceived => "payment_received",
+            Notification::PaymentSent => "payment_sent",
+            Notification::LargeWithdrawal => "large_withdrawal",
+            Notification::NewLogin => "new_login",
+            Notification::PasswordChanged => "password_changed",
+            Notification::EmailVerification => "email_verification",
+        }
+    }
+
+    /// Template the email is rendered from: a `Subject:` line, a blank
+    /// line and the body, with `{{name}}` placeholders
+    fn template(&self) -> &'static str {
+        match self {
+            Notification::PaymentReceived => {
+                include_str!("../../templates/email/payment_received.txt")
+            }
+            Notification::PaymentSent => include_str!("../../templates/email/payment_sent.txt"),
+            Notification::LargeWithdrawal => {
+                include_str!("../../templates/email/large_withdrawal.txt")
+            }
+            Notification::NewLogin => include_str!("../../templates/email/new_login.txt"),
+            Notification::PasswordChanged => {
+                include_str!("../../templates/email/password_changed.txt")
+            }
+            Notification::EmailVerification => {
+                include_str!("../../templates/email/email_verification.txt")
+            }
+        }
+    }
+
+    /// Whether the user wants this email
+    fn is_enabled(&self, preferences: &NotificationPreferences) -> bool {
+        match self {
+            Notification::PaymentReceived => preferences.payment_received,
+            Notification::PaymentSent => preferences.payment_sent,
+            Notification::LargeWithdrawal => preferences.large_withdrawal,
+            Notification::NewLogin => preferences.new_login,
+            Notification::PasswordChanged => preferences.password_changed,
+            Notification::EmailVerification => true,
+        }
+    }
+}
+
+/// Fills in a template, returning the subject and the body
+fn render(template: &str, vars: &[(&str, String)]) -> (String, String) {
+    let mut text = template.to_string();
+    for (name, value) in vars {
+        text = text.replace(&format!("{{{{{name}}}}}"), value);
+    }
+
+    let (subject, body) = text.split_once("\n\n").unwrap_or((text.as_str(), ""));
+
+    (
+        subject.trim_start_matches("Subject:").trim().to_string(),
+        body.to_string(),
+    )
+}
+
+/// Shows an amount of millisatoshis in sats, e.g. "1500 sat" or "0.5 sat"
+pub fn format_sat(amount_msat: &BigDecimal) -> String {
+    let sat = amount_msat / BigDecimal::from(1000);
+    let sat = if sat.is_integer() {
+        sat.with_scale(0)
+    } else {
+        sat.with_scale_round(3, RoundingMode::Down)
+    };
+    format!("{sat} sat")
+}
+
+/// Shortens a user agent to quote it in an email
+pub fn describe_user_agent(user_agent: Option<&str>) -> String {
+    user_agent
+        .map(|user_agent| user_agent.chars().take(EMAIL_USER_AGENT_MAX_LEN).collect())
+        .unwrap_or_else(|| "Unknown device".to_string())
+}
+
+/// Wait before the next attempt after `attempts` failed ones
+fn retry_delay_seconds(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    (EMAIL_RETRY_BASE_SECONDS * 2i64.pow(exponent)).min(EMAIL_RETRY_MAX_SECONDS)
+}
+
+/// Sends emails through the SMTP server in the configuration
+pub struct EmailSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl EmailSender {
+    /// Builds the sender, or returns 'None' when no SMTP server is set
+    pub fn from_config(config: &Config) -> Result<Option<Self>, EmailError> {
+        let Some(host) = config.smtp_host.as_deref() else {
+            return Ok(None);
+        };
+
+        let builder = match config.smtp_tls.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+            _ => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                host,
+            )),
+        }
+        .map_err(|e| EmailError::Config(e.to_string()))?;
+
+        let mut builder = builder
+            .port(config.smtp_port)
+            .timeout(Some(Duration::from_secs(EMAIL_SMTP_TIMEOUT_SECONDS)));
+
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        let from = config
+            .from_email
+            .parse::<Mailbox>()
+            .map_err(|e| EmailError::Config(format!("FROM_EMAIL: {e}")))?;
+
+        Ok(Some(Self {
+            transport: builder.build(),
+            from,
+        }))
+    }
+
+    async fn send(&self, to_address: &str, subject: &str, body: &str) -> Result<(), EmailError> {
+        let to = to_address
+            .parse::<Mailbox>()
+            .map_err(|e| EmailError::Message(e.to_string()))?;
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())
+            .map_err(|e| EmailError::Message(e.to_string()))?;
+
+        self.transport.send(message).await.map_err(|e| {
+            if e.is_permanent() {
+                EmailError::Rejected(e.to_string())
+            } else {
+                EmailError::Transport(e.to_string())
+            }
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Email claimed by the sender
+struct DueEmail {
+    id: String,
+    to_address: String,
+    subject: String,
+    body: String,
+    attempts: i32,
+}
+
+// Service layer for Notification related Operation
+pub struct NotificationService<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> NotificationService<'a> {
+    /// Creates a new notification service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves which emails a user gets.
+    pub async fn get_preferences(&self, user_id: &str) -> ServiceResult<NotificationPreferences> {
+        Ok(NotificationRepository::new(self.pool)
+            .get_preferences_by_user_id(user_id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .unwrap_or_default())
+    }
+
+    /// Turns emails on or off, leaving the preferences not given as they are.
+    pub async fn update_preferences(
+        &self,
+        user_id: &str,
+        update: UpdateNotificationPreferences,
+    ) -> ServiceResult<NotificationPreferences> {
+        let preferences = sqlx::query_as!(
+            NotificationPreferences,
+            r#"
+            INSERT INTO notification_preferences (
+                user_id,
+                payment_received,
+                payment_sent,
+                large_withdrawal,
+                new_login,
+                password_changed
+            )
+            VALUES (
+                $1,
+                COALESCE($2, true),
+                COALESCE($3, true),
+                COALESCE($4, true),
+                COALESCE($5, true),
+                COALESCE($6, true)
+            )
+            ON CONFLICT (user_id) DO UPDATE
+            SET payment_received = COALESCE($2, notification_preferences.payment_received),
+                payment_sent = COALESCE($3, notification_preferences.payment_sent),
+                large_withdrawal = COALESCE($4, notification_preferences.large_withdrawal),
+                new_login = COALESCE($5, notification_preferences.new_login),
+                password_changed = COALESCE($6, notification_preferences.password_changed),
+                updated_at = now()
+            RETURNING
+                payment_received as "payment_received!",
+                payment_sent as "payment_sent!",
+                large_withdrawal as "large_withdrawal!",
+                new_login as "new_login!",
+                password_changed as "password_changed!"
+            "#,
+            user_id,
+            update.payment_received,
+            update.payment_sent,
+            update.large_withdrawal,
+            update.new_login,
+            update.password_changed
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(preferences)
+    }
+
+    /// Queues an email to the user's address, unless they turned it off or
+    /// have no address.
+    ///
+    /// Takes the connection of the caller's transaction so the email is only
+    /// sent if the change it reports is committed.
+    pub async fn enqueue(
+        conn: &mut PgConnection,
+        user_id: &str,
+        notification: Notification,
+        vars: Vec<(&str, String)>,
+    ) -> ServiceResult<()> {
+        Self::queue(conn, user_id, None, notification, vars).await
+    }
+
+    /// Queues an email to `to_address` rather than the user's current
+    /// address, whatever their preferences.
+    pub async fn enqueue_to(
+        conn: &mut PgConnection,
+        user_id: &str,
+        to_address: &str,
+        notification: Notification,
+        vars: Vec<(&str, String)>,
+    ) -> ServiceResult<()> {
+        Self::queue(conn, user_id, Some(to_address), notification, vars).await
+    }
+
+    async fn queue(
+        conn: &mut PgConnection,
+        user_id: &str,
+        to_address: Option<&str>,
+        notification: Notification,
+        mut vars: Vec<(&str, String)>,
+    ) -> ServiceResult<()> {
+        let recipient = sqlx::query!(
+            r#"
+            SELECT
+                u.username as "username!",
+                u.email as "email?",
+                COALESCE(p.payment_received, true) as "payment_received!",
+                COALESCE(p.payment_sent, true) as "payment_sent!",
+                COALESCE(p.large_withdrawal, true) as "large_withdrawal!",
+                COALESCE(p.new_login, true) as "new_login!",
+                COALESCE(p.password_changed, true) as "password_changed!"
+            FROM users u
+            LEFT JOIN notification_preferences p ON p.user_id = u.id
+            WHERE u.id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let Some(recipient) = recipient else {
+            return Ok(());
+        };
+
+        let preferences = NotificationPreferences {
+            payment_received: recipient.payment_received,
+            payment_sent: recipient.payment_sent,
+            large_withdrawal: recipient.large_withdrawal,
+            new_login: recipient.new_login,
+            password_changed: recipient.password_changed,
+        };
+
+        // Users who signed up with a wallet key may have no address
+        let to_address = match to_address {
+            Some(to_address) => to_address.to_string(),
+            None if notification.is_enabled(&preferences) => match recipient.email {
+                Some(email) => email,
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+
+        vars.push(("username", recipient.username));
+        let (subject, body) = render(notification.template(), &vars);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO email_outbox (id, user_id, to_address, kind, subject, body)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            Uuid::now_v7().to_string(),
+            user_id,
+            to_address,
+            notification.as_str(),
+            subject,
+            body
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Sends every email that is due and records how it went.
+    pub async fn send_due(&self, sender: &EmailSender) -> ServiceResult<()> {
+        // Claimed emails are pushed back for a while, so a sender that dies
+        // mid-send leaves them to be retried
+        let due = sqlx::query_as!(
+            DueEmail,
+            r#"
+            UPDATE email_outbox
+            SET next_attempt_at = now() + make_interval(secs => $1),
+                updated_at = now()
+            WHERE id IN (
+                SELECT id
+                FROM email_outbox
+                WHERE status = 'pending'
+                  AND next_attempt_at <= now()
+                ORDER BY next_attempt_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING
+                id as "id!",
+                to_address as "to_address!",
+                subject as "subject!",
+                body as "body!",
+                attempts as "attempts!"
+            "#,
+            EMAIL_CLAIM_SECONDS,
+            EMAIL_BATCH_SIZE
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        for email in due {
+            let result = sender
+                .send(&email.to_address, &email.subject, &email.body)
+                .await;
+
+            if let Err(error) = self.record_attempt(&email, result).await {
+                tracing::warn!("Recording email {} failed: {}", email.id, error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Marks an email sent, schedules its retry, or gives up on it when the
+    /// server refused it or it has run out of attempts.
+    async fn record_attempt(
+        &self,
+        email: &DueEmail,
+        result: Result<(), EmailError>,
+    ) -> ServiceResult<()> {
+        let attempts = email.attempts + 1;
+
+        let error = match result {
+            Ok(()) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE email_outbox
+                    SET status = 'sent',
+                        attempts = $2,
+                        last_error = NULL,
+                        sent_at = now(),
+                        updated_at = now()
+                    WHERE id = $1
+                    "#,
+                    email.id,
+                    attempts
+                )
+                .execute(self.pool)
+                .await
+                .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+                return Ok(());
+            }
+            Err(error) => error,
+        };
+
+        let permanent = matches!(error, EmailError::Message(_) | EmailError::Rejected(_));
+        let dead = permanent || attempts >= EMAIL_MAX_ATTEMPTS;
+        let status = if dead { "dead" } else { "pending" };
+        let error: String = error
+            .to_string()
+            .chars()
+            .take(EMAIL_ERROR_MAX_LEN)
+            .collect();
+
+        sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = $2,
+                attempts = $3,
+                last_error = $4,
+                next_attempt_at = now() + make_interval(secs => $5),
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            email.id,
+            status,
+            attempts,
+            error,
+            retry_delay_seconds(attempts) as f64
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if dead {
+            tracing::warn!(
+                "Gave up on email {} after {} attempts: {}",
+                email.id,
+                attempts,
+                error
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Sends queued emails every few seconds.
+pub async fn run_email_sender(pool: PgPool, sender: EmailSender) {
+    let mut interval = tokio::time::interval(Duration::from_secs(EMAIL_POLL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        let service = NotificationService::new(&pool);
+
+        if let Err(error) = service.send_due(&sender).await {
+            tracing::warn!("Sending emails failed: {}", error);
+        }
+    }
+}
+
+/// Time shown in security emails
+pub fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
@@ -0,0 +1,175 @@
+// Offer Service Logic
+//! Reusable BOLT12 offers. Unlike BOLT11 invoices an offer can be paid any
+//! number of times; the node answers each payer's invoice request and
+//! settled payments are credited to the account the offer belongs to.
+
+use crate::db::models::{ApiKeyScope, CreateOffer, Offer};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::account_repository::AccountRepository;
+use crate::repositories::offer_repository::OfferRepository;
+use crate::service::node_service::LightningClient;
+use crate::utilities::auth::AuthUser;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+
+// Service layer for Offer related Operation
+pub struct OfferService<'a> {
+    pool: &'a PgPool,
+    lightning: &'a dyn LightningClient,
+}
+
+impl<'a> OfferService<'a> {
+    /// Creates a new offer service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    /// * 'lightning' - Node that answers invoice requests for the offers
+    pub fn new(pool: &'a PgPool, lightning: &'a dyn LightningClient) -> Self {
+        Self { pool, lightning }
+    }
+
+    /// Creates an offer on the node that credits the caller's account.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'invoices:write' scope
+    /// - Validation failures
+    /// - Accounts that cannot receive
+    /// - Node backends without BOLT12 support
+    pub async fn create_offer(
+        &self,
+        auth: &AuthUser,
+        create_offer: CreateOffer,
+    ) -> ServiceResult<Offer> {
+        auth.require_scope(ApiKeyScope::InvoicesWrite)?;
+
+        if let Err(validation_errors) = create_offer.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        AccountRepository::new(self.pool)
+            .get_accoount_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|account| account.is_active)
+            .ok_or_else(|| ServiceError::invalid_operation("Account is not able to receive"))?;
+
+        let encoded_offer = self
+            .lightning
+            .create_offer(create_offer.amount_msat, &create_offer.description)
+            .await?;
+
+        let offer_id = offer_id(&encoded_offer)?;
+
+        let offer = sqlx::query_as!(
+            Offer,
+            r#"
+            INSERT INTO offers (
+                id,
+                user_id,
+                account_id,
+                offer,
+                offer_id,
+                description,
+                amount_msat
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                offer as "offer!",
+                offer_id as "offer_id!",
+                description as "description!",
+                amount_msat as "amount_msat?",
+                status as "status!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            auth.user_id(),
+            auth.account_id(),
+            encoded_offer,
+            offer_id,
+            create_offer.description,
+            create_offer.amount_msat.map(BigDecimal::from)
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(offer)
+    }
+
+    /// Lists the caller's offers.
+    pub async fn list_offers(&self, auth: &AuthUser) -> ServiceResult<Vec<Offer>> {
+        auth.require_scope(ApiKeyScope::InvoicesRead)?;
+
+        OfferRepository::new(self.pool)
+            .get_offers_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })
+    }
+
+    /// Disables one of the caller's offers on the node so it can no longer be paid.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError::NotFound' if the caller has no active offer with that id
+    pub async fn disable_offer(&self, auth: &AuthUser, id: &str) -> ServiceResult<()> {
+        auth.require_scope(ApiKeyScope::InvoicesWrite)?;
+
+        let offer = OfferRepository::new(self.pool)
+            .get_offers_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .into_iter()
+            .find(|offer| offer.id == id && offer.status == "active")
+            .ok_or_else(|| ServiceError::not_found("Offer", id))?;
+
+        self.lightning.disable_offer(&offer.offer_id).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE offers
+            SET status = 'disabled',
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            offer.id
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+}
+
+/// Hex encoded ID of a bech32 `lno1...` offer, as reported on the invoices
+/// the node issues for it
+fn offer_id(encoded_offer: &str) -> ServiceResult<String> {
+    let offer = lightning::offers::offer::Offer::from_str(encoded_offer).map_err(|e| {
+        ServiceError::InternalError {
+            message: format!("Node returned an invalid offer: {e:?}"),
+        }
+    })?;
+
+    Ok(hex::encode(offer.id().0))
+}
@@ -0,0 +1,886 @@
+// On-chain Withdrawal Service Logic
+//! Withdrawals to on-chain addresses, paid out in batches to cut fees.
+//!
+//! A withdrawal reserves its amount plus a fee estimate for its tier and
+//! waits in a queue. Every batch interval the queue of each tier is paid out
+//! in a single transaction. Once that confirms, each withdrawal is charged
+//! an even share of the transaction fee, never more than it reserved, and
+//! the rest of the reserve goes back to the balance.
+//!
+//! A batch is recorded, under a label its transaction carries in the node
+//! wallet, before it is sent, so a send whose outcome was lost is found
+//! again rather than paid a second time. A batch stuck below the market rate
+//! is replaced by a higher-fee version paid out of its change (RBF), and the
+//! batch follows the new txid.
+
+use crate::Config;
+use crate::common::common::PaginationFilter;
+use crate::db::models::{
+    ApiKeyScope, CreateOnchainWithdrawal, FeeTier, OnchainBatch, OnchainWithdrawal,
+};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::onchain_withdrawal_repository::OnchainWithdrawalRepository;
+use crate::service::event_service::EventService;
+use crate::service::node_service::LightningClient;
+use crate::service::payment_service::debit_account;
+use crate::utilities::auth::AuthUser;
+use bigdecimal::ToPrimitive;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, Network};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Virtual size budgeted per withdrawal when reserving its fee: its own
+/// output plus a share of the inputs and change of a typical batch
+const WITHDRAWAL_VBYTES: u64 = 150;
+
+/// How often broadcast batches are checked for confirmation
+const BATCH_POLL_SECONDS: u64 = 60;
+
+/// Each bump raises the fee rate by at least a quarter
+const BUMP_RATE_PERCENT: u64 = 125;
+
+/// How long a batch may stay sending before its send is taken to have
+/// finished one way or the other
+const SENDING_RECOVER_SECONDS: f64 = 600.0;
+
+/// Label of the transaction paying a batch, which finds it in the node wallet
+fn batch_label(batch_id: &str) -> String {
+    format!("moya batch {batch_id}")
+}
+
+// Service layer for On-chain Withdrawal related Operation
+pub struct OnchainWithdrawalService<'a> {
+    pool: &'a PgPool,
+    lightning: &'a dyn LightningClient,
+}
+
+impl<'a> OnchainWithdrawalService<'a> {
+    /// Creates a new on-chain withdrawal service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    /// * 'lightning' - Node whose wallet pays the withdrawals
+    pub fn new(pool: &'a PgPool, lightning: &'a dyn LightningClient) -> Self {
+        Self { pool, lightning }
+    }
+
+    fn load_config() -> ServiceResult<Config> {
+        Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })
+    }
+
+    /// Queues a withdrawal and reserves its amount and fee from the caller's account.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'payments:send' scope or over their spend limit
+    /// - Validation failures, including addresses for another network
+    /// - Insufficient balance for the amount and fee reserve
+    pub async fn create_withdrawal(
+        &self,
+        auth: &AuthUser,
+        create_withdrawal: CreateOnchainWithdrawal,
+    ) -> ServiceResult<OnchainWithdrawal> {
+        auth.require_scope(ApiKeyScope::PaymentsSend)?;
+
+        if let Err(validation_errors) = create_withdrawal.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let config = Self::load_config()?;
+
+        let address = parse_address(
+            "address",
+            &create_withdrawal.address,
+            config.bitcoin_network,
+        )?
+        .to_string();
+
+        if create_withdrawal.amount_sat < config.onchain_min_withdrawal_sat {
+            return Err(ServiceError::validation(format!(
+                "amount_sat: Amount must be at least {} sat",
+                config.onchain_min_withdrawal_sat
+            )));
+        }
+
+        let fee_tier = create_withdrawal.fee_tier;
+        let sat_per_vbyte = self
+            .lightning
+            .estimate_fee_rate(fee_tier.conf_target())
+            .await?;
+
+        let too_large = || ServiceError::validation("amount_sat: Amount is too large");
+        let amount_msat = create_withdrawal
+            .amount_sat
+            .checked_mul(1000)
+            .ok_or_else(too_large)?;
+        let fee_reserve_msat = sat_per_vbyte
+            .checked_mul(WITHDRAWAL_VBYTES * 1000)
+            .ok_or_else(|| ServiceError::ExternalService {
+                message: format!(
+                    "Node estimated an implausible fee rate of {sat_per_vbyte} sat/vB"
+                ),
+            })?;
+        let reserved_msat = amount_msat
+            .checked_add(fee_reserve_msat)
+            .ok_or_else(too_large)?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        debit_account(&mut tx, auth, reserved_msat).await?;
+
+        let withdrawal_id = Uuid::now_v7().to_string();
+        let transaction_id = Uuid::now_v7().to_string();
+
+        // On-chain withdrawals have no payment hash, the withdrawal id
+        // identifies them
+        sqlx::query!(
+            r#"
+            INSERT INTO transactions (
+                id,
+                user_id,
+                account_id,
+                direction,
+                invoice,
+                amount,
+                payment_hash,
+                payment_status
+            )
+            VALUES ($1, $2, $3, 'outgoing', $4, $5, $6, 'pending')
+            "#,
+            transaction_id,
+            auth.user_id(),
+            auth.account_id(),
+            address,
+            BigDecimal::from(amount_msat),
+            withdrawal_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let withdrawal = sqlx::query_as!(
+            OnchainWithdrawal,
+            r#"
+            INSERT INTO onchain_withdrawals (
+                id,
+                user_id,
+                account_id,
+                transaction_id,
+                address,
+                amount_msat,
+                fee_tier,
+                fee_reserve_msat
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                transaction_id as "transaction_id!",
+                batch_id as "batch_id?",
+                NULL::TEXT as "txid?",
+                address as "address!",
+                amount_msat as "amount_msat!",
+                fee_tier as "fee_tier!",
+                fee_reserve_msat as "fee_reserve_msat!",
+                fee_msat as "fee_msat?",
+                status as "status!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            withdrawal_id,
+            auth.user_id(),
+            auth.account_id(),
+            transaction_id,
+            address,
+            BigDecimal::from(amount_msat),
+            fee_tier.as_str(),
+            BigDecimal::from(fee_reserve_msat)
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        EventService::payment_made(&mut tx, &transaction_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!(
+            "On-chain withdrawal {} of {} sat queued for the {} batch",
+            withdrawal.id,
+            create_withdrawal.amount_sat,
+            fee_tier.as_str()
+        );
+
+        Ok(withdrawal)
+    }
+
+    /// Lists the caller's on-chain withdrawals, newest first, with their total count.
+    pub async fn list_withdrawals(
+        &self,
+        auth: &AuthUser,
+        filter: &PaginationFilter,
+    ) -> ServiceResult<(Vec<OnchainWithdrawal>, u64)> {
+        auth.require_scope(ApiKeyScope::PaymentsRead)?;
+
+        let withdrawal_repo = OnchainWithdrawalRepository::new(self.pool);
+
+        let withdrawals = withdrawal_repo
+            .get_withdrawals_by_user_id(auth.user_id(), filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = withdrawal_repo
+            .count_withdrawals_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        Ok((withdrawals, total))
+    }
+
+    /// Cancels one of the caller's withdrawals that has not been batched yet
+    /// and returns its amount and fee reserve to the balance.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError::NotFound' if the caller has no queued withdrawal with that id
+    pub async fn cancel_withdrawal(&self, auth: &AuthUser, id: &str) -> ServiceResult<()> {
+        auth.require_scope(ApiKeyScope::PaymentsSend)?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let cancelled = sqlx::query!(
+            r#"
+            UPDATE onchain_withdrawals
+            SET status = 'cancelled',
+                updated_at = now()
+            WHERE id = $1
+              AND user_id = $2
+              AND status = 'queued'
+            RETURNING
+                account_id,
+                transaction_id,
+                amount_msat + fee_reserve_msat AS "released_msat!"
+            "#,
+            id,
+            auth.user_id()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        .ok_or_else(|| ServiceError::not_found("Queued withdrawal", id))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET balance = balance + $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            cancelled.account_id,
+            cancelled.released_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE transactions
+            SET payment_status = 'failed',
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            cancelled.transaction_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        EventService::payment_updated(&mut tx, &cancelled.transaction_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!(
+            "On-chain withdrawal {} cancelled, released {} msat",
+            id,
+            cancelled.released_msat
+        );
+
+        Ok(())
+    }
+
+    /// Pays out the queue of every fee tier, one transaction per tier.
+    ///
+    /// A tier that fails is logged and retried on the next run.
+    pub async fn send_batches(&self) {
+        for fee_tier in FeeTier::ALL {
+            if let Err(error) = self.send_batch(fee_tier).await {
+                tracing::warn!(
+                    "Sending the {} on-chain batch failed: {}",
+                    fee_tier.as_str(),
+                    error
+                );
+            }
+        }
+    }
+
+    /// Pays out the queued withdrawals of one fee tier in a single transaction.
+    ///
+    /// The batch is written, and its withdrawals taken off the queue so they
+    /// can no longer be cancelled, before anything is sent. The node labels
+    /// the transaction after the batch, so if the outcome of the send is lost
+    /// the batch can be found in the wallet again instead of being paid twice.
+    /// The withdrawals only go back on the queue when the node refused to send.
+    async fn send_batch(&self, fee_tier: FeeTier) -> ServiceResult<()> {
+        let sat_per_vbyte = self
+            .lightning
+            .estimate_fee_rate(fee_tier.conf_target())
+            .await?;
+        let broadcast_height = self.lightning.get_block_height().await?;
+
+        let batch_id = Uuid::now_v7().to_string();
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO onchain_batches (id, fee_tier, sat_per_vbyte, status, broadcast_height)
+            VALUES ($1, $2, $3, 'sending', $4)
+            "#,
+            batch_id,
+            fee_tier.as_str(),
+            sat_per_vbyte as i64,
+            broadcast_height as i32
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let claimed = sqlx::query!(
+            r#"
+            UPDATE onchain_withdrawals
+            SET status = 'sending',
+                batch_id = $2,
+                updated_at = now()
+            WHERE fee_tier = $1
+              AND status = 'queued'
+            RETURNING id, address, amount_msat
+            "#,
+            fee_tier.as_str(),
+            batch_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if claimed.is_empty() {
+            // Dropping the transaction discards the empty batch
+            return Ok(());
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        // Withdrawals to the same address share one output
+        let mut outputs = BTreeMap::new();
+        for withdrawal in &claimed {
+            let amount_sat = withdrawal.amount_msat.to_u64().unwrap_or(0) / 1000;
+            *outputs.entry(withdrawal.address.clone()).or_insert(0) += amount_sat;
+        }
+
+        match self
+            .lightning
+            .send_batch(&outputs, sat_per_vbyte, &batch_label(&batch_id))
+            .await
+        {
+            Ok(txid) => {
+                self.mark_batch_broadcast(&batch_id, &txid).await?;
+
+                tracing::info!(
+                    "On-chain batch {} broadcast as {} paying {} withdrawals at {} sat/vB",
+                    batch_id,
+                    txid,
+                    claimed.len(),
+                    sat_per_vbyte
+                );
+
+                Ok(())
+            }
+            Err(error @ LightningError::WalletError(_)) => {
+                self.requeue_batch(&batch_id).await?;
+
+                Err(error.into())
+            }
+            Err(error) => {
+                // The node may still have sent it, so the batch is left
+                // sending until recover_sending_batches looks for its label
+                tracing::warn!(
+                    "Lost the outcome of sending on-chain batch {}: {}",
+                    batch_id,
+                    error
+                );
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the transaction a batch was sent in.
+    async fn mark_batch_broadcast(&self, batch_id: &str, txid: &str) -> ServiceResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let marked = sqlx::query!(
+            r#"
+            UPDATE onchain_batches
+            SET status = 'broadcast',
+                txid = $2,
+                updated_at = now()
+            WHERE id = $1
+              AND status = 'sending'
+            "#,
+            batch_id,
+            txid
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if marked.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE onchain_withdrawals
+            SET status = 'broadcast',
+                updated_at = now()
+            WHERE batch_id = $1
+              AND status = 'sending'
+            "#,
+            batch_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Gives up on a batch that was never sent and puts its withdrawals back
+    /// on the queue for the next one.
+    async fn requeue_batch(&self, batch_id: &str) -> ServiceResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let failed = sqlx::query!(
+            r#"
+            UPDATE onchain_batches
+            SET status = 'failed',
+                updated_at = now()
+            WHERE id = $1
+              AND status = 'sending'
+            "#,
+            batch_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if failed.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE onchain_withdrawals
+            SET status = 'queued',
+                batch_id = NULL,
+                updated_at = now()
+            WHERE batch_id = $1
+              AND status = 'sending'
+            "#,
+            batch_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Settles batches whose send outcome was lost, such as when the bank
+    /// stopped mid-send.
+    ///
+    /// Once a batch has waited long enough for any send in flight to have
+    /// finished, it is marked broadcast if its label is in the wallet and
+    /// requeued if not. Backends that keep no labels leave it for an operator.
+    async fn recover_sending_batches(&self) -> ServiceResult<()> {
+        let batches = sqlx::query!(
+            r#"
+            SELECT id, broadcast_height
+            FROM onchain_batches
+            WHERE status = 'sending'
+              AND created_at < now() - make_interval(secs => $1)
+            ORDER BY created_at ASC
+            "#,
+            SENDING_RECOVER_SECONDS
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        for batch in batches {
+            match self
+                .lightning
+                .find_labeled_transaction(&batch_label(&batch.id), batch.broadcast_height as u32)
+                .await
+            {
+                Ok(Some(txid)) => {
+                    self.mark_batch_broadcast(&batch.id, &txid).await?;
+                    tracing::info!(
+                        "On-chain batch {} found in the wallet as {}",
+                        batch.id,
+                        txid
+                    );
+                }
+                Ok(None) => {
+                    self.requeue_batch(&batch.id).await?;
+                    tracing::info!(
+                        "On-chain batch {} was never sent, its withdrawals are queued again",
+                        batch.id
+                    );
+                }
+                Err(error) => {
+                    tracing::warn!(
+                        "On-chain batch {} is stuck sending and needs review: {}",
+                        batch.id,
+                        error
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Follows broadcast batches until they confirm, replacing those that
+    /// have waited too long with a higher-fee transaction (RBF).
+    ///
+    /// Until one of them confirms, a batch could still be mined in any of the
+    /// transactions it was replaced by, so all of them are watched.
+    pub async fn track_batches(&self) -> ServiceResult<()> {
+        let config = Self::load_config()?;
+
+        self.recover_sending_batches().await?;
+
+        let batches = OnchainWithdrawalRepository::new(self.pool)
+            .get_batches_in_flight()
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        if batches.is_empty() {
+            return Ok(());
+        }
+
+        let height = self.lightning.get_block_height().await?;
+
+        for batch in batches {
+            let Some(txid) = batch.txid.clone() else {
+                continue;
+            };
+
+            let mut confirmed = None;
+            let mut in_wallet = false;
+            for candidate in std::iter::once(&txid).chain(batch.replaced_txids.iter()) {
+                if let Some(wallet_transaction) = self
+                    .lightning
+                    .get_wallet_transaction(candidate, batch.broadcast_height as u32)
+                    .await?
+                {
+                    if wallet_transaction.confirmations > 0 {
+                        confirmed = Some((candidate.clone(), wallet_transaction.fee_sat));
+                        break;
+                    }
+                    in_wallet |= *candidate == txid;
+                }
+            }
+
+            if let Some((confirmed_txid, fee_sat)) = confirmed {
+                self.confirm_batch(&batch, &confirmed_txid, fee_sat).await?;
+                continue;
+            }
+
+            if !in_wallet {
+                tracing::warn!(
+                    "On-chain batch {} ({}) is missing from the node wallet",
+                    batch.id,
+                    txid
+                );
+                continue;
+            }
+
+            let waiting_since = batch.bumped_height.unwrap_or(batch.broadcast_height) as u32;
+            if height < waiting_since + config.onchain_bump_after_blocks {
+                continue;
+            }
+
+            let market_rate = self
+                .lightning
+                .estimate_fee_rate(FeeTier::Fast.conf_target())
+                .await?;
+            let sat_per_vbyte =
+                market_rate.max((batch.sat_per_vbyte as u64 * BUMP_RATE_PERCENT).div_ceil(100));
+
+            let replacement_txid = match self
+                .lightning
+                .bump_fee(&txid, sat_per_vbyte, &batch_label(&batch.id))
+                .await
+            {
+                Ok(replacement_txid) => replacement_txid,
+                Err(error) => {
+                    tracing::warn!("On-chain batch {} could not be bumped: {}", batch.id, error);
+                    continue;
+                }
+            };
+
+            sqlx::query!(
+                r#"
+                UPDATE onchain_batches
+                SET txid = $2,
+                    replaced_txids = array_append(replaced_txids, txid),
+                    sat_per_vbyte = $3,
+                    bumped_height = $4,
+                    bump_count = bump_count + 1,
+                    updated_at = now()
+                WHERE id = $1
+                "#,
+                batch.id,
+                replacement_txid,
+                sat_per_vbyte as i64,
+                height as i32
+            )
+            .execute(self.pool)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+            tracing::info!(
+                "On-chain batch {} replaced by {} at {} sat/vB after {} blocks",
+                batch.id,
+                replacement_txid,
+                sat_per_vbyte,
+                height - waiting_since
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Settles the withdrawals of a batch confirmed in `txid`, charging each
+    /// an even share of its fee and releasing what is left of its reserve.
+    ///
+    /// A replacement pays the whole fee of the transaction it replaces and
+    /// more, so the fee of the confirmed transaction covers any bumps.
+    async fn confirm_batch(
+        &self,
+        batch: &OnchainBatch,
+        txid: &str,
+        fee_sat: u64,
+    ) -> ServiceResult<()> {
+        let withdrawals = OnchainWithdrawalRepository::new(self.pool)
+            .get_withdrawals_by_batch_id(&batch.id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let confirmed = sqlx::query!(
+            r#"
+            UPDATE onchain_batches
+            SET status = 'confirmed',
+                txid = $3,
+                fee_sat = $2,
+                confirmed_at = now(),
+                updated_at = now()
+            WHERE id = $1
+              AND status = 'broadcast'
+            "#,
+            batch.id,
+            BigDecimal::from(fee_sat),
+            txid
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if confirmed.rows_affected() == 0 || withdrawals.is_empty() {
+            return Ok(());
+        }
+
+        let fee_share_msat = BigDecimal::from((fee_sat * 1000).div_ceil(withdrawals.len() as u64));
+
+        for withdrawal in &withdrawals {
+            let fee_msat = (&fee_share_msat).min(&withdrawal.fee_reserve_msat).clone();
+            let released_msat = &withdrawal.fee_reserve_msat - &fee_msat;
+
+            sqlx::query!(
+                r#"
+                UPDATE onchain_withdrawals
+                SET status = 'confirmed',
+                    fee_msat = $2,
+                    updated_at = now()
+                WHERE id = $1
+                "#,
+                withdrawal.id,
+                fee_msat
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+            sqlx::query!(
+                r#"
+                UPDATE accounts
+                SET balance = balance + $2,
+                    updated_at = now()
+                WHERE id = $1
+                "#,
+                withdrawal.account_id,
+                released_msat
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+            sqlx::query!(
+                r#"
+                UPDATE transactions
+                SET payment_status = 'succeeded',
+                    fee_msat = $2,
+                    updated_at = now()
+                WHERE id = $1
+                "#,
+                withdrawal.transaction_id,
+                fee_msat
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+            EventService::payment_updated(&mut tx, &withdrawal.transaction_id).await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!(
+            "On-chain batch {} confirmed in {} with a fee of {} sat",
+            batch.id,
+            txid,
+            fee_sat
+        );
+
+        Ok(())
+    }
+}
+
+/// Parses the address given in `field`, making sure it belongs to `network`.
+pub fn parse_address(field: &str, address: &str, network: Network) -> ServiceResult<Address> {
+    address
+        .trim()
+        .parse::<Address<NetworkUnchecked>>()
+        .map_err(|e| ServiceError::validation(format!("{field}: Invalid address: {e}")))?
+        .require_network(network)
+        .map_err(|_| {
+            ServiceError::validation(format!("{field}: Address is not for the {network} network"))
+        })
+}
+
+/// Sends out queued on-chain withdrawals every batch interval.
+pub async fn run_onchain_batcher(
+    pool: PgPool,
+    lightning: Arc<dyn LightningClient>,
+    interval_seconds: u64,
+) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_seconds));
+
+    loop {
+        interval.tick().await;
+
+        OnchainWithdrawalService::new(&pool, lightning.as_ref())
+            .send_batches()
+            .await;
+    }
+}
+
+/// Keeps broadcast batches moving until they confirm.
+pub async fn run_onchain_batch_tracker(pool: PgPool, lightning: Arc<dyn LightningClient>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(BATCH_POLL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = OnchainWithdrawalService::new(&pool, lightning.as_ref())
+            .track_batches()
+            .await
+        {
+            tracing::warn!("Tracking on-chain batches failed: {}", error);
+        }
+    }
+}
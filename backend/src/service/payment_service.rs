@@ -0,0 +1,2062 @@
+// Payment Service Logic
+//! Pays BOLT11 invoices, BOLT12 offers, LNURL-pay links and Lightning
+//! Addresses from a user's account balance.
+//!
+//! Invoices issued by this bank are settled on the ledger directly; all
+//! others are paid through the node with the amount plus a routing fee
+//! reserve held from the balance until the outcome is known. LNURL-withdraw
+//! links pay the same way, out of the reservation they hold instead.
+
+use crate::Config;
+use crate::db::models::{
+    ApiKeyScope, DecodePayment, DecodedPayment, Invoice, Keysend, LnurlPayInvoice, LnurlPayRequest,
+    PayInvoice, PaymentReceipt, RoutingOptions, WithdrawLink,
+};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::invoice_repository::InvoiceRepository;
+use crate::repositories::role_repository::RoleRepository;
+use crate::repositories::transaction_repository::TransactionRepository;
+use crate::service::event_service::EventService;
+use crate::service::invoice_service::INVOICE_SOURCE_HOLD;
+use crate::service::node_service::{KEYSEND_PREIMAGE_RECORD_TYPE, LightningClient};
+use crate::utilities::auth::AuthUser;
+use crate::utilities::lnurl::{
+    decode_lnurl, ensure_public_url, ensure_secure_url, lightning_address_url,
+    metadata_description, resolve_success_action,
+};
+use crate::utilities::network::{PublicResolver, public_redirect_policy};
+use crate::utilities::{KeysendRequest, PaymentOutcome, RoutingPolicy};
+use bigdecimal::ToPrimitive;
+use bitcoin::secp256k1::PublicKey;
+use chrono::DateTime;
+use lightning::offers::offer::{Amount, Offer};
+use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription};
+use rand::RngCore;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use sqlx::types::BigDecimal;
+use sqlx::{PgConnection, PgPool};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+use validator::Validate;
+
+/// How long to wait on a remote LNURL service
+const LNURL_HTTP_TIMEOUT_SECONDS: u64 = 10;
+
+/// Most redirects followed when talking to an LNURL service
+const LNURL_MAX_REDIRECTS: usize = 10;
+
+/// Longest a caller may let the node search for a route
+const MAX_PAYMENT_TIMEOUT_SECONDS: u64 = 3600;
+
+/// How often payments with an unknown outcome are looked up on the node
+const PAYMENT_TRACK_INTERVAL_SECONDS: u64 = 30;
+
+/// How long to wait on a payment still in flight before moving on to the next
+const PAYMENT_TRACK_WAIT_SECONDS: u64 = 5;
+
+/// Payments younger than this are left to the request making them
+const PAYMENT_TRACK_AFTER_SECONDS: f64 = 60.0;
+
+/// Age after which a payment the node has no record of is failed
+const PAYMENT_UNKNOWN_FAIL_SECONDS: f64 = 600.0;
+
+/// Most pending payments looked up per run
+const PAYMENT_TRACK_BATCH_SIZE: i64 = 100;
+
+/// Routing fee budget for a payment of `amount_msat` when the caller sets none
+pub fn default_fee_limit_msat(amount_msat: u64, config: &Config) -> u64 {
+    fee_share_msat(amount_msat, config.payment_fee_limit_percent)
+        .max(config.payment_fee_limit_floor_msat)
+}
+
+/// What a payment holds while in flight: the amount plus the most its
+/// routing may cost
+fn reserved_msat(amount_msat: u64, policy: &RoutingPolicy) -> ServiceResult<u64> {
+    amount_msat
+        .checked_add(policy.fee_limit_msat)
+        .ok_or_else(|| ServiceError::validation("amount_msat: Amount is too large"))
+}
+
+/// `percent` of `amount_msat`, rounded down
+fn fee_share_msat(amount_msat: u64, percent: f64) -> u64 {
+    (amount_msat as f64 * percent / 100.0) as u64
+}
+
+/// Where the money for a payment comes from
+pub enum FundingSource<'a> {
+    /// The caller's account balance
+    Account(&'a AuthUser),
+    /// The reservation held by an LNURL-withdraw link
+    WithdrawLink(&'a WithdrawLink),
+}
+
+impl FundingSource<'_> {
+    fn user_id(&self) -> &str {
+        match self {
+            FundingSource::Account(auth) => auth.user_id(),
+            FundingSource::WithdrawLink(link) => &link.user_id,
+        }
+    }
+
+    fn account_id(&self) -> &str {
+        match self {
+            FundingSource::Account(auth) => auth.account_id(),
+            FundingSource::WithdrawLink(link) => &link.account_id,
+        }
+    }
+
+    fn reserve(&self) -> PaymentReserve {
+        match self {
+            FundingSource::Account(auth) => PaymentReserve {
+                account_id: auth.account_id().to_string(),
+                api_key_id: auth.api_key.as_ref().map(|api_key| api_key.id.clone()),
+                withdraw_link_id: None,
+            },
+            FundingSource::WithdrawLink(link) => PaymentReserve {
+                account_id: link.account_id.clone(),
+                api_key_id: None,
+                withdraw_link_id: Some(link.id.clone()),
+            },
+        }
+    }
+}
+
+/// Where the reserve of a payment goes back to once it completes, kept on
+/// the ledger entry so the payment tracker can finish it later
+struct PaymentReserve {
+    account_id: String,
+    /// API key whose spend limit the payment counted against
+    api_key_id: Option<String>,
+    /// Withdraw link the payment was made out of
+    withdraw_link_id: Option<String>,
+}
+
+// Service layer for Payment related Operation
+pub struct PaymentService<'a> {
+    pool: &'a PgPool,
+    lightning: &'a dyn LightningClient,
+}
+
+impl<'a> PaymentService<'a> {
+    /// Creates a new payment service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    /// * 'lightning' - Node outgoing payments are made from
+    pub fn new(pool: &'a PgPool, lightning: &'a dyn LightningClient) -> Self {
+        Self { pool, lightning }
+    }
+
+    /// Pays a BOLT11 invoice, BOLT12 offer, `lnurl1...` pay link or Lightning Address.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'payments:send' scope or over their spend limit
+    /// - Validation failures and invoices that do not match the LNURL request
+    /// - Insufficient balance
+    /// - Payments the node reports as failed
+    pub async fn pay(
+        &self,
+        auth: &AuthUser,
+        pay_invoice: PayInvoice,
+    ) -> ServiceResult<PaymentReceipt> {
+        auth.require_scope(ApiKeyScope::PaymentsSend)?;
+
+        if let Err(validation_errors) = pay_invoice.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })?;
+
+        let destination = strip_lightning_scheme(&pay_invoice.destination);
+
+        if destination.to_lowercase().starts_with("lnurl1") {
+            let url = decode_lnurl(destination)?;
+            self.pay_lnurl(
+                auth,
+                url,
+                pay_invoice.amount_msat,
+                pay_invoice.comment,
+                &pay_invoice.routing,
+                &config,
+            )
+            .await
+        } else if destination.to_lowercase().starts_with("lno1") {
+            self.pay_offer(
+                &FundingSource::Account(auth),
+                destination,
+                pay_invoice.amount_msat,
+                pay_invoice.comment,
+                &pay_invoice.routing,
+            )
+            .await
+        } else if destination.contains('@') {
+            let url = lightning_address_url(destination, config.lnurl_allow_http)?;
+            self.pay_lnurl(
+                auth,
+                url,
+                pay_invoice.amount_msat,
+                pay_invoice.comment,
+                &pay_invoice.routing,
+                &config,
+            )
+            .await
+        } else {
+            self.pay_bolt11(
+                &FundingSource::Account(auth),
+                destination,
+                pay_invoice.amount_msat,
+                &pay_invoice.routing,
+            )
+            .await
+        }
+    }
+
+    /// Previews what paying a destination would look like, without paying.
+    ///
+    /// Problems that would make the payment fail, such as an expired or
+    /// already paid invoice, are reported as warnings rather than errors.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'payments:read' scope
+    /// - Destinations that cannot be parsed
+    /// - LNURL services that cannot be reached
+    pub async fn decode(
+        &self,
+        auth: &AuthUser,
+        decode_payment: DecodePayment,
+    ) -> ServiceResult<DecodedPayment> {
+        auth.require_scope(ApiKeyScope::PaymentsRead)?;
+
+        if let Err(validation_errors) = decode_payment.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })?;
+
+        let destination = strip_lightning_scheme(&decode_payment.destination);
+
+        let mut decoded = if destination.to_lowercase().starts_with("lnurl1") {
+            let url = decode_lnurl(destination)?;
+            self.decode_lnurl("lnurl_pay", url, decode_payment.amount_msat, &config)
+                .await?
+        } else if destination.to_lowercase().starts_with("lno1") {
+            decode_offer(destination, decode_payment.amount_msat)?
+        } else if destination.contains('@') {
+            let url = lightning_address_url(destination, config.lnurl_allow_http)?;
+            self.decode_lnurl(
+                "lightning_address",
+                url,
+                decode_payment.amount_msat,
+                &config,
+            )
+            .await?
+        } else {
+            self.decode_bolt11(auth, destination, decode_payment.amount_msat)
+                .await?
+        };
+
+        // Internal payments move funds between accounts and never route
+        if let Some(amount_msat) = decoded.amount_msat {
+            let fee_limit_msat = if decoded.internal {
+                0
+            } else {
+                self.routing_policy(
+                    &FundingSource::Account(auth),
+                    amount_msat,
+                    &decode_payment.routing,
+                )
+                .await?
+                .fee_limit_msat
+            };
+
+            if decoded
+                .fee_estimate_msat
+                .is_some_and(|fee_estimate| fee_estimate > fee_limit_msat)
+            {
+                decoded.warnings.push(
+                    "Estimated routing fee is above the fee limit, the payment may fail"
+                        .to_string(),
+                );
+            }
+            decoded.fee_limit_msat = Some(fee_limit_msat);
+        }
+
+        Ok(decoded)
+    }
+
+    /// Previews a BOLT11 invoice, estimating the routing fee with the node.
+    async fn decode_bolt11(
+        &self,
+        auth: &AuthUser,
+        payment_request: &str,
+        amount_msat: Option<u64>,
+    ) -> ServiceResult<DecodedPayment> {
+        let bolt11 = Bolt11Invoice::from_str(payment_request)
+            .map_err(|e| ServiceError::validation(format!("destination: Invalid invoice: {e}")))?;
+
+        let payment_hash = bolt11.payment_hash().to_string();
+        let mut warnings = Vec::new();
+
+        let (description, description_hash) = match bolt11.description() {
+            Bolt11InvoiceDescription::Direct(description) => (Some(description.to_string()), None),
+            Bolt11InvoiceDescription::Hash(hash) => (None, Some(hash.0.to_string())),
+        };
+
+        let expired = bolt11.is_expired();
+        if expired {
+            warnings.push("Invoice has expired".to_string());
+        }
+
+        let amount_msat = match (bolt11.amount_milli_satoshis(), amount_msat) {
+            (Some(invoice_amount), Some(amount)) if invoice_amount != amount => {
+                warnings.push("Amount does not match the invoice".to_string());
+                Some(invoice_amount)
+            }
+            (Some(invoice_amount), _) => Some(invoice_amount),
+            (None, Some(amount)) => Some(amount),
+            (None, None) => {
+                warnings.push("Amount is required for invoices without an amount".to_string());
+                None
+            }
+        };
+
+        let internal_invoice = InvoiceRepository::new(self.pool)
+            .get_invoice_by_payment_hash(&payment_hash)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        let outgoing_payment = TransactionRepository::new(self.pool)
+            .get_outgoing_payment_by_payment_hash(&payment_hash)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        let internal_status = internal_invoice
+            .as_ref()
+            .map(|invoice| invoice.status.as_str());
+
+        match (outgoing_payment, internal_status) {
+            (Some(payment), _) if payment.payment_status == "pending" => {
+                warnings.push("A payment for this invoice is already in flight".to_string());
+            }
+            (Some(_), _) | (None, Some("settled")) => {
+                warnings.push("Invoice has already been paid".to_string());
+            }
+            (None, Some(status)) if status != "open" => {
+                warnings.push("Invoice is no longer payable".to_string());
+            }
+            _ => {}
+        }
+
+        if internal_invoice
+            .as_ref()
+            .is_some_and(|invoice| invoice.user_id == auth.user_id())
+        {
+            warnings.push("Cannot pay your own invoice".to_string());
+        }
+
+        if internal_invoice
+            .as_ref()
+            .is_some_and(|invoice| invoice.source == INVOICE_SOURCE_HOLD)
+        {
+            warnings.push(
+                "Hold invoices issued by this bank must be paid from another wallet".to_string(),
+            );
+        }
+
+        let internal = internal_invoice.is_some();
+        let fee_estimate_msat = match amount_msat {
+            Some(_) if internal => Some(0),
+            Some(amount) if !expired => {
+                match self
+                    .lightning
+                    .estimate_route_fee(payment_request, amount)
+                    .await
+                {
+                    Ok(fee_msat) => Some(fee_msat),
+                    Err(LightningError::NotFound(_)) => {
+                        warnings.push(
+                            "No route to the payee was found, the payment may fail".to_string(),
+                        );
+                        None
+                    }
+                    Err(error) => {
+                        tracing::warn!("Fee estimate for {} failed: {}", payment_hash, error);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        Ok(DecodedPayment {
+            kind: "bolt11".to_string(),
+            amount_msat,
+            description,
+            description_hash,
+            payee: Some(bolt11.recover_payee_pub_key().to_string()),
+            payment_hash: Some(payment_hash),
+            expires_at: bolt11
+                .expires_at()
+                .and_then(|expires_at| DateTime::from_timestamp(expires_at.as_secs() as i64, 0)),
+            internal,
+            fee_estimate_msat,
+            warnings,
+            ..Default::default()
+        })
+    }
+
+    /// Previews an LNURL-pay link or Lightning Address from its pay request.
+    ///
+    /// No invoice is requested, so the routing fee cannot be estimated yet.
+    async fn decode_lnurl(
+        &self,
+        kind: &str,
+        url: Url,
+        amount_msat: Option<u64>,
+        config: &Config,
+    ) -> ServiceResult<DecodedPayment> {
+        ensure_secure_url(&url, config.lnurl_allow_http)?;
+        ensure_public_url(&url, config.lnurl_allow_private_networks).await?;
+
+        let payee = url.host_str().map(str::to_lowercase);
+        let client = lnurl_client(config.lnurl_allow_private_networks)?;
+        let pay_request = fetch_pay_request(&client, url).await?;
+
+        let mut warnings = Vec::new();
+        let amount_msat = amount_msat.or((pay_request.min_sendable == pay_request.max_sendable)
+            .then_some(pay_request.min_sendable));
+
+        if amount_msat.is_some_and(|amount| {
+            amount < pay_request.min_sendable || amount > pay_request.max_sendable
+        }) {
+            warnings.push(format!(
+                "Amount must be between {} and {} msat",
+                pay_request.min_sendable, pay_request.max_sendable
+            ));
+        }
+
+        // Our own Lightning Addresses hand out invoices that settle internally
+        let internal = payee.as_deref().is_some_and(|host| {
+            host.eq_ignore_ascii_case(&config.lnurl_domain)
+                || Url::parse(&config.public_url)
+                    .is_ok_and(|public_url| public_url.host_str() == Some(host))
+        });
+
+        Ok(DecodedPayment {
+            kind: kind.to_string(),
+            amount_msat,
+            min_amount_msat: Some(pay_request.min_sendable),
+            max_amount_msat: Some(pay_request.max_sendable),
+            description: metadata_description(&pay_request.metadata),
+            description_hash: Some(hex::encode(Sha256::digest(pay_request.metadata.as_bytes()))),
+            payee,
+            internal,
+            comment_allowed: Some(pay_request.comment_allowed),
+            fee_estimate_msat: internal.then_some(0),
+            warnings,
+            ..Default::default()
+        })
+    }
+
+    /// Sends a keysend payment straight to a node, without an invoice.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'payments:send' scope or over their spend limit
+    /// - Invalid public keys and custom records outside the custom range
+    /// - Keysends to this bank's own node
+    /// - Insufficient balance
+    /// - Payments the node reports as failed
+    pub async fn keysend(
+        &self,
+        auth: &AuthUser,
+        keysend: Keysend,
+    ) -> ServiceResult<PaymentReceipt> {
+        auth.require_scope(ApiKeyScope::PaymentsSend)?;
+
+        if let Err(validation_errors) = keysend.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let destination = bitcoin::PublicKey::from_str(keysend.destination.trim())
+            .ok()
+            .filter(|destination| destination.compressed)
+            .ok_or_else(|| ServiceError::validation("destination: Invalid node public key"))?
+            .inner;
+
+        if destination == self.lightning.get_node_info().pubkey {
+            return Err(ServiceError::invalid_operation(
+                "Keysend to this bank's own node is not supported, pay the user's Lightning Address instead",
+            ));
+        }
+
+        let mut custom_records = BTreeMap::new();
+        for (record_type, value) in keysend.custom_records {
+            // Types below 2^16 are reserved for the protocol itself
+            if record_type < 65536 || record_type == KEYSEND_PREIMAGE_RECORD_TYPE {
+                return Err(ServiceError::validation(format!(
+                    "custom_records: Record type {record_type} is reserved"
+                )));
+            }
+
+            let value = hex::decode(&value).map_err(|_| {
+                ServiceError::validation(format!(
+                    "custom_records: Record {record_type} must be hex encoded"
+                ))
+            })?;
+            custom_records.insert(record_type, value);
+        }
+
+        let mut payment_preimage = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut payment_preimage);
+        let payment_hash = hex::encode(Sha256::digest(payment_preimage));
+
+        let source = FundingSource::Account(auth);
+        let amount_msat = keysend.amount_msat;
+        let policy = self
+            .routing_policy(&source, amount_msat, &keysend.routing)
+            .await?;
+        let reserved_msat = reserved_msat(amount_msat, &policy)?;
+
+        // Keysends have no invoice, the ledger records the destination instead
+        let transaction_id = self
+            .reserve_payment(
+                &source,
+                &destination.to_string(),
+                &payment_hash,
+                amount_msat,
+                reserved_msat,
+            )
+            .await?;
+
+        let result = self
+            .lightning
+            .keysend(
+                KeysendRequest {
+                    destination,
+                    amount_msat,
+                    payment_preimage,
+                    custom_records,
+                },
+                &policy,
+            )
+            .await;
+
+        self.complete_payment(
+            &source,
+            transaction_id,
+            payment_hash,
+            amount_msat,
+            policy.fee_limit_msat,
+            result,
+        )
+        .await
+    }
+
+    /// Pays an invoice presented to an LNURL-withdraw link out of its reservation.
+    pub async fn pay_withdraw_link(
+        &self,
+        withdraw_link: &WithdrawLink,
+        payment_request: &str,
+    ) -> ServiceResult<PaymentReceipt> {
+        self.pay_bolt11(
+            &FundingSource::WithdrawLink(withdraw_link),
+            payment_request,
+            None,
+            &RoutingOptions::default(),
+        )
+        .await
+    }
+
+    /// Resolves an LNURL-pay request, fetches an invoice for the amount and pays it.
+    async fn pay_lnurl(
+        &self,
+        auth: &AuthUser,
+        url: Url,
+        amount_msat: Option<u64>,
+        comment: Option<String>,
+        routing: &RoutingOptions,
+        config: &Config,
+    ) -> ServiceResult<PaymentReceipt> {
+        let (lnurl_invoice, amount_msat) = request_lnurl_invoice(
+            url,
+            amount_msat,
+            comment,
+            config.lnurl_allow_http,
+            config.lnurl_allow_private_networks,
+        )
+        .await?;
+
+        let mut receipt = self
+            .pay_bolt11(
+                &FundingSource::Account(auth),
+                &lnurl_invoice.pr,
+                Some(amount_msat),
+                routing,
+            )
+            .await?;
+
+        if let (Some(action), Some(preimage)) = (
+            lnurl_invoice.success_action,
+            receipt.payment_preimage.as_deref(),
+        ) {
+            // The payment has gone through, so a bad success action is only logged
+            match resolve_success_action(action, preimage) {
+                Ok(action) => receipt.success_action = Some(action),
+                Err(error) => tracing::warn!(
+                    "Ignoring success action for payment {}: {}",
+                    receipt.payment_hash,
+                    error
+                ),
+            }
+        }
+
+        Ok(receipt)
+    }
+
+    /// Pays a BOLT11 invoice from the given funding source.
+    ///
+    /// `amount_msat` must match the invoice, or for invoices without an
+    /// amount says how much to send.
+    async fn pay_bolt11(
+        &self,
+        source: &FundingSource<'_>,
+        payment_request: &str,
+        amount_msat: Option<u64>,
+        routing: &RoutingOptions,
+    ) -> ServiceResult<PaymentReceipt> {
+        let bolt11 = Bolt11Invoice::from_str(payment_request)
+            .map_err(|e| ServiceError::validation(format!("destination: Invalid invoice: {e}")))?;
+
+        let amount_msat = match (bolt11.amount_milli_satoshis(), amount_msat) {
+            (Some(invoice_amount), Some(amount)) if invoice_amount != amount => {
+                return Err(ServiceError::validation(
+                    "amount_msat: Amount does not match the invoice",
+                ));
+            }
+            (Some(invoice_amount), _) => invoice_amount,
+            (None, Some(amount)) if amount > 0 => amount,
+            (None, _) => {
+                return Err(ServiceError::validation(
+                    "amount_msat: Amount is required for invoices without an amount",
+                ));
+            }
+        };
+
+        if bolt11.is_expired() {
+            return Err(ServiceError::validation("destination: Invoice has expired"));
+        }
+
+        let payment_hash = bolt11.payment_hash().to_string();
+
+        let internal_invoice = InvoiceRepository::new(self.pool)
+            .get_invoice_by_payment_hash(&payment_hash)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        if let Some(invoice) = internal_invoice {
+            return self.pay_internal(source, &invoice, amount_msat).await;
+        }
+
+        let policy = self.routing_policy(source, amount_msat, routing).await?;
+        let reserved_msat = reserved_msat(amount_msat, &policy)?;
+
+        let transaction_id = self
+            .reserve_payment(
+                source,
+                payment_request,
+                &payment_hash,
+                amount_msat,
+                reserved_msat,
+            )
+            .await?;
+
+        let result = self
+            .lightning
+            .pay_invoice(
+                payment_request,
+                bolt11
+                    .amount_milli_satoshis()
+                    .is_none()
+                    .then_some(amount_msat),
+                &policy,
+            )
+            .await;
+
+        self.complete_payment(
+            source,
+            transaction_id,
+            payment_hash,
+            amount_msat,
+            policy.fee_limit_msat,
+            result,
+        )
+        .await
+    }
+
+    /// Pays a BOLT12 offer with an invoice fetched from the payee's node.
+    async fn pay_offer(
+        &self,
+        source: &FundingSource<'_>,
+        encoded_offer: &str,
+        amount_msat: Option<u64>,
+        payer_note: Option<String>,
+        routing: &RoutingOptions,
+    ) -> ServiceResult<PaymentReceipt> {
+        let offer = Offer::from_str(encoded_offer)
+            .map_err(|e| ServiceError::validation(format!("destination: Invalid offer: {e:?}")))?;
+
+        if offer.is_expired() {
+            return Err(ServiceError::validation("destination: Offer has expired"));
+        }
+
+        let amount_msat = match (offer.amount(), amount_msat) {
+            (Some(Amount::Bitcoin { amount_msats }), None) => *amount_msats,
+            (Some(Amount::Bitcoin { amount_msats }), Some(amount)) if amount >= *amount_msats => {
+                amount
+            }
+            (Some(Amount::Bitcoin { .. }), Some(_)) => {
+                return Err(ServiceError::validation(
+                    "amount_msat: Amount is below the price of the offer",
+                ));
+            }
+            (Some(Amount::Currency { .. }), _) => {
+                return Err(ServiceError::validation(
+                    "destination: Offers priced in a fiat currency are not supported",
+                ));
+            }
+            (None, Some(amount)) => amount,
+            (None, None) => {
+                return Err(ServiceError::validation(
+                    "amount_msat: Amount is required for this offer",
+                ));
+            }
+        };
+
+        let offer_invoice = self
+            .lightning
+            .fetch_offer_invoice(encoded_offer, amount_msat, payer_note)
+            .await?;
+
+        if offer_invoice.amount_msat != amount_msat {
+            return Err(ServiceError::ExternalService {
+                message: "Invoice for the offer does not match the requested amount".to_string(),
+            });
+        }
+
+        let policy = self.routing_policy(source, amount_msat, routing).await?;
+        let reserved_msat = reserved_msat(amount_msat, &policy)?;
+
+        let transaction_id = self
+            .reserve_payment(
+                source,
+                &offer_invoice.invoice,
+                &offer_invoice.payment_hash,
+                amount_msat,
+                reserved_msat,
+            )
+            .await?;
+
+        let result = self
+            .lightning
+            .pay_offer_invoice(&offer_invoice.invoice, &policy)
+            .await;
+
+        self.complete_payment(
+            source,
+            transaction_id,
+            offer_invoice.payment_hash,
+            amount_msat,
+            policy.fee_limit_msat,
+            result,
+        )
+        .await
+    }
+
+    /// Works out the limits the node routes a payment of `amount_msat` under.
+    ///
+    /// The caller's fee limits fall back to the configured default and are
+    /// capped by the ceiling on the role of the user paying.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Fee percentages outside 0 to 100
+    /// - Timeouts outside 1 second to an hour
+    /// - Last hop public keys that cannot be parsed
+    async fn routing_policy(
+        &self,
+        source: &FundingSource<'_>,
+        amount_msat: u64,
+        routing: &RoutingOptions,
+    ) -> ServiceResult<RoutingPolicy> {
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })?;
+
+        if routing
+            .max_fee_percent
+            .is_some_and(|percent| !(0.0..=100.0).contains(&percent))
+        {
+            return Err(ServiceError::validation(
+                "max_fee_percent: Must be between 0 and 100",
+            ));
+        }
+
+        let timeout_seconds = routing
+            .timeout_seconds
+            .unwrap_or(config.payment_timeout_seconds);
+        if !(1..=MAX_PAYMENT_TIMEOUT_SECONDS).contains(&timeout_seconds) {
+            return Err(ServiceError::validation(format!(
+                "timeout_seconds: Must be between 1 and {MAX_PAYMENT_TIMEOUT_SECONDS}"
+            )));
+        }
+
+        let last_hop_pubkey = routing
+            .last_hop_pubkey
+            .as_deref()
+            .map(|pubkey| PublicKey::from_str(pubkey.trim()))
+            .transpose()
+            .map_err(|_| ServiceError::validation("last_hop_pubkey: Invalid node public key"))?;
+
+        // The tighter of the caller's limits wins
+        let requested_msat = [
+            routing.max_fee_msat,
+            routing
+                .max_fee_percent
+                .map(|percent| fee_share_msat(amount_msat, percent)),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or_else(|| default_fee_limit_msat(amount_msat, &config));
+
+        let role = RoleRepository::new(self.pool)
+            .get_role_by_user_id(source.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        let ceiling_msat = role.and_then(|role| {
+            [
+                role.max_fee_msat.as_ref().and_then(ToPrimitive::to_u64),
+                role.max_fee_percent
+                    .map(|percent| fee_share_msat(amount_msat, percent)),
+            ]
+            .into_iter()
+            .flatten()
+            .min()
+        });
+
+        Ok(RoutingPolicy {
+            fee_limit_msat: ceiling_msat
+                .map_or(requested_msat, |ceiling| requested_msat.min(ceiling)),
+            timeout_seconds,
+            outgoing_chan_ids: routing.outgoing_chan_ids.clone(),
+            last_hop_pubkey,
+        })
+    }
+
+    /// Settles the ledger for a reserved payment once the node has answered.
+    async fn complete_payment(
+        &self,
+        source: &FundingSource<'_>,
+        transaction_id: String,
+        payment_hash: String,
+        amount_msat: u64,
+        fee_limit_msat: u64,
+        result: Result<PaymentOutcome, LightningError>,
+    ) -> ServiceResult<PaymentReceipt> {
+        match result {
+            Ok(outcome) => {
+                let unused_msat = fee_limit_msat.saturating_sub(outcome.fee_msat);
+                self.finish_payment(
+                    &source.reserve(),
+                    &transaction_id,
+                    None,
+                    outcome.fee_msat,
+                    unused_msat,
+                )
+                .await?;
+
+                Ok(PaymentReceipt {
+                    transaction_id,
+                    payment_hash,
+                    payment_preimage: Some(outcome.payment_preimage),
+                    amount_msat,
+                    fee_msat: outcome.fee_msat,
+                    status: "succeeded".to_string(),
+                    success_action: None,
+                })
+            }
+            Err(LightningError::PaymentError(reason)) => {
+                self.finish_payment(
+                    &source.reserve(),
+                    &transaction_id,
+                    Some(amount_msat),
+                    0,
+                    amount_msat + fee_limit_msat,
+                )
+                .await?;
+                Err(ServiceError::invalid_operation(format!(
+                    "Payment failed: {reason}"
+                )))
+            }
+            Err(error) => {
+                // The payment may still complete, so the reserve stays held
+                // until the payment tracker learns the outcome from the node
+                tracing::error!("Outcome of payment {} unknown: {}", payment_hash, error);
+
+                Ok(PaymentReceipt {
+                    transaction_id,
+                    payment_hash,
+                    payment_preimage: None,
+                    amount_msat,
+                    fee_msat: 0,
+                    status: "pending".to_string(),
+                    success_action: None,
+                })
+            }
+        }
+    }
+
+    /// Finishes payments whose outcome was unknown when they were made,
+    /// once the node knows it, handing back what is left of their reserve.
+    pub async fn track_pending_payments(&self) -> ServiceResult<()> {
+        let payments = sqlx::query!(
+            r#"
+            SELECT
+                id,
+                payment_hash,
+                amount,
+                fee_reserve_msat as "fee_reserve_msat!",
+                account_id as "account_id!",
+                api_key_id,
+                withdraw_link_id,
+                created_at < now() - make_interval(secs => $2) as "abandoned!"
+            FROM transactions
+            WHERE direction = 'outgoing'
+              AND payment_status = 'pending'
+              AND fee_reserve_msat IS NOT NULL
+              AND created_at < now() - make_interval(secs => $1)
+            ORDER BY created_at
+            LIMIT $3
+            "#,
+            PAYMENT_TRACK_AFTER_SECONDS,
+            PAYMENT_UNKNOWN_FAIL_SECONDS,
+            PAYMENT_TRACK_BATCH_SIZE
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        for payment in payments {
+            let (Some(amount_msat), Some(fee_reserve_msat)) =
+                (payment.amount.to_u64(), payment.fee_reserve_msat.to_u64())
+            else {
+                tracing::error!("Payment {} has an invalid amount", payment.id);
+                continue;
+            };
+
+            let reserve = PaymentReserve {
+                account_id: payment.account_id,
+                api_key_id: payment.api_key_id,
+                withdraw_link_id: payment.withdraw_link_id,
+            };
+
+            // Payments still in flight are looked at again on the next run
+            let Ok(result) = tokio::time::timeout(
+                Duration::from_secs(PAYMENT_TRACK_WAIT_SECONDS),
+                self.lightning.track_payment(&payment.payment_hash),
+            )
+            .await
+            else {
+                continue;
+            };
+
+            match result {
+                Ok(outcome) => {
+                    self.finish_payment(
+                        &reserve,
+                        &payment.id,
+                        None,
+                        outcome.fee_msat,
+                        fee_reserve_msat.saturating_sub(outcome.fee_msat),
+                    )
+                    .await?;
+                    tracing::info!("Pending payment {} succeeded", payment.payment_hash);
+                }
+                Err(LightningError::PaymentError(reason)) => {
+                    self.finish_payment(
+                        &reserve,
+                        &payment.id,
+                        Some(amount_msat),
+                        0,
+                        amount_msat + fee_reserve_msat,
+                    )
+                    .await?;
+                    tracing::info!(
+                        "Pending payment {} failed: {}",
+                        payment.payment_hash,
+                        reason
+                    );
+                }
+                // The request never reached the node, so nothing was sent
+                Err(LightningError::NotFound(_)) if payment.abandoned => {
+                    self.finish_payment(
+                        &reserve,
+                        &payment.id,
+                        Some(amount_msat),
+                        0,
+                        amount_msat + fee_reserve_msat,
+                    )
+                    .await?;
+                    tracing::warn!(
+                        "Pending payment {} is unknown to the node, marked failed",
+                        payment.payment_hash
+                    );
+                }
+                Err(error) => {
+                    tracing::warn!(
+                        "Could not track payment {}: {}",
+                        payment.payment_hash,
+                        error
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Settles an invoice issued by this bank by moving funds between accounts.
+    async fn pay_internal(
+        &self,
+        source: &FundingSource<'_>,
+        invoice: &Invoice,
+        amount_msat: u64,
+    ) -> ServiceResult<PaymentReceipt> {
+        if invoice.user_id == source.user_id() {
+            return Err(ServiceError::invalid_operation(
+                "Cannot pay your own invoice",
+            ));
+        }
+
+        // Holding the payment needs the node, which cannot pay itself
+        if invoice.source == INVOICE_SOURCE_HOLD {
+            return Err(ServiceError::invalid_operation(
+                "Hold invoices issued by this bank must be paid from another wallet",
+            ));
+        }
+
+        // Fetched up front so the payer gets the same proof of payment as
+        // for a payment over the network
+        let preimage = self
+            .lightning
+            .get_invoice_details(&invoice.payment_hash)
+            .await?
+            .payment_preimage;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let locked = sqlx::query!(
+            r#"
+            SELECT id
+            FROM invoices
+            WHERE id = $1
+              AND status = 'open'
+              AND expires_at > now()
+            FOR UPDATE
+            "#,
+            invoice.id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if locked.is_none() {
+            return Err(ServiceError::invalid_operation(
+                "Invoice is no longer payable",
+            ));
+        }
+
+        debit(&mut tx, source, amount_msat, amount_msat).await?;
+
+        let amount = BigDecimal::from(amount_msat);
+
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET balance = balance + $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            invoice.account_id,
+            amount
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE invoices
+            SET status = 'settled',
+                preimage = $2,
+                amount_paid_msat = $3,
+                settled_at = now(),
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            invoice.id,
+            preimage,
+            amount
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let transaction_id = Uuid::now_v7().to_string();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO transactions (
+                id,
+                user_id,
+                account_id,
+                direction,
+                invoice,
+                amount,
+                payment_hash,
+                payment_status
+            )
+            VALUES
+                ($1, $2, $3, 'outgoing', $4, $5, $6, 'succeeded'),
+                ($7, $8, $9, 'incoming', $4, $5, $6, 'settled')
+            "#,
+            transaction_id,
+            source.user_id(),
+            source.account_id(),
+            invoice.payment_request,
+            amount,
+            invoice.payment_hash,
+            Uuid::now_v7().to_string(),
+            invoice.user_id,
+            invoice.account_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(map_duplicate_payment)?;
+
+        EventService::payment_made(&mut tx, &transaction_id).await?;
+        EventService::invoice_paid(&mut tx, &invoice.payment_hash).await?;
+
+        // The node would still accept the invoice from another wallet, and
+        // the settlement watcher would find it already settled and credit
+        // nobody. Canceling fails if a payment got there first, which rolls
+        // this one back.
+        if let Err(error) = self
+            .lightning
+            .cancel_hold_invoice(&invoice.payment_hash)
+            .await
+        {
+            tracing::warn!(
+                "Could not cancel invoice {} on the node: {}",
+                invoice.payment_hash,
+                error
+            );
+            return Err(ServiceError::invalid_operation(
+                "Invoice is no longer payable",
+            ));
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!("Invoice {} settled internally", invoice.payment_hash);
+
+        Ok(PaymentReceipt {
+            transaction_id,
+            payment_hash: invoice.payment_hash.clone(),
+            payment_preimage: Some(preimage).filter(|preimage| !preimage.is_empty()),
+            amount_msat,
+            fee_msat: 0,
+            status: "succeeded".to_string(),
+            success_action: None,
+        })
+    }
+
+    /// Holds the amount plus fee reserve and records the pending payment.
+    async fn reserve_payment(
+        &self,
+        source: &FundingSource<'_>,
+        payment_request: &str,
+        payment_hash: &str,
+        amount_msat: u64,
+        reserved_msat: u64,
+    ) -> ServiceResult<String> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        debit(&mut tx, source, amount_msat, reserved_msat).await?;
+
+        let transaction_id = Uuid::now_v7().to_string();
+        let reserve = source.reserve();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO transactions (
+                id,
+                user_id,
+                account_id,
+                direction,
+                invoice,
+                amount,
+                payment_hash,
+                payment_status,
+                fee_reserve_msat,
+                api_key_id,
+                withdraw_link_id
+            )
+            VALUES ($1, $2, $3, 'outgoing', $4, $5, $6, 'pending', $7, $8, $9)
+            "#,
+            transaction_id,
+            source.user_id(),
+            source.account_id(),
+            payment_request,
+            BigDecimal::from(amount_msat),
+            payment_hash,
+            BigDecimal::from(reserved_msat - amount_msat),
+            reserve.api_key_id,
+            reserve.withdraw_link_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(map_duplicate_payment)?;
+
+        EventService::payment_made(&mut tx, &transaction_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(transaction_id)
+    }
+
+    /// Records the outcome and routing fee of a payment and returns
+    /// `refund_msat` of the reserve.
+    ///
+    /// `failed_amount_msat` is set when the payment failed, so a withdraw
+    /// link gets the use and amount back as well.
+    async fn finish_payment(
+        &self,
+        reserve: &PaymentReserve,
+        transaction_id: &str,
+        failed_amount_msat: Option<u64>,
+        fee_msat: u64,
+        refund_msat: u64,
+    ) -> ServiceResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let status = if failed_amount_msat.is_some() {
+            "failed"
+        } else {
+            "succeeded"
+        };
+
+        // The payment tracker and the request that made the payment may both
+        // learn the outcome; only the first one hands back the reserve
+        let finished = sqlx::query!(
+            r#"
+            UPDATE transactions
+            SET payment_status = $2,
+                fee_msat = $3,
+                updated_at = now()
+            WHERE id = $1
+              AND payment_status = 'pending'
+            RETURNING id
+            "#,
+            transaction_id,
+            status,
+            BigDecimal::from(fee_msat)
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if finished.is_none() {
+            return Ok(());
+        }
+
+        let refund = BigDecimal::from(refund_msat);
+
+        // Money only goes back to the balance directly when paying from it,
+        // or when the withdraw link has already released its reservation
+        let credit_balance = match &reserve.withdraw_link_id {
+            None => {
+                if let Some(api_key_id) = &reserve.api_key_id {
+                    sqlx::query!(
+                        r#"
+                        UPDATE api_keys
+                        SET spent_msat = GREATEST(spent_msat - $2, 0),
+                            updated_at = now()
+                        WHERE id = $1
+                        "#,
+                        api_key_id,
+                        refund
+                    )
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| ServiceError::Database { source: e.into() })?;
+                }
+                true
+            }
+            Some(withdraw_link_id) => {
+                let released = sqlx::query!(
+                    r#"
+                    UPDATE withdraw_links
+                    SET spent_msat = spent_msat - $2,
+                        withdrawn_msat = withdrawn_msat - $3,
+                        uses = uses - $4,
+                        updated_at = now()
+                    WHERE id = $1
+                    RETURNING status
+                    "#,
+                    withdraw_link_id,
+                    refund,
+                    BigDecimal::from(failed_amount_msat.unwrap_or_default()),
+                    i32::from(failed_amount_msat.is_some())
+                )
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+                released.status != "active"
+            }
+        };
+
+        if credit_balance && refund_msat > 0 {
+            sqlx::query!(
+                r#"
+                UPDATE accounts
+                SET balance = balance + $2,
+                    updated_at = now()
+                WHERE id = $1
+                "#,
+                reserve.account_id,
+                refund
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+        }
+
+        EventService::payment_updated(&mut tx, transaction_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+}
+
+/// Takes `reserved_msat` (the amount plus any fee reserve) from the funding
+/// source.
+///
+/// For an account this counts against the spend limit of the API key in
+/// use; for a withdraw link it uses up one use and `amount_msat` of its cap.
+/// Either way the account and its owner must not be frozen.
+async fn debit(
+    conn: &mut PgConnection,
+    source: &FundingSource<'_>,
+    amount_msat: u64,
+    reserved_msat: u64,
+) -> ServiceResult<()> {
+    match source {
+        FundingSource::Account(auth) => debit_account(conn, auth, reserved_msat).await,
+        FundingSource::WithdrawLink(withdraw_link) => {
+            let debited = sqlx::query!(
+                r#"
+                UPDATE withdraw_links
+                SET uses = uses + 1,
+                    withdrawn_msat = withdrawn_msat + $2,
+                    spent_msat = spent_msat + $3,
+                    updated_at = now()
+                WHERE id = $1
+                  AND status = 'active'
+                  AND expires_at > now()
+                  AND uses < max_uses
+                  AND withdrawn_msat + $2 <= total_msat
+                  AND spent_msat + $3 <= reserved_msat
+                  AND EXISTS (
+                    SELECT 1
+                    FROM accounts a
+                    JOIN users u ON u.id = a.user_id
+                    WHERE a.id = withdraw_links.account_id
+                      AND a.is_active = true
+                      AND a.is_deleted = false
+                      AND u.is_active = true
+                  )
+                "#,
+                withdraw_link.id,
+                BigDecimal::from(amount_msat),
+                BigDecimal::from(reserved_msat)
+            )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+            if debited.rows_affected() == 0 {
+                if is_frozen(conn, &withdraw_link.account_id).await? {
+                    return Err(ServiceError::forbidden("Account is frozen"));
+                }
+
+                return Err(ServiceError::invalid_operation(
+                    "Withdraw link cannot cover this amount",
+                ));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Whether the account, or the user owning it, has been frozen.
+async fn is_frozen(conn: &mut PgConnection, account_id: &str) -> ServiceResult<bool> {
+    let frozen = sqlx::query_scalar!(
+        r#"
+        SELECT NOT (a.is_active AND u.is_active) as "frozen!"
+        FROM accounts a
+        JOIN users u ON u.id = a.user_id
+        WHERE a.id = $1
+        "#,
+        account_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| ServiceError::Database { source: e.into() })?
+    .unwrap_or(false);
+
+    Ok(frozen)
+}
+
+/// Takes `amount_msat` from the caller's account, counting it against the
+/// spend limit of the API key in use.
+pub async fn debit_account(
+    conn: &mut PgConnection,
+    auth: &AuthUser,
+    amount_msat: u64,
+) -> ServiceResult<()> {
+    let amount = BigDecimal::from(amount_msat);
+
+    let debited = sqlx::query!(
+        r#"
+        UPDATE accounts
+        SET balance = balance - $2,
+            updated_at = now()
+        WHERE id = $1
+          AND is_active = true
+          AND is_deleted = false
+          AND balance >= $2
+          AND EXISTS (
+            SELECT 1
+            FROM users
+            WHERE users.id = accounts.user_id
+              AND users.is_active = true
+          )
+        "#,
+        auth.account_id(),
+        amount
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+    if debited.rows_affected() == 0 {
+        // Frozen users and accounts get told so rather than sent to top up
+        if is_frozen(conn, auth.account_id()).await? {
+            return Err(ServiceError::forbidden("Account is frozen"));
+        }
+
+        return Err(ServiceError::invalid_operation("Insufficient balance"));
+    }
+
+    if let Some(api_key) = &auth.api_key {
+        let charged = sqlx::query!(
+            r#"
+            UPDATE api_keys
+            SET spent_msat = spent_msat + $2,
+                updated_at = now()
+            WHERE id = $1
+              AND (spend_limit_msat IS NULL OR spent_msat + $2 <= spend_limit_msat)
+            "#,
+            api_key.id,
+            amount
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if charged.rows_affected() == 0 {
+            return Err(ServiceError::forbidden("API key spend limit exceeded"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Accepts destinations with or without a `lightning:` URI scheme
+fn strip_lightning_scheme(destination: &str) -> &str {
+    let destination = destination.trim();
+    destination
+        .get(..10)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("lightning:"))
+        .map_or(destination, |_| &destination[10..])
+}
+
+/// Previews a BOLT12 offer. Paying one needs an invoice from the payee's
+/// node, so there is no fee estimate.
+fn decode_offer(encoded_offer: &str, amount_msat: Option<u64>) -> ServiceResult<DecodedPayment> {
+    let offer = Offer::from_str(encoded_offer)
+        .map_err(|e| ServiceError::validation(format!("destination: Invalid offer: {e:?}")))?;
+
+    let mut warnings = Vec::new();
+    if offer.is_expired() {
+        warnings.push("Offer has expired".to_string());
+    }
+
+    let (amount_msat, min_amount_msat) = match offer.amount() {
+        Some(Amount::Bitcoin { amount_msats }) => {
+            if amount_msat.is_some_and(|amount| amount < *amount_msats) {
+                warnings.push("Amount is below the price of the offer".to_string());
+            }
+            (
+                Some(amount_msat.unwrap_or(*amount_msats).max(*amount_msats)),
+                Some(*amount_msats),
+            )
+        }
+        Some(Amount::Currency { .. }) => {
+            warnings.push("Offers priced in a fiat currency are not supported".to_string());
+            (None, None)
+        }
+        None => (amount_msat, None),
+    };
+
+    Ok(DecodedPayment {
+        kind: "bolt12_offer".to_string(),
+        amount_msat,
+        min_amount_msat,
+        description: offer
+            .description()
+            .map(|description| description.to_string()),
+        payee: offer.signing_pubkey().map(|pubkey| pubkey.to_string()),
+        expires_at: offer
+            .absolute_expiry()
+            .and_then(|expires_at| DateTime::from_timestamp(expires_at.as_secs() as i64, 0)),
+        warnings,
+        ..Default::default()
+    })
+}
+
+/// HTTP client for talking to remote LNURL services. Unless
+/// `allow_private_networks` is set, it only connects and redirects to
+/// public addresses.
+fn lnurl_client(allow_private_networks: bool) -> ServiceResult<reqwest::Client> {
+    let builder =
+        reqwest::Client::builder().timeout(Duration::from_secs(LNURL_HTTP_TIMEOUT_SECONDS));
+
+    let builder = if allow_private_networks {
+        builder
+    } else {
+        builder
+            .dns_resolver(Arc::new(PublicResolver))
+            .redirect(public_redirect_policy(LNURL_MAX_REDIRECTS))
+    };
+
+    builder.build().map_err(|e| ServiceError::InternalError {
+        message: e.to_string(),
+    })
+}
+
+/// Asks an LNURL-pay service for an invoice and checks it is for exactly
+/// `amount_msat` (or the fixed amount of the service) and commits to the
+/// metadata the payer was shown.
+///
+/// # Returns
+/// The invoice and the amount it is for
+async fn request_lnurl_invoice(
+    url: Url,
+    amount_msat: Option<u64>,
+    comment: Option<String>,
+    allow_http: bool,
+    allow_private_networks: bool,
+) -> ServiceResult<(LnurlPayInvoice, u64)> {
+    ensure_secure_url(&url, allow_http)?;
+    ensure_public_url(&url, allow_private_networks).await?;
+
+    let client = lnurl_client(allow_private_networks)?;
+    let pay_request = fetch_pay_request(&client, url).await?;
+
+    let amount_msat = amount_msat
+        .or((pay_request.min_sendable == pay_request.max_sendable)
+            .then_some(pay_request.min_sendable))
+        .ok_or_else(|| {
+            ServiceError::validation("amount_msat: Amount is required for this destination")
+        })?;
+
+    if amount_msat < pay_request.min_sendable || amount_msat > pay_request.max_sendable {
+        return Err(ServiceError::validation(format!(
+            "amount_msat: Amount must be between {} and {} msat",
+            pay_request.min_sendable, pay_request.max_sendable
+        )));
+    }
+
+    let comment = comment.filter(|comment| !comment.is_empty());
+    if comment
+        .as_ref()
+        .is_some_and(|comment| comment.chars().count() > pay_request.comment_allowed)
+    {
+        return Err(ServiceError::validation(format!(
+            "comment: Recipient accepts comments of at most {} characters",
+            pay_request.comment_allowed
+        )));
+    }
+
+    let mut callback =
+        Url::parse(&pay_request.callback).map_err(|e| ServiceError::ExternalService {
+            message: format!("LNURL service returned an invalid callback: {e}"),
+        })?;
+    ensure_secure_url(&callback, allow_http)?;
+    ensure_public_url(&callback, allow_private_networks).await?;
+    callback
+        .query_pairs_mut()
+        .append_pair("amount", &amount_msat.to_string());
+    if let Some(comment) = &comment {
+        callback.query_pairs_mut().append_pair("comment", comment);
+    }
+
+    let lnurl_invoice: LnurlPayInvoice = fetch_lnurl(&client, callback).await?;
+
+    // The invoice must be for exactly what was asked and commit to the
+    // metadata the payer was shown
+    let bolt11 =
+        Bolt11Invoice::from_str(&lnurl_invoice.pr).map_err(|e| ServiceError::ExternalService {
+            message: format!("LNURL service returned an invalid invoice: {e}"),
+        })?;
+
+    if bolt11.amount_milli_satoshis() != Some(amount_msat) {
+        return Err(ServiceError::ExternalService {
+            message: "LNURL service returned an invoice for a different amount".to_string(),
+        });
+    }
+
+    let metadata_hash = hex::encode(Sha256::digest(pay_request.metadata.as_bytes()));
+    match bolt11.description() {
+        Bolt11InvoiceDescription::Hash(hash) if hash.0.to_string() == metadata_hash => {}
+        _ => {
+            return Err(ServiceError::ExternalService {
+                message: "LNURL invoice description hash does not match the metadata".to_string(),
+            });
+        }
+    }
+
+    Ok((lnurl_invoice, amount_msat))
+}
+
+/// Fetches the LUD-06 pay request behind an LNURL-pay link or Lightning Address.
+async fn fetch_pay_request(client: &reqwest::Client, url: Url) -> ServiceResult<LnurlPayRequest> {
+    let pay_request: LnurlPayRequest = fetch_lnurl(client, url).await?;
+    if pay_request.tag != "payRequest" {
+        return Err(ServiceError::validation(
+            "destination: LNURL is not a pay request",
+        ));
+    }
+
+    Ok(pay_request)
+}
+
+/// A payment hash can only be paid once
+fn map_duplicate_payment(error: sqlx::Error) -> ServiceError {
+    match &error {
+        sqlx::Error::Database(db_error) if db_error.is_unique_violation() => {
+            ServiceError::invalid_operation("Invoice has already been paid")
+        }
+        _ => ServiceError::Database {
+            source: error.into(),
+        },
+    }
+}
+
+/// Fetches a JSON document from an LNURL service, surfacing its error status.
+async fn fetch_lnurl<T: DeserializeOwned>(client: &reqwest::Client, url: Url) -> ServiceResult<T> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| ServiceError::ExternalService {
+            message: format!("LNURL service unreachable: {e}"),
+        })?;
+
+    // Errors usually come with a non-2xx status, but the body says why
+    let http_status = response.status();
+    let body: serde_json::Value =
+        response
+            .json()
+            .await
+            .map_err(|e| ServiceError::ExternalService {
+                message: if http_status.is_success() {
+                    format!("LNURL service returned invalid JSON: {e}")
+                } else {
+                    format!("LNURL service responded with {http_status}")
+                },
+            })?;
+
+    if body
+        .get("status")
+        .and_then(|status| status.as_str())
+        .is_some_and(|status| status.eq_ignore_ascii_case("ERROR"))
+    {
+        let reason = body
+            .get("reason")
+            .and_then(|reason| reason.as_str())
+            .unwrap_or("unknown error");
+        return Err(ServiceError::ExternalService {
+            message: format!("LNURL service error: {reason}"),
+        });
+    }
+
+    if !http_status.is_success() {
+        return Err(ServiceError::ExternalService {
+            message: format!("LNURL service responded with {http_status}"),
+        });
+    }
+
+    serde_json::from_value(body).map_err(|e| ServiceError::ExternalService {
+        message: format!("LNURL service returned an unexpected response: {e}"),
+    })
+}
+
+/// Keeps finishing payments whose outcome was unknown when they were made.
+pub async fn run_payment_tracker(pool: PgPool, lightning: Arc<dyn LightningClient>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(PAYMENT_TRACK_INTERVAL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = PaymentService::new(&pool, lightning.as_ref())
+            .track_pending_payments()
+            .await
+        {
+            tracing::warn!("Tracking pending payments failed: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::CreateWithdrawLink;
+    use crate::service::withdraw_service::WithdrawService;
+    use crate::utilities::testing::{configure, customer};
+    use axum::extract::{Query, State};
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use bitcoin_030::hashes::{Hash, sha256};
+    use bitcoin_030::secp256k1::{Secp256k1, SecretKey};
+    use lightning_invoice::{Currency, InvoiceBuilder, PaymentSecret};
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+
+    const METADATA: &str = r#"[["text/plain","Pay alice"],["text/identifier","alice@localhost"]]"#;
+
+    /// How the stand-in service answers the invoice callback
+    #[derive(Clone, Copy)]
+    enum Callback {
+        Honest,
+        /// Returns an invoice for this many msat more than was asked
+        ExtraAmount(u64),
+        /// Commits to other metadata than the payer was shown
+        OtherMetadata,
+        Error,
+    }
+
+    fn signed_invoice(amount_msat: u64, description: &str) -> String {
+        let key = SecretKey::from_slice(&[42u8; 32]).unwrap();
+
+        InvoiceBuilder::new(Currency::Bitcoin)
+            .description_hash(sha256::Hash::hash(description.as_bytes()))
+            .payment_hash(sha256::Hash::hash(b"preimage"))
+            .payment_secret(PaymentSecret([1u8; 32]))
+            .amount_milli_satoshis(amount_msat)
+            .current_timestamp()
+            .min_final_cltv_expiry_delta(144)
+            .build_signed(|hash| Secp256k1::new().sign_ecdsa_recoverable(hash, &key))
+            .unwrap()
+            .to_string()
+    }
+
+    /// Starts a local LNURL-pay service for `alice`, returning its base URL
+    async fn stand_in_service(callback: Callback) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let pay_request = json!({
+            "tag": "payRequest",
+            "callback": format!("{base_url}/callback"),
+            "minSendable": 1_000,
+            "maxSendable": 1_000_000,
+            "metadata": METADATA,
+            "commentAllowed": 20,
+        });
+
+        let app = Router::new()
+            .route(
+                "/.well-known/lnurlp/alice",
+                get(move || async move { Json(pay_request) }),
+            )
+            .route(
+                "/callback",
+                get(
+                    |State(callback): State<Callback>,
+                     Query(params): Query<HashMap<String, String>>| async move {
+                        let amount_msat: u64 = params["amount"].parse().unwrap();
+                        let pr = match callback {
+                            Callback::Honest => signed_invoice(amount_msat, METADATA),
+                            Callback::ExtraAmount(extra) => {
+                                signed_invoice(amount_msat + extra, METADATA)
+                            }
+                            Callback::OtherMetadata => {
+                                signed_invoice(amount_msat, r#"[["text/plain","Pay mallory"]]"#)
+                            }
+                            Callback::Error => {
+                                return Json::<Value>(
+                                    json!({ "status": "ERROR", "reason": "Amount too low" }),
+                                );
+                            }
+                        };
+                        Json(json!({ "pr": pr, "routes": [] }))
+                    },
+                ),
+            )
+            .with_state(callback);
+
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        base_url
+    }
+
+    async fn request_from(
+        callback: Callback,
+        amount_msat: Option<u64>,
+        comment: Option<&str>,
+    ) -> ServiceResult<(LnurlPayInvoice, u64)> {
+        let base_url = stand_in_service(callback).await;
+        let address = format!("alice@{}", base_url.trim_start_matches("http://"));
+        let url = lightning_address_url(&address, true).unwrap();
+
+        request_lnurl_invoice(url, amount_msat, comment.map(str::to_string), true, true).await
+    }
+
+    #[tokio::test]
+    async fn accepts_an_invoice_for_the_amount_and_metadata() {
+        let (invoice, amount_msat) = request_from(Callback::Honest, Some(21_000), Some("thanks"))
+            .await
+            .unwrap();
+
+        assert_eq!(amount_msat, 21_000);
+        let bolt11 = Bolt11Invoice::from_str(&invoice.pr).unwrap();
+        assert_eq!(bolt11.amount_milli_satoshis(), Some(21_000));
+    }
+
+    #[tokio::test]
+    async fn rejects_an_invoice_for_a_different_amount() {
+        let error = request_from(Callback::ExtraAmount(1_000), Some(21_000), None)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("different amount"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn rejects_an_invoice_committing_to_other_metadata() {
+        let error = request_from(Callback::OtherMetadata, Some(21_000), None)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("description hash"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn surfaces_errors_from_the_service() {
+        let error = request_from(Callback::Error, Some(21_000), None)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("Amount too low"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn checks_the_amount_and_comment_against_the_pay_request() {
+        assert!(
+            request_from(Callback::Honest, Some(500), None)
+                .await
+                .is_err()
+        );
+        assert!(
+            request_from(Callback::Honest, Some(2_000_000), None)
+                .await
+                .is_err()
+        );
+        assert!(request_from(Callback::Honest, None, None).await.is_err());
+        assert!(
+            request_from(
+                Callback::Honest,
+                Some(21_000),
+                Some("far too long a comment")
+            )
+            .await
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_plain_http_unless_allowed() {
+        let base_url = stand_in_service(Callback::Honest).await;
+        let url = Url::parse(&format!("{base_url}/.well-known/lnurlp/alice")).unwrap();
+
+        assert!(
+            request_lnurl_invoice(url, Some(21_000), None, false, true)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_services_on_private_networks_unless_allowed() {
+        let base_url = stand_in_service(Callback::Honest).await;
+        let address = format!("alice@{}", base_url.trim_start_matches("http://"));
+
+        for address in [address.as_str(), "alice@localhost"] {
+            let url = lightning_address_url(address, true).unwrap();
+            let error = request_lnurl_invoice(url, Some(21_000), None, true, false)
+                .await
+                .unwrap_err();
+
+            assert!(
+                error.to_string().contains("not a public address"),
+                "{error}"
+            );
+        }
+    }
+
+    #[sqlx::test]
+    async fn links_of_frozen_owners_cannot_be_paid_out(pool: PgPool) {
+        configure();
+        let auth = customer(&pool, 1_000_000).await;
+        let withdraw_link = WithdrawService::new(&pool)
+            .create_withdraw_link(
+                &auth,
+                CreateWithdrawLink {
+                    title: "Voucher".to_string(),
+                    min_withdrawable_msat: 1_000,
+                    max_withdrawable_msat: 10_000,
+                    uses: None,
+                    total_msat: None,
+                    expires_at: None,
+                },
+            )
+            .await
+            .unwrap()
+            .withdraw_link;
+        let source = FundingSource::WithdrawLink(&withdraw_link);
+
+        sqlx::query("UPDATE users SET is_active = false WHERE id = $1")
+            .bind(auth.user_id())
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        let refused = debit(&mut conn, &source, 10_000, 10_000).await;
+        assert!(
+            matches!(refused, Err(ServiceError::Forbidden { .. })),
+            "{refused:?}"
+        );
+
+        let uses: i32 = sqlx::query_scalar("SELECT uses FROM withdraw_links WHERE id = $1")
+            .bind(&withdraw_link.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(uses, 0);
+
+        // Paid out again once the owner is unfrozen
+        sqlx::query("UPDATE users SET is_active = true WHERE id = $1")
+            .bind(auth.user_id())
+            .execute(&pool)
+            .await
+            .unwrap();
+        debit(&mut conn, &source, 10_000, 10_000).await.unwrap();
+    }
+}
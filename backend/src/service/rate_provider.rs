@@ -0,0 +1,243 @@
+// Rate Provider Logic
+//! Exchange-rate providers, which price bitcoin in the fiat currencies
+//! accounts are shown in.
+//!
+//! `HttpRateProvider` speaks the CoinGecko simple price API; point
+//! `RATE_API_URL` at a local stand-in to run without the public service.
+//! `StaticRateProvider` serves fixed rates from a JSON file such as
+//! `{"USD": "65000", "EUR": "60000"}`, which keeps valuations predictable
+//! in tests. `RateStore` sits in front of either and caches what it fetched.
+
+use crate::Config;
+use crate::errors::RateError;
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a request to the rate provider may take
+const RATE_HTTP_TIMEOUT_SECONDS: u64 = 15;
+
+/// A unified interface for exchange-rate providers.
+#[async_trait]
+pub trait RateProvider: Send + Sync {
+    /// Short name recorded next to every rate the provider gave.
+    fn name(&self) -> &'static str;
+
+    /// Price of one bitcoin in each of `currencies`, keyed by the upper-case
+    /// currency code. Currencies the provider does not know are left out.
+    async fn get_rates(
+        &self,
+        currencies: &[String],
+    ) -> Result<HashMap<String, BigDecimal>, RateError>;
+}
+
+pub struct HttpRateProvider {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl HttpRateProvider {
+    pub fn new(base_url: &str) -> Result<Self, RateError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(RATE_HTTP_TIMEOUT_SECONDS))
+            .build()
+            .map_err(|e| RateError::ConnectionError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl RateProvider for HttpRateProvider {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    async fn get_rates(
+        &self,
+        currencies: &[String],
+    ) -> Result<HashMap<String, BigDecimal>, RateError> {
+        let vs_currencies = currencies
+            .iter()
+            .map(|currency| currency.to_lowercase())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let response = self
+            .client
+            .get(format!("{}/simple/price", self.base_url))
+            .query(&[("ids", "bitcoin"), ("vs_currencies", &vs_currencies)])
+            .send()
+            .await
+            .map_err(|e| RateError::ConnectionError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(RateError::ConnectionError(format!(
+                "responded with {}",
+                response.status()
+            )));
+        }
+
+        let mut prices: HashMap<String, HashMap<String, serde_json::Number>> = response
+            .json()
+            .await
+            .map_err(|e| RateError::Parse(e.to_string()))?;
+
+        prices
+            .remove("bitcoin")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(currency, price)| {
+                BigDecimal::from_str(&price.to_string())
+                    .map(|rate| (currency.to_uppercase(), rate))
+                    .map_err(|e| RateError::Parse(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+pub struct StaticRateProvider {
+    rates: HashMap<String, BigDecimal>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum StaticRate {
+    Text(String),
+    Number(serde_json::Number),
+}
+
+impl StaticRateProvider {
+    pub fn new(rates: HashMap<String, BigDecimal>) -> Self {
+        Self {
+            rates: rates
+                .into_iter()
+                .map(|(currency, rate)| (currency.to_uppercase(), rate))
+                .collect(),
+        }
+    }
+
+    /// Reads fixed rates from a JSON object of currency code to price,
+    /// given either as a number or a decimal string.
+    pub fn from_file(path: &str) -> Result<Self, RateError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| RateError::Parse(format!("Failed to read rate file {path}: {e}")))?;
+        let entries: HashMap<String, StaticRate> =
+            serde_json::from_str(&contents).map_err(|e| RateError::Parse(e.to_string()))?;
+
+        let rates = entries
+            .into_iter()
+            .map(|(currency, rate)| {
+                let text = match rate {
+                    StaticRate::Text(text) => text,
+                    StaticRate::Number(number) => number.to_string(),
+                };
+                match BigDecimal::from_str(&text) {
+                    Ok(rate) => Ok((currency, rate)),
+                    Err(e) => Err(RateError::Parse(format!("Rate for {currency}: {e}"))),
+                }
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(Self::new(rates))
+    }
+}
+
+#[async_trait]
+impl RateProvider for StaticRateProvider {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn get_rates(
+        &self,
+        currencies: &[String],
+    ) -> Result<HashMap<String, BigDecimal>, RateError> {
+        Ok(currencies
+            .iter()
+            .filter_map(|currency| {
+                self.rates
+                    .get(&currency.to_uppercase())
+                    .map(|rate| (currency.to_uppercase(), rate.clone()))
+            })
+            .collect())
+    }
+}
+
+/// Rates fetched from a provider, reused until they are older than the TTL
+pub struct RateStore {
+    provider: Arc<dyn RateProvider>,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (BigDecimal, Instant)>>,
+}
+
+impl RateStore {
+    pub fn new(provider: Arc<dyn RateProvider>, ttl: Duration) -> Self {
+        Self {
+            provider,
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Builds the store over the provider chosen by `RATE_PROVIDER`
+    pub fn from_config(config: &Config) -> Result<Self, RateError> {
+        let provider: Arc<dyn RateProvider> = match config.rate_file.as_deref() {
+            Some(path) if config.rate_provider == "file" => {
+                Arc::new(StaticRateProvider::from_file(path)?)
+            }
+            _ => Arc::new(HttpRateProvider::new(&config.rate_api_url)?),
+        };
+
+        Ok(Self::new(
+            provider,
+            Duration::from_secs(config.rate_cache_seconds),
+        ))
+    }
+
+    /// Name of the provider behind the store
+    pub fn source(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Price of one bitcoin in each of `currencies`, asking the provider
+    /// only for the ones not cached within the TTL.
+    pub async fn get_rates(
+        &self,
+        currencies: &[String],
+    ) -> Result<HashMap<String, BigDecimal>, RateError> {
+        let mut rates = HashMap::new();
+        let mut missing = Vec::new();
+        {
+            let cache = self.cache.lock().unwrap();
+            for currency in currencies {
+                let currency = currency.to_uppercase();
+                match cache.get(&currency) {
+                    Some((rate, fetched_at)) if fetched_at.elapsed() < self.ttl => {
+                        rates.insert(currency, rate.clone());
+                    }
+                    _ => missing.push(currency),
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let fetched = self.provider.get_rates(&missing).await?;
+            let now = Instant::now();
+            let mut cache = self.cache.lock().unwrap();
+            for (currency, rate) in fetched {
+                cache.insert(currency.clone(), (rate.clone(), now));
+                rates.insert(currency, rate);
+            }
+        }
+
+        Ok(rates)
+    }
+}
@@ -1,14 +1,19 @@
 // Role Service Logic
 //! Handles all role related activities
 
-use crate::db::models::{CreateRole, NewRole, Role};
+use crate::common::common::PaginationFilter;
+use crate::config::Config;
+use crate::db::models::{CreateRole, NewAuditEvent, NewRole, Role, RoleFeeCeiling, UpdateRole};
 use crate::errors::{ServiceError, ServiceResult};
+use crate::service::audit_service::AuditService;
+use crate::utilities::client::ClientInfo;
+use serde_json::json;
 use sqlx::PgPool;
+use sqlx::types::BigDecimal;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::repositories::role_repository::RoleRepository;
-use sqlx::types::BigDecimal;
 
 // Service layer for Role related Operation
 pub struct RoleService<'a> {
@@ -24,7 +29,12 @@ impl<'a> RoleService<'a> {
         Self { pool }
     }
 
-    pub async fn create_role(&self, create_role: CreateRole) -> ServiceResult<NewRole> {
+    pub async fn create_role(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        create_role: CreateRole,
+    ) -> ServiceResult<NewRole> {
         // validate role payload
         if let Err(validation_errors) = create_role.validate() {
             let error_messages: Vec<String> = validation_errors
@@ -75,6 +85,8 @@ impl<'a> RoleService<'a> {
               id as "id!",
               name as "name!",
               is_active as "is_active!",
+              max_fee_msat as "max_fee_msat?",
+              max_fee_percent as "max_fee_percent?",
               created_at as "created_at!: chrono::DateTime<chrono::Utc>",
               updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
               is_deleted as "is_deleted!",
@@ -88,6 +100,20 @@ impl<'a> RoleService<'a> {
         .await
         .map_err(|e| ServiceError::Database { source: e.into() })?;
 
+        AuditService::record(
+            &mut tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(actor_id.to_string()),
+                action: "role.created".to_string(),
+                target_type: "role".to_string(),
+                target_id: role.id.clone(),
+                before: None,
+                after: Some(json!(role)),
+            },
+        )
+        .await?;
+
         // Commit the transaction
         tx.commit()
             .await
@@ -96,4 +122,440 @@ impl<'a> RoleService<'a> {
         let new_role = NewRole { role: role };
         Ok(new_role)
     }
+
+    /// Lists roles one page at a time.
+    ///
+    /// # Arguments
+    /// * 'filter' - Page and page size to fetch
+    ///
+    /// # Returns
+    /// The roles on the page together with the total number of roles
+    pub async fn list_roles(&self, filter: &PaginationFilter) -> ServiceResult<(Vec<Role>, u64)> {
+        let role_repo = RoleRepository::new(self.pool);
+
+        let roles = role_repo.list_roles(filter).await?;
+        let total = role_repo.count_roles().await?;
+
+        Ok((roles, total))
+    }
+
+    /// Retrieves a single role that has not been deleted.
+    pub async fn get_role(&self, role_id: &str) -> ServiceResult<Role> {
+        let role_repo = RoleRepository::new(self.pool);
+
+        role_repo
+            .get_role_id(role_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Role", role_id))
+    }
+
+    /// Updates the editable fields of a role.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Validation failures
+    /// - Unknown or deleted roles
+    /// - Renaming the configured default and admin roles
+    /// - A name already used by another role
+    pub async fn update_role(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        role_id: &str,
+        update_role: UpdateRole,
+    ) -> ServiceResult<Role> {
+        if let Err(validation_errors) = update_role.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let role = self.get_role(role_id).await?;
+
+        let Some(name) = update_role.name.filter(|name| *name != role.name) else {
+            return Ok(role);
+        };
+        self.ensure_not_system_role(&role)?;
+
+        let role_repo = RoleRepository::new(self.pool);
+        if role_repo.role_name_taken(&name, role_id).await? {
+            return Err(ServiceError::already_exists(
+                "Role the provided name already exist",
+                &name,
+            ));
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let updated_role = sqlx::query_as!(
+            Role,
+            r#"
+            UPDATE roles
+            SET name = $2,
+                updated_at = now()
+            WHERE id = $1
+              AND is_deleted = false
+            RETURNING
+                id as "id!",
+                name as "name!",
+                is_active as "is_active!",
+                max_fee_msat as "max_fee_msat?",
+                max_fee_percent as "max_fee_percent?",
+                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: chrono::DateTime<chrono::Utc>"
+            "#,
+            role.id,
+            name
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        .ok_or_else(|| ServiceError::not_found("Role", role_id))?;
+
+        self.record_change(
+            &mut tx,
+            actor_id,
+            client,
+            "role.renamed",
+            &role,
+            &updated_role,
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(updated_role)
+    }
+
+    /// Sets or, with neither field given, removes the routing fee ceilings
+    /// of a role.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for validation failures and unknown or deleted roles
+    pub async fn set_fee_ceiling(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        role_id: &str,
+        fee_ceiling: RoleFeeCeiling,
+    ) -> ServiceResult<Role> {
+        if let Err(validation_errors) = fee_ceiling.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let role = self.get_role(role_id).await?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let updated_role = sqlx::query_as!(
+            Role,
+            r#"
+            UPDATE roles
+            SET max_fee_msat = $2,
+                max_fee_percent = $3,
+                updated_at = now()
+            WHERE id = $1
+              AND is_deleted = false
+            RETURNING
+                id as "id!",
+                name as "name!",
+                is_active as "is_active!",
+                max_fee_msat as "max_fee_msat?",
+                max_fee_percent as "max_fee_percent?",
+                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: chrono::DateTime<chrono::Utc>"
+            "#,
+            role.id,
+            fee_ceiling.max_fee_msat.map(BigDecimal::from),
+            fee_ceiling.max_fee_percent
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        .ok_or_else(|| ServiceError::not_found("Role", role_id))?;
+
+        self.record_change(
+            &mut tx,
+            actor_id,
+            client,
+            "role.fee_ceiling_changed",
+            &role,
+            &updated_role,
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(updated_role)
+    }
+
+    /// Marks a role as inactive without deleting it.
+    pub async fn deactivate_role(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        role_id: &str,
+    ) -> ServiceResult<Role> {
+        let role = self.get_role(role_id).await?;
+        self.ensure_not_system_role(&role)?;
+
+        self.set_role_active(actor_id, client, role, false).await
+    }
+
+    /// Marks a previously deactivated role as active again.
+    pub async fn reactivate_role(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        role_id: &str,
+    ) -> ServiceResult<Role> {
+        let role = self.get_role(role_id).await?;
+
+        self.set_role_active(actor_id, client, role, true).await
+    }
+
+    /// Soft-deletes a role.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Unknown or already deleted roles
+    /// - The configured default and admin roles
+    /// - Roles that are still assigned to users
+    pub async fn delete_role(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        role_id: &str,
+    ) -> ServiceResult<Role> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        // Locking the role holds back assignments until the role is deleted,
+        // and waits for assignments already in flight to commit
+        let role = sqlx::query_as!(
+            Role,
+            r#"
+            SELECT
+                id as "id!",
+                name as "name!",
+                is_active as "is_active!",
+                max_fee_msat as "max_fee_msat?",
+                max_fee_percent as "max_fee_percent?",
+                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: chrono::DateTime<chrono::Utc>"
+            FROM roles
+            WHERE id = $1
+              AND is_deleted = false
+            FOR UPDATE
+            "#,
+            role_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        .ok_or_else(|| ServiceError::not_found("Role", role_id))?;
+        self.ensure_not_system_role(&role)?;
+
+        let assigned_users = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)::BIGINT as "count!"
+            FROM users
+            WHERE role_id = $1
+              AND is_deleted = false
+            "#,
+            role.id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+        if assigned_users > 0 {
+            return Err(ServiceError::invalid_operation(format!(
+                "Role '{}' is still assigned to {assigned_users} user(s)",
+                role.name
+            )));
+        }
+
+        let deleted_role = sqlx::query_as!(
+            Role,
+            r#"
+            UPDATE roles
+            SET is_deleted = true,
+                is_active = false,
+                deleted_at = now(),
+                updated_at = now()
+            WHERE id = $1
+            RETURNING
+                id as "id!",
+                name as "name!",
+                is_active as "is_active!",
+                max_fee_msat as "max_fee_msat?",
+                max_fee_percent as "max_fee_percent?",
+                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: chrono::DateTime<chrono::Utc>"
+            "#,
+            role.id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        self.record_change(
+            &mut tx,
+            actor_id,
+            client,
+            "role.deleted",
+            &role,
+            &deleted_role,
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(deleted_role)
+    }
+
+    /// Rejects changes that would disable signup or lock admins out.
+    fn ensure_not_system_role(&self, role: &Role) -> ServiceResult<()> {
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: format!("Config error: {e}"),
+        })?;
+
+        if role.name == config.default_role || role.name == config.admin_role {
+            return Err(ServiceError::invalid_operation(format!(
+                "Role '{}' is required by the system",
+                role.name
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn set_role_active(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        role: Role,
+        is_active: bool,
+    ) -> ServiceResult<Role> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let updated_role = sqlx::query_as!(
+            Role,
+            r#"
+            UPDATE roles
+            SET is_active = $2,
+                updated_at = now()
+            WHERE id = $1
+              AND is_deleted = false
+            RETURNING
+                id as "id!",
+                name as "name!",
+                is_active as "is_active!",
+                max_fee_msat as "max_fee_msat?",
+                max_fee_percent as "max_fee_percent?",
+                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: chrono::DateTime<chrono::Utc>"
+            "#,
+            role.id,
+            is_active
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        .ok_or_else(|| ServiceError::not_found("Role", &role.id))?;
+
+        let action = if is_active {
+            "role.reactivated"
+        } else {
+            "role.deactivated"
+        };
+        self.record_change(&mut tx, actor_id, client, action, &role, &updated_role)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(updated_role)
+    }
+
+    /// Records a role as it was before and after a change.
+    async fn record_change(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        actor_id: &str,
+        client: &ClientInfo,
+        action: &str,
+        before: &Role,
+        after: &Role,
+    ) -> ServiceResult<()> {
+        AuditService::record(
+            tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(actor_id.to_string()),
+                action: action.to_string(),
+                target_type: "role".to_string(),
+                target_id: after.id.clone(),
+                before: Some(json!(before)),
+                after: Some(json!(after)),
+            },
+        )
+        .await
+    }
 }
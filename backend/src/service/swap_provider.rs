@@ -0,0 +1,202 @@
+// Swap Provider Logic
+//! Submarine swap providers, which trade on-chain coins for Lightning
+//! payments through HTLCs on both sides.
+//!
+//! `BoltzClient` speaks the Boltz swap API with P2WSH swap scripts. Point
+//! `BOLTZ_API_URL` at a local mock server or a regtest Boltz to try swaps
+//! without real funds.
+
+use crate::Config;
+use crate::errors::SwapError;
+use crate::utilities::{ReverseSwapTerms, SubmarineSwapTerms, SwapUpdate};
+use async_trait::async_trait;
+use bitcoin::secp256k1::PublicKey;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::time::Duration;
+
+/// How long a request to the swap provider may take
+const SWAP_HTTP_TIMEOUT_SECONDS: u64 = 30;
+
+/// A unified interface for submarine swap providers.
+#[async_trait]
+pub trait SwapProvider: Send + Sync {
+    /// Sets up a swap where the user locks coins on chain and the provider
+    /// pays `invoice` in exchange. `refund_key` can take the coins back
+    /// after the timeout if the provider never pays.
+    async fn create_submarine_swap(
+        &self,
+        invoice: &str,
+        refund_key: &PublicKey,
+    ) -> Result<SubmarineSwapTerms, SwapError>;
+
+    /// Sets up a swap where the bank pays the provider's hold invoice for
+    /// `amount_sat` and the provider locks coins that `claim_key` can claim
+    /// with the preimage of `payment_hash`.
+    async fn create_reverse_swap(
+        &self,
+        amount_sat: u64,
+        payment_hash: &[u8; 32],
+        claim_key: &PublicKey,
+    ) -> Result<ReverseSwapTerms, SwapError>;
+
+    /// Latest state of a swap.
+    async fn get_swap_status(&self, id: &str) -> Result<SwapUpdate, SwapError>;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BoltzSubmarineSwap {
+    id: String,
+    address: String,
+    expected_amount: u64,
+    redeem_script: String,
+    timeout_block_height: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BoltzReverseSwap {
+    id: String,
+    invoice: String,
+    lockup_address: String,
+    onchain_amount: u64,
+    redeem_script: String,
+    timeout_block_height: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct BoltzSwapTransaction {
+    hex: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BoltzSwapStatus {
+    status: String,
+    transaction: Option<BoltzSwapTransaction>,
+}
+
+pub struct BoltzClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl BoltzClient {
+    pub fn new(base_url: &str) -> Result<Self, SwapError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(SWAP_HTTP_TIMEOUT_SECONDS))
+            .build()
+            .map_err(|e| SwapError::ConnectionError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self, SwapError> {
+        Self::new(&config.boltz_api_url)
+    }
+
+    /// Posts a JSON request to the provider, surfacing the error it gives.
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: serde_json::Value,
+    ) -> Result<T, SwapError> {
+        let response = self
+            .client
+            .post(format!("{}{}", self.base_url, path))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| SwapError::ConnectionError(e.to_string()))?;
+
+        let http_status = response.status();
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| SwapError::Parse(e.to_string()))?;
+
+        if !http_status.is_success() {
+            let reason = body
+                .get("error")
+                .and_then(|error| error.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("responded with {http_status}"));
+            return Err(SwapError::ProviderError(reason));
+        }
+
+        serde_json::from_value(body).map_err(|e| SwapError::Parse(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl SwapProvider for BoltzClient {
+    async fn create_submarine_swap(
+        &self,
+        invoice: &str,
+        refund_key: &PublicKey,
+    ) -> Result<SubmarineSwapTerms, SwapError> {
+        let swap: BoltzSubmarineSwap = self
+            .post(
+                "/createswap",
+                json!({
+                    "type": "submarine",
+                    "pairId": "BTC/BTC",
+                    "orderSide": "sell",
+                    "invoice": invoice,
+                    "refundPublicKey": refund_key.to_string(),
+                }),
+            )
+            .await?;
+
+        Ok(SubmarineSwapTerms {
+            id: swap.id,
+            address: swap.address,
+            expected_amount_sat: swap.expected_amount,
+            redeem_script: swap.redeem_script,
+            timeout_block_height: swap.timeout_block_height,
+        })
+    }
+
+    async fn create_reverse_swap(
+        &self,
+        amount_sat: u64,
+        payment_hash: &[u8; 32],
+        claim_key: &PublicKey,
+    ) -> Result<ReverseSwapTerms, SwapError> {
+        let swap: BoltzReverseSwap = self
+            .post(
+                "/createswap",
+                json!({
+                    "type": "reversesubmarine",
+                    "pairId": "BTC/BTC",
+                    "orderSide": "buy",
+                    "invoiceAmount": amount_sat,
+                    "preimageHash": hex::encode(payment_hash),
+                    "claimPublicKey": claim_key.to_string(),
+                }),
+            )
+            .await?;
+
+        Ok(ReverseSwapTerms {
+            id: swap.id,
+            invoice: swap.invoice,
+            lockup_address: swap.lockup_address,
+            onchain_amount_sat: swap.onchain_amount,
+            redeem_script: swap.redeem_script,
+            timeout_block_height: swap.timeout_block_height,
+        })
+    }
+
+    async fn get_swap_status(&self, id: &str) -> Result<SwapUpdate, SwapError> {
+        let status: BoltzSwapStatus = self.post("/swapstatus", json!({ "id": id })).await?;
+
+        Ok(SwapUpdate {
+            status: status.status,
+            transaction_hex: status.transaction.and_then(|transaction| transaction.hex),
+        })
+    }
+}
@@ -0,0 +1,1848 @@
+// Swap Service Logic
+//! Submarine swaps that move funds between the Lightning balance and the
+//! chain when the node is short on liquidity.
+//!
+//! A submarine swap (chain to Lightning) issues an invoice for the user's
+//! account and has the swap provider pay it once the user has locked coins
+//! on the swap address. If the provider never pays, the coins are refunded
+//! to the user's refund address after the swap times out.
+//!
+//! A reverse swap (Lightning to chain) pays the provider's hold invoice from
+//! the balance. Once the node has seen the provider's lockup confirmed with
+//! the agreed amount, the bank claims it to the user's address, which
+//! reveals the preimage and lets the provider settle. The ledger entry ends
+//! up with the amount delivered on chain and every fee paid on the way.
+
+use crate::Config;
+use crate::common::common::PaginationFilter;
+use crate::db::models::{
+    ApiKeyScope, CreateReverseSwap, CreateSubmarineSwap, FeeTier, NewInvoice, Swap,
+};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::invoice_repository::InvoiceRepository;
+use crate::repositories::swap_repository::SwapRepository;
+use crate::service::event_service::EventService;
+use crate::service::invoice_service::{INVOICE_SOURCE_SWAP, InvoiceService};
+use crate::service::node_service::LightningClient;
+use crate::service::onchain_withdrawal_service::parse_address;
+use crate::service::payment_service::{debit_account, default_fee_limit_msat};
+use crate::service::swap_provider::SwapProvider;
+use crate::utilities::auth::AuthUser;
+use crate::utilities::swap::{HtlcSpend, build_htlc_spend, swap_output, verify_swap_script};
+use crate::utilities::{RoutingPolicy, SwapUpdate};
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+use bitcoin::{ScriptBuf, Transaction};
+use chrono::{DateTime, Utc};
+use lightning_invoice::Bolt11Invoice;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// How often open swaps are checked with the swap provider
+const SWAP_POLL_SECONDS: u64 = 15;
+
+/// How long the node may look for a route to the provider's hold invoice
+const REVERSE_SWAP_PAYMENT_TIMEOUT_SECONDS: u64 = 60;
+
+/// Provider states in which the user's lockup has been seen
+const SUBMARINE_FUNDED_STATUSES: [&str; 5] = [
+    "transaction.mempool",
+    "transaction.confirmed",
+    "invoice.pending",
+    "invoice.paid",
+    "transaction.claimed",
+];
+
+/// Provider states after which a submarine swap will not be paid
+const SUBMARINE_FAILED_STATUSES: [&str; 3] = [
+    "invoice.failedToPay",
+    "transaction.lockupFailed",
+    "swap.expired",
+];
+
+/// Provider states in which it has locked up the coins of a reverse swap
+const REVERSE_LOCKED_STATUSES: [&str; 2] = ["transaction.mempool", "transaction.confirmed"];
+
+/// Provider states after which a reverse swap will not lock up or has
+/// taken its lockup back
+const REVERSE_FAILED_STATUSES: [&str; 4] = [
+    "transaction.failed",
+    "transaction.refunded",
+    "invoice.expired",
+    "swap.expired",
+];
+
+// Service layer for Swap related Operation
+pub struct SwapService<'a> {
+    pool: &'a PgPool,
+    lightning: &'a dyn LightningClient,
+    provider: &'a dyn SwapProvider,
+}
+
+impl<'a> SwapService<'a> {
+    /// Creates a new swap service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    /// * 'lightning' - Node on the Lightning side of the swaps
+    /// * 'provider' - Swap provider on the other side
+    pub fn new(
+        pool: &'a PgPool,
+        lightning: &'a dyn LightningClient,
+        provider: &'a dyn SwapProvider,
+    ) -> Self {
+        Self {
+            pool,
+            lightning,
+            provider,
+        }
+    }
+
+    fn load_config() -> ServiceResult<Config> {
+        Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })
+    }
+
+    /// Refuses swaps on nodes that cannot see their lockups confirm, before
+    /// the provider is asked for one.
+    fn ensure_lockups_are_watched(&self) -> ServiceResult<()> {
+        if self.lightning.can_watch_addresses() {
+            Ok(())
+        } else {
+            Err(ServiceError::Unsupported {
+                message: "Swaps are not supported by this node backend".to_string(),
+            })
+        }
+    }
+
+    /// Starts a swap of on-chain coins into the caller's balance.
+    ///
+    /// # Returns
+    /// The swap, whose 'lockup_address' the user must send
+    /// 'onchain_amount_sat' to
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'invoices:write' scope
+    /// - Node backends that cannot watch lockup addresses
+    /// - Validation failures, including refund addresses for another network
+    /// - Swaps the provider refuses or offers on unexpected terms
+    pub async fn create_submarine_swap(
+        &self,
+        auth: &AuthUser,
+        create_swap: CreateSubmarineSwap,
+    ) -> ServiceResult<Swap> {
+        auth.require_scope(ApiKeyScope::InvoicesWrite)?;
+        self.ensure_lockups_are_watched()?;
+
+        if let Err(validation_errors) = create_swap.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let config = Self::load_config()?;
+        let refund_address = parse_address(
+            "refund_address",
+            &create_swap.refund_address,
+            config.bitcoin_network,
+        )?;
+
+        let secret_key = generate_key();
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key);
+
+        let amount_msat = create_swap.amount_sat * 1000;
+        let invoice = InvoiceService::new(self.pool, self.lightning)
+            .issue_invoice(
+                auth.user_id(),
+                NewInvoice {
+                    amount_msat: Some(amount_msat),
+                    fiat: None,
+                    memo: "Swap from on-chain".to_string(),
+                    hashed_description: None,
+                    comment: None,
+                    source: INVOICE_SOURCE_SWAP.to_string(),
+                },
+            )
+            .await?;
+
+        // The lockup cannot be in any earlier block
+        let start_height = self.lightning.get_block_height().await?;
+
+        let terms = self
+            .provider
+            .create_submarine_swap(&invoice.payment_request, &public_key)
+            .await?;
+
+        verify_swap_script(
+            &terms.redeem_script,
+            &terms.address,
+            config.bitcoin_network,
+            &decode_hash(&invoice.payment_hash)?,
+            &public_key,
+            terms.timeout_block_height,
+            false,
+        )?;
+
+        let swap = sqlx::query_as!(
+            Swap,
+            r#"
+            INSERT INTO swaps (
+                id,
+                user_id,
+                account_id,
+                kind,
+                provider_swap_id,
+                invoice,
+                payment_hash,
+                amount_msat,
+                onchain_amount_sat,
+                lockup_address,
+                destination_address,
+                redeem_script,
+                timeout_block_height,
+                start_height,
+                private_key
+            )
+            VALUES ($1, $2, $3, 'submarine', $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                kind as "kind!",
+                provider_swap_id as "provider_swap_id!",
+                provider_status as "provider_status?",
+                status as "status!",
+                invoice as "invoice!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat!",
+                onchain_amount_sat as "onchain_amount_sat!",
+                lockup_address as "lockup_address!",
+                destination_address as "destination_address!",
+                redeem_script as "redeem_script!",
+                timeout_block_height as "timeout_block_height!",
+                start_height as "start_height!",
+                preimage as "preimage?",
+                private_key as "private_key!",
+                transaction_id as "transaction_id?",
+                fee_reserve_msat as "fee_reserve_msat!",
+                routing_fee_msat as "routing_fee_msat?",
+                fee_msat as "fee_msat?",
+                lockup_txid as "lockup_txid?",
+                sweep_txid as "sweep_txid?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            invoice.user_id,
+            invoice.account_id,
+            terms.id,
+            invoice.payment_request,
+            invoice.payment_hash,
+            BigDecimal::from(amount_msat),
+            terms.expected_amount_sat as i64,
+            terms.address,
+            refund_address.to_string(),
+            terms.redeem_script,
+            terms.timeout_block_height as i32,
+            start_height as i32,
+            hex::encode(secret_key.secret_bytes())
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!(
+            "Submarine swap {} created for {} sat into account {}",
+            swap.id,
+            create_swap.amount_sat,
+            swap.account_id
+        );
+
+        Ok(swap)
+    }
+
+    /// Starts a swap of part of the caller's balance out to an on-chain address.
+    ///
+    /// The amount plus a routing fee reserve is held from the balance until
+    /// the swap completes or fails.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'payments:send' scope or over their spend limit
+    /// - Node backends that cannot watch lockup addresses
+    /// - Validation failures, including addresses for another network
+    /// - Swaps the provider refuses or offers on unexpected terms
+    /// - Insufficient balance
+    pub async fn create_reverse_swap(
+        &self,
+        auth: &AuthUser,
+        create_swap: CreateReverseSwap,
+    ) -> ServiceResult<Swap> {
+        auth.require_scope(ApiKeyScope::PaymentsSend)?;
+        self.ensure_lockups_are_watched()?;
+
+        if let Err(validation_errors) = create_swap.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let config = Self::load_config()?;
+        let address = parse_address("address", &create_swap.address, config.bitcoin_network)?;
+
+        let secret_key = generate_key();
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key);
+
+        let mut preimage = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut preimage);
+        let payment_hash: [u8; 32] = Sha256::digest(preimage).into();
+
+        // The lockup cannot be in any earlier block
+        let start_height = self.lightning.get_block_height().await?;
+
+        let terms = self
+            .provider
+            .create_reverse_swap(create_swap.amount_sat, &payment_hash, &public_key)
+            .await?;
+
+        // The provider may only be paid for the swap that was asked for
+        let amount_msat = create_swap.amount_sat * 1000;
+        let invoice =
+            Bolt11Invoice::from_str(&terms.invoice).map_err(|e| ServiceError::ExternalService {
+                message: format!("Swap provider sent an invalid invoice: {e}"),
+            })?;
+        if invoice.payment_hash().to_string() != hex::encode(payment_hash)
+            || invoice.amount_milli_satoshis() != Some(amount_msat)
+        {
+            return Err(ServiceError::ExternalService {
+                message: "Swap provider sent an invoice for a different swap".to_string(),
+            });
+        }
+
+        verify_swap_script(
+            &terms.redeem_script,
+            &terms.lockup_address,
+            config.bitcoin_network,
+            &payment_hash,
+            &public_key,
+            terms.timeout_block_height,
+            true,
+        )?;
+
+        let fee_reserve_msat = default_fee_limit_msat(amount_msat, &config);
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        debit_account(&mut tx, auth, amount_msat + fee_reserve_msat).await?;
+
+        let transaction_id = Uuid::now_v7().to_string();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO transactions (
+                id,
+                user_id,
+                account_id,
+                direction,
+                invoice,
+                amount,
+                payment_hash,
+                payment_status
+            )
+            VALUES ($1, $2, $3, 'outgoing', $4, $5, $6, 'pending')
+            "#,
+            transaction_id,
+            auth.user_id(),
+            auth.account_id(),
+            terms.invoice,
+            BigDecimal::from(amount_msat),
+            hex::encode(payment_hash)
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let swap = sqlx::query_as!(
+            Swap,
+            r#"
+            INSERT INTO swaps (
+                id,
+                user_id,
+                account_id,
+                kind,
+                provider_swap_id,
+                invoice,
+                payment_hash,
+                amount_msat,
+                onchain_amount_sat,
+                lockup_address,
+                destination_address,
+                redeem_script,
+                timeout_block_height,
+                start_height,
+                preimage,
+                private_key,
+                transaction_id,
+                fee_reserve_msat
+            )
+            VALUES ($1, $2, $3, 'reverse', $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                kind as "kind!",
+                provider_swap_id as "provider_swap_id!",
+                provider_status as "provider_status?",
+                status as "status!",
+                invoice as "invoice!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat!",
+                onchain_amount_sat as "onchain_amount_sat!",
+                lockup_address as "lockup_address!",
+                destination_address as "destination_address!",
+                redeem_script as "redeem_script!",
+                timeout_block_height as "timeout_block_height!",
+                start_height as "start_height!",
+                preimage as "preimage?",
+                private_key as "private_key!",
+                transaction_id as "transaction_id?",
+                fee_reserve_msat as "fee_reserve_msat!",
+                routing_fee_msat as "routing_fee_msat?",
+                fee_msat as "fee_msat?",
+                lockup_txid as "lockup_txid?",
+                sweep_txid as "sweep_txid?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            auth.user_id(),
+            auth.account_id(),
+            terms.id,
+            terms.invoice,
+            hex::encode(payment_hash),
+            BigDecimal::from(amount_msat),
+            terms.onchain_amount_sat as i64,
+            terms.lockup_address,
+            address.to_string(),
+            terms.redeem_script,
+            terms.timeout_block_height as i32,
+            start_height as i32,
+            hex::encode(preimage),
+            hex::encode(secret_key.secret_bytes()),
+            transaction_id,
+            BigDecimal::from(fee_reserve_msat)
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        EventService::payment_made(&mut tx, &transaction_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!(
+            "Reverse swap {} created for {} sat from account {}",
+            swap.id,
+            create_swap.amount_sat,
+            swap.account_id
+        );
+
+        Ok(swap)
+    }
+
+    /// Lists the caller's swaps, newest first, with their total count.
+    pub async fn list_swaps(
+        &self,
+        auth: &AuthUser,
+        filter: &PaginationFilter,
+    ) -> ServiceResult<(Vec<Swap>, u64)> {
+        auth.require_scope(ApiKeyScope::PaymentsRead)?;
+
+        let swap_repo = SwapRepository::new(self.pool);
+
+        let swaps = swap_repo
+            .get_swaps_by_user_id(auth.user_id(), filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = swap_repo
+            .count_swaps_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        Ok((swaps, total))
+    }
+
+    /// Retrieves one of the caller's swaps.
+    pub async fn get_swap(&self, auth: &AuthUser, id: &str) -> ServiceResult<Swap> {
+        auth.require_scope(ApiKeyScope::PaymentsRead)?;
+
+        SwapRepository::new(self.pool)
+            .get_swap_by_id(id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|swap| swap.user_id == auth.user_id())
+            .ok_or_else(|| ServiceError::not_found("Swap", id))
+    }
+
+    /// Moves every open swap along with what the swap provider reports.
+    ///
+    /// A swap that fails to sync is logged and retried on the next run.
+    pub async fn sync_swaps(&self) -> ServiceResult<()> {
+        let swaps = SwapRepository::new(self.pool)
+            .get_swaps_in_flight()
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        for swap in swaps {
+            if let Err(error) = self.sync_swap(&swap).await {
+                tracing::warn!("Syncing swap {} failed: {}", swap.id, error);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn sync_swap(&self, swap: &Swap) -> ServiceResult<()> {
+        let update = self
+            .provider
+            .get_swap_status(&swap.provider_swap_id)
+            .await?;
+
+        if swap.provider_status.as_deref() != Some(update.status.as_str()) {
+            sqlx::query!(
+                r#"
+                UPDATE swaps
+                SET provider_status = $2,
+                    updated_at = now()
+                WHERE id = $1
+                "#,
+                swap.id,
+                update.status
+            )
+            .execute(self.pool)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+        }
+
+        if swap.kind == "reverse" {
+            self.sync_reverse_swap(swap, &update).await
+        } else {
+            self.sync_submarine_swap(swap, &update).await
+        }
+    }
+
+    async fn sync_submarine_swap(&self, swap: &Swap, update: &SwapUpdate) -> ServiceResult<()> {
+        let status = update.status.as_str();
+
+        if swap.status == "created" && SUBMARINE_FUNDED_STATUSES.contains(&status) {
+            let lockup_txid = update
+                .transaction_hex
+                .as_deref()
+                .and_then(|tx_hex| deserialize_hex::<Transaction>(tx_hex).ok())
+                .map(|lockup_tx| lockup_tx.compute_txid().to_string());
+
+            sqlx::query!(
+                r#"
+                UPDATE swaps
+                SET status = 'funded',
+                    lockup_txid = COALESCE($2, lockup_txid),
+                    updated_at = now()
+                WHERE id = $1
+                  AND status = 'created'
+                "#,
+                swap.id,
+                lockup_txid
+            )
+            .execute(self.pool)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+        }
+
+        if status == "invoice.paid" || status == "transaction.claimed" {
+            return self.complete_submarine_swap(swap).await;
+        }
+
+        if SUBMARINE_FAILED_STATUSES.contains(&status) {
+            return self.refund_submarine_swap(swap).await;
+        }
+
+        Ok(())
+    }
+
+    /// Closes a submarine swap once its invoice has been credited, putting
+    /// the provider and miner fees the user paid on the ledger entry.
+    async fn complete_submarine_swap(&self, swap: &Swap) -> ServiceResult<()> {
+        let settled = InvoiceRepository::new(self.pool)
+            .get_invoice_by_payment_hash(&swap.payment_hash)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .is_some_and(|invoice| invoice.status == "settled");
+
+        // The settlement watcher credits the invoice; wait for it
+        if !settled {
+            return Ok(());
+        }
+
+        let fee_msat = BigDecimal::from(swap.onchain_amount_sat * 1000) - &swap.amount_msat;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE swaps
+            SET status = 'completed',
+                fee_msat = $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            swap.id,
+            fee_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE transactions
+            SET fee_msat = $2,
+                updated_at = now()
+            WHERE payment_hash = $1
+              AND direction = 'incoming'
+            "#,
+            swap.payment_hash,
+            fee_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!("Submarine swap {} completed", swap.id);
+
+        Ok(())
+    }
+
+    /// Sends the coins of a failed submarine swap back to the user's refund
+    /// address once the swap has timed out.
+    ///
+    /// The lockup is whatever the node has seen confirmed on the lockup
+    /// address, whatever the provider says about it.
+    async fn refund_submarine_swap(&self, swap: &Swap) -> ServiceResult<()> {
+        let height = self.lightning.get_block_height().await?;
+        if height < swap.timeout_block_height as u32 {
+            return Ok(());
+        }
+
+        let Some(lockup_tx) = self.find_lockup(swap, 1).await? else {
+            // Never funded, nothing to give back
+            if swap.status == "created" {
+                return self.close_swap(swap, "expired").await;
+            }
+            return Ok(());
+        };
+
+        let (sweep_txid, _) = self
+            .sweep(
+                swap,
+                &lockup_tx,
+                &swap.destination_address,
+                HtlcSpend::Refund,
+            )
+            .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE swaps
+            SET status = 'refunded',
+                lockup_txid = $2,
+                sweep_txid = $3,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            swap.id,
+            lockup_tx.compute_txid().to_string(),
+            sweep_txid
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!("Submarine swap {} refunded in {}", swap.id, sweep_txid);
+
+        Ok(())
+    }
+
+    /// Transaction locking coins on the swap address, once the node has seen
+    /// it reach `confirmations` confirmations.
+    async fn find_lockup(
+        &self,
+        swap: &Swap,
+        confirmations: u32,
+    ) -> ServiceResult<Option<Transaction>> {
+        let config = Self::load_config()?;
+
+        let lockup_address = parse_address(
+            "lockup_address",
+            &swap.lockup_address,
+            config.bitcoin_network,
+        )?;
+
+        let Some(tx_hex) = self
+            .lightning
+            .find_confirmed_transaction(
+                lockup_address.script_pubkey().as_bytes(),
+                confirmations,
+                swap.start_height as u32,
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        deserialize_hex(&tx_hex)
+            .map(Some)
+            .map_err(|e| ServiceError::InternalError {
+                message: format!("Node sent an invalid transaction: {e}"),
+            })
+    }
+
+    async fn sync_reverse_swap(&self, swap: &Swap, update: &SwapUpdate) -> ServiceResult<()> {
+        let status = update.status.as_str();
+
+        match swap.status.as_str() {
+            // Once the provider reports a lockup the node is asked for it
+            "paying" if REVERSE_LOCKED_STATUSES.contains(&status) => {
+                self.claim_reverse_swap(swap).await
+            }
+            "created" | "paying" if REVERSE_FAILED_STATUSES.contains(&status) => {
+                self.fail_reverse_swap(swap).await
+            }
+            // The payment outcome was lost, settle on the reserve
+            "claimed" if status == "invoice.settled" => {
+                let routing_fee_msat = swap
+                    .routing_fee_msat
+                    .clone()
+                    .unwrap_or_else(|| swap.fee_reserve_msat.clone());
+                self.complete_reverse_swap(&swap.id, routing_fee_msat).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Takes the reverse swaps whose hold invoice has not been paid yet,
+    /// so each is paid exactly once.
+    pub async fn take_unpaid_reverse_swaps(&self) -> ServiceResult<Vec<Swap>> {
+        let swaps = sqlx::query_as!(
+            Swap,
+            r#"
+            UPDATE swaps
+            SET status = 'paying',
+                updated_at = now()
+            WHERE kind = 'reverse'
+              AND status = 'created'
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                kind as "kind!",
+                provider_swap_id as "provider_swap_id!",
+                provider_status as "provider_status?",
+                status as "status!",
+                invoice as "invoice!",
+                payment_hash as "payment_hash!",
+                amount_msat as "amount_msat!",
+                onchain_amount_sat as "onchain_amount_sat!",
+                lockup_address as "lockup_address!",
+                destination_address as "destination_address!",
+                redeem_script as "redeem_script!",
+                timeout_block_height as "timeout_block_height!",
+                start_height as "start_height!",
+                preimage as "preimage?",
+                private_key as "private_key!",
+                transaction_id as "transaction_id?",
+                fee_reserve_msat as "fee_reserve_msat!",
+                routing_fee_msat as "routing_fee_msat?",
+                fee_msat as "fee_msat?",
+                lockup_txid as "lockup_txid?",
+                sweep_txid as "sweep_txid?",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(swaps)
+    }
+
+    /// Pays the provider's hold invoice of a reverse swap.
+    ///
+    /// This only returns once the provider settles, which it does after the
+    /// claim has revealed the preimage.
+    pub async fn pay_reverse_swap(&self, swap: &Swap) -> ServiceResult<()> {
+        let policy = RoutingPolicy {
+            fee_limit_msat: bigdecimal::ToPrimitive::to_u64(&swap.fee_reserve_msat)
+                .unwrap_or_default(),
+            timeout_seconds: REVERSE_SWAP_PAYMENT_TIMEOUT_SECONDS,
+            outgoing_chan_ids: Vec::new(),
+            last_hop_pubkey: None,
+        };
+
+        match self
+            .lightning
+            .pay_invoice(&swap.invoice, None, &policy)
+            .await
+        {
+            Ok(outcome) => {
+                let routing_fee_msat = BigDecimal::from(outcome.fee_msat);
+
+                sqlx::query!(
+                    r#"
+                    UPDATE swaps
+                    SET routing_fee_msat = $2,
+                        updated_at = now()
+                    WHERE id = $1
+                    "#,
+                    swap.id,
+                    routing_fee_msat
+                )
+                .execute(self.pool)
+                .await
+                .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+                self.complete_reverse_swap(&swap.id, routing_fee_msat).await
+            }
+            Err(LightningError::PaymentError(reason)) => {
+                tracing::warn!("Paying reverse swap {} failed: {}", swap.id, reason);
+                self.fail_reverse_swap(swap).await
+            }
+            // Outcome unknown; the provider status settles it later
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Claims the provider's lockup of a reverse swap to the user's address.
+    ///
+    /// Claiming reveals the preimage, so the lockup must have the agreed
+    /// amount and be confirmed deep enough, as seen by the node, that the
+    /// provider can no longer double spend it.
+    async fn claim_reverse_swap(&self, swap: &Swap) -> ServiceResult<()> {
+        let config = Self::load_config()?;
+
+        let Some(lockup_tx) = self
+            .find_lockup(swap, config.swap_lockup_confirmations)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let script =
+            ScriptBuf::from_hex(&swap.redeem_script).map_err(|e| ServiceError::InternalError {
+                message: e.to_string(),
+            })?;
+        let locked_sat = swap_output(&lockup_tx, &script)
+            .map(|(_, output)| output.value.to_sat())
+            .unwrap_or_default();
+        if locked_sat < swap.onchain_amount_sat as u64 {
+            return Err(ServiceError::ExternalService {
+                message: format!(
+                    "Swap provider locked {} sat instead of {}",
+                    locked_sat, swap.onchain_amount_sat
+                ),
+            });
+        }
+
+        let preimage = decode_hash(swap.preimage.as_deref().unwrap_or_default())?;
+
+        let (sweep_txid, delivered_sat) = self
+            .sweep(
+                swap,
+                &lockup_tx,
+                &swap.destination_address,
+                HtlcSpend::Claim(preimage),
+            )
+            .await?;
+
+        // What the provider and miners took, the routing fee follows later
+        sqlx::query!(
+            r#"
+            UPDATE swaps
+            SET status = 'claimed',
+                lockup_txid = $2,
+                sweep_txid = $3,
+                fee_msat = amount_msat - $4,
+                updated_at = now()
+            WHERE id = $1
+              AND status = 'paying'
+            "#,
+            swap.id,
+            lockup_tx.compute_txid().to_string(),
+            sweep_txid,
+            BigDecimal::from(delivered_sat * 1000)
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!("Reverse swap {} claimed in {}", swap.id, sweep_txid);
+
+        Ok(())
+    }
+
+    /// Closes a claimed reverse swap, releasing what is left of the routing
+    /// fee reserve.
+    async fn complete_reverse_swap(
+        &self,
+        swap_id: &str,
+        routing_fee_msat: BigDecimal,
+    ) -> ServiceResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let completed = sqlx::query!(
+            r#"
+            UPDATE swaps
+            SET status = 'completed',
+                routing_fee_msat = $2,
+                fee_msat = fee_msat + $2,
+                updated_at = now()
+            WHERE id = $1
+              AND status = 'claimed'
+            RETURNING
+                account_id,
+                transaction_id,
+                fee_reserve_msat - $2 AS "released_msat!",
+                amount_msat + $2 - fee_msat AS "delivered_msat!",
+                fee_msat AS "fee_msat!"
+            "#,
+            swap_id,
+            routing_fee_msat
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        // Not claimed yet, or already completed
+        let Some(completed) = completed else {
+            return Ok(());
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET balance = balance + $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            completed.account_id,
+            completed.released_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE transactions
+            SET payment_status = 'succeeded',
+                amount = $2,
+                fee_msat = $3,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            completed.transaction_id,
+            completed.delivered_msat,
+            completed.fee_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if let Some(transaction_id) = &completed.transaction_id {
+            EventService::payment_updated(&mut tx, transaction_id).await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!("Reverse swap {} completed", swap_id);
+
+        Ok(())
+    }
+
+    /// Gives the amount and fee reserve of a reverse swap that will not go
+    /// through back to the balance.
+    async fn fail_reverse_swap(&self, swap: &Swap) -> ServiceResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let failed = sqlx::query!(
+            r#"
+            UPDATE swaps
+            SET status = 'failed',
+                updated_at = now()
+            WHERE id = $1
+              AND status IN ('created', 'paying')
+            RETURNING account_id, transaction_id, amount_msat + fee_reserve_msat AS "released_msat!"
+            "#,
+            swap.id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let Some(failed) = failed else {
+            return Ok(());
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET balance = balance + $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            failed.account_id,
+            failed.released_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE transactions
+            SET payment_status = 'failed',
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            failed.transaction_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if let Some(transaction_id) = &failed.transaction_id {
+            EventService::payment_updated(&mut tx, transaction_id).await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!(
+            "Reverse swap {} failed, released {} msat",
+            swap.id,
+            failed.released_msat
+        );
+
+        Ok(())
+    }
+
+    /// Records a swap that ended without anything left to move.
+    async fn close_swap(&self, swap: &Swap, status: &str) -> ServiceResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE swaps
+            SET status = $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            swap.id,
+            status
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!("Swap {} {}", swap.id, status);
+
+        Ok(())
+    }
+
+    /// Spends the swap output of `lockup_tx` to `destination` and broadcasts it.
+    ///
+    /// # Returns
+    /// The txid of the spend and the amount it delivered in sat
+    async fn sweep(
+        &self,
+        swap: &Swap,
+        lockup_tx: &Transaction,
+        destination: &str,
+        spend: HtlcSpend,
+    ) -> ServiceResult<(String, u64)> {
+        let config = Self::load_config()?;
+
+        let destination = parse_address("destination", destination, config.bitcoin_network)?;
+        let script =
+            ScriptBuf::from_hex(&swap.redeem_script).map_err(|e| ServiceError::InternalError {
+                message: e.to_string(),
+            })?;
+        let secret_key = SecretKey::from_slice(&decode_hash(&swap.private_key)?).map_err(|e| {
+            ServiceError::InternalError {
+                message: e.to_string(),
+            }
+        })?;
+
+        let sat_per_vbyte = self
+            .lightning
+            .estimate_fee_rate(FeeTier::Fast.conf_target())
+            .await?;
+
+        let sweep_tx = build_htlc_spend(
+            lockup_tx,
+            &script,
+            &destination,
+            &secret_key,
+            sat_per_vbyte,
+            swap.timeout_block_height as u32,
+            spend,
+        )?;
+
+        self.lightning
+            .publish_transaction(&serialize_hex(&sweep_tx), &format!("moya swap {}", swap.id))
+            .await?;
+
+        Ok((
+            sweep_tx.compute_txid().to_string(),
+            sweep_tx.output[0].value.to_sat(),
+        ))
+    }
+}
+
+/// Fresh key for one side of a swap script
+fn generate_key() -> SecretKey {
+    loop {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        if let Ok(secret_key) = SecretKey::from_slice(&bytes) {
+            return secret_key;
+        }
+    }
+}
+
+/// Decodes a hex encoded 32 byte hash, preimage or key
+fn decode_hash(value: &str) -> ServiceResult<[u8; 32]> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ServiceError::InternalError {
+            message: "Invalid 32 byte hex value".to_string(),
+        })
+}
+
+/// Keeps open swaps moving and pays the hold invoices of new reverse swaps.
+pub async fn run_swap_watcher(
+    pool: PgPool,
+    lightning: Arc<dyn LightningClient>,
+    provider: Arc<dyn SwapProvider>,
+) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(SWAP_POLL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        let service = SwapService::new(&pool, lightning.as_ref(), provider.as_ref());
+
+        if let Err(error) = service.sync_swaps().await {
+            tracing::warn!("Syncing swaps failed: {}", error);
+        }
+
+        let swaps = match service.take_unpaid_reverse_swaps().await {
+            Ok(swaps) => swaps,
+            Err(error) => {
+                tracing::warn!("Taking unpaid reverse swaps failed: {}", error);
+                continue;
+            }
+        };
+
+        // Each payment stays in flight until its swap is claimed
+        for swap in swaps {
+            let pool = pool.clone();
+            let lightning = lightning.clone();
+            let provider = provider.clone();
+
+            tokio::spawn(async move {
+                if let Err(error) = SwapService::new(&pool, lightning.as_ref(), provider.as_ref())
+                    .pay_reverse_swap(&swap)
+                    .await
+                {
+                    tracing::warn!("Paying reverse swap {} failed: {}", swap.id, error);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::SwapError;
+    use crate::service::node_service::InvoiceStream;
+    use crate::utilities::swap::{reverse_swap_script, submarine_swap_script};
+    use crate::utilities::testing::{configure, customer};
+    use crate::utilities::{
+        CustomInvoice, HoldInvoiceRequest, InvoiceRequest, InvoiceStatus, KeysendRequest, NodeInfo,
+        OfferInvoice, OnchainReceipt, PaymentOutcome, ReverseSwapTerms, SubmarineSwapTerms,
+        WalletTransaction,
+    };
+    use async_trait::async_trait;
+    use bitcoin::{Address, Amount, CompressedPublicKey, Network, TxIn, TxOut};
+    use bitcoin_030::hashes::{Hash, sha256};
+    use bitcoin_030::secp256k1::{Secp256k1 as Secp256k1_030, SecretKey as SecretKey_030};
+    use lightning_invoice::{Currency, InvoiceBuilder, PaymentSecret};
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    const START_HEIGHT: u32 = 700;
+    const TIMEOUT: u32 = 800;
+    const BALANCE_MSAT: u64 = 10_000_000_000;
+    const SWAP_SAT: u64 = 100_000;
+    /// What the provider keeps of a reverse swap
+    const PROVIDER_FEE_SAT: u64 = 500;
+    /// Payment hash of every invoice the mock node issues
+    const NODE_PAYMENT_HASH: [u8; 32] = [3; 32];
+
+    fn public_key(byte: u8) -> PublicKey {
+        let secret_key = SecretKey::from_slice(&[byte; 32]).unwrap();
+        PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key)
+    }
+
+    fn address(byte: u8) -> String {
+        Address::p2wpkh(&CompressedPublicKey(public_key(byte)), Network::Regtest).to_string()
+    }
+
+    fn hold_invoice(payment_hash: &[u8; 32], amount_msat: u64) -> String {
+        let key = SecretKey_030::from_slice(&[42u8; 32]).unwrap();
+
+        InvoiceBuilder::new(Currency::Regtest)
+            .description("Reverse swap".to_string())
+            .payment_hash(sha256::Hash::from_slice(payment_hash).unwrap())
+            .payment_secret(PaymentSecret([1u8; 32]))
+            .amount_milli_satoshis(amount_msat)
+            .current_timestamp()
+            .min_final_cltv_expiry_delta(144)
+            .build_signed(|hash| Secp256k1_030::new().sign_ecdsa_recoverable(hash, &key))
+            .unwrap()
+            .to_string()
+    }
+
+    /// Transaction paying `amount_sat` to `lockup_address`
+    fn lockup_tx(lockup_address: &str, amount_sat: u64) -> Transaction {
+        let lockup_address = lockup_address
+            .parse::<Address<_>>()
+            .unwrap()
+            .assume_checked();
+
+        Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut {
+                value: Amount::from_sat(amount_sat),
+                script_pubkey: lockup_address.script_pubkey(),
+            }],
+        }
+    }
+
+    /// Swap provider that agrees to every swap and reports whatever status
+    /// the test sets
+    struct MockProvider {
+        status: Mutex<String>,
+    }
+
+    impl MockProvider {
+        fn new() -> Self {
+            Self {
+                status: Mutex::new("swap.created".to_string()),
+            }
+        }
+
+        fn set_status(&self, status: &str) {
+            *self.status.lock().unwrap() = status.to_string();
+        }
+    }
+
+    #[async_trait]
+    impl SwapProvider for MockProvider {
+        async fn create_submarine_swap(
+            &self,
+            _invoice: &str,
+            refund_key: &PublicKey,
+        ) -> Result<SubmarineSwapTerms, SwapError> {
+            let script =
+                submarine_swap_script(&NODE_PAYMENT_HASH, &public_key(2), refund_key, TIMEOUT);
+
+            Ok(SubmarineSwapTerms {
+                id: "submarine".to_string(),
+                address: Address::p2wsh(&script, Network::Regtest).to_string(),
+                expected_amount_sat: SWAP_SAT + PROVIDER_FEE_SAT,
+                redeem_script: script.to_hex_string(),
+                timeout_block_height: TIMEOUT,
+            })
+        }
+
+        async fn create_reverse_swap(
+            &self,
+            amount_sat: u64,
+            payment_hash: &[u8; 32],
+            claim_key: &PublicKey,
+        ) -> Result<ReverseSwapTerms, SwapError> {
+            let script = reverse_swap_script(payment_hash, claim_key, &public_key(2), TIMEOUT);
+
+            Ok(ReverseSwapTerms {
+                id: "reverse".to_string(),
+                invoice: hold_invoice(payment_hash, amount_sat * 1000),
+                lockup_address: Address::p2wsh(&script, Network::Regtest).to_string(),
+                onchain_amount_sat: amount_sat - PROVIDER_FEE_SAT,
+                redeem_script: script.to_hex_string(),
+                timeout_block_height: TIMEOUT,
+            })
+        }
+
+        async fn get_swap_status(&self, _id: &str) -> Result<SwapUpdate, SwapError> {
+            Ok(SwapUpdate {
+                status: self.status.lock().unwrap().clone(),
+                transaction_hex: None,
+            })
+        }
+    }
+
+    /// Node that answers what swaps ask of it and records what they publish
+    struct MockNode {
+        info: NodeInfo,
+        height: Mutex<u32>,
+        /// Transactions the node has seen on chain, with their confirmations
+        chain: Mutex<Vec<(Transaction, u32)>>,
+        published: Mutex<Vec<Transaction>>,
+    }
+
+    impl MockNode {
+        fn new() -> Self {
+            Self {
+                info: NodeInfo {
+                    pubkey: public_key(5),
+                    alias: "mock".to_string(),
+                },
+                height: Mutex::new(START_HEIGHT),
+                chain: Mutex::new(Vec::new()),
+                published: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn mine(&self, tx: Transaction, confirmations: u32) {
+            self.chain.lock().unwrap().push((tx, confirmations));
+        }
+
+        fn published(&self) -> Vec<Transaction> {
+            self.published.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl LightningClient for MockNode {
+        fn get_node_info(&self) -> &NodeInfo {
+            &self.info
+        }
+
+        async fn create_invoice(
+            &self,
+            request: InvoiceRequest,
+        ) -> Result<CustomInvoice, LightningError> {
+            Ok(CustomInvoice {
+                memo: request.memo,
+                payment_hash: hex::encode(NODE_PAYMENT_HASH),
+                payment_preimage: String::new(),
+                value: request.amount_msat.unwrap_or_default() / 1000,
+                value_msat: request.amount_msat.unwrap_or_default(),
+                amount_paid_msat: 0,
+                creation_date: Some(Utc::now().timestamp()),
+                settle_date: None,
+                payment_request: "lnbcrt1submarine".to_string(),
+                expiry: Some(request.expiry),
+                state: InvoiceStatus::Open,
+                is_keysend: None,
+                is_amp: None,
+                payment_addr: None,
+                htlcs: None,
+                offer_id: None,
+            })
+        }
+
+        async fn get_invoice_details(
+            &self,
+            _payment_hash: &str,
+        ) -> Result<CustomInvoice, LightningError> {
+            unimplemented!()
+        }
+
+        async fn subscribe_invoices(&self) -> Result<InvoiceStream, LightningError> {
+            unimplemented!()
+        }
+
+        async fn create_hold_invoice(
+            &self,
+            _request: HoldInvoiceRequest,
+        ) -> Result<CustomInvoice, LightningError> {
+            unimplemented!()
+        }
+
+        async fn settle_hold_invoice(&self, _preimage: [u8; 32]) -> Result<(), LightningError> {
+            unimplemented!()
+        }
+
+        async fn cancel_hold_invoice(&self, _payment_hash: &str) -> Result<(), LightningError> {
+            unimplemented!()
+        }
+
+        async fn get_block_height(&self) -> Result<u32, LightningError> {
+            Ok(*self.height.lock().unwrap())
+        }
+
+        async fn new_address(&self) -> Result<String, LightningError> {
+            unimplemented!()
+        }
+
+        async fn list_onchain_receipts(
+            &self,
+            _start_height: u32,
+        ) -> Result<Vec<OnchainReceipt>, LightningError> {
+            unimplemented!()
+        }
+
+        async fn estimate_fee_rate(&self, _conf_target: u32) -> Result<u64, LightningError> {
+            Ok(2)
+        }
+
+        async fn send_batch(
+            &self,
+            _outputs: &BTreeMap<String, u64>,
+            _sat_per_vbyte: u64,
+            _label: &str,
+        ) -> Result<String, LightningError> {
+            unimplemented!()
+        }
+
+        async fn get_wallet_transaction(
+            &self,
+            _txid: &str,
+            _start_height: u32,
+        ) -> Result<Option<WalletTransaction>, LightningError> {
+            unimplemented!()
+        }
+
+        async fn find_labeled_transaction(
+            &self,
+            _label: &str,
+            _start_height: u32,
+        ) -> Result<Option<String>, LightningError> {
+            unimplemented!()
+        }
+
+        async fn bump_fee(
+            &self,
+            _txid: &str,
+            _sat_per_vbyte: u64,
+            _label: &str,
+        ) -> Result<String, LightningError> {
+            unimplemented!()
+        }
+
+        async fn publish_transaction(
+            &self,
+            tx_hex: &str,
+            _label: &str,
+        ) -> Result<(), LightningError> {
+            self.published
+                .lock()
+                .unwrap()
+                .push(deserialize_hex(tx_hex).unwrap());
+            Ok(())
+        }
+
+        fn can_watch_addresses(&self) -> bool {
+            true
+        }
+
+        async fn find_confirmed_transaction(
+            &self,
+            script_pubkey: &[u8],
+            confirmations: u32,
+            start_height: u32,
+        ) -> Result<Option<String>, LightningError> {
+            assert_eq!(start_height, START_HEIGHT);
+
+            Ok(self
+                .chain
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(tx, seen)| {
+                    *seen >= confirmations
+                        && tx
+                            .output
+                            .iter()
+                            .any(|output| output.script_pubkey.as_bytes() == script_pubkey)
+                })
+                .map(|(tx, _)| serialize_hex(tx)))
+        }
+
+        async fn pay_invoice(
+            &self,
+            _payment_request: &str,
+            _amount_msat: Option<u64>,
+            _policy: &RoutingPolicy,
+        ) -> Result<PaymentOutcome, LightningError> {
+            Ok(PaymentOutcome {
+                payment_preimage: String::new(),
+                fee_msat: 1_000,
+            })
+        }
+
+        async fn track_payment(
+            &self,
+            _payment_hash: &str,
+        ) -> Result<PaymentOutcome, LightningError> {
+            unimplemented!()
+        }
+
+        async fn estimate_route_fee(
+            &self,
+            _payment_request: &str,
+            _amount_msat: u64,
+        ) -> Result<u64, LightningError> {
+            unimplemented!()
+        }
+
+        async fn keysend(
+            &self,
+            _request: KeysendRequest,
+            _policy: &RoutingPolicy,
+        ) -> Result<PaymentOutcome, LightningError> {
+            unimplemented!()
+        }
+
+        async fn create_offer(
+            &self,
+            _amount_msat: Option<u64>,
+            _description: &str,
+        ) -> Result<String, LightningError> {
+            unimplemented!()
+        }
+
+        async fn disable_offer(&self, _offer_id: &str) -> Result<(), LightningError> {
+            unimplemented!()
+        }
+
+        async fn fetch_offer_invoice(
+            &self,
+            _offer: &str,
+            _amount_msat: u64,
+            _payer_note: Option<String>,
+        ) -> Result<OfferInvoice, LightningError> {
+            unimplemented!()
+        }
+
+        async fn pay_offer_invoice(
+            &self,
+            _invoice: &str,
+            _policy: &RoutingPolicy,
+        ) -> Result<PaymentOutcome, LightningError> {
+            unimplemented!()
+        }
+    }
+
+    async fn balance(pool: &PgPool, auth: &AuthUser) -> BigDecimal {
+        sqlx::query_scalar("SELECT balance FROM accounts WHERE id = $1")
+            .bind(auth.account_id())
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn reload(pool: &PgPool, swap: &Swap) -> Swap {
+        SwapRepository::new(pool)
+            .get_swaps_in_flight()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|in_flight| in_flight.id == swap.id)
+            .unwrap_or_else(|| {
+                panic!("Swap {} is no longer in flight", swap.id);
+            })
+    }
+
+    async fn status(pool: &PgPool, swap: &Swap) -> String {
+        sqlx::query_scalar("SELECT status FROM swaps WHERE id = $1")
+            .bind(&swap.id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn reverse_swap(pool: &PgPool, service: &SwapService<'_>, auth: &AuthUser) -> Swap {
+        let swap = service
+            .create_reverse_swap(
+                auth,
+                CreateReverseSwap {
+                    amount_sat: SWAP_SAT,
+                    address: address(9),
+                },
+            )
+            .await
+            .unwrap();
+
+        let taken = service.take_unpaid_reverse_swaps().await.unwrap();
+        assert_eq!(taken.len(), 1);
+
+        reload(pool, &swap).await
+    }
+
+    #[sqlx::test]
+    async fn reverse_swap_is_claimed_once_the_node_sees_the_lockup(pool: PgPool) {
+        configure();
+        let (node, provider) = (MockNode::new(), MockProvider::new());
+        let service = SwapService::new(&pool, &node, &provider);
+        let auth = customer(&pool, BALANCE_MSAT).await;
+
+        let swap = reverse_swap(&pool, &service, &auth).await;
+        assert_eq!(swap.status, "paying");
+        let onchain_sat = SWAP_SAT - PROVIDER_FEE_SAT;
+        assert_eq!(swap.onchain_amount_sat as u64, onchain_sat);
+        let reserve_msat = bigdecimal::ToPrimitive::to_u64(&swap.fee_reserve_msat).unwrap();
+        assert_eq!(
+            balance(&pool, &auth).await,
+            BigDecimal::from(BALANCE_MSAT - SWAP_SAT * 1000 - reserve_msat)
+        );
+
+        // The provider's word alone reveals nothing
+        provider.set_status("transaction.confirmed");
+        service.sync_swaps().await.unwrap();
+        assert_eq!(status(&pool, &swap).await, "paying");
+
+        // Nor does a lockup the node has only seen in the mempool
+        let lockup = lockup_tx(&swap.lockup_address, onchain_sat);
+        node.mine(lockup.clone(), 0);
+        service.sync_swaps().await.unwrap();
+        assert_eq!(status(&pool, &swap).await, "paying");
+        assert!(node.published().is_empty());
+
+        node.chain.lock().unwrap()[0].1 = 1;
+        service.sync_swaps().await.unwrap();
+        let claimed = reload(&pool, &swap).await;
+        assert_eq!(claimed.status, "claimed");
+        assert_eq!(claimed.lockup_txid, Some(lockup.compute_txid().to_string()));
+
+        let published = node.published();
+        assert_eq!(published.len(), 1);
+        let claim = &published[0];
+        assert_eq!(claim.input[0].previous_output.txid, lockup.compute_txid());
+        assert_eq!(
+            hex::encode(&claim.input[0].witness[1]),
+            claimed.preimage.clone().unwrap()
+        );
+        assert_eq!(
+            claim.output[0].script_pubkey,
+            address(9)
+                .parse::<Address<_>>()
+                .unwrap()
+                .assume_checked()
+                .script_pubkey()
+        );
+        assert_eq!(claimed.sweep_txid, Some(claim.compute_txid().to_string()));
+
+        // The provider settles once it has the preimage
+        service.pay_reverse_swap(&claimed).await.unwrap();
+        assert_eq!(status(&pool, &swap).await, "completed");
+
+        let delivered_sat = claim.output[0].value.to_sat();
+        let (amount, fee_msat, payment_status): (BigDecimal, BigDecimal, String) = sqlx::query_as(
+            "SELECT amount, fee_msat, payment_status FROM transactions WHERE id = $1",
+        )
+        .bind(&claimed.transaction_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(payment_status, "succeeded");
+        assert_eq!(amount, BigDecimal::from(delivered_sat * 1000));
+        assert_eq!(
+            fee_msat,
+            BigDecimal::from((SWAP_SAT - delivered_sat) * 1000 + 1_000)
+        );
+        assert_eq!(
+            balance(&pool, &auth).await,
+            BigDecimal::from(BALANCE_MSAT - SWAP_SAT * 1000 - 1_000)
+        );
+    }
+
+    #[sqlx::test]
+    async fn reverse_swap_lockup_short_of_the_agreed_amount_is_not_claimed(pool: PgPool) {
+        configure();
+        let (node, provider) = (MockNode::new(), MockProvider::new());
+        let service = SwapService::new(&pool, &node, &provider);
+        let auth = customer(&pool, BALANCE_MSAT).await;
+
+        let swap = reverse_swap(&pool, &service, &auth).await;
+        provider.set_status("transaction.confirmed");
+        node.mine(
+            lockup_tx(&swap.lockup_address, swap.onchain_amount_sat as u64 - 1),
+            6,
+        );
+
+        // Logged, and tried again on the next sync
+        service.sync_swaps().await.unwrap();
+
+        assert_eq!(status(&pool, &swap).await, "paying");
+        assert!(node.published().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn failed_reverse_swap_releases_the_balance(pool: PgPool) {
+        configure();
+        let (node, provider) = (MockNode::new(), MockProvider::new());
+        let service = SwapService::new(&pool, &node, &provider);
+        let auth = customer(&pool, BALANCE_MSAT).await;
+
+        let swap = reverse_swap(&pool, &service, &auth).await;
+        provider.set_status("swap.expired");
+        service.sync_swaps().await.unwrap();
+
+        assert_eq!(status(&pool, &swap).await, "failed");
+        assert_eq!(balance(&pool, &auth).await, BigDecimal::from(BALANCE_MSAT));
+    }
+
+    async fn submarine_swap(service: &SwapService<'_>, auth: &AuthUser) -> Swap {
+        service
+            .create_submarine_swap(
+                auth,
+                CreateSubmarineSwap {
+                    amount_sat: SWAP_SAT,
+                    refund_address: address(8),
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn failed_submarine_swap_refunds_the_lockup_the_node_sees(pool: PgPool) {
+        configure();
+        let (node, provider) = (MockNode::new(), MockProvider::new());
+        let service = SwapService::new(&pool, &node, &provider);
+        let auth = customer(&pool, BALANCE_MSAT).await;
+
+        let swap = submarine_swap(&service, &auth).await;
+        assert_eq!(swap.status, "created");
+        assert_eq!(swap.start_height as u32, START_HEIGHT);
+
+        provider.set_status("transaction.mempool");
+        service.sync_swaps().await.unwrap();
+        assert_eq!(status(&pool, &swap).await, "funded");
+
+        let lockup = lockup_tx(&swap.lockup_address, SWAP_SAT + PROVIDER_FEE_SAT);
+        node.mine(lockup.clone(), 3);
+        provider.set_status("invoice.failedToPay");
+
+        // Only the refund key can spend it, and only after the timeout
+        service.sync_swaps().await.unwrap();
+        assert_eq!(status(&pool, &swap).await, "funded");
+        assert!(node.published().is_empty());
+
+        *node.height.lock().unwrap() = TIMEOUT;
+        service.sync_swaps().await.unwrap();
+
+        let published = node.published();
+        assert_eq!(published.len(), 1);
+        let refund = &published[0];
+        assert_eq!(refund.input[0].previous_output.txid, lockup.compute_txid());
+        assert_eq!(
+            refund.lock_time,
+            bitcoin::absolute::LockTime::from_height(TIMEOUT).unwrap()
+        );
+        assert_eq!(
+            refund.output[0].script_pubkey,
+            address(8)
+                .parse::<Address<_>>()
+                .unwrap()
+                .assume_checked()
+                .script_pubkey()
+        );
+
+        let (status, lockup_txid): (String, Option<String>) =
+            sqlx::query_as("SELECT status, lockup_txid FROM swaps WHERE id = $1")
+                .bind(&swap.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(status, "refunded");
+        assert_eq!(lockup_txid, Some(lockup.compute_txid().to_string()));
+    }
+
+    #[sqlx::test]
+    async fn unfunded_submarine_swap_expires(pool: PgPool) {
+        configure();
+        let (node, provider) = (MockNode::new(), MockProvider::new());
+        let service = SwapService::new(&pool, &node, &provider);
+        let auth = customer(&pool, BALANCE_MSAT).await;
+
+        let swap = submarine_swap(&service, &auth).await;
+        provider.set_status("swap.expired");
+        *node.height.lock().unwrap() = TIMEOUT;
+        service.sync_swaps().await.unwrap();
+
+        assert_eq!(status(&pool, &swap).await, "expired");
+        assert!(node.published().is_empty());
+    }
+}
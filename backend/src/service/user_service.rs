@@ -2,19 +2,35 @@
 //! Handles all account-related business operations
 
 use crate::Config;
-use crate::db::models::UserWithAccount;
-use crate::db::models::{Account, CreateUser, LoginResponse, User, UserInfo, UserLogin};
+use crate::db::models::{
+    Account, AccountBalance, AdminCreateUser, AssignRole, ChangePassword, ConfirmEmailChange,
+    CreateUser, LoginResponse, NewAuditEvent, Role, UpdateDisplayCurrency, UpdateProfile, User,
+    UserInfo, UserLogin, UserProfile, UserWithAccount,
+};
 use crate::errors::{ServiceError, ServiceResult};
 use crate::repositories::account_repository::AccountRepository;
+use crate::repositories::email_verification_repository::EmailVerificationRepository;
 use crate::repositories::role_repository::RoleRepository;
 use crate::repositories::user_repository::UserRepository;
+use crate::service::audit_service::AuditService;
+use crate::service::fiat_service::FiatService;
+use crate::service::notification_service::{
+    Notification, NotificationService, describe_user_agent, format_time,
+};
+use crate::utilities::client::ClientInfo;
 use crate::utilities::jwt::JwtUtils;
-use bcrypt::verify;
-use sqlx::PgPool;
+use crate::utilities::password::{PasswordManager, PasswordMatch};
+use crate::utilities::token::{generate_token, hash_token};
+use bigdecimal::Zero;
+use chrono::{Duration, Utc};
 use sqlx::types::BigDecimal;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 use validator::Validate;
 
+/// How long an emailed verification token for a new address stays valid
+const EMAIL_VERIFICATION_TTL_HOURS: i64 = 24;
+
 // Service layer for User related Operation
 pub struct UserService<'a> {
     pool: &'a PgPool,
@@ -66,44 +82,481 @@ impl<'a> UserService<'a> {
             return Err(ServiceError::validation(error_messages.join(", ")));
         }
 
+        self.ensure_identity_available(&create_user.username, &create_user.email)
+            .await?;
+
+        // Self-service signups always receive the configured default role
+        let role = self.get_default_role().await?;
+
+        // Start a transaction for atomic account + user creation
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let mut user_with_account = self
+            .insert_user_with_account(
+                &mut tx,
+                &create_user.username,
+                Some(&create_user.email),
+                Some(&create_user.password),
+                &role.id,
+            )
+            .await?;
+
+        // Commit the transaction
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        // The first admin of a new deployment signs up like anyone else
+        match self.bootstrap_admin().await {
+            Ok(Some(admin)) if admin.id == user_with_account.user.id => {
+                user_with_account.user = admin;
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Initial admin was not assigned: {e}"),
+        }
+
+        Ok(user_with_account)
+    }
+
+    /// Creates a user with an explicitly chosen role on behalf of an admin.
+    ///
+    /// # Arguments
+    /// * 'actor_id' - ID of the admin performing the change
+    /// * 'client' - Request the change came from
+    /// * 'create_user' - User creation data including the role to assign
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Validation failures
+    /// - Duplicate usernames or emails
+    /// - Unknown or inactive roles
+    pub async fn admin_create_user(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        create_user: AdminCreateUser,
+    ) -> ServiceResult<UserWithAccount> {
+        if let Err(validation_errors) = create_user.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        self.ensure_identity_available(&create_user.username, &create_user.email)
+            .await?;
+
+        let role = self.get_assignable_role(&create_user.role_id).await?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+        self.lock_assignable_role(&mut tx, &role).await?;
+
+        let user_with_account = self
+            .insert_user_with_account(
+                &mut tx,
+                &create_user.username,
+                Some(&create_user.email),
+                Some(&create_user.password),
+                &role.id,
+            )
+            .await?;
+
+        AuditService::record(
+            &mut tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(actor_id.to_string()),
+                action: "user.created".to_string(),
+                target_type: "user".to_string(),
+                target_id: user_with_account.user.id.clone(),
+                before: None,
+                after: Some(serde_json::json!({
+                    "username": user_with_account.user.username,
+                    "email": user_with_account.user.email,
+                    "role_id": role.id,
+                    "role": role.name,
+                })),
+            },
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(user_with_account)
+    }
+
+    /// Moves a user to a different role on behalf of an admin.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Admins attempting to change their own role
+    /// - Unknown users
+    /// - Unknown or inactive roles
+    pub async fn assign_role(
+        &self,
+        actor_id: &str,
+        client: &ClientInfo,
+        user_id: &str,
+        assign_role: AssignRole,
+    ) -> ServiceResult<User> {
+        if let Err(validation_errors) = assign_role.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        if actor_id == user_id {
+            return Err(ServiceError::invalid_operation(
+                "Admins cannot change their own role",
+            ));
+        }
+
         let user_repo = UserRepository::new(self.pool);
-        let account_repo = AccountRepository::new(self.pool);
+        let user = user_repo
+            .get_user_by_id(user_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("User", user_id))?;
+
+        let role = self.get_assignable_role(&assign_role.role_id).await?;
+        if user.role_id == role.id {
+            return Ok(user);
+        }
+
         let role_repo = RoleRepository::new(self.pool);
+        let previous_role = role_repo.get_role_id(&user.role_id).await?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+        self.lock_assignable_role(&mut tx, &role).await?;
+
+        let updated_user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET role_id = $2,
+                updated_at = now()
+            WHERE id = $1
+              AND is_deleted = false
+            RETURNING
+                id as "id!",
+                role_id as "role_id!",
+                username as "username!",
+                password_hash as "password_hash?",
+                email as "email?",
+                is_active as "is_active!",
+                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: chrono::DateTime<chrono::Utc>"
+            "#,
+            user.id,
+            role.id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        AuditService::record(
+            &mut tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(actor_id.to_string()),
+                action: "user.role_changed".to_string(),
+                target_type: "user".to_string(),
+                target_id: user.id.clone(),
+                before: Some(serde_json::json!({
+                    "role_id": user.role_id,
+                    "role": previous_role.map(|role| role.name),
+                })),
+                after: Some(serde_json::json!({
+                    "role_id": role.id,
+                    "role": role.name,
+                })),
+            },
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(updated_user)
+    }
+
+    /// Gives the admin role to the user named by `INITIAL_ADMIN_EMAIL`, as
+    /// long as no user holds it yet.
+    ///
+    /// Runs at startup and after every signup, so the first admin of a new
+    /// deployment only has to sign up with that email.
+    ///
+    /// # Returns
+    /// The promoted user, or 'None' when there was nobody to promote
+    pub async fn bootstrap_admin(&self) -> ServiceResult<Option<User>> {
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: format!("Config error: {e}"),
+        })?;
+        let Some(email) = config.initial_admin_email else {
+            return Ok(None);
+        };
+
+        let role_repo = RoleRepository::new(self.pool);
+        let admin_role = role_repo
+            .get_role_by_name(&config.admin_role)
+            .await?
+            .ok_or_else(|| ServiceError::InternalError {
+                message: format!("Admin role '{}' is not available", config.admin_role),
+            })?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        // Locking the role keeps two concurrent bootstraps from both
+        // finding nobody holds it
+        sqlx::query!(
+            "SELECT id FROM roles WHERE id = $1 FOR UPDATE",
+            admin_role.id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let admins = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)::BIGINT as "count!"
+            FROM users
+            WHERE role_id = $1
+              AND is_deleted = false
+            "#,
+            admin_role.id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+        if admins > 0 {
+            return Ok(None);
+        }
+
+        let Some(user) = sqlx::query!(
+            r#"
+            SELECT id, role_id
+            FROM users
+            WHERE email = $1
+              AND is_deleted = false
+            FOR UPDATE
+            "#,
+            email
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        else {
+            return Ok(None);
+        };
+
+        let promoted = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET role_id = $2,
+                updated_at = now()
+            WHERE id = $1
+            RETURNING
+                id as "id!",
+                role_id as "role_id!",
+                username as "username!",
+                password_hash as "password_hash?",
+                email as "email?",
+                is_active as "is_active!",
+                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: chrono::DateTime<chrono::Utc>"
+            "#,
+            user.id,
+            admin_role.id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        AuditService::record(
+            &mut tx,
+            &ClientInfo::default(),
+            NewAuditEvent {
+                actor_id: None,
+                action: "user.role_changed".to_string(),
+                target_type: "user".to_string(),
+                target_id: promoted.id.clone(),
+                before: Some(serde_json::json!({ "role_id": user.role_id })),
+                after: Some(serde_json::json!({
+                    "role_id": admin_role.id,
+                    "role": admin_role.name,
+                    "reason": "INITIAL_ADMIN_EMAIL",
+                })),
+            },
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(Some(promoted))
+    }
+
+    /// Rejects usernames and emails that are already taken.
+    async fn ensure_identity_available(&self, username: &str, email: &str) -> ServiceResult<()> {
+        let user_repo = UserRepository::new(self.pool);
 
         // Check if username already exists
-        if user_repo.username_exists(&create_user.username).await? {
+        if user_repo.username_exists(username).await? {
             return Err(ServiceError::already_exists(
                 "User with the username Exist",
-                &create_user.username,
+                username,
             ));
         }
 
-        // Check if username already exists
-        if user_repo.email_exists(&create_user.email).await? {
+        // Check if email already exists
+        if user_repo.email_exists(email).await? {
             return Err(ServiceError::already_exists(
                 "User with the email Exist",
-                &create_user.email,
+                email,
             ));
         }
 
-        // Check if role id does not exists
-        if !role_repo.role_exists(&create_user.role_id).await? {
-            return Err(ServiceError::already_exists(
-                "Role does not exist",
-                &create_user.role_id,
-            ));
-        }
+        Ok(())
+    }
 
-        // Start a transaction for atomic account + user creation
-        let mut tx = self
-            .pool
-            .begin()
+    /// Creates a user without email or password, who signs in with a wallet key.
+    ///
+    /// Runs inside the caller's transaction so the linking key can be stored
+    /// alongside the new user.
+    pub async fn create_keyed_user(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        username: &str,
+    ) -> ServiceResult<UserWithAccount> {
+        let role = self.get_default_role().await?;
+
+        self.insert_user_with_account(tx, username, None, None, &role.id)
             .await
-            .map_err(|e| ServiceError::Database { source: e.into() })?;
+    }
+
+    /// Retrieves the role given to self-service signups.
+    async fn get_default_role(&self) -> ServiceResult<Role> {
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: format!("Config error: {e}"),
+        })?;
+        let role_repo = RoleRepository::new(self.pool);
+        role_repo
+            .get_role_by_name(&config.default_role)
+            .await?
+            .filter(|role| role.is_active)
+            .ok_or_else(|| ServiceError::InternalError {
+                message: format!("Default role '{}' is not available", config.default_role),
+            })
+    }
+
+    /// Retrieves a role that may be assigned to users.
+    async fn get_assignable_role(&self, role_id: &str) -> ServiceResult<Role> {
+        let role_repo = RoleRepository::new(self.pool);
+        let role = role_repo
+            .get_role_id(role_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Role", role_id))?;
+
+        if !role.is_active {
+            return Err(ServiceError::invalid_operation(format!(
+                "Role '{}' is inactive",
+                role.name
+            )));
+        }
+
+        Ok(role)
+    }
+
+    /// Keeps a role from being deleted or deactivated until the caller's
+    /// transaction commits, and checks it is still assignable.
+    async fn lock_assignable_role(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        role: &Role,
+    ) -> ServiceResult<()> {
+        let locked = sqlx::query_scalar!(
+            r#"
+            SELECT id as "id!"
+            FROM roles
+            WHERE id = $1
+              AND is_active = true
+              AND is_deleted = false
+            FOR SHARE
+            "#,
+            role.id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if locked.is_none() {
+            return Err(ServiceError::invalid_operation(format!(
+                "Role '{}' is no longer available",
+                role.name
+            )));
+        }
+
+        Ok(())
+    }
 
+    /// Inserts a user and their zero-balance account inside a transaction.
+    async fn insert_user_with_account(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        username: &str,
+        email: Option<&str>,
+        password: Option<&str>,
+        role_id: &str,
+    ) -> ServiceResult<UserWithAccount> {
         // create the user
-        let password_hash = bcrypt::hash(&create_user.password, bcrypt::DEFAULT_COST)
-            .map_err(|e| ServiceError::validation(format!("Password hashing failed: {e}")))?;
+        let password_hash = password
+            .map(|password| PasswordManager::from_env()?.hash(password))
+            .transpose()?;
 
         let user_id = Uuid::now_v7().to_string();
         let user = sqlx::query_as!(
@@ -124,8 +577,8 @@ impl<'a> UserService<'a> {
               id as "id!",
               role_id as "role_id!",
               username as "username!",
-              password_hash as "password_hash!",
-              email as "email!",
+              password_hash as "password_hash?",
+              email as "email?",
               is_active as "is_active!",
               created_at as "created_at!: chrono::DateTime<chrono::Utc>",
               updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
@@ -133,13 +586,13 @@ impl<'a> UserService<'a> {
               deleted_at as "deleted_at?: chrono::DateTime<chrono::Utc>"
           "#,
             user_id,
-            create_user.role_id,
-            create_user.username,
+            role_id,
+            username,
             password_hash,
-            create_user.email,
+            email,
             true
         )
-        .fetch_one(&mut *tx)
+        .fetch_one(&mut **tx)
         .await
         .map_err(|e| ServiceError::Database { source: e.into() })?;
 
@@ -155,6 +608,7 @@ impl<'a> UserService<'a> {
             user_id as "user_id!",
             balance as "balance!",
             is_active as "is_active!",
+            display_currency as "display_currency!",
             created_at as "created_at!: chrono::DateTime<chrono::Utc>",
             updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
             is_deleted as "is_deleted!",
@@ -165,23 +619,19 @@ impl<'a> UserService<'a> {
             BigDecimal::from(0),
             true
         )
-        .fetch_one(&mut *tx)
+        .fetch_one(&mut **tx)
         .await
         .map_err(|e| ServiceError::Database { source: e.into() })?;
 
-        // Commit the transaction
-        tx.commit()
-            .await
-            .map_err(|e| ServiceError::Database { source: e.into() })?;
-
-        //  &user.password_hash = "";
-        let user_with_account = UserWithAccount { account, user };
-
-        Ok(user_with_account)
+        Ok(UserWithAccount { account, user })
     }
 
     /// Authenticate user and generate JWT tokens with node credentials if available
-    pub async fn login(&self, login_request: UserLogin) -> ServiceResult<LoginResponse> {
+    pub async fn login(
+        &self,
+        login_request: UserLogin,
+        client: &ClientInfo,
+    ) -> ServiceResult<LoginResponse> {
         // Validate input
         if let Err(validation_errors) = login_request.validate() {
             let error_messages: Vec<String> = validation_errors
@@ -202,10 +652,35 @@ impl<'a> UserService<'a> {
 
         // Authenticate user using UserService
 
-        let user = self
+        let user = match self
             .authenticate_user(&login_request.email, &login_request.password)
-            .await?;
+            .await
+        {
+            Ok(user) => user,
+            Err(error @ ServiceError::Validation { .. }) => {
+                self.record_failed_login(&login_request.email, client)
+                    .await?;
+                return Err(error);
+            }
+            Err(error) => return Err(error),
+        };
+
+        self.issue_login(user, client).await
+    }
 
+    /// Generates the JWT tokens that open a session for an authenticated user.
+    ///
+    /// Shared by every sign-in method so they all produce the same session,
+    /// and all warn the user about sign-ins from devices not seen before.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' if the user's account or role is missing or
+    /// the account is inactive
+    pub async fn issue_login(
+        &self,
+        user: User,
+        client: &ClientInfo,
+    ) -> ServiceResult<LoginResponse> {
         // Get account information
         let account_repo = AccountRepository::new(self.pool);
         let account = account_repo
@@ -218,6 +693,8 @@ impl<'a> UserService<'a> {
             return Err(ServiceError::validation("Account is inactive".to_string()));
         }
 
+        self.record_login(&user.id, client).await?;
+
         // Store user ID before potential moves
         let user_id = user.id.clone();
         let account_id = account.id.clone();
@@ -258,31 +735,699 @@ impl<'a> UserService<'a> {
         })
     }
 
-    pub async fn authenticate_user(&self, email: &str, password: &str) -> ServiceResult<User> {
-        let user_repo = UserRepository::new(self.pool);
-        // Get user by username
-        let user = user_repo
-            .get_user_by_email(email)
-            .await?
-            .ok_or_else(|| ServiceError::validation("Invalid credentials".to_string()))?;
+    /// Audits a sign-in and remembers the device it came from, emailing the
+    /// user when it is one not seen before.
+    ///
+    /// Devices are told apart by their user agent. The first device of an
+    /// account is not reported, since that is the user's own first sign-in.
+    async fn record_login(&self, user_id: &str, client: &ClientInfo) -> ServiceResult<()> {
+        let device_hash = hash_token(client.user_agent.as_deref().unwrap_or_default());
 
-        // Check if user is active
-        if !user.is_active {
-            return Err(ServiceError::validation("Invalid credentials".to_string()));
-        }
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
 
-        // Verify password
-        if !self.verify_password(password, &user.password_hash)? {
-            return Err(ServiceError::validation(
-                "Invalid username or password".to_string(),
-            ));
-        }
+        let known_devices = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM login_devices
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
 
-        Ok(user)
-    }
+        // A device seen before gets a later 'last_seen_at' than when it was
+        // first stored
+        let new_device = sqlx::query_scalar!(
+            r#"
+            INSERT INTO login_devices (id, user_id, device_hash, user_agent, ip_address)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, device_hash) DO UPDATE
+            SET ip_address = EXCLUDED.ip_address,
+                last_seen_at = now()
+            RETURNING first_seen_at = last_seen_at as "new_device!"
+            "#,
+            Uuid::now_v7().to_string(),
+            user_id,
+            device_hash,
+            client.user_agent,
+            client.ip_address
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        AuditService::record(
+            &mut tx,
+            client,
+            NewAuditEvent {
+                actor_id: Some(user_id.to_string()),
+                action: "user.login".to_string(),
+                target_type: "user".to_string(),
+                target_id: user_id.to_string(),
+                before: None,
+                after: Some(serde_json::json!({
+                    "user_agent": client.user_agent,
+                    "new_device": new_device,
+                })),
+            },
+        )
+        .await?;
+
+        if new_device && known_devices > 0 {
+            NotificationService::enqueue(
+                &mut tx,
+                user_id,
+                Notification::NewLogin,
+                vec![
+                    (
+                        "user_agent",
+                        describe_user_agent(client.user_agent.as_deref()),
+                    ),
+                    (
+                        "ip_address",
+                        client
+                            .ip_address
+                            .clone()
+                            .unwrap_or_else(|| "Unknown".to_string()),
+                    ),
+                    ("time", format_time(Utc::now())),
+                ],
+            )
+            .await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Audits a failed password sign-in to an existing user. Attempts on
+    /// unknown emails are not recorded.
+    async fn record_failed_login(&self, email: &str, client: &ClientInfo) -> ServiceResult<()> {
+        let Some(user) = UserRepository::new(self.pool)
+            .get_user_by_email(email)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        AuditService::record(
+            &mut conn,
+            client,
+            NewAuditEvent {
+                actor_id: None,
+                action: "user.login_failed".to_string(),
+                target_type: "user".to_string(),
+                target_id: user.id,
+                before: None,
+                after: Some(serde_json::json!({
+                    "user_agent": client.user_agent,
+                })),
+            },
+        )
+        .await
+    }
+
+    /// Retrieves the profile of a user, without any credential material.
+    ///
+    /// # Arguments
+    /// * 'user_id' - ID of the user
+    ///
+    /// # Returns
+    /// 'UserProfile' with the user's role, accounts and any pending email change
+    pub async fn get_profile(&self, user_id: &str) -> ServiceResult<UserProfile> {
+        let user_repo = UserRepository::new(self.pool);
+        let user = user_repo
+            .get_user_by_id(user_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("User", user_id))?;
+
+        let role_repo = RoleRepository::new(self.pool);
+        let role = role_repo
+            .get_role_id(&user.role_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Role", &user.role_id))?;
+
+        let account_repo = AccountRepository::new(self.pool);
+        let accounts = account_repo.get_accounts_by_user_id(&user.id).await?;
+        let accounts = FiatService::new(self.pool).value_accounts(accounts).await?;
+
+        let verification_repo = EmailVerificationRepository::new(self.pool);
+        let pending_email = verification_repo
+            .get_latest_pending(&user.id)
+            .await?
+            .map(|verification| verification.email);
+
+        Ok(UserProfile {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            pending_email,
+            role: role.name,
+            is_active: user.is_active,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            accounts,
+        })
+    }
+
+    /// Changes the fiat currency one of the user's accounts is shown in.
+    ///
+    /// Transactions already valued keep the currency they were valued in.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Validation failures, including currencies rates are not kept for
+    /// - Accounts of other users
+    pub async fn set_display_currency(
+        &self,
+        user_id: &str,
+        account_id: &str,
+        update_currency: UpdateDisplayCurrency,
+    ) -> ServiceResult<AccountBalance> {
+        if let Err(validation_errors) = update_currency.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: format!("Config error: {e}"),
+        })?;
+        let currency = update_currency.currency.to_uppercase();
+        if !config.fiat_currencies.contains(&currency) {
+            return Err(ServiceError::validation(format!(
+                "currency: Must be one of {}",
+                config.fiat_currencies.join(", ")
+            )));
+        }
+
+        let account = sqlx::query_as!(
+            Account,
+            r#"
+            UPDATE accounts
+            SET display_currency = $3,
+                updated_at = now()
+            WHERE id = $1
+              AND user_id = $2
+              AND is_deleted = false
+            RETURNING
+            id as "id!",
+            user_id as "user_id!",
+            balance as "balance!",
+            is_active as "is_active!",
+            display_currency as "display_currency!",
+            created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+            updated_at as "updated_at!: chrono::DateTime<chrono::Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: chrono::DateTime<chrono::Utc>"
+            "#,
+            account_id,
+            user_id,
+            currency
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?
+        .ok_or_else(|| ServiceError::not_found("Account", account_id))?;
+
+        let mut balances = FiatService::new(self.pool)
+            .value_accounts(vec![account])
+            .await?;
+
+        Ok(balances.remove(0))
+    }
+
+    /// Updates the username and/or email of a user.
+    ///
+    /// A new username applies immediately. A new email only replaces the
+    /// current one after it has been confirmed with the token sent to it.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Validation failures
+    /// - Usernames or emails already used by another user
+    pub async fn update_profile(
+        &self,
+        user_id: &str,
+        update_profile: UpdateProfile,
+    ) -> ServiceResult<UserProfile> {
+        if let Err(validation_errors) = update_profile.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let user_repo = UserRepository::new(self.pool);
+        let user = user_repo
+            .get_user_by_id(user_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("User", user_id))?;
+
+        let new_username = update_profile
+            .username
+            .filter(|username| *username != user.username);
+        let new_email = update_profile
+            .email
+            .filter(|email| user.email.as_ref() != Some(email));
+
+        if let Some(username) = &new_username
+            && user_repo.username_exists(username).await?
+        {
+            return Err(ServiceError::already_exists(
+                "User with the username Exist",
+                username,
+            ));
+        }
+
+        if let Some(email) = &new_email
+            && user_repo.email_exists(email).await?
+        {
+            return Err(ServiceError::already_exists(
+                "User with the email Exist",
+                email,
+            ));
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if let Some(username) = &new_username {
+            sqlx::query!(
+                r#"
+                UPDATE users
+                SET username = $2,
+                    updated_at = now()
+                WHERE id = $1
+                "#,
+                user.id,
+                username
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+        }
+
+        if let Some(email) = &new_email {
+            // Only the latest requested address can be confirmed
+            sqlx::query!(
+                r#"
+                UPDATE email_verifications
+                SET consumed_at = now()
+                WHERE user_id = $1
+                  AND consumed_at IS NULL
+                "#,
+                user.id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+            let token = generate_token();
+            let expires_at = Utc::now() + Duration::hours(EMAIL_VERIFICATION_TTL_HOURS);
+            sqlx::query!(
+                r#"
+                INSERT INTO email_verifications (id, user_id, email, token_hash, expires_at)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                Uuid::now_v7().to_string(),
+                user.id,
+                email,
+                hash_token(&token),
+                expires_at
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+            NotificationService::enqueue_to(
+                &mut tx,
+                &user.id,
+                email,
+                Notification::EmailVerification,
+                vec![
+                    ("email", email.clone()),
+                    ("token", token),
+                    ("hours", EMAIL_VERIFICATION_TTL_HOURS.to_string()),
+                ],
+            )
+            .await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        self.get_profile(&user.id).await
+    }
+
+    /// Applies a pending email change once its token has been presented.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Unknown, consumed or expired tokens
+    /// - Emails claimed by another user since the change was requested
+    pub async fn confirm_email_change(
+        &self,
+        user_id: &str,
+        confirm: ConfirmEmailChange,
+    ) -> ServiceResult<UserProfile> {
+        if let Err(validation_errors) = confirm.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let verification_repo = EmailVerificationRepository::new(self.pool);
+        let verification = verification_repo
+            .get_pending_by_token(user_id, &hash_token(&confirm.token))
+            .await?
+            .ok_or_else(|| ServiceError::validation("Invalid or expired verification token"))?;
+
+        let user_repo = UserRepository::new(self.pool);
+        if user_repo.email_exists(&verification.email).await? {
+            return Err(ServiceError::already_exists(
+                "User with the email Exist",
+                &verification.email,
+            ));
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET email = $2,
+                updated_at = now()
+            WHERE id = $1
+              AND is_deleted = false
+            "#,
+            verification.user_id,
+            verification.email
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE email_verifications
+            SET consumed_at = now()
+            WHERE id = $1
+            "#,
+            verification.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        self.get_profile(user_id).await
+    }
+
+    /// Replaces the password of a user after checking the current one.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - Validation failures
+    /// - A wrong current password
+    /// - A new password identical to the current one
+    pub async fn change_password(
+        &self,
+        user_id: &str,
+        change_password: ChangePassword,
+    ) -> ServiceResult<()> {
+        if let Err(validation_errors) = change_password.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let user_repo = UserRepository::new(self.pool);
+        let user = user_repo
+            .get_user_by_id(user_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("User", user_id))?;
+
+        // Accounts created through LNURL-auth sign in with their wallet only
+        let password_hash = user
+            .password_hash
+            .as_deref()
+            .ok_or_else(|| ServiceError::invalid_operation("Account does not have a password"))?;
+
+        let current_password =
+            self.verify_password(&change_password.current_password, password_hash)?;
+        if current_password == PasswordMatch::Invalid {
+            return Err(ServiceError::validation(
+                "Current password is incorrect".to_string(),
+            ));
+        }
+
+        if change_password.current_password == change_password.new_password {
+            return Err(ServiceError::validation(
+                "New password must differ from the current password".to_string(),
+            ));
+        }
+
+        let password_hash = PasswordManager::from_env()?.hash(&change_password.new_password)?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            user.id,
+            password_hash
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        NotificationService::enqueue(
+            &mut tx,
+            &user.id,
+            Notification::PasswordChanged,
+            vec![("time", format_time(Utc::now()))],
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Soft-deletes a user together with their accounts.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' if any of the user's accounts still holds a
+    /// non-zero balance
+    pub async fn delete_user(&self, user_id: &str) -> ServiceResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        // Lock the accounts so no payment can land between the check and the delete
+        let balances = sqlx::query!(
+            r#"
+            SELECT balance as "balance!"
+            FROM accounts
+            WHERE user_id = $1
+              AND is_deleted = false
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if balances.iter().any(|account| !account.balance.is_zero()) {
+            return Err(ServiceError::invalid_operation(
+                "All account balances must be zero before the user can be deleted",
+            ));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET is_deleted = true,
+                is_active = false,
+                deleted_at = now(),
+                updated_at = now()
+            WHERE user_id = $1
+              AND is_deleted = false
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let deleted = sqlx::query!(
+            r#"
+            UPDATE users
+            SET is_deleted = true,
+                is_active = false,
+                deleted_at = now(),
+                updated_at = now()
+            WHERE id = $1
+              AND is_deleted = false
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(ServiceError::not_found("User", user_id));
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    pub async fn authenticate_user(&self, email: &str, password: &str) -> ServiceResult<User> {
+        let user_repo = UserRepository::new(self.pool);
+        // Get user by username
+        let user = user_repo
+            .get_user_by_email(email)
+            .await?
+            .ok_or_else(|| ServiceError::validation("Invalid credentials".to_string()))?;
+
+        // Check if user is active
+        if !user.is_active {
+            return Err(ServiceError::validation("Invalid credentials".to_string()));
+        }
+
+        let password_hash = user
+            .password_hash
+            .as_deref()
+            .ok_or_else(|| ServiceError::validation("Invalid credentials".to_string()))?;
+
+        // Verify password
+        match self.verify_password(password, password_hash)? {
+            PasswordMatch::Invalid => {
+                return Err(ServiceError::validation(
+                    "Invalid username or password".to_string(),
+                ));
+            }
+            PasswordMatch::ValidNeedsRehash => {
+                // A failed upgrade must not block the login, the next one retries it
+                if let Err(e) = self.rehash_password(&user.id, password).await {
+                    tracing::warn!("Failed to rehash password of User {}: {}", user.id, e);
+                }
+            }
+            PasswordMatch::Valid => {}
+        }
+
+        Ok(user)
+    }
+
+    fn verify_password(&self, password: &str, hash: &str) -> ServiceResult<PasswordMatch> {
+        PasswordManager::from_env()?.verify(password, hash)
+    }
+
+    /// Replaces a legacy or outdated password hash with one from the current scheme.
+    async fn rehash_password(&self, user_id: &str, password: &str) -> ServiceResult<()> {
+        let password_hash = PasswordManager::from_env()?.hash(password)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $2
+            WHERE id = $1
+            "#,
+            user_id,
+            password_hash
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
 
-    fn verify_password(&self, password: &str, hash: &str) -> ServiceResult<bool> {
-        verify(password, hash)
-            .map_err(|e| ServiceError::validation(format!("Password verification failed: {e}")))
+        Ok(())
     }
 }
@@ -0,0 +1,663 @@
+// Webhook Service Logic
+//! Webhooks that tell integrators about account events without polling.
+//!
+//! Events are written to the 'webhook_deliveries' outbox, one row per
+//! subscribed endpoint, in the same transaction as the ledger change they
+//! describe. An event is never sent for a change that rolled back, nor lost
+//! for one that committed.
+//!
+//! The dispatcher posts due deliveries signed with the endpoint's secret.
+//! Failed deliveries are retried with exponential backoff and dead-lettered
+//! after `WEBHOOK_MAX_ATTEMPTS`; dead deliveries can be retried by hand.
+//!
+//! Endpoints must resolve to public addresses, so an endpoint cannot be
+//! used to reach the bank's own network. The check is made when the
+//! endpoint is registered and again on every delivery, through the
+//! dispatcher's resolver, so a name pointed elsewhere afterwards is caught.
+
+use crate::Config;
+use crate::common::common::PaginationFilter;
+use crate::db::models::{
+    ApiKeyScope, CreateWebhookEndpoint, NewWebhookEndpoint, WebhookDelivery, WebhookEndpoint,
+    WebhookEvent,
+};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::webhook_repository::WebhookRepository;
+use crate::utilities::auth::AuthUser;
+use crate::utilities::crypto::{decrypt_secret, encrypt_secret};
+use crate::utilities::network::{PublicResolver, check_destination};
+use crate::utilities::token::generate_token;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+use validator::Validate;
+
+/// How often the outbox is checked for due deliveries
+const WEBHOOK_POLL_SECONDS: u64 = 5;
+
+/// Most deliveries sent per run
+const WEBHOOK_BATCH_SIZE: i64 = 20;
+
+/// How long an endpoint may take to answer
+const WEBHOOK_HTTP_TIMEOUT_SECONDS: u64 = 10;
+
+/// How long a claimed delivery is kept from other dispatchers
+const WEBHOOK_CLAIM_SECONDS: f64 = 120.0;
+
+/// Attempts after which a delivery is dead-lettered
+const WEBHOOK_MAX_ATTEMPTS: i32 = 10;
+
+/// Wait before the first retry, doubled on every further attempt
+const WEBHOOK_RETRY_BASE_SECONDS: i64 = 30;
+
+/// Longest wait between two attempts
+const WEBHOOK_RETRY_MAX_SECONDS: i64 = 6 * 60 * 60;
+
+/// Longest error kept on a delivery
+const WEBHOOK_ERROR_MAX_LEN: usize = 500;
+
+/// Prefix of endpoint signing secrets
+const WEBHOOK_SECRET_PREFIX: &str = "whsec_";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signature of a delivery body, sent as
+/// `Moya-Signature: t=<timestamp>,v1=<signature>`
+///
+/// The HMAC-SHA256 covers `<timestamp>.<body>` so a captured delivery
+/// cannot be replayed later with a fresh timestamp.
+fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Wait before the next attempt after `attempts` failed ones
+fn retry_delay_seconds(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    (WEBHOOK_RETRY_BASE_SECONDS * 2i64.pow(exponent)).min(WEBHOOK_RETRY_MAX_SECONDS)
+}
+
+/// Delivery claimed by the dispatcher, with where to send it
+struct DueDelivery {
+    id: String,
+    event_type: String,
+    payload: serde_json::Value,
+    attempts: i32,
+    url: String,
+    secret_ciphertext: String,
+    disabled_at: Option<DateTime<Utc>>,
+}
+
+/// What an endpoint made of one attempt
+enum AttemptOutcome {
+    Delivered(i32),
+    Failed(Option<i32>, String),
+}
+
+// Service layer for Webhook related Operation
+pub struct WebhookService<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> WebhookService<'a> {
+    /// Creates a new webhook service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    fn load_config() -> ServiceResult<Config> {
+        Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })
+    }
+
+    /// Registers an endpoint to be told about the caller's account events.
+    ///
+    /// # Returns
+    /// 'NewWebhookEndpoint' holding the signing secret, which is not
+    /// retrievable afterwards
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'webhooks:manage' scope
+    /// - Validation failures, including URLs that are not HTTPS or do not
+    ///   resolve to public addresses
+    pub async fn create_endpoint(
+        &self,
+        auth: &AuthUser,
+        create_endpoint: CreateWebhookEndpoint,
+    ) -> ServiceResult<NewWebhookEndpoint> {
+        auth.require_scope(ApiKeyScope::WebhooksManage)?;
+
+        if let Err(validation_errors) = create_endpoint.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let config = Self::load_config()?;
+        let url = Url::parse(&create_endpoint.url)
+            .map_err(|_| ServiceError::validation("url: Must be a valid URL"))?;
+        match url.scheme() {
+            "https" => {}
+            "http" if config.webhook_allow_http => {}
+            _ => return Err(ServiceError::validation("url: Webhooks must use HTTPS")),
+        }
+        if !config.webhook_allow_private_networks {
+            check_destination(&url)
+                .await
+                .map_err(|e| ServiceError::validation(format!("url: {e}")))?;
+        }
+
+        let mut events: Vec<String> = create_endpoint
+            .events
+            .iter()
+            .map(|event| event.as_str().to_string())
+            .collect();
+        events.sort();
+        events.dedup();
+
+        let secret = format!("{WEBHOOK_SECRET_PREFIX}{}", generate_token());
+
+        let endpoint = sqlx::query_as!(
+            WebhookEndpoint,
+            r#"
+            INSERT INTO webhook_endpoints (id, user_id, url, secret_ciphertext, events)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                url as "url!",
+                events as "events!",
+                disabled_at as "disabled_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            auth.user_id(),
+            url.to_string(),
+            encrypt_secret(&config.encryption_key, &secret)?,
+            &events
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(NewWebhookEndpoint { secret, endpoint })
+    }
+
+    /// Lists the caller's enabled webhook endpoints.
+    pub async fn list_endpoints(&self, auth: &AuthUser) -> ServiceResult<Vec<WebhookEndpoint>> {
+        auth.require_scope(ApiKeyScope::WebhooksManage)?;
+
+        WebhookRepository::new(self.pool)
+            .get_endpoints_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })
+    }
+
+    /// Disables one of the caller's endpoints. Deliveries still waiting to
+    /// be sent to it are dead-lettered; the delivery log is kept.
+    pub async fn disable_endpoint(&self, auth: &AuthUser, id: &str) -> ServiceResult<()> {
+        auth.require_scope(ApiKeyScope::WebhooksManage)?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let disabled = sqlx::query!(
+            r#"
+            UPDATE webhook_endpoints
+            SET disabled_at = now(),
+                updated_at = now()
+            WHERE id = $1
+              AND user_id = $2
+              AND disabled_at IS NULL
+            "#,
+            id,
+            auth.user_id()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if disabled.rows_affected() == 0 {
+            return Err(ServiceError::not_found("Webhook endpoint", id));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'dead',
+                last_error = 'Endpoint disabled',
+                updated_at = now()
+            WHERE endpoint_id = $1
+              AND status = 'pending'
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        Ok(())
+    }
+
+    /// Lists the deliveries made to one of the caller's endpoints, newest
+    /// first, with the outcome of their last attempt.
+    pub async fn list_deliveries(
+        &self,
+        auth: &AuthUser,
+        endpoint_id: &str,
+        filter: &PaginationFilter,
+    ) -> ServiceResult<(Vec<WebhookDelivery>, u64)> {
+        auth.require_scope(ApiKeyScope::WebhooksManage)?;
+
+        let webhook_repo = WebhookRepository::new(self.pool);
+
+        webhook_repo
+            .get_endpoint_by_id(endpoint_id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|endpoint| endpoint.user_id == auth.user_id())
+            .ok_or_else(|| ServiceError::not_found("Webhook endpoint", endpoint_id))?;
+
+        let deliveries = webhook_repo
+            .get_deliveries_by_endpoint_id(endpoint_id, filter)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+        let total = webhook_repo
+            .count_deliveries_by_endpoint_id(endpoint_id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        Ok((deliveries, total))
+    }
+
+    /// Puts a dead-lettered delivery back in the outbox with a fresh set of
+    /// attempts.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for deliveries of other users, deliveries
+    /// that are not dead, and deliveries to disabled endpoints
+    pub async fn retry_delivery(
+        &self,
+        auth: &AuthUser,
+        id: &str,
+    ) -> ServiceResult<WebhookDelivery> {
+        auth.require_scope(ApiKeyScope::WebhooksManage)?;
+
+        let webhook_repo = WebhookRepository::new(self.pool);
+
+        let delivery = webhook_repo
+            .get_delivery_by_id(id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|delivery| delivery.user_id == auth.user_id())
+            .ok_or_else(|| ServiceError::not_found("Webhook delivery", id))?;
+
+        if delivery.status != "dead" {
+            return Err(ServiceError::invalid_operation(
+                "Only dead-lettered deliveries can be retried",
+            ));
+        }
+
+        let requeued = sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries d
+            SET status = 'pending',
+                attempts = 0,
+                next_attempt_at = now(),
+                updated_at = now()
+            FROM webhook_endpoints e
+            WHERE d.id = $1
+              AND d.status = 'dead'
+              AND e.id = d.endpoint_id
+              AND e.disabled_at IS NULL
+            "#,
+            id
+        )
+        .execute(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if requeued.rows_affected() == 0 {
+            return Err(ServiceError::invalid_operation(
+                "Deliveries to disabled endpoints cannot be retried",
+            ));
+        }
+
+        webhook_repo
+            .get_delivery_by_id(id)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .ok_or_else(|| ServiceError::not_found("Webhook delivery", id))
+    }
+
+    /// Adds an event to the outbox of every enabled endpoint of the user
+    /// subscribed to it.
+    ///
+    /// Takes the connection of the caller's transaction so the event is
+    /// only delivered if the change it describes is committed.
+    pub async fn enqueue(
+        conn: &mut PgConnection,
+        user_id: &str,
+        event: WebhookEvent,
+        data: serde_json::Value,
+    ) -> ServiceResult<()> {
+        let endpoints = sqlx::query!(
+            r#"
+            SELECT id
+            FROM webhook_endpoints
+            WHERE user_id = $1
+              AND disabled_at IS NULL
+              AND $2 = ANY(events)
+            "#,
+            user_id,
+            event.as_str()
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        if endpoints.is_empty() {
+            return Ok(());
+        }
+
+        let event_id = Uuid::now_v7().to_string();
+        let payload = json!({
+            "id": event_id,
+            "type": event.as_str(),
+            "created_at": Utc::now(),
+            "data": data,
+        });
+
+        for endpoint in endpoints {
+            sqlx::query!(
+                r#"
+                INSERT INTO webhook_deliveries (
+                    id,
+                    endpoint_id,
+                    user_id,
+                    event_id,
+                    event_type,
+                    payload
+                )
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                Uuid::now_v7().to_string(),
+                endpoint.id,
+                user_id,
+                event_id,
+                event.as_str(),
+                payload
+            )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends every delivery that is due and records how it went.
+    ///
+    /// Unless `allow_private_networks` is set, deliveries to endpoints that
+    /// no longer resolve to public addresses fail without being sent.
+    pub async fn deliver_due(
+        &self,
+        client: &reqwest::Client,
+        encryption_key: &str,
+        allow_private_networks: bool,
+    ) -> ServiceResult<()> {
+        // Claimed deliveries are pushed back for a while, so a dispatcher
+        // that dies mid-send leaves them to be retried
+        let due = sqlx::query_as!(
+            DueDelivery,
+            r#"
+            UPDATE webhook_deliveries d
+            SET next_attempt_at = now() + make_interval(secs => $1),
+                updated_at = now()
+            FROM webhook_endpoints e
+            WHERE e.id = d.endpoint_id
+              AND d.id IN (
+                  SELECT id
+                  FROM webhook_deliveries
+                  WHERE status = 'pending'
+                    AND next_attempt_at <= now()
+                  ORDER BY next_attempt_at
+                  LIMIT $2
+                  FOR UPDATE SKIP LOCKED
+              )
+            RETURNING
+                d.id as "id!",
+                d.event_type as "event_type!",
+                d.payload as "payload!",
+                d.attempts as "attempts!",
+                e.url as "url!",
+                e.secret_ciphertext as "secret_ciphertext!",
+                e.disabled_at as "disabled_at?: DateTime<Utc>"
+            "#,
+            WEBHOOK_CLAIM_SECONDS,
+            WEBHOOK_BATCH_SIZE
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let attempts = due.into_iter().map(|delivery| async move {
+            let outcome = if delivery.disabled_at.is_some() {
+                AttemptOutcome::Failed(None, "Endpoint disabled".to_string())
+            } else {
+                Self::attempt(client, encryption_key, allow_private_networks, &delivery).await
+            };
+            (delivery, outcome)
+        });
+
+        for (delivery, outcome) in futures::future::join_all(attempts).await {
+            if let Err(error) = self.record_attempt(&delivery, outcome).await {
+                tracing::warn!(
+                    "Recording webhook delivery {} failed: {}",
+                    delivery.id,
+                    error
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Posts a delivery to its endpoint.
+    async fn attempt(
+        client: &reqwest::Client,
+        encryption_key: &str,
+        allow_private_networks: bool,
+        delivery: &DueDelivery,
+    ) -> AttemptOutcome {
+        if !allow_private_networks {
+            let checked = match Url::parse(&delivery.url) {
+                Ok(url) => check_destination(&url).await,
+                Err(error) => Err(error.to_string()),
+            };
+            if let Err(error) = checked {
+                return AttemptOutcome::Failed(None, error);
+            }
+        }
+
+        let secret = match decrypt_secret(encryption_key, &delivery.secret_ciphertext) {
+            Ok(secret) => secret,
+            Err(error) => return AttemptOutcome::Failed(None, error.to_string()),
+        };
+
+        let body = delivery.payload.to_string();
+        let timestamp = Utc::now().timestamp();
+        let signature = sign_payload(&secret, timestamp, &body);
+
+        let response = client
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header("Moya-Event", &delivery.event_type)
+            .header("Moya-Delivery", &delivery.id)
+            .header("Moya-Signature", format!("t={timestamp},v1={signature}"))
+            .body(body)
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => {
+                AttemptOutcome::Delivered(response.status().as_u16() as i32)
+            }
+            Ok(response) => AttemptOutcome::Failed(
+                Some(response.status().as_u16() as i32),
+                format!("Endpoint responded with {}", response.status()),
+            ),
+            Err(error) => AttemptOutcome::Failed(None, error.to_string()),
+        }
+    }
+
+    /// Marks a delivery delivered, schedules its retry, or dead-letters it
+    /// once it has run out of attempts.
+    async fn record_attempt(
+        &self,
+        delivery: &DueDelivery,
+        outcome: AttemptOutcome,
+    ) -> ServiceResult<()> {
+        let attempts = delivery.attempts + 1;
+
+        match outcome {
+            AttemptOutcome::Delivered(status_code) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE webhook_deliveries
+                    SET status = 'delivered',
+                        attempts = $2,
+                        last_attempt_at = now(),
+                        last_status_code = $3,
+                        last_error = NULL,
+                        delivered_at = now(),
+                        updated_at = now()
+                    WHERE id = $1
+                    "#,
+                    delivery.id,
+                    attempts,
+                    status_code
+                )
+                .execute(self.pool)
+                .await
+                .map_err(|e| ServiceError::Database { source: e.into() })?;
+            }
+            AttemptOutcome::Failed(status_code, error) => {
+                let dead = delivery.disabled_at.is_some() || attempts >= WEBHOOK_MAX_ATTEMPTS;
+                let status = if dead { "dead" } else { "pending" };
+                let error: String = error.chars().take(WEBHOOK_ERROR_MAX_LEN).collect();
+
+                sqlx::query!(
+                    r#"
+                    UPDATE webhook_deliveries
+                    SET status = $2,
+                        attempts = $3,
+                        last_attempt_at = now(),
+                        last_status_code = $4,
+                        last_error = $5,
+                        next_attempt_at = now() + make_interval(secs => $6),
+                        updated_at = now()
+                    WHERE id = $1
+                    "#,
+                    delivery.id,
+                    status,
+                    attempts,
+                    status_code,
+                    error,
+                    retry_delay_seconds(attempts) as f64
+                )
+                .execute(self.pool)
+                .await
+                .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+                if dead {
+                    tracing::warn!(
+                        "Webhook delivery {} dead-lettered after {} attempts: {}",
+                        delivery.id,
+                        attempts,
+                        error
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Client deliveries are sent with. Redirects are not followed, and
+/// unless `allow_private_networks` is set, names are only connected to at
+/// public addresses.
+fn delivery_client(allow_private_networks: bool) -> reqwest::Result<reqwest::Client> {
+    let builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(WEBHOOK_HTTP_TIMEOUT_SECONDS))
+        .redirect(reqwest::redirect::Policy::none());
+
+    if allow_private_networks {
+        builder.build()
+    } else {
+        builder.dns_resolver(Arc::new(PublicResolver)).build()
+    }
+}
+
+/// Sends due webhook deliveries every few seconds.
+pub async fn run_webhook_dispatcher(
+    pool: PgPool,
+    encryption_key: String,
+    allow_private_networks: bool,
+) {
+    let client = match delivery_client(allow_private_networks) {
+        Ok(client) => client,
+        Err(error) => {
+            tracing::error!("Webhook dispatcher could not start: {}", error);
+            return;
+        }
+    };
+
+    let mut interval = tokio::time::interval(Duration::from_secs(WEBHOOK_POLL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        let service = WebhookService::new(&pool);
+
+        if let Err(error) = service
+            .deliver_due(&client, &encryption_key, allow_private_networks)
+            .await
+        {
+            tracing::warn!("Delivering webhooks failed: {}", error);
+        }
+    }
+}
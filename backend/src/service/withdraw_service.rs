@@ -0,0 +1,432 @@
+// Withdraw Link Service Logic
+//! LNURL-withdraw (LUD-03) links that let someone else pull a bounded
+//! amount out of a user's account, for vouchers, gifts or ATMs.
+//!
+//! Creating a link reserves its total cap, plus a routing fee reserve per
+//! use, from the owner's balance. Claims are paid out of that reservation
+//! and whatever is left goes back to the balance once the link expires,
+//! is used up or is cancelled.
+
+use crate::Config;
+use crate::db::models::{
+    ApiKeyScope, CreateWithdrawLink, LnurlWithdrawRequest, WithdrawLink, WithdrawLinkWithLnurl,
+};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::withdraw_link_repository::WithdrawLinkRepository;
+use crate::service::event_service::EventService;
+use crate::service::node_service::LightningClient;
+use crate::service::payment_service::{PaymentService, debit_account, default_fee_limit_msat};
+use crate::utilities::auth::AuthUser;
+use crate::utilities::lnurl::encode_lnurl;
+use crate::utilities::token::generate_token;
+use bigdecimal::ToPrimitive;
+use chrono::{DateTime, Duration, Utc};
+use lightning_invoice::Bolt11Invoice;
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Lifetime of a link created without an explicit expiry
+const DEFAULT_WITHDRAW_LINK_TTL_DAYS: i64 = 7;
+
+/// How often closed-out links are swept for their remaining reservation
+const SWEEP_INTERVAL_SECONDS: u64 = 60;
+
+// Service layer for Withdraw Link related Operation
+pub struct WithdrawService<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> WithdrawService<'a> {
+    /// Creates a new withdraw link service instance.
+    ///
+    /// # Arguments
+    /// * 'pool' - Reference to Postgres connection pool
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    fn load_config() -> ServiceResult<Config> {
+        Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })
+    }
+
+    /// Creates a withdraw link and reserves its funds from the caller's account.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for:
+    /// - API keys without the 'payments:send' scope or over their spend limit
+    /// - Validation failures
+    /// - Insufficient balance for the reservation
+    pub async fn create_withdraw_link(
+        &self,
+        auth: &AuthUser,
+        create_withdraw_link: CreateWithdrawLink,
+    ) -> ServiceResult<WithdrawLinkWithLnurl> {
+        auth.require_scope(ApiKeyScope::PaymentsSend)?;
+
+        if let Err(validation_errors) = create_withdraw_link.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let min_msat = create_withdraw_link.min_withdrawable_msat;
+        let max_msat = create_withdraw_link.max_withdrawable_msat;
+        let uses = create_withdraw_link.uses.unwrap_or(1);
+
+        if min_msat > max_msat {
+            return Err(ServiceError::validation(
+                "min_withdrawable_msat: Minimum cannot exceed the maximum",
+            ));
+        }
+
+        let too_large = || ServiceError::validation("total_msat: Total is too large");
+
+        let total_msat = match create_withdraw_link.total_msat {
+            Some(total_msat) => total_msat,
+            None => max_msat.checked_mul(uses as u64).ok_or_else(too_large)?,
+        };
+        if total_msat < min_msat {
+            return Err(ServiceError::validation(
+                "total_msat: Total must cover at least one minimum withdrawal",
+            ));
+        }
+
+        let expires_at = create_withdraw_link
+            .expires_at
+            .unwrap_or_else(|| Utc::now() + Duration::days(DEFAULT_WITHDRAW_LINK_TTL_DAYS));
+        if expires_at <= Utc::now() {
+            return Err(ServiceError::validation(
+                "expires_at: Expiry must be in the future",
+            ));
+        }
+
+        let config = Self::load_config()?;
+        let reserved_msat = default_fee_limit_msat(max_msat, &config)
+            .checked_mul(uses as u64)
+            .and_then(|fee_limit_msat| fee_limit_msat.checked_add(total_msat))
+            .ok_or_else(too_large)?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        // Counts against an API key's spend limit when the link is made;
+        // released funds do not restore that allowance
+        debit_account(&mut tx, auth, reserved_msat).await?;
+
+        let withdraw_link = sqlx::query_as!(
+            WithdrawLink,
+            r#"
+            INSERT INTO withdraw_links (
+                id,
+                user_id,
+                account_id,
+                title,
+                k1,
+                min_withdrawable_msat,
+                max_withdrawable_msat,
+                max_uses,
+                total_msat,
+                reserved_msat,
+                expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                title as "title!",
+                k1 as "k1!",
+                min_withdrawable_msat as "min_withdrawable_msat!",
+                max_withdrawable_msat as "max_withdrawable_msat!",
+                max_uses as "max_uses!",
+                uses as "uses!",
+                total_msat as "total_msat!",
+                withdrawn_msat as "withdrawn_msat!",
+                reserved_msat as "reserved_msat!",
+                spent_msat as "spent_msat!",
+                status as "status!",
+                expires_at as "expires_at!: DateTime<Utc>",
+                released_at as "released_at?: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            Uuid::now_v7().to_string(),
+            auth.user_id(),
+            auth.account_id(),
+            create_withdraw_link.title,
+            generate_token(),
+            BigDecimal::from(min_msat),
+            BigDecimal::from(max_msat),
+            uses,
+            BigDecimal::from(total_msat),
+            BigDecimal::from(reserved_msat),
+            expires_at
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        EventService::balance_updated(&mut tx, auth.account_id()).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        with_lnurl(withdraw_link, &config)
+    }
+
+    /// Lists the caller's withdraw links.
+    pub async fn list_withdraw_links(
+        &self,
+        auth: &AuthUser,
+    ) -> ServiceResult<Vec<WithdrawLinkWithLnurl>> {
+        auth.require_scope(ApiKeyScope::PaymentsRead)?;
+
+        let config = Self::load_config()?;
+
+        WithdrawLinkRepository::new(self.pool)
+            .get_withdraw_links_by_user_id(auth.user_id())
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .into_iter()
+            .map(|withdraw_link| with_lnurl(withdraw_link, &config))
+            .collect()
+    }
+
+    /// Cancels one of the caller's links and releases what is left of its reservation.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError::NotFound' if the caller has no active link with that id
+    pub async fn cancel_withdraw_link(&self, auth: &AuthUser, id: &str) -> ServiceResult<()> {
+        auth.require_scope(ApiKeyScope::PaymentsSend)?;
+
+        if !self.release(id, Some(auth.user_id()), "cancelled").await? {
+            return Err(ServiceError::not_found("Withdraw link", id));
+        }
+
+        Ok(())
+    }
+
+    /// Looks up an active link by its secret.
+    async fn get_usable_link(&self, k1: &str) -> ServiceResult<WithdrawLink> {
+        WithdrawLinkRepository::new(self.pool)
+            .get_withdraw_link_by_k1(k1)
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?
+            .filter(|withdraw_link| {
+                withdraw_link.status == "active"
+                    && withdraw_link.expires_at > Utc::now()
+                    && withdraw_link.uses < withdraw_link.max_uses
+            })
+            .ok_or_else(|| ServiceError::not_found("Withdraw link", "link is no longer valid"))
+    }
+
+    /// Builds the `withdrawRequest` a wallet sees when it scans the link.
+    pub async fn withdraw_request(&self, k1: &str) -> ServiceResult<LnurlWithdrawRequest> {
+        let config = Self::load_config()?;
+        let withdraw_link = self.get_usable_link(k1).await?;
+        let (min_withdrawable, max_withdrawable) = withdrawable_range(&withdraw_link)?;
+
+        Ok(LnurlWithdrawRequest {
+            tag: "withdrawRequest".to_string(),
+            callback: format!("{}/api/lnurl/withdraw/callback", config.public_url),
+            k1: withdraw_link.k1,
+            default_description: withdraw_link.title,
+            min_withdrawable,
+            max_withdrawable,
+        })
+    }
+
+    /// Pays the invoice a wallet submitted against a withdraw link.
+    ///
+    /// # Errors
+    /// Returns 'ServiceError' for unknown or used up links, invoices outside
+    /// the withdrawable range and payments that fail
+    pub async fn claim(
+        &self,
+        lightning: &dyn LightningClient,
+        k1: &str,
+        payment_request: &str,
+    ) -> ServiceResult<()> {
+        let withdraw_link = self.get_usable_link(k1).await?;
+        let (min_withdrawable, max_withdrawable) = withdrawable_range(&withdraw_link)?;
+
+        let amount_msat = Bolt11Invoice::from_str(payment_request)
+            .map_err(|e| ServiceError::validation(format!("pr: Invalid invoice: {e}")))?
+            .amount_milli_satoshis()
+            .ok_or_else(|| ServiceError::validation("pr: Invoice must have an amount"))?;
+
+        if amount_msat < min_withdrawable || amount_msat > max_withdrawable {
+            return Err(ServiceError::validation(format!(
+                "pr: Amount must be between {min_withdrawable} and {max_withdrawable} msat"
+            )));
+        }
+
+        let receipt = PaymentService::new(self.pool, lightning)
+            .pay_withdraw_link(&withdraw_link, payment_request)
+            .await?;
+
+        tracing::info!(
+            "Withdraw link {} paid {} ({})",
+            withdraw_link.id,
+            receipt.payment_hash,
+            receipt.status
+        );
+
+        Ok(())
+    }
+
+    /// Closes an active link and credits the unspent reservation back to its account.
+    ///
+    /// # Returns
+    /// 'true' if the link was active and has been closed
+    async fn release(&self, id: &str, owner_id: Option<&str>, status: &str) -> ServiceResult<bool> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let closed = sqlx::query!(
+            r#"
+            UPDATE withdraw_links
+            SET status = $3,
+                released_at = now(),
+                updated_at = now()
+            WHERE id = $1
+              AND ($2::TEXT IS NULL OR user_id = $2)
+              AND status = 'active'
+            RETURNING account_id, reserved_msat - spent_msat AS "released_msat!"
+            "#,
+            id,
+            owner_id,
+            status
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        let Some(closed) = closed else {
+            return Ok(false);
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET balance = balance + $2,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            closed.account_id,
+            closed.released_msat
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        EventService::balance_updated(&mut tx, &closed.account_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ServiceError::Database { source: e.into() })?;
+
+        tracing::info!(
+            "Withdraw link {} {}, released {} msat",
+            id,
+            status,
+            closed.released_msat
+        );
+
+        Ok(true)
+    }
+
+    /// Releases the reservation of every link that has expired or been used up.
+    pub async fn release_closed_links(&self) -> ServiceResult<()> {
+        let withdraw_links = WithdrawLinkRepository::new(self.pool)
+            .get_releasable_withdraw_links()
+            .await
+            .map_err(|e| ServiceError::Database { source: e })?;
+
+        for withdraw_link in withdraw_links {
+            let status = if withdraw_link.expires_at <= Utc::now() {
+                "expired"
+            } else {
+                "exhausted"
+            };
+            self.release(&withdraw_link.id, None, status).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Attaches the bech32 encoded LNURL a wallet scans
+fn with_lnurl(
+    withdraw_link: WithdrawLink,
+    config: &Config,
+) -> ServiceResult<WithdrawLinkWithLnurl> {
+    let lnurl = encode_lnurl(&format!(
+        "{}/api/lnurl/withdraw/{}",
+        config.public_url, withdraw_link.k1
+    ))?;
+
+    Ok(WithdrawLinkWithLnurl {
+        withdraw_link,
+        lnurl,
+    })
+}
+
+/// Amounts a single claim may take, given what is left under the total cap
+fn withdrawable_range(withdraw_link: &WithdrawLink) -> ServiceResult<(u64, u64)> {
+    let to_msat = |amount: &BigDecimal| {
+        amount.to_u64().ok_or_else(|| ServiceError::InternalError {
+            message: format!("Invalid amount on withdraw link {}", withdraw_link.id),
+        })
+    };
+
+    let min_msat = to_msat(&withdraw_link.min_withdrawable_msat)?;
+    let remaining_msat =
+        to_msat(&withdraw_link.total_msat)?.saturating_sub(to_msat(&withdraw_link.withdrawn_msat)?);
+    let max_msat = to_msat(&withdraw_link.max_withdrawable_msat)?.min(remaining_msat);
+
+    if max_msat < min_msat {
+        return Err(ServiceError::invalid_operation(
+            "Withdraw link has no funds left",
+        ));
+    }
+
+    Ok((min_msat, max_msat))
+}
+
+/// Keeps releasing the reservations of links that have closed on their own.
+pub async fn run_withdraw_link_sweeper(pool: PgPool) {
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(SWEEP_INTERVAL_SECONDS));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = WithdrawService::new(&pool).release_closed_links().await {
+            tracing::warn!("Releasing withdraw links failed: {}", error);
+        }
+    }
+}
@@ -0,0 +1,357 @@
+//! Request extractors for authenticated API routes.
+//!
+//! Handlers take an [`AuthUser`] argument to require either a valid access
+//! token in the `Authorization: Bearer <token>` header or a personal API key
+//! (as the bearer token or in `X-Api-Key`). An [`AdminUser`] argument
+//! additionally requires a JWT session holding the configured admin role,
+//! and a [`StreamUser`] also takes the access token from the query string.
+
+use axum::extract::{FromRequestParts, Query};
+use axum::http::{StatusCode, header::AUTHORIZATION, request::Parts};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::common::common::service_error_to_http;
+use crate::config::Config;
+use crate::db::models::{ApiKey, ApiKeyScope};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::account_repository::AccountRepository;
+use crate::repositories::role_repository::RoleRepository;
+use crate::repositories::user_repository::UserRepository;
+use crate::service::api_key_service::{API_KEY_PREFIX, ApiKeyService};
+use crate::utilities::jwt::JwtUtils;
+
+/// Header carrying an API key as an alternative to the bearer token
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// The caller behind an authenticated request
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: String,
+    pub account_id: String,
+    /// Set when the request was authenticated with an API key instead of a JWT
+    pub api_key: Option<ApiKey>,
+}
+
+impl AuthUser {
+    /// ID of the authenticated user
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// ID of the account the credential acts on
+    pub fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    /// Rejects API keys that were not granted `scope`. JWT sessions hold every scope.
+    pub fn require_scope(&self, scope: ApiKeyScope) -> ServiceResult<()> {
+        match &self.api_key {
+            Some(api_key) if !api_key.has_scope(scope) => Err(ServiceError::forbidden(format!(
+                "API key lacks the '{}' scope",
+                scope.as_str()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects API keys for actions that need an interactive login, such as
+    /// changing credentials or managing keys.
+    pub fn require_session(&self) -> ServiceResult<()> {
+        if self.api_key.is_some() {
+            return Err(ServiceError::forbidden(
+                "This action requires a logged in session",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let credential = credential(parts).map_err(service_error_to_http)?;
+
+        if credential.starts_with(API_KEY_PREFIX) {
+            let pool = pool(parts).map_err(service_error_to_http)?;
+            return authenticate_api_key(pool, credential)
+                .await
+                .map_err(service_error_to_http);
+        }
+
+        let pool = pool(parts).map_err(service_error_to_http)?;
+        authenticate_access_token(pool, credential)
+            .await
+            .map_err(service_error_to_http)
+    }
+}
+
+async fn authenticate_access_token(pool: &PgPool, token: &str) -> ServiceResult<AuthUser> {
+    let claims = JwtUtils::new()
+        .and_then(|jwt_utils| jwt_utils.validate_token(token))
+        .map_err(|_| ServiceError::unauthorized("Invalid or expired token"))?;
+
+    // Refresh tokens carry no account and must not be used as access tokens
+    if claims.account_id().is_empty() {
+        return Err(ServiceError::unauthorized("Access token required"));
+    }
+
+    // Tokens outlive a deactivation or deletion, so the user is checked
+    // against the database on every request
+    UserRepository::new(pool)
+        .get_user_by_id(&claims.sub)
+        .await?
+        .filter(|user| user.is_active)
+        .ok_or_else(|| ServiceError::unauthorized("User is not active"))?;
+
+    Ok(AuthUser {
+        user_id: claims.sub,
+        account_id: claims.account_id,
+        api_key: None,
+    })
+}
+
+async fn authenticate_api_key(pool: &PgPool, raw_key: &str) -> ServiceResult<AuthUser> {
+    let (api_key, user) = ApiKeyService::new(pool).authenticate(raw_key).await?;
+
+    let account = AccountRepository::new(pool)
+        .get_accoount_by_user_id(&user.id)
+        .await?
+        .ok_or_else(|| ServiceError::unauthorized("Invalid API key"))?;
+
+    Ok(AuthUser {
+        user_id: user.id,
+        account_id: account.id,
+        api_key: Some(api_key),
+    })
+}
+
+/// An authenticated caller whose current role is the admin role
+#[derive(Debug, Clone)]
+pub struct AdminUser {
+    pub auth: AuthUser,
+}
+
+impl AdminUser {
+    /// ID of the authenticated admin
+    pub fn user_id(&self) -> &str {
+        self.auth.user_id()
+    }
+}
+
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let auth = AuthUser::from_request_parts(parts, state).await?;
+        auth.require_session().map_err(service_error_to_http)?;
+
+        let pool = pool(parts).map_err(service_error_to_http)?;
+
+        // The role claim may be stale, so the role is checked against the database
+        ensure_admin(pool, auth.user_id())
+            .await
+            .map_err(service_error_to_http)?;
+
+        Ok(AdminUser { auth })
+    }
+}
+
+/// An authenticated caller of an event stream.
+///
+/// Browsers cannot set headers when opening an `EventSource` or a
+/// WebSocket, so besides the usual credentials an access token may be
+/// passed in the `access_token` query parameter. API keys are not accepted
+/// there, as URLs end up in logs.
+#[derive(Debug, Clone)]
+pub struct StreamUser {
+    pub auth: AuthUser,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    access_token: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for StreamUser
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let has_credential =
+            parts.headers.contains_key(AUTHORIZATION) || parts.headers.contains_key(API_KEY_HEADER);
+
+        let access_token = Query::<StreamQuery>::try_from_uri(&parts.uri)
+            .ok()
+            .and_then(|Query(query)| query.access_token)
+            .filter(|_| !has_credential);
+
+        let auth = match access_token {
+            Some(token) => {
+                let pool = pool(parts).map_err(service_error_to_http)?;
+                authenticate_access_token(pool, &token)
+                    .await
+                    .map_err(service_error_to_http)?
+            }
+            None => AuthUser::from_request_parts(parts, state).await?,
+        };
+
+        Ok(StreamUser { auth })
+    }
+}
+
+async fn ensure_admin(pool: &PgPool, user_id: &str) -> Result<(), ServiceError> {
+    let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+        message: format!("Config error: {e}"),
+    })?;
+
+    let user = UserRepository::new(pool)
+        .get_user_by_id(user_id)
+        .await?
+        .filter(|user| user.is_active)
+        .ok_or_else(|| ServiceError::unauthorized("User is not active"))?;
+
+    let role = RoleRepository::new(pool).get_role_id(&user.role_id).await?;
+
+    match role {
+        Some(role) if role.is_active && role.name == config.admin_role => Ok(()),
+        _ => Err(ServiceError::forbidden("Admin role required")),
+    }
+}
+
+fn pool(parts: &Parts) -> Result<&PgPool, ServiceError> {
+    parts
+        .extensions
+        .get::<PgPool>()
+        .ok_or_else(|| ServiceError::InternalError {
+            message: "Database pool extension missing".to_string(),
+        })
+}
+
+fn credential(parts: &Parts) -> Result<&str, ServiceError> {
+    if let Some(api_key) = parts.headers.get(API_KEY_HEADER) {
+        return api_key
+            .to_str()
+            .map(str::trim)
+            .map_err(|_| ServiceError::unauthorized("Malformed X-Api-Key header"));
+    }
+
+    let header = parts
+        .headers
+        .get(AUTHORIZATION)
+        .ok_or_else(|| ServiceError::unauthorized("Missing Authorization header"))?
+        .to_str()
+        .map_err(|_| ServiceError::unauthorized("Malformed Authorization header"))?;
+
+    header
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| ServiceError::unauthorized("Authorization header must be a Bearer token"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::testing::{configure, customer};
+    use axum::http::Request;
+
+    fn access_token(auth: &AuthUser) -> String {
+        JwtUtils::new()
+            .unwrap()
+            .generate_token(
+                auth.user_id.clone(),
+                auth.account_id.clone(),
+                "customer".to_string(),
+            )
+            .unwrap()
+    }
+
+    fn request_parts(pool: &PgPool, uri: &str, token: Option<&str>) -> Parts {
+        let mut request = Request::builder().uri(uri).extension(pool.clone());
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    async fn authenticate(pool: &PgPool, token: &str) -> Result<String, StatusCode> {
+        let mut parts = request_parts(pool, "/api/invoices", Some(token));
+        AuthUser::from_request_parts(&mut parts, &())
+            .await
+            .map(|auth| auth.user_id)
+            .map_err(|(status, _)| status)
+    }
+
+    async fn authenticate_stream(pool: &PgPool, token: &str) -> Result<String, StatusCode> {
+        let uri = format!("/api/events?access_token={token}");
+        let mut parts = request_parts(pool, &uri, None);
+        StreamUser::from_request_parts(&mut parts, &())
+            .await
+            .map(|stream| stream.auth.user_id)
+            .map_err(|(status, _)| status)
+    }
+
+    #[sqlx::test]
+    async fn access_tokens_of_active_users_are_accepted(pool: PgPool) {
+        configure();
+        let auth = customer(&pool, 0).await;
+        let token = access_token(&auth);
+
+        assert_eq!(authenticate(&pool, &token).await, Ok(auth.user_id.clone()));
+        assert_eq!(authenticate_stream(&pool, &token).await, Ok(auth.user_id));
+    }
+
+    #[sqlx::test]
+    async fn access_tokens_of_deactivated_users_are_rejected(pool: PgPool) {
+        configure();
+        let auth = customer(&pool, 0).await;
+        let token = access_token(&auth);
+
+        sqlx::query("UPDATE users SET is_active = false WHERE id = $1")
+            .bind(&auth.user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            authenticate(&pool, &token).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            authenticate_stream(&pool, &token).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[sqlx::test]
+    async fn access_tokens_of_deleted_users_are_rejected(pool: PgPool) {
+        configure();
+        let auth = customer(&pool, 0).await;
+        let token = access_token(&auth);
+
+        sqlx::query("UPDATE users SET is_deleted = true, deleted_at = now() WHERE id = $1")
+            .bind(&auth.user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            authenticate(&pool, &token).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            authenticate_stream(&pool, &token).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+}
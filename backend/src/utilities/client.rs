@@ -0,0 +1,96 @@
+//! Request extractor for what is known about the client behind a request,
+//! and the middleware giving every request an ID.
+
+use axum::extract::{ConnectInfo, FromRequestParts, Request};
+use axum::http::{HeaderValue, header::USER_AGENT, request::Parts};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+/// Header a reverse proxy puts the original client address in
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Header carrying the ID of a request, set by a proxy or by `set_request_id`
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request ID taken from a proxy
+const REQUEST_ID_MAX_LEN: usize = 128;
+
+/// Address and user agent of the client behind a request
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    /// First address of `X-Forwarded-For` when set by a proxy, the peer
+    /// address otherwise
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// ID the request is logged and audited under
+    pub request_id: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let forwarded_for = parts
+            .headers
+            .get(FORWARDED_FOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+            .map(String::from);
+
+        let ip_address = forwarded_for.or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip().to_string())
+        });
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+
+        let request_id = parts
+            .headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+
+        Ok(ClientInfo {
+            ip_address,
+            user_agent,
+            request_id,
+        })
+    }
+}
+
+/// Keeps the request ID a proxy sent, or gives the request a new one, and
+/// echoes it in the response so a client can quote it to support.
+pub async fn set_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .filter(|value| !value.is_empty() && value.len() <= REQUEST_ID_MAX_LEN)
+        .filter(|value| value.to_str().is_ok())
+        .cloned()
+        .unwrap_or_else(|| {
+            HeaderValue::from_str(&Uuid::now_v7().to_string())
+                .expect("UUIDs are valid header values")
+        });
+
+    request
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER, request_id.clone());
+
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, request_id);
+    response
+}
@@ -0,0 +1,49 @@
+//! Secrets the bank has to read back later, encrypted at rest with
+//! `ENCRYPTION_KEY`.
+
+use crate::errors::{ServiceError, ServiceResult};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+const NONCE_LEN: usize = 12;
+
+fn cipher(encryption_key: &str) -> Aes256Gcm {
+    // Any length of configured key is stretched to the 256 bits AES needs
+    let key = Sha256::digest(encryption_key.as_bytes());
+    Aes256Gcm::new(&key)
+}
+
+/// Encrypts `plaintext`, returning the hex encoded nonce and ciphertext
+pub fn encrypt_secret(encryption_key: &str, plaintext: &str) -> ServiceResult<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = cipher(encryption_key)
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| ServiceError::InternalError {
+            message: "Failed to encrypt secret".to_string(),
+        })?;
+
+    Ok(hex::encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+/// Decrypts a secret produced by `encrypt_secret`
+pub fn decrypt_secret(encryption_key: &str, encrypted: &str) -> ServiceResult<String> {
+    let failed = || ServiceError::InternalError {
+        message: "Failed to decrypt secret".to_string(),
+    };
+
+    let bytes = hex::decode(encrypted).map_err(|_| failed())?;
+    if bytes.len() < NONCE_LEN {
+        return Err(failed());
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+
+    let plaintext = cipher(encryption_key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| failed())?;
+
+    String::from_utf8(plaintext).map_err(|_| failed())
+}
@@ -0,0 +1,330 @@
+//! LNURL encoding helpers, success action decryption and LNURL-auth
+//! signature checks.
+
+use aes::Aes256;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use bitcoin::bech32;
+use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, ecdsa::Signature};
+use cbc::cipher::{BlockDecryptMut, KeyIvInit, block_padding::Pkcs7};
+use reqwest::Url;
+
+use crate::db::models::{PaymentSuccessAction, SuccessAction};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::utilities::network::check_destination;
+
+/// Human readable part of bech32 encoded LNURLs
+const LNURL_HRP: &str = "lnurl";
+
+/// Encode a URL as an uppercase `LNURL1...` string, which makes for denser QR codes
+pub fn encode_lnurl(url: &str) -> ServiceResult<String> {
+    let hrp = bech32::Hrp::parse(LNURL_HRP).map_err(|e| ServiceError::InternalError {
+        message: e.to_string(),
+    })?;
+
+    bech32::encode::<bech32::Bech32>(hrp, url.as_bytes())
+        .map(|lnurl| lnurl.to_uppercase())
+        .map_err(|e| ServiceError::InternalError {
+            message: format!("Could not encode LNURL: {e}"),
+        })
+}
+
+/// Decode a `lnurl1...` string into the URL it carries
+pub fn decode_lnurl(lnurl: &str) -> ServiceResult<Url> {
+    let (hrp, data) = bech32::decode(lnurl)
+        .map_err(|e| ServiceError::validation(format!("destination: Invalid LNURL: {e}")))?;
+
+    if !hrp.as_str().eq_ignore_ascii_case(LNURL_HRP) {
+        return Err(ServiceError::validation(
+            "destination: Invalid LNURL prefix",
+        ));
+    }
+
+    let url = String::from_utf8(data)
+        .map_err(|_| ServiceError::validation("destination: LNURL does not contain a URL"))?;
+
+    Url::parse(&url).map_err(|e| ServiceError::validation(format!("destination: Invalid URL: {e}")))
+}
+
+/// Well-known LNURL-pay URL behind a `name@domain` Lightning Address (LUD-16)
+pub fn lightning_address_url(address: &str, allow_http: bool) -> ServiceResult<Url> {
+    let (name, domain) = address
+        .split_once('@')
+        .filter(|(name, domain)| !name.is_empty() && !domain.is_empty())
+        .ok_or_else(|| ServiceError::validation("destination: Invalid Lightning Address"))?;
+
+    let scheme = if allow_http || domain.ends_with(".onion") {
+        "http"
+    } else {
+        "https"
+    };
+
+    Url::parse(&format!(
+        "{scheme}://{domain}/.well-known/lnurlp/{}",
+        name.to_lowercase()
+    ))
+    .map_err(|e| ServiceError::validation(format!("destination: Invalid Lightning Address: {e}")))
+}
+
+/// Plain text description from LNURL-pay metadata, a JSON array of
+/// `[mime type, content]` pairs (LUD-06)
+pub fn metadata_description(metadata: &str) -> Option<String> {
+    let entries: Vec<(String, serde_json::Value)> = serde_json::from_str(metadata).ok()?;
+
+    entries
+        .into_iter()
+        .find(|(mime, _)| mime == "text/plain")
+        .and_then(|(_, content)| content.as_str().map(str::to_string))
+}
+
+/// Only talk to LNURL services over HTTPS, except for Tor hidden services
+/// and when plain HTTP is explicitly allowed
+pub fn ensure_secure_url(url: &Url, allow_http: bool) -> ServiceResult<()> {
+    let onion = url.host_str().is_some_and(|host| host.ends_with(".onion"));
+
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if allow_http || onion => Ok(()),
+        _ => Err(ServiceError::validation(
+            "destination: LNURL services must use HTTPS",
+        )),
+    }
+}
+
+/// Only talk to LNURL services at public addresses, unless private networks
+/// are explicitly allowed
+pub async fn ensure_public_url(url: &Url, allow_private_networks: bool) -> ServiceResult<()> {
+    if allow_private_networks {
+        return Ok(());
+    }
+
+    check_destination(url)
+        .await
+        .map_err(|e| ServiceError::validation(format!("destination: {e}")))
+}
+
+/// Turn a success action into what the payer sees, decrypting LUD-10 AES
+/// payloads with the payment preimage
+pub fn resolve_success_action(
+    action: SuccessAction,
+    preimage_hex: &str,
+) -> ServiceResult<PaymentSuccessAction> {
+    match action {
+        SuccessAction::Message { message } => Ok(PaymentSuccessAction::Message { message }),
+        SuccessAction::Url { description, url } => {
+            Ok(PaymentSuccessAction::Url { description, url })
+        }
+        SuccessAction::Aes {
+            description,
+            ciphertext,
+            iv,
+        } => {
+            let key = hex::decode(preimage_hex).map_err(|e| ServiceError::InternalError {
+                message: format!("Invalid preimage: {e}"),
+            })?;
+            let iv = STANDARD
+                .decode(iv)
+                .map_err(|e| ServiceError::validation(format!("Invalid success action IV: {e}")))?;
+            let mut ciphertext = STANDARD.decode(ciphertext).map_err(|e| {
+                ServiceError::validation(format!("Invalid success action ciphertext: {e}"))
+            })?;
+
+            let plaintext = cbc::Decryptor::<Aes256>::new_from_slices(&key, &iv)
+                .map_err(|e| ServiceError::validation(format!("Invalid success action key: {e}")))?
+                .decrypt_padded_mut::<Pkcs7>(&mut ciphertext)
+                .map_err(|_| ServiceError::validation("Success action could not be decrypted"))?;
+
+            Ok(PaymentSuccessAction::Aes {
+                description,
+                plaintext: String::from_utf8_lossy(plaintext).into_owned(),
+            })
+        }
+    }
+}
+
+/// Check a LUD-04 signature: a DER encoded ECDSA signature by the wallet's
+/// linking key over the raw 32 bytes of k1. Returns the linking key as
+/// lowercase compressed hex, so one key always maps to the same string
+pub fn verify_auth_signature(k1: &str, sig: &str, key: &str) -> ServiceResult<String> {
+    let k1 = hex::decode(k1).map_err(|_| ServiceError::validation("k1: Invalid challenge"))?;
+    let message = Message::from_digest_slice(&k1)
+        .map_err(|_| ServiceError::validation("k1: Invalid challenge"))?;
+
+    let mut signature = hex::decode(sig)
+        .ok()
+        .and_then(|sig| Signature::from_der(&sig).ok())
+        .ok_or_else(|| ServiceError::validation("sig: Invalid signature encoding"))?;
+    // Wallets are not required to produce low-S signatures
+    signature.normalize_s();
+
+    let key = hex::decode(key)
+        .ok()
+        .and_then(|key| PublicKey::from_slice(&key).ok())
+        .ok_or_else(|| ServiceError::validation("key: Invalid linking key"))?;
+
+    Secp256k1::verification_only()
+        .verify_ecdsa(&message, &signature, &key)
+        .map_err(|_| ServiceError::validation("sig: Signature does not match the linking key"))?;
+
+    Ok(hex::encode(key.serialize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cbc::cipher::BlockEncryptMut;
+
+    const SERVICE_URL: &str = "https://service.com/api?q=3fc3645b439ce8e7f2553a69e5267081d96dcd340693afabe04be7b0ccd178df";
+
+    /// Encrypts a LUD-10 success action the way an LNURL-pay service does
+    fn aes_action(preimage: &[u8; 32], iv: &[u8; 16], plaintext: &str) -> SuccessAction {
+        let ciphertext = cbc::Encryptor::<Aes256>::new_from_slices(preimage, iv)
+            .unwrap()
+            .encrypt_padded_vec_mut::<Pkcs7>(plaintext.as_bytes());
+
+        SuccessAction::Aes {
+            description: "Your voucher".to_string(),
+            ciphertext: STANDARD.encode(ciphertext),
+            iv: STANDARD.encode(iv),
+        }
+    }
+
+    #[test]
+    fn decodes_the_lud01_example() {
+        let lnurl = "LNURL1DP68GURN8GHJ7UM9WFMXJCM99E3K7MF0V9CXJ0M385EKVCENXC6R2C35XVUKXEFCV5MKVV34X5EKZD3EV56NYD3HXQURZEPEXEJXXEPNXSCRVWFNV9NXZCN9XQ6XYEFHVGCXXCMYXYMNSERXFQ5FNS";
+
+        assert_eq!(decode_lnurl(lnurl).unwrap().as_str(), SERVICE_URL);
+        assert_eq!(
+            decode_lnurl(&lnurl.to_lowercase()).unwrap().as_str(),
+            SERVICE_URL
+        );
+    }
+
+    #[test]
+    fn encoded_lnurls_decode_to_the_same_url() {
+        let lnurl = encode_lnurl(SERVICE_URL).unwrap();
+
+        assert!(lnurl.starts_with("LNURL1"));
+        assert_eq!(decode_lnurl(&lnurl).unwrap().as_str(), SERVICE_URL);
+    }
+
+    #[test]
+    fn rejects_other_bech32_strings() {
+        let hrp = bech32::Hrp::parse("lnbc").unwrap();
+        let invoice_like = bech32::encode::<bech32::Bech32>(hrp, SERVICE_URL.as_bytes()).unwrap();
+
+        assert!(decode_lnurl(&invoice_like).is_err());
+        assert!(decode_lnurl("lnurl1notbech32").is_err());
+
+        let hrp = bech32::Hrp::parse(LNURL_HRP).unwrap();
+        let not_a_url = bech32::encode::<bech32::Bech32>(hrp, b"not a url").unwrap();
+        assert!(decode_lnurl(&not_a_url).is_err());
+    }
+
+    #[test]
+    fn lightning_addresses_map_to_the_well_known_url() {
+        assert_eq!(
+            lightning_address_url("Alice@example.com", false)
+                .unwrap()
+                .as_str(),
+            "https://example.com/.well-known/lnurlp/alice"
+        );
+        assert_eq!(
+            lightning_address_url("alice@localhost:8080", true)
+                .unwrap()
+                .as_str(),
+            "http://localhost:8080/.well-known/lnurlp/alice"
+        );
+        assert_eq!(
+            lightning_address_url("bob@wallet.onion", false)
+                .unwrap()
+                .scheme(),
+            "http"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_lightning_addresses() {
+        for address in ["alice", "@example.com", "alice@", "alice@exa mple.com"] {
+            assert!(
+                lightning_address_url(address, false).is_err(),
+                "{address} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn plain_http_is_only_allowed_for_onions_or_when_enabled() {
+        let http = Url::parse("http://example.com/lnurlp").unwrap();
+        let onion = Url::parse("http://example.onion/lnurlp").unwrap();
+        let https = Url::parse("https://example.com/lnurlp").unwrap();
+
+        assert!(ensure_secure_url(&http, false).is_err());
+        assert!(ensure_secure_url(&http, true).is_ok());
+        assert!(ensure_secure_url(&onion, false).is_ok());
+        assert!(ensure_secure_url(&https, false).is_ok());
+    }
+
+    #[test]
+    fn reads_the_plain_text_description_from_metadata() {
+        let metadata = r#"[["text/identifier","alice@example.com"],["text/plain","Pay alice"]]"#;
+
+        assert_eq!(metadata_description(metadata).as_deref(), Some("Pay alice"));
+        assert_eq!(metadata_description("not json"), None);
+    }
+
+    #[test]
+    fn decrypts_aes_success_actions_with_the_preimage() {
+        let preimage = [7u8; 32];
+        let action = aes_action(&preimage, &[9u8; 16], "Voucher code: 1234-5678");
+
+        match resolve_success_action(action, &hex::encode(preimage)).unwrap() {
+            PaymentSuccessAction::Aes {
+                description,
+                plaintext,
+            } => {
+                assert_eq!(description, "Your voucher");
+                assert_eq!(plaintext, "Voucher code: 1234-5678");
+            }
+            other => panic!("unexpected success action {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_aes_success_actions_that_do_not_decrypt() {
+        let preimage = [7u8; 32];
+
+        // A different preimage leaves the padding invalid
+        let action = aes_action(&preimage, &[9u8; 16], "Voucher code: 1234-5678");
+        assert!(resolve_success_action(action, &hex::encode([8u8; 32])).is_err());
+
+        let SuccessAction::Aes {
+            description,
+            ciphertext,
+            ..
+        } = aes_action(&preimage, &[9u8; 16], "Voucher")
+        else {
+            unreachable!()
+        };
+        let short_iv = SuccessAction::Aes {
+            description,
+            ciphertext,
+            iv: STANDARD.encode([9u8; 8]),
+        };
+        assert!(resolve_success_action(short_iv, &hex::encode(preimage)).is_err());
+    }
+
+    #[test]
+    fn passes_other_success_actions_through() {
+        let action = SuccessAction::Url {
+            description: "Receipt".to_string(),
+            url: "https://example.com/receipt".to_string(),
+        };
+
+        match resolve_success_action(action, "").unwrap() {
+            PaymentSuccessAction::Url { url, .. } => {
+                assert_eq!(url, "https://example.com/receipt")
+            }
+            other => panic!("unexpected success action {other:?}"),
+        }
+    }
+}
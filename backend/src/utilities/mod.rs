@@ -4,11 +4,21 @@ use bitcoin::secp256k1::PublicKey;
 use expanduser::expanduser;
 use lightning::ln::features::NodeFeatures;
 use serde::{Deserialize, Deserializer, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
+pub mod auth;
+pub mod client;
+pub mod crypto;
 pub mod jwt;
+pub mod lnurl;
+pub mod network;
+pub mod password;
+pub mod swap;
+#[cfg(test)]
+pub mod testing;
+pub mod token;
 
 // #[derive(Serialize, Debug, Clone)]
 // pub enum NodeId {
@@ -51,26 +61,76 @@ pub mod jwt;
 //     }
 // }
 
-// #[derive(Serialize, Deserialize, Debug, Clone)]
-// pub struct NodeInfo {
-//     pub pubkey: PublicKey,
-//     pub alias: String,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NodeInfo {
+    pub pubkey: PublicKey,
+    pub alias: String,
+}
 
-//     #[serde(with = "node_features_serde")]
-//     pub features: NodeFeatures
-// }
+impl Display for NodeInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let pk = self.pubkey.to_string();
+        let pk_summary = format!("{}...{}", &pk[..6], &pk[pk.len() - 6..]);
+        if self.alias.is_empty() {
+            write!(f, "{pk_summary}")
+        } else {
+            write!(f, "{}({})", self.alias, pk_summary)
+        }
+    }
+}
 
-// impl Display for NodeInfo {
-//   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//     let pk = self.pubkey.to_string();
-//     let pk_summary = format!("{}...{}", &pk[..6], &pk[pk.len() - 6..]);
-//     if self.alias.is_empty() {
-//        write!(f, "{pk_summary}")
-//     } else {
-//        write!(f, "{}({})", self.alias, pk_summary)
-//     }
-//   }
-// }
+/// Parameters for a new invoice on the node
+#[derive(Debug, Clone, Default)]
+pub struct InvoiceRequest {
+    /// 'None' lets the payer choose the amount
+    pub amount_msat: Option<u64>,
+    pub memo: String,
+    /// Description committed to by its SHA-256 instead of the memo (`h` tag)
+    pub hashed_description: Option<String>,
+    pub expiry: u64,
+}
+
+/// Invoice the node holds payments to until they are settled or canceled
+#[derive(Debug, Clone)]
+pub struct HoldInvoiceRequest {
+    pub invoice: InvoiceRequest,
+    /// Payment hash of the preimage the invoice will be settled with
+    pub payment_hash: [u8; 32],
+    /// Blocks the payer's HTLC must stay claimable for after it arrives
+    pub cltv_expiry: u64,
+}
+
+/// Spontaneous payment to a node, without an invoice
+#[derive(Debug, Clone)]
+pub struct KeysendRequest {
+    pub destination: PublicKey,
+    pub amount_msat: u64,
+    /// Chosen by the sender and carried to the recipient in the onion
+    pub payment_preimage: [u8; 32],
+    /// Extra TLV records for the recipient, such as podcast boost metadata
+    pub custom_records: BTreeMap<u64, Vec<u8>>,
+}
+
+/// Limits the node routes an outgoing payment under
+#[derive(Debug, Clone)]
+pub struct RoutingPolicy {
+    pub fee_limit_msat: u64,
+    /// How long the node may keep looking for a route
+    pub timeout_seconds: u64,
+    /// Channels the payment may leave through, any when empty
+    pub outgoing_chan_ids: Vec<u64>,
+    /// Peer the payment must reach the payee through
+    pub last_hop_pubkey: Option<PublicKey>,
+}
+
+/// BOLT12 invoice fetched from the node behind an offer
+#[derive(Debug, Clone)]
+pub struct OfferInvoice {
+    /// Bech32 encoded `lni1...` invoice
+    pub invoice: String,
+    pub payment_hash: String,
+    pub amount_msat: u64,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CustomInvoice {
@@ -79,6 +139,7 @@ pub struct CustomInvoice {
     pub payment_preimage: String,
     pub value: u64,
     pub value_msat: u64,
+    pub amount_paid_msat: u64,
     pub creation_date: Option<i64>,
     pub settle_date: Option<i64>,
     pub payment_request: String,
@@ -88,9 +149,70 @@ pub struct CustomInvoice {
     pub is_amp: Option<bool>,
     pub payment_addr: Option<String>,
     pub htlcs: Option<Vec<InvoiceHtlc>>,
+    /// Hex ID of the BOLT12 offer the invoice was issued for, if any
+    pub offer_id: Option<String>,
     //  pub features: Option<HashMap<u32, Feature>>,
 }
 
+/// Output paying an address in the node's on-chain wallet
+#[derive(Debug, Clone)]
+pub struct OnchainReceipt {
+    pub txid: String,
+    pub vout: u32,
+    pub address: String,
+    pub amount_sat: u64,
+    pub confirmations: u32,
+    /// 'None' while the transaction is unconfirmed
+    pub block_height: Option<u32>,
+}
+
+/// Transaction in the node's on-chain wallet
+#[derive(Debug, Clone)]
+pub struct WalletTransaction {
+    pub confirmations: u32,
+    pub fee_sat: u64,
+}
+
+/// Terms of a submarine swap (chain to Lightning) agreed with a swap provider
+#[derive(Debug, Clone)]
+pub struct SubmarineSwapTerms {
+    pub id: String,
+    /// Address the user locks the coins on
+    pub address: String,
+    pub expected_amount_sat: u64,
+    pub redeem_script: String,
+    pub timeout_block_height: u32,
+}
+
+/// Terms of a reverse swap (Lightning to chain) agreed with a swap provider
+#[derive(Debug, Clone)]
+pub struct ReverseSwapTerms {
+    pub id: String,
+    /// Hold invoice the bank pays to start the swap
+    pub invoice: String,
+    /// Address the provider locks the coins on
+    pub lockup_address: String,
+    pub onchain_amount_sat: u64,
+    pub redeem_script: String,
+    pub timeout_block_height: u32,
+}
+
+/// Latest state of a swap as reported by the swap provider
+#[derive(Debug, Clone)]
+pub struct SwapUpdate {
+    /// Provider status, such as 'transaction.confirmed' or 'swap.expired'
+    pub status: String,
+    /// Lockup transaction, once the provider has seen one
+    pub transaction_hex: Option<String>,
+}
+
+/// Result of an outgoing payment made by the node
+#[derive(Debug, Clone)]
+pub struct PaymentOutcome {
+    pub payment_preimage: String,
+    pub fee_msat: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum PaymentType {
     Outgoing,
@@ -103,6 +225,8 @@ pub enum InvoiceStatus {
     #[default]
     Settled,
     Open,
+    /// Paid to a hold invoice, waiting to be settled or canceled
+    Accepted,
     Expired,
     Failed,
 }
@@ -116,6 +240,8 @@ pub struct InvoiceHtlc {
     pub resolve_time: Option<i64>,
     pub expiry_height: Option<u32>,
     pub mpp_total_amt_msat: Option<u64>,
+    /// TLV records the sender attached to the payment onion
+    pub custom_records: BTreeMap<u64, Vec<u8>>,
 }
 
 #[derive(Debug, Serialize)]
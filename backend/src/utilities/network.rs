@@ -0,0 +1,203 @@
+//! Guards for outbound requests to hosts named by users, such as webhook
+//! endpoints and LNURL services, so they cannot be used to reach loopback,
+//! private or link-local addresses on the bank's own network.
+
+use hyper::client::connect::dns::Name;
+use reqwest::Url;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// Whether `ip` is reachable on the public internet, rather than a
+/// loopback, private, link-local or other special-purpose address.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => {
+                let prefix = ip.segments()[0];
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    // Unique local, fc00::/7
+                    || prefix & 0xfe00 == 0xfc00
+                    // Link-local, fe80::/10
+                    || prefix & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+
+    !(first == 0
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // Carrier-grade NAT, 100.64.0.0/10
+        || (first == 100 && second & 0xc0 == 64))
+}
+
+/// Resolves `host`, failing unless every address it resolves to is public.
+async fn resolve_public(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("Could not resolve {host}: {e}"))?
+        .collect();
+
+    if addresses.is_empty() {
+        return Err(format!("Could not resolve {host}"));
+    }
+    if let Some(address) = addresses
+        .iter()
+        .find(|address| !is_public_address(address.ip()))
+    {
+        return Err(format!(
+            "{host} resolves to {}, which is not a public address",
+            address.ip()
+        ));
+    }
+
+    Ok(addresses)
+}
+
+/// Checks that `url` points at a public address.
+pub async fn check_destination(url: &Url) -> Result<(), String> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| "URL has no host".to_string())?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+
+    resolve_public(host, url.port_or_known_default().unwrap_or(443))
+        .await
+        .map(|_| ())
+}
+
+/// Resolver for clients that must only reach public addresses. Every
+/// connection is checked, so a name pointed at a non-public address after
+/// an earlier check is still refused.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = resolve_public(name.as_str(), 0).await?;
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Follows up to `max_redirects` redirects, refusing ones to a non-public
+/// IP address. Redirects to a name are checked by `PublicResolver` when
+/// connecting.
+pub fn public_redirect_policy(max_redirects: usize) -> Policy {
+    Policy::custom(move |attempt| {
+        let host = attempt
+            .url()
+            .host_str()
+            .map(|host| host.trim_start_matches('[').trim_end_matches(']'));
+
+        match host.and_then(|host| host.parse::<IpAddr>().ok()) {
+            Some(ip) if !is_public_address(ip) => {
+                let error = format!("Redirect to {ip}, which is not a public address");
+                attempt.error(error)
+            }
+            _ if attempt.previous().len() >= max_redirects => attempt.stop(),
+            _ => attempt.follow(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn only_public_addresses_are_allowed() {
+        let public = ["93.184.216.34", "8.8.8.8", "2606:4700::1111"];
+        let internal = [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.0.0.5",
+            "172.16.3.4",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "255.255.255.255",
+            "224.0.0.1",
+            "::",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ];
+
+        for address in public {
+            assert!(is_public_address(address.parse().unwrap()), "{address}");
+        }
+        for address in internal {
+            assert!(!is_public_address(address.parse().unwrap()), "{address}");
+        }
+    }
+
+    #[tokio::test]
+    async fn internal_destinations_are_refused() {
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "https://[::1]/hook",
+            "https://10.1.2.3:8443/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "https://localhost/hook",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert!(check_destination(&url).await.is_err(), "{url}");
+        }
+
+        let url = Url::parse("https://93.184.216.34/hook").unwrap();
+        assert_eq!(check_destination(&url).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn resolver_refuses_internal_names() {
+        let resolved = PublicResolver
+            .resolve(Name::from_str("localhost").unwrap())
+            .await;
+
+        assert!(resolved.is_err());
+    }
+
+    #[tokio::test]
+    async fn redirects_to_internal_addresses_are_refused() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let target = format!("{base_url}/target");
+        let app = axum::Router::new()
+            .route(
+                "/redirect",
+                axum::routing::get(move || async move { axum::response::Redirect::to(&target) }),
+            )
+            .route("/target", axum::routing::get(|| async { "reached" }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        // No resolver, so only the redirect policy stands in the way
+        let client = reqwest::Client::builder()
+            .redirect(public_redirect_policy(10))
+            .build()
+            .unwrap();
+
+        let error = client
+            .get(format!("{base_url}/redirect"))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(error.is_redirect(), "{error}");
+    }
+}
@@ -0,0 +1,378 @@
+//! Password hashing and password strength policy.
+//!
+//! New hashes are produced with Argon2id and stored in PHC string format
+//! (`$argon2id$v=19$m=...,t=...,p=...$salt$hash`), so every stored hash
+//! records the algorithm and parameters it was made with. Hashes from the
+//! previous bcrypt scheme still verify and are flagged for a rehash.
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use validator::ValidationError;
+
+use crate::config::Config;
+use crate::errors::{ServiceError, ServiceResult};
+
+/// A password hashing scheme
+pub trait PasswordHasher: Send + Sync {
+    /// Whether `hash` was produced by this scheme
+    fn recognizes(&self, hash: &str) -> bool;
+
+    /// Hash a password into a self-describing string
+    fn hash(&self, password: &str) -> ServiceResult<String>;
+
+    /// Check a password against a hash produced by this scheme
+    fn verify(&self, password: &str, hash: &str) -> ServiceResult<bool>;
+
+    /// Whether a hash produced by this scheme should be replaced on next login
+    fn needs_rehash(&self, hash: &str) -> bool;
+}
+
+/// Argon2id with tunable memory, time and parallelism costs
+pub struct Argon2idHasher {
+    params: Params,
+}
+
+impl Argon2idHasher {
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> ServiceResult<Self> {
+        let params = Params::new(memory_kib, iterations, parallelism, None).map_err(|e| {
+            ServiceError::InternalError {
+                message: format!("Invalid Argon2 parameters: {e}"),
+            }
+        })?;
+
+        Ok(Self { params })
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+impl PasswordHasher for Argon2idHasher {
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$argon2")
+    }
+
+    fn hash(&self, password: &str) -> ServiceResult<String> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| ServiceError::InternalError {
+                message: format!("Password hashing failed: {e}"),
+            })
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> ServiceResult<bool> {
+        let parsed = PasswordHash::new(hash).map_err(|e| ServiceError::InternalError {
+            message: format!("Stored password hash is malformed: {e}"),
+        })?;
+
+        // Verification uses the parameters recorded in the hash, not the current ones
+        Ok(self
+            .argon2()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok())
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return true;
+        };
+
+        if parsed.algorithm != Algorithm::Argon2id.ident()
+            || parsed.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+
+        match Params::try_from(&parsed) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+/// Legacy bcrypt hashes, kept for verification only
+pub struct BcryptHasher;
+
+impl PasswordHasher for BcryptHasher {
+    fn recognizes(&self, hash: &str) -> bool {
+        ["$2a$", "$2b$", "$2x$", "$2y$"]
+            .iter()
+            .any(|prefix| hash.starts_with(prefix))
+    }
+
+    fn hash(&self, password: &str) -> ServiceResult<String> {
+        bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|e| ServiceError::InternalError {
+            message: format!("Password hashing failed: {e}"),
+        })
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> ServiceResult<bool> {
+        bcrypt::verify(password, hash)
+            .map_err(|e| ServiceError::validation(format!("Password verification failed: {e}")))
+    }
+
+    fn needs_rehash(&self, _hash: &str) -> bool {
+        true
+    }
+}
+
+/// Outcome of checking a password against a stored hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordMatch {
+    /// The password is wrong
+    Invalid,
+    /// The password is right and the stored hash is current
+    Valid,
+    /// The password is right but the stored hash should be replaced
+    ValidNeedsRehash,
+}
+
+/// Hashes with the current scheme and verifies against any supported scheme
+pub struct PasswordManager {
+    current: Box<dyn PasswordHasher>,
+    legacy: Vec<Box<dyn PasswordHasher>>,
+}
+
+impl PasswordManager {
+    /// Build the manager from the Argon2 settings in the environment
+    pub fn from_env() -> ServiceResult<Self> {
+        let config = Config::from_env().map_err(|e| ServiceError::InternalError {
+            message: format!("Config error: {e}"),
+        })?;
+
+        Self::from_config(&config)
+    }
+
+    pub fn from_config(config: &Config) -> ServiceResult<Self> {
+        let current = Argon2idHasher::new(
+            config.argon2_memory_kib,
+            config.argon2_iterations,
+            config.argon2_parallelism,
+        )?;
+
+        Ok(Self {
+            current: Box::new(current),
+            legacy: vec![Box::new(BcryptHasher)],
+        })
+    }
+
+    /// Hash a password with the current scheme
+    pub fn hash(&self, password: &str) -> ServiceResult<String> {
+        self.current.hash(password)
+    }
+
+    /// Check a password against a stored hash of any supported scheme
+    pub fn verify(&self, password: &str, hash: &str) -> ServiceResult<PasswordMatch> {
+        let (hasher, is_current) = if self.current.recognizes(hash) {
+            (&self.current, true)
+        } else {
+            let hasher = self
+                .legacy
+                .iter()
+                .find(|hasher| hasher.recognizes(hash))
+                .ok_or_else(|| ServiceError::InternalError {
+                    message: "Stored password hash uses an unsupported algorithm".to_string(),
+                })?;
+            (hasher, false)
+        };
+
+        if !hasher.verify(password, hash)? {
+            return Ok(PasswordMatch::Invalid);
+        }
+
+        if !is_current || hasher.needs_rehash(hash) {
+            Ok(PasswordMatch::ValidNeedsRehash)
+        } else {
+            Ok(PasswordMatch::Valid)
+        }
+    }
+}
+
+/// Minimum requirements for new passwords
+#[derive(Debug)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    breached: HashSet<String>,
+}
+
+static PASSWORD_POLICY: OnceLock<PasswordPolicy> = OnceLock::new();
+
+impl PasswordPolicy {
+    /// Load the policy once at startup, reading the breach list from disk
+    pub fn init(config: &Config) -> anyhow::Result<()> {
+        PASSWORD_POLICY
+            .set(Self::from_config(config)?)
+            .map_err(|_| anyhow::anyhow!("Password policy already initialized"))
+    }
+
+    fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let breached = match &config.password_breach_list {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Failed to read breach list {path}: {e}"))?
+                .lines()
+                .map(|line| line.trim().to_lowercase())
+                .filter(|line| !line.is_empty())
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        Ok(PasswordPolicy {
+            min_length: config.password_min_length,
+            breached,
+        })
+    }
+
+    fn global() -> &'static PasswordPolicy {
+        PASSWORD_POLICY.get_or_init(|| PasswordPolicy {
+            min_length: DEFAULT_PASSWORD_MIN_LENGTH,
+            breached: HashSet::new(),
+        })
+    }
+
+    fn check(&self, password: &str) -> Result<(), ValidationError> {
+        if password.chars().count() < self.min_length {
+            return Err(
+                ValidationError::new("password_too_short").with_message(Cow::from(format!(
+                    "Password must be at least {} characters",
+                    self.min_length
+                ))),
+            );
+        }
+
+        if self.breached.contains(&password.to_lowercase()) {
+            return Err(
+                ValidationError::new("password_breached").with_message(Cow::from(
+                    "Password appears in a list of breached passwords",
+                )),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Minimum password length used when none is configured
+pub const DEFAULT_PASSWORD_MIN_LENGTH: usize = 12;
+
+/// Validator hook enforcing the password policy on new passwords
+pub fn validate_password_strength(password: &str) -> Result<(), ValidationError> {
+    PasswordPolicy::global().check(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::testing::configure;
+    use uuid::Uuid;
+
+    /// Cheapest costs Argon2 accepts, to keep the tests fast
+    fn argon2id(iterations: u32) -> Argon2idHasher {
+        Argon2idHasher::new(8, iterations, 1).unwrap()
+    }
+
+    fn manager() -> PasswordManager {
+        PasswordManager {
+            current: Box::new(argon2id(1)),
+            legacy: vec![Box::new(BcryptHasher)],
+        }
+    }
+
+    #[test]
+    fn argon2id_hashes_are_phc_strings() {
+        let hasher = argon2id(1);
+
+        let hash = hasher.hash("correct horse battery").unwrap();
+
+        assert!(hash.starts_with("$argon2id$v=19$m=8,t=1,p=1$"), "{hash}");
+        assert!(hasher.recognizes(&hash));
+        assert!(!BcryptHasher.recognizes(&hash));
+        assert!(hasher.verify("correct horse battery", &hash).unwrap());
+        assert!(!hasher.verify("wrong horse battery", &hash).unwrap());
+        // Fresh salt every time
+        assert_ne!(hash, hasher.hash("correct horse battery").unwrap());
+    }
+
+    #[test]
+    fn argon2id_hashes_with_other_costs_need_a_rehash() {
+        let hash = argon2id(1).hash("correct horse battery").unwrap();
+
+        assert!(!argon2id(1).needs_rehash(&hash));
+        assert!(argon2id(2).needs_rehash(&hash));
+        assert!(argon2id(1).needs_rehash("$argon2i$v=19$m=8,t=1,p=1$c2FsdHNhbHQ$aGFzaA"));
+        assert!(argon2id(1).needs_rehash("not a hash"));
+
+        // The costs the hash was made with still verify it
+        assert!(argon2id(2).verify("correct horse battery", &hash).unwrap());
+    }
+
+    #[test]
+    fn manager_verifies_current_hashes() {
+        let manager = manager();
+        let hash = manager.hash("correct horse battery").unwrap();
+
+        assert_eq!(
+            manager.verify("correct horse battery", &hash).unwrap(),
+            PasswordMatch::Valid
+        );
+        assert_eq!(
+            manager.verify("wrong horse battery", &hash).unwrap(),
+            PasswordMatch::Invalid
+        );
+    }
+
+    #[test]
+    fn manager_falls_back_to_bcrypt_and_asks_for_a_rehash() {
+        let manager = manager();
+        let hash = bcrypt::hash("correct horse battery", 4).unwrap();
+
+        assert!(BcryptHasher.recognizes(&hash));
+        assert_eq!(
+            manager.verify("correct horse battery", &hash).unwrap(),
+            PasswordMatch::ValidNeedsRehash
+        );
+        assert_eq!(
+            manager.verify("wrong horse battery", &hash).unwrap(),
+            PasswordMatch::Invalid
+        );
+    }
+
+    #[test]
+    fn manager_rejects_unknown_schemes() {
+        assert!(manager().verify("password", "$1$salt$hash").is_err());
+    }
+
+    #[test]
+    fn policy_refuses_short_and_breached_passwords() {
+        configure();
+        let path = std::env::temp_dir().join(format!("breached-{}.txt", Uuid::now_v7()));
+        std::fs::write(&path, "Password1234\n\n  correcthorsebattery  \n").unwrap();
+
+        let mut config = Config::from_env().unwrap();
+        config.password_min_length = 12;
+        config.password_breach_list = Some(path.to_str().unwrap().to_string());
+        let policy = PasswordPolicy::from_config(&config).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(policy.breached.len(), 2);
+        let code = |password: &str| policy.check(password).map_err(|error| error.code);
+        assert_eq!(code("short"), Err(Cow::from("password_too_short")));
+        assert_eq!(code("PASSWORD1234"), Err(Cow::from("password_breached")));
+        assert_eq!(
+            code("CorrectHorseBattery"),
+            Err(Cow::from("password_breached"))
+        );
+        assert_eq!(code("a long unlisted passphrase"), Ok(()));
+    }
+}
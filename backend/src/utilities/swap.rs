@@ -0,0 +1,473 @@
+//! HTLC scripts of submarine swaps and the transactions that spend them.
+//!
+//! Swaps lock coins in a P2WSH output that the claimer can spend with the
+//! preimage of the payment hash, or the refunder can take back once the
+//! timeout height has passed. The scripts follow the Boltz swap templates.
+
+use crate::errors::{ServiceError, ServiceResult};
+use bitcoin::blockdata::opcodes::all::{
+    OP_CHECKSIG, OP_CLTV, OP_DROP, OP_ELSE, OP_ENDIF, OP_EQUAL, OP_EQUALVERIFY, OP_HASH160, OP_IF,
+    OP_SIZE,
+};
+use bitcoin::hashes::{Hash, ripemd160};
+use bitcoin::script::{Builder, Instruction};
+use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::{
+    Address, Amount, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness,
+    absolute::LockTime, ecdsa, transaction::Version,
+};
+
+/// Virtual size of a transaction spending one swap output to one address,
+/// rounded up to cover the longest script and signature
+const HTLC_SPEND_VBYTES: u64 = 170;
+
+/// Outputs below this are not relayed
+const DUST_LIMIT_SAT: u64 = 546;
+
+/// Which branch of the swap script a spend takes
+pub enum HtlcSpend {
+    /// Claim with the preimage
+    Claim([u8; 32]),
+    /// Take the coins back after the timeout
+    Refund,
+}
+
+/// Script of a submarine swap (chain to Lightning): the swap provider
+/// claims with the preimage, the user refunds after `timeout_height`.
+pub fn submarine_swap_script(
+    payment_hash: &[u8; 32],
+    claim_key: &PublicKey,
+    refund_key: &PublicKey,
+    timeout_height: u32,
+) -> ScriptBuf {
+    Builder::new()
+        .push_opcode(OP_HASH160)
+        .push_slice(ripemd160::Hash::hash(payment_hash).to_byte_array())
+        .push_opcode(OP_EQUAL)
+        .push_opcode(OP_IF)
+        .push_slice(claim_key.serialize())
+        .push_opcode(OP_ELSE)
+        .push_int(timeout_height as i64)
+        .push_opcode(OP_CLTV)
+        .push_opcode(OP_DROP)
+        .push_slice(refund_key.serialize())
+        .push_opcode(OP_ENDIF)
+        .push_opcode(OP_CHECKSIG)
+        .into_script()
+}
+
+/// Script of a reverse swap (Lightning to chain): the user claims with the
+/// preimage, the swap provider refunds after `timeout_height`.
+pub fn reverse_swap_script(
+    payment_hash: &[u8; 32],
+    claim_key: &PublicKey,
+    refund_key: &PublicKey,
+    timeout_height: u32,
+) -> ScriptBuf {
+    Builder::new()
+        .push_opcode(OP_SIZE)
+        .push_int(32)
+        .push_opcode(OP_EQUAL)
+        .push_opcode(OP_IF)
+        .push_opcode(OP_HASH160)
+        .push_slice(ripemd160::Hash::hash(payment_hash).to_byte_array())
+        .push_opcode(OP_EQUALVERIFY)
+        .push_slice(claim_key.serialize())
+        .push_opcode(OP_ELSE)
+        .push_opcode(OP_DROP)
+        .push_int(timeout_height as i64)
+        .push_opcode(OP_CLTV)
+        .push_opcode(OP_DROP)
+        .push_slice(refund_key.serialize())
+        .push_opcode(OP_ENDIF)
+        .push_opcode(OP_CHECKSIG)
+        .into_script()
+}
+
+/// Public keys pushed by a swap script, in order
+fn script_keys(script: &ScriptBuf) -> Vec<PublicKey> {
+    script
+        .instructions()
+        .filter_map(|instruction| match instruction {
+            Ok(Instruction::PushBytes(bytes)) => PublicKey::from_slice(bytes.as_bytes()).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parses the script a swap provider sent and checks it is exactly the
+/// expected template for our key, payment hash and timeout, locked to
+/// `address`.
+///
+/// `reverse` selects the reverse swap template, where our key claims;
+/// otherwise our key refunds.
+pub fn verify_swap_script(
+    redeem_script: &str,
+    address: &str,
+    network: Network,
+    payment_hash: &[u8; 32],
+    our_key: &PublicKey,
+    timeout_height: u32,
+    reverse: bool,
+) -> ServiceResult<ScriptBuf> {
+    let invalid = |reason: &str| ServiceError::ExternalService {
+        message: format!("Swap provider sent an unexpected script: {reason}"),
+    };
+
+    let script = ScriptBuf::from_hex(redeem_script).map_err(|_| invalid("not hex"))?;
+
+    let keys = script_keys(&script);
+    let [first_key, second_key] = keys.as_slice() else {
+        return Err(invalid("wrong number of keys"));
+    };
+
+    let expected = if reverse {
+        reverse_swap_script(payment_hash, our_key, second_key, timeout_height)
+    } else {
+        submarine_swap_script(payment_hash, first_key, our_key, timeout_height)
+    };
+    if expected != script {
+        return Err(invalid("does not match the swap template"));
+    }
+
+    if Address::p2wsh(&script, network).to_string() != address {
+        return Err(invalid("lockup address does not match"));
+    }
+
+    Ok(script)
+}
+
+/// Output of `lockup_tx` locked to the swap `script`, with its index
+pub fn swap_output<'a>(
+    lockup_tx: &'a Transaction,
+    script: &ScriptBuf,
+) -> Option<(usize, &'a TxOut)> {
+    let script_pubkey = ScriptBuf::new_p2wsh(&script.wscript_hash());
+
+    lockup_tx
+        .output
+        .iter()
+        .enumerate()
+        .find(|(_, output)| output.script_pubkey == script_pubkey)
+}
+
+/// Builds and signs a transaction spending the swap output of `lockup_tx`
+/// to `destination`, paying `sat_per_vbyte` out of the swap amount.
+///
+/// A refund is only valid once the chain has reached `timeout_height`.
+pub fn build_htlc_spend(
+    lockup_tx: &Transaction,
+    script: &ScriptBuf,
+    destination: &Address,
+    secret_key: &SecretKey,
+    sat_per_vbyte: u64,
+    timeout_height: u32,
+    spend: HtlcSpend,
+) -> ServiceResult<Transaction> {
+    let (vout, lockup_output) = swap_output(lockup_tx, script)
+        .ok_or_else(|| ServiceError::invalid_operation("Lockup transaction has no swap output"))?;
+
+    let fee_sat = sat_per_vbyte * HTLC_SPEND_VBYTES;
+    let value_sat = lockup_output.value.to_sat().saturating_sub(fee_sat);
+    if value_sat < DUST_LIMIT_SAT {
+        return Err(ServiceError::invalid_operation(
+            "Swap output does not cover the fee to spend it",
+        ));
+    }
+
+    let lock_time = match spend {
+        HtlcSpend::Claim(_) => LockTime::ZERO,
+        HtlcSpend::Refund => {
+            LockTime::from_height(timeout_height).map_err(|e| ServiceError::InternalError {
+                message: e.to_string(),
+            })?
+        }
+    };
+
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(lockup_tx.compute_txid(), vout as u32),
+            script_sig: ScriptBuf::new(),
+            // Not final, so the timelock of a refund is enforced
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::from_sat(value_sat),
+            script_pubkey: destination.script_pubkey(),
+        }],
+    };
+
+    let sighash = SighashCache::new(&tx)
+        .p2wsh_signature_hash(0, script, lockup_output.value, EcdsaSighashType::All)
+        .map_err(|e| ServiceError::InternalError {
+            message: e.to_string(),
+        })?;
+    let signature = ecdsa::Signature::sighash_all(
+        Secp256k1::signing_only()
+            .sign_ecdsa(&Message::from_digest(sighash.to_byte_array()), secret_key),
+    );
+
+    let mut witness = Witness::new();
+    witness.push_ecdsa_signature(&signature);
+    match spend {
+        HtlcSpend::Claim(preimage) => witness.push(preimage),
+        HtlcSpend::Refund => witness.push(Vec::<u8>::new()),
+    }
+    witness.push(script.as_bytes());
+    tx.input[0].witness = witness;
+
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::CompressedPublicKey;
+    use bitcoin::hashes::sha256;
+
+    const TIMEOUT: u32 = 850;
+    const PREIMAGE: [u8; 32] = [7; 32];
+
+    fn key(byte: u8) -> (SecretKey, PublicKey) {
+        let secret_key = SecretKey::from_slice(&[byte; 32]).unwrap();
+        (
+            secret_key,
+            PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key),
+        )
+    }
+
+    fn payment_hash() -> [u8; 32] {
+        sha256::Hash::hash(&PREIMAGE).to_byte_array()
+    }
+
+    fn destination() -> Address {
+        Address::p2wpkh(&CompressedPublicKey(key(9).1), Network::Regtest)
+    }
+
+    /// Lockup paying `amount_sat` to `script`, after an unrelated output
+    fn lockup(script: &ScriptBuf, amount_sat: u64) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![
+                TxOut {
+                    value: Amount::from_sat(1_000),
+                    script_pubkey: destination().script_pubkey(),
+                },
+                TxOut {
+                    value: Amount::from_sat(amount_sat),
+                    script_pubkey: ScriptBuf::new_p2wsh(&script.wscript_hash()),
+                },
+            ],
+        }
+    }
+
+    /// Checks the signature in a spend of `lockup_tx` against `public_key`
+    fn assert_signed_by(
+        spend: &Transaction,
+        lockup_tx: &Transaction,
+        script: &ScriptBuf,
+        public_key: &PublicKey,
+    ) {
+        let signature = ecdsa::Signature::from_slice(&spend.input[0].witness[0]).unwrap();
+        let sighash = SighashCache::new(spend)
+            .p2wsh_signature_hash(0, script, lockup_tx.output[1].value, EcdsaSighashType::All)
+            .unwrap();
+
+        Secp256k1::verification_only()
+            .verify_ecdsa(
+                &Message::from_digest(sighash.to_byte_array()),
+                &signature.signature,
+                public_key,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn accepts_the_expected_reverse_swap_script() {
+        let (_, ours) = key(1);
+        let (_, provider) = key(2);
+        let script = reverse_swap_script(&payment_hash(), &ours, &provider, TIMEOUT);
+        let address = Address::p2wsh(&script, Network::Regtest).to_string();
+
+        let verified = verify_swap_script(
+            &script.to_hex_string(),
+            &address,
+            Network::Regtest,
+            &payment_hash(),
+            &ours,
+            TIMEOUT,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(verified, script);
+    }
+
+    #[test]
+    fn accepts_the_expected_submarine_swap_script() {
+        let (_, ours) = key(1);
+        let (_, provider) = key(2);
+        let script = submarine_swap_script(&payment_hash(), &provider, &ours, TIMEOUT);
+        let address = Address::p2wsh(&script, Network::Regtest).to_string();
+
+        assert!(
+            verify_swap_script(
+                &script.to_hex_string(),
+                &address,
+                Network::Regtest,
+                &payment_hash(),
+                &ours,
+                TIMEOUT,
+                false,
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn rejects_scripts_that_do_not_match_the_swap() {
+        let (_, ours) = key(1);
+        let (_, provider) = key(2);
+        let script = reverse_swap_script(&payment_hash(), &ours, &provider, TIMEOUT);
+        let address = Address::p2wsh(&script, Network::Regtest).to_string();
+        let verify = |script: &ScriptBuf, address: &str, reverse: bool| {
+            verify_swap_script(
+                &script.to_hex_string(),
+                address,
+                Network::Regtest,
+                &payment_hash(),
+                &ours,
+                TIMEOUT,
+                reverse,
+            )
+        };
+
+        // The provider's key in our place
+        let stolen = reverse_swap_script(&payment_hash(), &provider, &provider, TIMEOUT);
+        assert!(verify(&stolen, &address, true).is_err());
+        // Another payment hash
+        let other_hash = reverse_swap_script(&[1; 32], &ours, &provider, TIMEOUT);
+        assert!(verify(&other_hash, &address, true).is_err());
+        // A later timeout
+        let later = reverse_swap_script(&payment_hash(), &ours, &provider, TIMEOUT + 1);
+        assert!(verify(&later, &address, true).is_err());
+        // The right script, locked somewhere else
+        assert!(verify(&script, &destination().to_string(), true).is_err());
+        // The right script, for the other kind of swap
+        assert!(verify(&script, &address, false).is_err());
+        assert!(
+            verify_swap_script(
+                "not hex",
+                &address,
+                Network::Regtest,
+                &payment_hash(),
+                &ours,
+                TIMEOUT,
+                true,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn claim_spends_the_swap_output_with_the_preimage() {
+        let (secret_key, ours) = key(1);
+        let (_, provider) = key(2);
+        let script = reverse_swap_script(&payment_hash(), &ours, &provider, TIMEOUT);
+        let lockup_tx = lockup(&script, 100_000);
+
+        let claim = build_htlc_spend(
+            &lockup_tx,
+            &script,
+            &destination(),
+            &secret_key,
+            2,
+            TIMEOUT,
+            HtlcSpend::Claim(PREIMAGE),
+        )
+        .unwrap();
+
+        assert_eq!(claim.lock_time, LockTime::ZERO);
+        assert_eq!(
+            claim.input[0].previous_output,
+            OutPoint::new(lockup_tx.compute_txid(), 1)
+        );
+        assert_eq!(
+            claim.output[0].value,
+            Amount::from_sat(100_000 - 2 * HTLC_SPEND_VBYTES)
+        );
+        assert_eq!(claim.output[0].script_pubkey, destination().script_pubkey());
+        assert_eq!(&claim.input[0].witness[1], PREIMAGE.as_slice());
+        assert_eq!(&claim.input[0].witness[2], script.as_bytes());
+        assert!(claim.vsize() as u64 <= HTLC_SPEND_VBYTES);
+        assert_signed_by(&claim, &lockup_tx, &script, &ours);
+    }
+
+    #[test]
+    fn refund_waits_for_the_timeout() {
+        let (secret_key, ours) = key(1);
+        let (_, provider) = key(2);
+        let script = submarine_swap_script(&payment_hash(), &provider, &ours, TIMEOUT);
+        let lockup_tx = lockup(&script, 100_000);
+
+        let refund = build_htlc_spend(
+            &lockup_tx,
+            &script,
+            &destination(),
+            &secret_key,
+            2,
+            TIMEOUT,
+            HtlcSpend::Refund,
+        )
+        .unwrap();
+
+        assert_eq!(refund.lock_time, LockTime::from_height(TIMEOUT).unwrap());
+        assert_ne!(refund.input[0].sequence, Sequence::MAX);
+        assert!(refund.input[0].witness[1].is_empty());
+        assert_signed_by(&refund, &lockup_tx, &script, &ours);
+    }
+
+    #[test]
+    fn spend_needs_a_swap_output_worth_spending() {
+        let (secret_key, ours) = key(1);
+        let (_, provider) = key(2);
+        let script = reverse_swap_script(&payment_hash(), &ours, &provider, TIMEOUT);
+        let spend = |lockup_tx: &Transaction| {
+            build_htlc_spend(
+                lockup_tx,
+                &script,
+                &destination(),
+                &secret_key,
+                2,
+                TIMEOUT,
+                HtlcSpend::Claim(PREIMAGE),
+            )
+        };
+
+        let other_script = reverse_swap_script(&[1; 32], &ours, &provider, TIMEOUT);
+        assert!(spend(&lockup(&other_script, 100_000)).is_err());
+        assert!(spend(&lockup(&script, 2 * HTLC_SPEND_VBYTES + DUST_LIMIT_SAT - 1)).is_err());
+        assert!(spend(&lockup(&script, 2 * HTLC_SPEND_VBYTES + DUST_LIMIT_SAT)).is_ok());
+    }
+
+    #[test]
+    fn finds_the_swap_output() {
+        let (_, ours) = key(1);
+        let (_, provider) = key(2);
+        let script = reverse_swap_script(&payment_hash(), &ours, &provider, TIMEOUT);
+
+        let lockup_tx = lockup(&script, 5_000);
+        let (vout, output) = swap_output(&lockup_tx, &script).unwrap();
+        assert_eq!(vout, 1);
+        assert_eq!(output.value, Amount::from_sat(5_000));
+
+        let other_script = reverse_swap_script(&[1; 32], &ours, &provider, TIMEOUT);
+        assert!(swap_output(&lockup(&other_script, 5_000), &script).is_none());
+    }
+}
@@ -0,0 +1,62 @@
+//! Helpers shared by the database-backed tests.
+
+use crate::utilities::auth::AuthUser;
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use std::sync::Once;
+use uuid::Uuid;
+
+/// Sets the environment `Config::from_env` needs, keeping anything already
+/// set, for a regtest node.
+pub fn configure() {
+    static CONFIGURE: Once = Once::new();
+
+    CONFIGURE.call_once(|| {
+        let vars = [
+            ("JWT_SECRET", "secret"),
+            ("ENCRYPTION_KEY", "key"),
+            ("RESENT_API_KEY", "key"),
+            ("FROM_EMAIL", "noreply@moya.test"),
+            ("LND_GRPC_URL", "localhost:10009"),
+            ("LND_TLS_CERT", "tls.cert"),
+            ("LND_MACAROON", "admin.macaroon"),
+            ("PUBLIC_URL", "https://moya.test"),
+            ("LNURL_DOMAIN", "moya.test"),
+            ("BITCOIN_NETWORK", "regtest"),
+        ];
+        for (name, value) in vars {
+            if std::env::var(name).is_err() {
+                // Set once, before any test reads the environment
+                unsafe { std::env::set_var(name, value) };
+            }
+        }
+    });
+}
+
+/// Creates an active customer whose account holds `balance_msat`.
+pub async fn customer(pool: &PgPool, balance_msat: u64) -> AuthUser {
+    let user_id = Uuid::now_v7().to_string();
+    let account_id = Uuid::now_v7().to_string();
+
+    sqlx::query(
+        "INSERT INTO users (id, username, role_id)
+         SELECT $1, $1, id FROM roles WHERE name = 'customer'",
+    )
+    .bind(&user_id)
+    .execute(pool)
+    .await
+    .unwrap();
+    sqlx::query("INSERT INTO accounts (id, user_id, balance) VALUES ($1, $2, $3)")
+        .bind(&account_id)
+        .bind(&user_id)
+        .bind(BigDecimal::from(balance_msat))
+        .execute(pool)
+        .await
+        .unwrap();
+
+    AuthUser {
+        user_id,
+        account_id,
+        api_key: None,
+    }
+}
@@ -0,0 +1,16 @@
+//! Opaque random tokens that are handed to users and stored only as digests.
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Generate a URL-safe random token with 256 bits of entropy
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// SHA-256 hex digest of a token, used for storage and lookup
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
@@ -39,9 +39,17 @@ async fn main() {
             "/invoice",
             axum::routing::post(routes::invoice::create_invoice),
         )
+        .route(
+            "/prepare_payment",
+            axum::routing::post(routes::invoice::prepare_payment),
+        )
         .route(
             "/pay_invoice",
-            axum::routing::post(routes::invoice::create_invoice),
+            axum::routing::post(routes::invoice::pay_invoice),
+        )
+        .route(
+            "/deposit_address",
+            axum::routing::post(routes::invoice::deposit_address),
         )
         .with_state(state);
 
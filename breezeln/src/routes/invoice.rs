@@ -1,15 +1,19 @@
+use crate::common::common::service_error_to_http;
+use crate::errors::ServiceError;
 use crate::state::AppState;
-use axum::{extract::State, Json};
+use axum::{extract::State, http::StatusCode, Json};
 use breez_sdk_spark::{
     GetInfoRequest, InputType, ListPaymentsRequest, Payment, PrepareSendPaymentRequest,
-    ReceivePaymentMethod, ReceivePaymentRequest, SendPaymentOptions, SendPaymentRequest,
+    ReceivePaymentMethod, ReceivePaymentRequest, SendPaymentMethod, SendPaymentOptions,
+    SendPaymentRequest,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct CreateInvoiceRequest {
-    pub amount_sats: u64,
+    /// Leave out to let the payer choose the amount
+    pub amount_sats: Option<u64>,
     pub description: Option<String>,
 }
 
@@ -18,9 +22,29 @@ pub struct CreateInvoiceResponse {
     pub paymentRequest: String,
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize)]
 pub struct PayInvoiceRequest {
     pub invoice: String,
+    /// Only needed for invoices without an amount
+    pub amount_sats: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct PreparePaymentRequest {
+    pub payment_request: String,
+    /// Only needed for invoices without an amount
+    pub amount_sats: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct PreparePaymentResponse {
+    pub amount_sats: u64,
+    pub fee_sats: u64,
+}
+
+#[derive(Serialize)]
+pub struct DepositAddressResponse {
+    pub address: String,
 }
 
 #[derive(Serialize)]
@@ -46,8 +70,8 @@ pub async fn create_invoice(
         .breeze
         .receive_payment(ReceivePaymentRequest {
             payment_method: ReceivePaymentMethod::Bolt11Invoice {
-                description: req.description.unwrap(),
-                amount_sats: Some(req.amount_sats),
+                description: req.description.unwrap_or_default(),
+                amount_sats: req.amount_sats,
             },
         })
         .await;
@@ -57,6 +81,29 @@ pub async fn create_invoice(
     })
 }
 
+// Fresh on-chain address; deposits to it are claimed into the balance once
+// confirmed. Standalone: the Moya backend hands out addresses from its own
+// LND or CLN wallet and does not call this route.
+pub async fn deposit_address(
+    State(state): State<AppState>,
+) -> Result<Json<DepositAddressResponse>, (StatusCode, String)> {
+    let response = state
+        .breeze
+        .receive_payment(ReceivePaymentRequest {
+            payment_method: ReceivePaymentMethod::BitcoinAddress,
+        })
+        .await
+        .map_err(|err| {
+            service_error_to_http(ServiceError::ExternalService {
+                message: err.to_string(),
+            })
+        })?;
+
+    Ok(Json(DepositAddressResponse {
+        address: response.payment_request,
+    }))
+}
+
 pub async fn list_payments(State(state): State<AppState>) -> Json<PaymentListsResponse> {
     let response = state
         .breeze
@@ -122,13 +169,15 @@ pub async fn pay_invoice(
 ) -> Json<PayInvoiceResponse> {
     let parsed_input = state.breeze.parse(req.invoice.as_str()).await;
 
-    let amount_msat = match parsed_input.unwrap() {
-        InputType::Bolt11Invoice(details) => {
-            if let Some(a) = details.amount_msat {
+    // Invoices with an amount carry it themselves, the rest are paid what the caller asked for
+    let amount = match parsed_input.unwrap() {
+        InputType::Bolt11Invoice(details) => match details.amount_msat {
+            Some(a) => {
                 println!("Input is BOLT11 invoice for {} msats", a);
+                None
             }
-            details.amount_msat.map(|a| a as u128)
-        }
+            None => req.amount_sats.map(|a| a as u128),
+        },
         _ => None,
     };
 
@@ -136,7 +185,7 @@ pub async fn pay_invoice(
         .breeze
         .prepare_send_payment(PrepareSendPaymentRequest {
             payment_request: req.invoice,
-            amount: amount_msat,
+            amount,
             token_identifier: None,
         })
         .await
@@ -160,6 +209,39 @@ pub async fn pay_invoice(
     })
 }
 
+// Fee preview for an invoice, without paying it. The Moya backend's decode
+// preview estimates fees through its own node and does not call this route.
+pub async fn prepare_payment(
+    State(state): State<AppState>,
+    Json(req): Json<PreparePaymentRequest>,
+) -> Result<Json<PreparePaymentResponse>, (StatusCode, String)> {
+    let prepare_response = state
+        .breeze
+        .prepare_send_payment(PrepareSendPaymentRequest {
+            payment_request: req.payment_request,
+            amount: req.amount_sats.map(|a| a as u128),
+            token_identifier: None,
+        })
+        .await
+        .map_err(|err| {
+            service_error_to_http(ServiceError::ExternalService {
+                message: err.to_string(),
+            })
+        })?;
+
+    let fee_sats = match prepare_response.payment_method {
+        SendPaymentMethod::Bolt11Invoice {
+            lightning_fee_sats, ..
+        } => lightning_fee_sats,
+        _ => 0,
+    };
+
+    Ok(Json(PreparePaymentResponse {
+        amount_sats: prepare_response.amount as u64,
+        fee_sats,
+    }))
+}
+
 pub async fn balance(State(state): State<AppState>) -> Json<BalanceResponse> {
     let response = state
         .breeze